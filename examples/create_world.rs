@@ -4,7 +4,7 @@ use minetestworld::{Node, World};
 #[async_std::main]
 async fn main() {
     let world = World::create_sqlite("NewWorld").await.unwrap();
-    let mut vm = world.get_voxel_manip(true).await.unwrap();
+    let vm = world.get_voxel_manip(true).await.unwrap();
     for y in -99..100 {
         for x in -100..100 {
             for z in -100..100 {
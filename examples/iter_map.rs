@@ -6,9 +6,7 @@ fn main() {
     task::block_on(async {
         let world = World::open("TestWorld");
         let data = world.get_map_data_backend(false).await.unwrap();
-        let mut positions = data.all_mapblock_positions().await;
-        while let Some(pos) = positions.try_next().await.unwrap() {
-            let _ = data.get_mapblock(pos).await.unwrap();
-        }
+        let mut blocks = data.all_mapblocks().await;
+        while let Some((_pos, _block)) = blocks.try_next().await.unwrap() {}
     });
 }
@@ -0,0 +1,559 @@
+//! Functions that examine a world's map data, e.g. to find mapgen errors
+
+use futures::TryStreamExt;
+use glam::{I16Vec3, IVec3};
+use std::collections::{HashMap, HashSet};
+
+use crate::positions::{BlockArea, BlockKey, BlockPos, NodeIndex, NodePos, FACE_OFFSETS};
+use crate::{
+    MapBlock, MapData, MapDataError, Node, BLOCK_NODES_1D, BLOCK_NODES_3D, WORLD_BLOCKS_RANGE,
+};
+
+/// Finds missing mapblocks within `region` whose six face neighbors all exist
+///
+/// Such blocks are typically left behind by an interrupted map generation.
+/// Blocks at the border of the world are never reported.
+///
+/// The result is sorted by [`BlockKey`].
+pub async fn find_generation_holes(
+    map: &MapData,
+    region: BlockArea,
+) -> Result<Vec<BlockPos>, MapDataError> {
+    // Holes at the border of the region have neighbors outside of it
+    let min = region.min().into_index_vec() - I16Vec3::ONE;
+    let max = region.max().into_index_vec() + I16Vec3::ONE;
+    let search_area = BlockArea::new(
+        BlockPos::from_index_vec(min.max(I16Vec3::splat(WORLD_BLOCKS_RANGE.start))),
+        BlockPos::from_index_vec(max.min(I16Vec3::splat(WORLD_BLOCKS_RANGE.end - 1))),
+    );
+    let existing: HashSet<BlockPos> = map
+        .mapblock_positions_in(search_area)
+        .await
+        .try_collect()
+        .await?;
+
+    // Every hole is a neighbor of an existing block
+    let mut holes: Vec<BlockPos> = existing
+        .iter()
+        .flat_map(|&pos| pos.neighbors6())
+        .filter(|pos| region.contains(*pos) && !existing.contains(pos))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .filter(|&pos| {
+            pos.neighbors6().count() == FACE_OFFSETS.len()
+                && pos
+                    .neighbors6()
+                    .all(|neighbor| existing.contains(&neighbor))
+        })
+        .collect();
+    holes.sort_unstable_by_key(|pos| BlockKey::from(*pos));
+    Ok(holes)
+}
+
+/// Counts the nodes of the whole map by the mod that defines their content
+///
+/// The mod name is the part of the content name before the `:`.
+/// Nodes without a mod prefix, like `air` or `ignore`, are counted under an empty name.
+///
+/// ```
+/// use minetestworld::{analysis, World};
+/// use async_std::task;
+///
+/// task::block_on(async {
+///     let map = World::open("TestWorld").get_map_data().await.unwrap();
+///     let usage = analysis::mod_usage(&map).await.unwrap();
+///     assert!(usage["default"] > 0);
+/// });
+/// ```
+pub async fn mod_usage(map: &MapData) -> Result<HashMap<String, u64>, MapDataError> {
+    Ok(mod_usage_of(&map.content_histogram(None).await?))
+}
+
+/// Groups a [`content_histogram`](`MapData::content_histogram`) by the mod prefix
+/// of the content names, like [`mod_usage`] does for the whole map
+pub fn mod_usage_of(histogram: &HashMap<Vec<u8>, u64>) -> HashMap<String, u64> {
+    let mut usage = HashMap::new();
+    for (name, &count) in histogram {
+        let mod_name = match name.iter().position(|&c| c == b':') {
+            Some(colon) => &name[..colon],
+            None => &[],
+        };
+        *usage
+            .entry(String::from_utf8_lossy(mod_name).into_owned())
+            .or_default() += count;
+    }
+    usage
+}
+
+/// The air nodes below the surface of a region, as found by [`air_volume_below_surface`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AirVolume {
+    /// Number of air nodes below the surface that are not connected to the open sky
+    pub enclosed: u64,
+    /// Number of non-air nodes below the surface
+    pub solid: u64,
+    /// The sizes of all enclosed cavities in nodes, largest first
+    pub cavities: Vec<u64>,
+}
+
+impl AirVolume {
+    /// Returns the share of enclosed air among all nodes below the surface
+    pub fn cave_density(&self) -> f64 {
+        let total = self.enclosed + self.solid;
+        if total == 0 {
+            0.0
+        } else {
+            self.enclosed as f64 / total as f64
+        }
+    }
+}
+
+/// The nodes of a region, classified into a dense grid
+///
+/// Grid coordinates count nodes from the minimum corner of the region.
+struct Grid<T> {
+    size: [usize; 3],
+    cells: Vec<T>,
+}
+
+impl<T: Clone> Grid<T> {
+    /// Classifies the nodes of every block within `region`
+    ///
+    /// `classify` returns the cells of a block's nodes, ordered by their [`NodeIndex`].
+    /// The nodes of blocks that have not been generated become `missing`.
+    async fn build(
+        map: &MapData,
+        region: BlockArea,
+        missing: T,
+        mut classify: impl FnMut(BlockPos, &MapBlock) -> Vec<T>,
+    ) -> Result<Self, MapDataError> {
+        let length = usize::from(BLOCK_NODES_1D);
+        let min = region.min().into_index_vec();
+        let blocks = region.max().into_index_vec() - min + I16Vec3::ONE;
+        let size = [blocks.x, blocks.y, blocks.z].map(|b| b as usize * length);
+        let mut grid = Grid {
+            size,
+            cells: vec![missing; size[0] * size[1] * size[2]],
+        };
+        for pos in region.iter() {
+            let block = match map.get_mapblock(pos).await {
+                Ok(block) => block,
+                Err(MapDataError::MapBlockNonexistent(_)) => continue,
+                Err(e) => return Err(e),
+            };
+            let offset = pos.into_index_vec() - min;
+            let offset = [offset.x, offset.y, offset.z].map(|o| o as usize * length);
+            for (i, cell) in classify(pos, &block).into_iter().enumerate() {
+                let (x, y, z) = (i % length, i / length % length, i / (length * length));
+                let index = grid.index([offset[0] + x, offset[1] + y, offset[2] + z]);
+                grid.cells[index] = cell;
+            }
+        }
+        Ok(grid)
+    }
+}
+
+impl<T> Grid<T> {
+    /// Returns the index of the cell at the grid coordinates `node`
+    fn index(&self, [x, y, z]: [usize; 3]) -> usize {
+        (z * self.size[1] + y) * self.size[0] + x
+    }
+
+    /// Iterates over the grid coordinates of all nodes, in the order of their index
+    fn nodes(&self) -> impl Iterator<Item = [usize; 3]> {
+        let [sx, sy, sz] = self.size;
+        (0..sz).flat_map(move |z| (0..sy).flat_map(move |y| (0..sx).map(move |x| [x, y, z])))
+    }
+
+    /// Visits the nodes that are face-connected to `start`, including `start`
+    ///
+    /// `enter` is asked for every face neighbor of a visited node whether to visit it,
+    /// or receives `None` if the neighbor lies beyond the border of the grid.
+    /// It has to remember the nodes it entered, as they are visited again otherwise.
+    fn flood_fill(
+        &self,
+        start: [usize; 3],
+        mut visit: impl FnMut([usize; 3]),
+        mut enter: impl FnMut(Option<[usize; 3]>) -> bool,
+    ) {
+        let mut stack = vec![start];
+        while let Some(node) = stack.pop() {
+            visit(node);
+            for (axis, forward) in (0..3).flat_map(|axis| [(axis, false), (axis, true)]) {
+                let mut neighbor = node;
+                if forward {
+                    neighbor[axis] += 1;
+                    if neighbor[axis] == self.size[axis] {
+                        enter(None);
+                        continue;
+                    }
+                } else if neighbor[axis] == 0 {
+                    enter(None);
+                    continue;
+                } else {
+                    neighbor[axis] -= 1;
+                }
+                if enter(Some(neighbor)) {
+                    stack.push(neighbor);
+                }
+            }
+        }
+    }
+}
+
+/// How a node of the analyzed region is classified
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cell {
+    /// The node belongs to a block that has not been generated
+    Missing,
+    Solid,
+    Air,
+}
+
+/// Measures the air enclosed below the surface of `region`, e.g. caves
+///
+/// The surface of each node column is its highest non-air node within the region.
+/// Air nodes below the surface are grouped into cavities of face-connected nodes.
+/// Cavities that connect to air above the surface, like cave entrances, are not
+/// enclosed and thus not counted. Cavities reaching the border of the region or
+/// blocks that have not been generated are counted, as nothing is known beyond.
+///
+/// Columns without any non-air node have no surface and are skipped.
+pub async fn air_volume_below_surface(
+    map: &MapData,
+    region: BlockArea,
+) -> Result<AirVolume, MapDataError> {
+    let grid = Grid::build(map, region, Cell::Missing, |_, block| {
+        let air = block.get_content_id(b"air");
+        block
+            .param0
+            .iter()
+            .map(|&content_id| {
+                if Some(content_id) == air {
+                    Cell::Air
+                } else {
+                    Cell::Solid
+                }
+            })
+            .collect()
+    })
+    .await?;
+    let size = grid.size;
+
+    // The y coordinate of the surface per column, if any
+    let surface: Vec<Option<usize>> = (0..size[2])
+        .flat_map(|z| (0..size[0]).map(move |x| (x, z)))
+        .map(|(x, z)| {
+            (0..size[1])
+                .rev()
+                .find(|&y| grid.cells[grid.index([x, y, z])] == Cell::Solid)
+        })
+        .collect();
+    let below_surface = |[x, y, z]: [usize; 3]| surface[z * size[0] + x].is_some_and(|s| y < s);
+
+    let mut volume = AirVolume::default();
+    let mut visited = vec![false; grid.cells.len()];
+    for node in grid.nodes() {
+        if !below_surface(node) {
+            continue;
+        }
+        let start = grid.index(node);
+        if grid.cells[start] != Cell::Air {
+            volume.solid += u64::from(grid.cells[start] == Cell::Solid);
+            continue;
+        }
+        if visited[start] {
+            continue;
+        }
+
+        // Flood fill the cavity
+        visited[start] = true;
+        let mut nodes = 0;
+        let mut open = false;
+        grid.flood_fill(
+            node,
+            |_| nodes += 1,
+            |neighbor| {
+                let Some(neighbor) = neighbor else {
+                    return false;
+                };
+                let i = grid.index(neighbor);
+                if grid.cells[i] != Cell::Air {
+                    false
+                } else if !below_surface(neighbor) {
+                    open = true;
+                    false
+                } else {
+                    !std::mem::replace(&mut visited[i], true)
+                }
+            },
+        );
+        if !open {
+            volume.enclosed += nodes;
+            volume.cavities.push(nodes);
+        }
+    }
+    volume.cavities.sort_unstable_by(|a, b| b.cmp(a));
+    Ok(volume)
+}
+
+/// How often contents appear close to each other, as found by [`co_occurrence`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoOccurrence {
+    /// The number of nodes per content
+    pub totals: HashMap<String, u64>,
+    /// For each content pair `(a, b)`, the number of `a` nodes
+    /// with at least one `b` node within the radius
+    pub pairs: HashMap<(String, String), u64>,
+}
+
+impl CoOccurrence {
+    /// Returns the share of `a` nodes that have a `b` node within the radius
+    pub fn share(&self, a: &str, b: &str) -> f64 {
+        let total = self.totals.get(a).copied().unwrap_or_default();
+        if total == 0 {
+            return 0.0;
+        }
+        let count = self
+            .pairs
+            .get(&(a.to_string(), b.to_string()))
+            .copied()
+            .unwrap_or_default();
+        count as f64 / total as f64
+    }
+}
+
+/// Counts how often the given contents appear within `radius` nodes of each other
+///
+/// The distance is Euclidean and may span mapblock borders.
+/// This is meant for comparably rare contents like ores or lava,
+/// as the positions of all their nodes are kept in memory.
+///
+/// ```
+/// use minetestworld::{analysis, World};
+/// use async_std::task;
+///
+/// task::block_on(async {
+///     let map = World::open("TestWorld").get_map_data().await.unwrap();
+///     let contents = ["default:stone_with_coal", "default:lava_source"];
+///     let stats = analysis::co_occurrence(&map, &contents, 3).await.unwrap();
+///     let near_lava = stats.share("default:stone_with_coal", "default:lava_source");
+///     println!("{:.1}% of coal is near lava", near_lava * 100.0);
+/// });
+/// ```
+pub async fn co_occurrence(
+    map: &MapData,
+    contents: &[&str],
+    radius: u16,
+) -> Result<CoOccurrence, MapDataError> {
+    let blocks: Vec<_> = map.all_mapblock_positions().await.try_collect().await?;
+    // The index into `contents` of every matching node
+    let mut nodes: HashMap<IVec3, usize> = HashMap::new();
+    let mut totals = vec![0; contents.len()];
+    for blockpos in blocks {
+        let block = map.get_mapblock(blockpos).await?;
+        let wanted: HashMap<u16, usize> = contents
+            .iter()
+            .enumerate()
+            .filter_map(|(i, content)| Some((block.get_content_id(content.as_bytes())?, i)))
+            .collect();
+        if wanted.is_empty() {
+            continue;
+        }
+        for (index, content_id) in block.param0.iter().enumerate() {
+            let Some(&i) = wanted.get(content_id) else {
+                continue;
+            };
+            // There are only 4096 nodes in a mapblock
+            let node_pos = NodePos::from(NodeIndex::try_from(index as u16).unwrap());
+            nodes.insert(blockpos.join(node_pos).as_ivec3(), i);
+            totals[i] += 1;
+        }
+    }
+
+    let radius = i32::from(radius);
+    let offsets: Vec<IVec3> = (-radius..=radius)
+        .flat_map(|z| {
+            (-radius..=radius)
+                .flat_map(move |y| (-radius..=radius).map(move |x| IVec3::new(x, y, z)))
+        })
+        .filter(|offset| *offset != IVec3::ZERO && offset.length_squared() <= radius * radius)
+        .collect();
+    let mut pairs = vec![0; contents.len() * contents.len()];
+    for (pos, &a) in &nodes {
+        let mut found = vec![false; contents.len()];
+        for offset in &offsets {
+            if let Some(&b) = nodes.get(&(*pos + *offset)) {
+                found[b] = true;
+            }
+        }
+        for (b, _) in found.iter().enumerate().filter(|(_, &found)| found) {
+            pairs[a * contents.len() + b] += 1;
+        }
+    }
+
+    Ok(CoOccurrence {
+        totals: contents
+            .iter()
+            .zip(totals)
+            .map(|(content, total)| (content.to_string(), total))
+            .collect(),
+        pairs: pairs
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, count)| count > 0)
+            .map(|(i, count)| {
+                let (a, b) = (i / contents.len(), i % contents.len());
+                ((contents[a].to_string(), contents[b].to_string()), count)
+            })
+            .collect(),
+    })
+}
+
+/// A region of face-connected nodes, as found by [`connected_components`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Component {
+    /// Number of nodes in this component
+    pub size: u64,
+    /// The corner of the bounding box with the smallest coordinates
+    pub min: I16Vec3,
+    /// The corner of the bounding box with the largest coordinates
+    pub max: I16Vec3,
+    /// Whether this component reaches the border of the analyzed region
+    ///
+    /// Such a component may continue outside of the region.
+    pub touches_border: bool,
+}
+
+/// The connected components of a region, as found by [`connected_components`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Components {
+    /// The corner of the region with the smallest coordinates
+    pub min: I16Vec3,
+    /// The corner of the region with the largest coordinates
+    pub max: I16Vec3,
+    /// The label of every node of the region, x being the fastest changing coordinate
+    ///
+    /// `0` marks nodes that do not match, every other label `n` refers to `components[n - 1]`.
+    pub labels: Vec<u32>,
+    /// The components in the order of their first node
+    pub components: Vec<Component>,
+}
+
+impl Components {
+    /// Returns the component that the node at `pos` belongs to
+    pub fn component_at(&self, pos: I16Vec3) -> Option<&Component> {
+        if pos.cmplt(self.min).any() || pos.cmpgt(self.max).any() {
+            return None;
+        }
+        let offset = (pos - self.min).as_uvec3();
+        let size = (self.max - self.min + I16Vec3::ONE).as_uvec3();
+        let index = (offset.z as usize * size.y as usize + offset.y as usize) * size.x as usize
+            + offset.x as usize;
+        match self.labels[index] {
+            0 => None,
+            label => self.components.get(label as usize - 1),
+        }
+    }
+}
+
+/// Labels the regions of face-connected nodes within `region` for which `matches` returns `true`
+///
+/// `matches` receives the world position of every node along with the node.
+/// Nodes of blocks that have not been generated never match.
+/// A label is kept in memory for every node of the region.
+///
+/// ```
+/// use minetestworld::{analysis, World};
+/// use minetestworld::positions::{BlockArea, BlockPos};
+/// use glam::I16Vec3;
+/// use async_std::task;
+///
+/// task::block_on(async {
+///     let map = World::open("TestWorld").get_map_data().await.unwrap();
+///     let region = BlockArea::new(
+///         BlockPos::from_index_vec(I16Vec3::new(-13, -3, 3)),
+///         BlockPos::from_index_vec(I16Vec3::new(-11, -1, 5)),
+///     );
+///     let caves = analysis::connected_components(&map, region, |pos, node| {
+///         pos.y < 0 && node.param0 == b"air"
+///     })
+///     .await
+///     .unwrap();
+///     for cave in caves.components.iter().filter(|cave| !cave.touches_border) {
+///         println!("Sealed cave of {} nodes at {}", cave.size, cave.min);
+///     }
+/// });
+/// ```
+pub async fn connected_components(
+    map: &MapData,
+    region: BlockArea,
+    mut matches: impl FnMut(I16Vec3, &Node) -> bool,
+) -> Result<Components, MapDataError> {
+    let grid = Grid::build(map, region, false, |pos, block| {
+        (0..BLOCK_NODES_3D)
+            .map(|i| {
+                // There are only 4096 nodes in a mapblock
+                let node_pos = NodePos::from(NodeIndex::try_from(i).unwrap());
+                matches(pos.join(node_pos), &block.get_node_at(node_pos))
+            })
+            .collect()
+    })
+    .await?;
+
+    let origin = region.min().into_index_vec() * BLOCK_NODES_1D as i16;
+    let position = |[x, y, z]: [usize; 3]| {
+        // The region lies within the world, so every offset fits into an i16
+        origin + I16Vec3::new(x as i16, y as i16, z as i16)
+    };
+    let mut labels = vec![0; grid.cells.len()];
+    let mut components = Vec::new();
+    for node in grid.nodes() {
+        let start = grid.index(node);
+        if !grid.cells[start] || labels[start] != 0 {
+            continue;
+        }
+
+        // Flood fill the component
+        let label = components.len() as u32 + 1;
+        labels[start] = label;
+        let mut component = Component {
+            size: 0,
+            min: position(node),
+            max: position(node),
+            touches_border: false,
+        };
+        let mut touches_border = false;
+        grid.flood_fill(
+            node,
+            |node| {
+                component.size += 1;
+                component.min = component.min.min(position(node));
+                component.max = component.max.max(position(node));
+            },
+            |neighbor| {
+                let Some(neighbor) = neighbor else {
+                    touches_border = true;
+                    return false;
+                };
+                let i = grid.index(neighbor);
+                if grid.cells[i] && labels[i] == 0 {
+                    labels[i] = label;
+                    true
+                } else {
+                    false
+                }
+            },
+        );
+        component.touches_border = touches_border;
+        components.push(component);
+    }
+
+    Ok(Components {
+        min: origin,
+        max: region.max().into_index_vec() * BLOCK_NODES_1D as i16
+            + I16Vec3::splat(BLOCK_NODES_1D as i16 - 1),
+        labels,
+        components,
+    })
+}
@@ -0,0 +1,325 @@
+//! Contains a type to read a world's authentication data
+
+use futures::stream::BoxStream;
+use futures::stream::StreamExt;
+use futures::TryStreamExt;
+use log::LevelFilter;
+#[cfg(feature = "postgres")]
+use sqlx::postgres::{PgConnectOptions, PgPool, PgRow};
+#[cfg(feature = "sqlite")]
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqliteRow};
+use sqlx::{ConnectOptions, FromRow, Row};
+#[cfg(feature = "sqlite")]
+use std::path::Path;
+#[cfg(feature = "postgres")]
+use std::str::FromStr;
+
+const SQLITE_ALL_USERS: &str = "SELECT auth.id, name, password, last_login,
+ group_concat(privilege, ',') AS privileges
+ FROM auth LEFT JOIN user_privileges ON auth.id = user_privileges.id
+ GROUP BY auth.id";
+
+const POSTGRES_ALL_USERS: &str = "SELECT auth.id, name, password, last_login,
+ string_agg(privilege, ',') AS privileges
+ FROM auth LEFT JOIN user_privileges ON auth.id = user_privileges.id
+ GROUP BY auth.id";
+
+const SQLITE_USER: &str = "SELECT auth.id, name, password, last_login,
+ group_concat(privilege, ',') AS privileges
+ FROM auth LEFT JOIN user_privileges ON auth.id = user_privileges.id
+ WHERE name = ?
+ GROUP BY auth.id";
+
+const POSTGRES_USER: &str = "SELECT auth.id, name, password, last_login,
+ string_agg(privilege, ',') AS privileges
+ FROM auth LEFT JOIN user_privileges ON auth.id = user_privileges.id
+ WHERE name = $1
+ GROUP BY auth.id";
+
+/// An error in the underlying auth database
+#[derive(thiserror::Error, Debug)]
+pub enum AuthError {
+    #[error("Database error: {0}")]
+    /// sqlx based error. This covers Sqlite and Postgres errors.
+    SqlError(#[from] sqlx::Error),
+
+    /// There is no player with this name
+    #[error("User '{0}' does not exist")]
+    UserNonexistent(String),
+}
+
+impl AuthError {
+    /// Converts an SQL error to an auth error
+    ///
+    /// while converting `RowNotFound` to `UserNonexistent(name)`
+    fn from_sqlx_error(e: sqlx::Error, name: &str) -> AuthError {
+        if let sqlx::Error::RowNotFound = e {
+            AuthError::UserNonexistent(name.to_string())
+        } else {
+            AuthError::SqlError(e)
+        }
+    }
+}
+
+/// The authentication record of a single player
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthEntry {
+    /// Database-internal ID of this record
+    pub id: i64,
+    /// The player name
+    pub name: String,
+    /// The serialized SRP verifier, in the form `#1#<salt>#<verifier>`
+    ///
+    /// An empty string means that the player has no password.
+    pub password: String,
+    /// The privileges granted to this player, e.g. `interact` or `shout`
+    pub privileges: Vec<String>,
+    /// Unix timestamp of the last login
+    pub last_login: i64,
+}
+
+impl AuthEntry {
+    /// Returns true if the player has been granted `privilege`
+    pub fn has_privilege(&self, privilege: &str) -> bool {
+        self.privileges.iter().any(|p| p == privilege)
+    }
+}
+
+fn split_privileges(privileges: Option<String>) -> Vec<String> {
+    privileges
+        .map(|privileges| privileges.split(',').map(String::from).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(feature = "sqlite")]
+impl FromRow<'_, SqliteRow> for AuthEntry {
+    fn from_row(row: &SqliteRow) -> sqlx::Result<Self> {
+        Ok(AuthEntry {
+            id: row.try_get("id")?,
+            name: row.try_get("name")?,
+            password: row.try_get("password")?,
+            privileges: split_privileges(row.try_get("privileges")?),
+            last_login: row.try_get("last_login")?,
+        })
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl FromRow<'_, PgRow> for AuthEntry {
+    fn from_row(row: &PgRow) -> sqlx::Result<Self> {
+        Ok(AuthEntry {
+            id: row.try_get::<i32, _>("id")?.into(),
+            name: row.try_get("name")?,
+            password: row.try_get("password")?,
+            privileges: split_privileges(row.try_get("privileges")?),
+            last_login: row.try_get::<i32, _>("last_login")?.into(),
+        })
+    }
+}
+
+/// A handle to the authentication database of a world
+///
+/// Modifications require a handle obtained via
+/// [`World::get_mutable_auth`](`crate::World::get_mutable_auth`).
+/// The server should not be running while the auth data is modified.
+///
+/// ```
+/// use minetestworld::World;
+/// use futures::TryStreamExt;
+/// use async_std::task;
+///
+/// task::block_on(async {
+///     let auth = World::open("TestWorld").get_auth().await.unwrap();
+///     let users: Vec<_> = auth.all_users().try_collect().await.unwrap();
+///     assert_eq!(users.len(), 2);
+/// });
+/// ```
+pub enum AuthData {
+    /// This variant covers the SQLite database backend
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqlitePool),
+
+    /// This variant supports PostgreSQL as a backend
+    #[cfg(feature = "postgres")]
+    Postgres(PgPool),
+}
+
+impl AuthData {
+    #[cfg(feature = "sqlite")]
+    /// Connects to the "auth.sqlite" database.
+    ///
+    /// `read_only` has to be false to be able to modify the auth data.
+    pub async fn from_sqlite_file(
+        filename: impl AsRef<Path>,
+        read_only: bool,
+    ) -> Result<AuthData, AuthError> {
+        let opts = SqliteConnectOptions::new()
+            .immutable(read_only)
+            .filename(filename)
+            .log_statements(LevelFilter::Debug);
+        Ok(AuthData::Sqlite(SqlitePool::connect_with(opts).await?))
+    }
+
+    #[cfg(feature = "postgres")]
+    /// Connects to a Postgres database
+    pub async fn from_pg_connection_params(url: &str) -> Result<AuthData, AuthError> {
+        let opts = PgConnectOptions::from_str(url)?.log_statements(LevelFilter::Debug);
+        Ok(AuthData::Postgres(PgPool::connect_with(opts).await?))
+    }
+
+    /// Returns the records of all known players
+    pub fn all_users(&self) -> BoxStream<'_, Result<AuthEntry, AuthError>> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            AuthData::Sqlite(pool) => sqlx::query_as(SQLITE_ALL_USERS)
+                .fetch(pool)
+                .map_err(AuthError::SqlError)
+                .boxed(),
+            #[cfg(feature = "postgres")]
+            AuthData::Postgres(pool) => sqlx::query_as(POSTGRES_ALL_USERS)
+                .fetch(pool)
+                .map_err(AuthError::SqlError)
+                .boxed(),
+        }
+    }
+
+    /// Returns the record of the player called `name`
+    pub async fn get_user(&self, name: &str) -> Result<AuthEntry, AuthError> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            AuthData::Sqlite(pool) => sqlx::query_as(SQLITE_USER)
+                .bind(name)
+                .fetch_one(pool)
+                .await
+                .map_err(|e| AuthError::from_sqlx_error(e, name)),
+            #[cfg(feature = "postgres")]
+            AuthData::Postgres(pool) => sqlx::query_as(POSTGRES_USER)
+                .bind(name)
+                .fetch_one(pool)
+                .await
+                .map_err(|e| AuthError::from_sqlx_error(e, name)),
+        }
+    }
+
+    /// Grants `privilege` to the player called `name`
+    ///
+    /// Granting a privilege the player already has is not an error.
+    pub async fn grant_privilege(&self, name: &str, privilege: &str) -> Result<(), AuthError> {
+        let id = self.get_user(name).await?.id;
+        match self {
+            #[cfg(feature = "sqlite")]
+            AuthData::Sqlite(pool) => sqlx::query(
+                "INSERT INTO user_privileges (id, privilege) VALUES (?, ?) ON CONFLICT DO NOTHING",
+            )
+            .bind(id)
+            .bind(privilege)
+            .execute(pool)
+            .await
+            .map(|_| {})
+            .map_err(AuthError::SqlError),
+            #[cfg(feature = "postgres")]
+            AuthData::Postgres(pool) => sqlx::query(
+                "INSERT INTO user_privileges (id, privilege) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+            )
+            .bind(id as i32)
+            .bind(privilege)
+            .execute(pool)
+            .await
+            .map(|_| {})
+            .map_err(AuthError::SqlError),
+        }
+    }
+
+    /// Revokes `privilege` from the player called `name`
+    ///
+    /// Revoking a privilege the player does not have is not an error.
+    pub async fn revoke_privilege(&self, name: &str, privilege: &str) -> Result<(), AuthError> {
+        let id = self.get_user(name).await?.id;
+        match self {
+            #[cfg(feature = "sqlite")]
+            AuthData::Sqlite(pool) => {
+                sqlx::query("DELETE FROM user_privileges WHERE id = ? AND privilege = ?")
+                    .bind(id)
+                    .bind(privilege)
+                    .execute(pool)
+                    .await
+                    .map(|_| {})
+                    .map_err(AuthError::SqlError)
+            }
+            #[cfg(feature = "postgres")]
+            AuthData::Postgres(pool) => {
+                sqlx::query("DELETE FROM user_privileges WHERE id = $1 AND privilege = $2")
+                    .bind(id as i32)
+                    .bind(privilege)
+                    .execute(pool)
+                    .await
+                    .map(|_| {})
+                    .map_err(AuthError::SqlError)
+            }
+        }
+    }
+
+    /// Replaces the password of the player called `name`
+    ///
+    /// `password` is the serialized SRP verifier as the engine stores it,
+    /// i.e. `#1#<salt>#<verifier>` with both parts base64-encoded.
+    /// An empty string removes the password.
+    pub async fn set_password(&self, name: &str, password: &str) -> Result<(), AuthError> {
+        let result = match self {
+            #[cfg(feature = "sqlite")]
+            AuthData::Sqlite(pool) => sqlx::query("UPDATE auth SET password = ? WHERE name = ?")
+                .bind(password)
+                .bind(name)
+                .execute(pool)
+                .await?
+                .rows_affected(),
+            #[cfg(feature = "postgres")]
+            AuthData::Postgres(pool) => {
+                sqlx::query("UPDATE auth SET password = $1 WHERE name = $2")
+                    .bind(password)
+                    .bind(name)
+                    .execute(pool)
+                    .await?
+                    .rows_affected()
+            }
+        };
+        if result == 0 {
+            Err(AuthError::UserNonexistent(name.to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Removes the player called `name` along with all privileges
+    pub async fn delete_user(&self, name: &str) -> Result<(), AuthError> {
+        let id = self.get_user(name).await?.id;
+        match self {
+            #[cfg(feature = "sqlite")]
+            AuthData::Sqlite(pool) => {
+                let mut tx = pool.begin().await?;
+                sqlx::query("DELETE FROM user_privileges WHERE id = ?")
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query("DELETE FROM auth WHERE id = ?")
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+                Ok(tx.commit().await?)
+            }
+            #[cfg(feature = "postgres")]
+            AuthData::Postgres(pool) => {
+                let mut tx = pool.begin().await?;
+                sqlx::query("DELETE FROM user_privileges WHERE id = $1")
+                    .bind(id as i32)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query("DELETE FROM auth WHERE id = $1")
+                    .bind(id as i32)
+                    .execute(&mut *tx)
+                    .await?;
+                Ok(tx.commit().await?)
+            }
+        }
+    }
+}
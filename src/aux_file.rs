@@ -0,0 +1,182 @@
+//! Contains a type to read and edit the `key = value` files of a world
+//!
+//! Besides `world.mt`, the engine stores e.g. `env_meta.txt` and `map_meta.txt`
+//! in this format, and so do several mods. Use
+//! [`World::aux_file`](`crate::World::aux_file`) to open such a file.
+
+use async_std::fs;
+use async_std::io::WriteExt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Marks the start and the end of a value that spans multiple lines
+const MULTILINE_DELIMITER: &str = "\"\"\"";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Line {
+    Entry {
+        key: String,
+        value: String,
+    },
+    /// A comment, an empty line or anything else that is preserved verbatim
+    Other(String),
+}
+
+/// A file of `key = value` lines in the world directory
+///
+/// Lines that are no entries, like comments, are kept when the file is saved.
+/// Values spanning multiple lines are enclosed in `"""`, like the engine does.
+///
+/// ```
+/// use minetestworld::World;
+/// use async_std::task;
+///
+/// task::block_on(async {
+///     let world_mt = World::open("TestWorld").aux_file("world.mt").await.unwrap();
+///     assert_eq!(world_mt.get("backend"), Some("sqlite3"));
+/// });
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuxFile {
+    path: PathBuf,
+    lines: Vec<Line>,
+}
+
+impl AuxFile {
+    /// Reads the file at `path`
+    ///
+    /// A missing file results in an empty one, which is created when it is saved.
+    pub async fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let content = match fs::read_to_string(&path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(AuxFile {
+            path,
+            lines: parse(&content),
+        })
+    }
+
+    /// Returns the path of the file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the value of `key`, if present
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries()
+            .find(|&(k, _)| k == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Iterates over all entries as `(key, value)`, in the order of the file
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.lines.iter().filter_map(|line| match line {
+            Line::Entry { key, value } => Some((key.as_str(), value.as_str())),
+            Line::Other(_) => None,
+        })
+    }
+
+    /// Sets `key` to `value`, replacing a previous value in place
+    ///
+    /// New keys are appended. Keys have to be non-empty and may neither contain
+    /// `=` nor line breaks, nor start with `#`, nor have surrounding whitespace.
+    /// Otherwise, an [`InvalidInput`](`io::ErrorKind::InvalidInput`) error is returned.
+    pub fn set(&mut self, key: &str, value: &str) -> io::Result<()> {
+        if key.is_empty()
+            || key.contains(['=', '\n', '\r'])
+            || key.starts_with('#')
+            || key.trim() != key
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("'{key}' is not a valid key"),
+            ));
+        }
+        for line in &mut self.lines {
+            if let Line::Entry { key: k, value: v } = line {
+                if k == key {
+                    *v = value.to_string();
+                    return Ok(());
+                }
+            }
+        }
+        self.lines.push(Line::Entry {
+            key: key.to_string(),
+            value: value.to_string(),
+        });
+        Ok(())
+    }
+
+    /// Removes `key`, returning its value
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let index = self
+            .lines
+            .iter()
+            .position(|line| matches!(line, Line::Entry { key: k, .. } if k == key))?;
+        match self.lines.remove(index) {
+            Line::Entry { value, .. } => Some(value),
+            Line::Other(_) => unreachable!(),
+        }
+    }
+
+    /// Writes the file back
+    ///
+    /// The content is written to a temporary file first, which then replaces the file,
+    /// so that a crash never leaves a partially written file behind.
+    pub async fn save(&self) -> io::Result<()> {
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let mut file = fs::File::create(&tmp_path).await?;
+        file.write_all(self.to_string().as_bytes()).await?;
+        file.sync_all().await?;
+        fs::rename(&tmp_path, &self.path).await
+    }
+}
+
+impl std::fmt::Display for AuxFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for line in &self.lines {
+            match line {
+                Line::Entry { key, value } if value.contains('\n') => writeln!(
+                    f,
+                    "{key} = {MULTILINE_DELIMITER}\n{value}\n{MULTILINE_DELIMITER}"
+                )?,
+                Line::Entry { key, value } => writeln!(f, "{key} = {value}")?,
+                Line::Other(line) => writeln!(f, "{line}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse(content: &str) -> Vec<Line> {
+    let mut lines = vec![];
+    let mut source = content.lines();
+    while let Some(line) = source.next() {
+        let entry = line
+            .split_once('=')
+            .filter(|_| !line.trim_start().starts_with('#'));
+        let Some((key, value)) = entry else {
+            lines.push(Line::Other(line.to_string()));
+            continue;
+        };
+        let value = value.trim();
+        let value = if value == MULTILINE_DELIMITER {
+            let value_lines: Vec<_> = source
+                .by_ref()
+                .take_while(|line| line.trim_end() != MULTILINE_DELIMITER)
+                .collect();
+            value_lines.join("\n")
+        } else {
+            value.to_string()
+        };
+        lines.push(Line::Entry {
+            key: key.trim().to_string(),
+            value,
+        });
+    }
+    lines
+}
@@ -0,0 +1,421 @@
+//! Contains a builder to queue many node edits and apply them at once
+//!
+//! Calling [`VoxelManip`](`crate::MapEdit`) methods node by node awaits once per node.
+//! An [`EditPlan`] instead collects whole operations and applies them mapblock by mapblock,
+//! so that every affected mapblock is loaded, edited and written exactly once.
+
+use glam::{I16Vec3, U16Vec3};
+use std::collections::{HashMap, HashSet};
+
+use crate::inventory::InventoryList;
+use crate::map_block::{MapBlock, Node, NodeVar};
+use crate::node_def::{MirrorAxis, NodeDefProvider};
+use crate::positions::{BlockKey, BlockPos, SplitPos};
+use crate::{MapDataError, MapEdit, BLOCK_NODES_1D, NODE_BITS_1D};
+
+/// A box of nodes, including both corners
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct NodeBox {
+    pub(crate) min: I16Vec3,
+    pub(crate) max: I16Vec3,
+}
+
+impl NodeBox {
+    pub(crate) fn new(a: I16Vec3, b: I16Vec3) -> Self {
+        NodeBox {
+            min: a.min(b),
+            max: a.max(b),
+        }
+    }
+
+    /// Returns the box of all nodes of the mapblock at `blockpos`
+    pub(crate) fn of_block(blockpos: BlockPos) -> Self {
+        let min = blockpos.into_index_vec() << NODE_BITS_1D;
+        NodeBox {
+            min,
+            max: min + I16Vec3::splat(BLOCK_NODES_1D as i16 - 1),
+        }
+    }
+
+    pub(crate) fn intersection(&self, other: &NodeBox) -> Option<NodeBox> {
+        let min = self.min.max(other.min);
+        let max = self.max.min(other.max);
+        min.cmple(max).all().then_some(NodeBox { min, max })
+    }
+
+    /// Iterates all node positions, with x changing fastest
+    pub(crate) fn iter(&self) -> impl Iterator<Item = I16Vec3> {
+        let NodeBox { min, max } = *self;
+        (min.z..=max.z).flat_map(move |z| {
+            (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| I16Vec3::new(x, y, z)))
+        })
+    }
+
+    /// Returns all mapblocks this box touches
+    pub(crate) fn blocks(&self) -> impl Iterator<Item = BlockPos> {
+        let min = self.min >> NODE_BITS_1D;
+        let max = self.max >> NODE_BITS_1D;
+        (min.z..=max.z).flat_map(move |z| {
+            (min.y..=max.y).flat_map(move |y| {
+                (min.x..=max.x).map(move |x| BlockPos::from_index_vec(I16Vec3::new(x, y, z)))
+            })
+        })
+    }
+}
+
+/// Sets the content of all nodes of `block` within `area` to `content`, keeping param1 and param2
+///
+/// If `area` covers the whole mapblock, the palette is replaced by `content` alone
+/// instead of setting each node. Returns true if the mapblock has been modified.
+pub(crate) fn fill_block(
+    block: &mut MapBlock,
+    blockpos: BlockPos,
+    area: &NodeBox,
+    content: &[u8],
+) -> bool {
+    let block_area = NodeBox::of_block(blockpos);
+    let Some(part) = area.intersection(&block_area) else {
+        return false;
+    };
+    if part.min == block_area.min && part.max == block_area.max {
+        block.name_id_mappings = HashMap::from([(0, content.to_vec())]);
+        block.param0.fill(0);
+    } else {
+        let content_id = block.get_or_create_content_id(content);
+        for pos in part.iter() {
+            block.set_content(pos.split().1, content_id);
+        }
+    }
+    true
+}
+
+/// The metadata of a node in a [`Schematic`], see [`NodeMetadata`](`crate::map_block::NodeMetadata`)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchematicMetadata {
+    /// Metadata variables
+    pub vars: Vec<NodeVar>,
+    /// Inventory lists
+    pub inventory: Vec<InventoryList>,
+}
+
+/// A cuboid arrangement of nodes that can be placed into the world
+#[derive(Debug, Clone)]
+pub struct Schematic {
+    size: U16Vec3,
+    nodes: Vec<Option<Node>>,
+    metadata: HashMap<usize, SchematicMetadata>,
+}
+
+impl Schematic {
+    /// Creates a schematic of `size` that leaves the world unchanged everywhere
+    pub fn new(size: U16Vec3) -> Self {
+        Schematic {
+            size,
+            nodes: vec![None; size.x as usize * size.y as usize * size.z as usize],
+            metadata: HashMap::new(),
+        }
+    }
+
+    /// Reads the box between `a` and `b` from the world, including node metadata
+    pub async fn from_world(vm: &MapEdit, a: I16Vec3, b: I16Vec3) -> Result<Self, MapDataError> {
+        let area = NodeBox::new(a, b);
+        let size = (area.max.as_ivec3() - area.min.as_ivec3() + 1).as_u16vec3();
+        let mut schematic = Schematic::new(size);
+        for pos in area.iter() {
+            let offset = (pos - area.min).as_u16vec3();
+            schematic.set_node(offset, Some(vm.get_node(pos).await?));
+            if let Some(metadata) = vm.get_metadata(pos).await? {
+                schematic.set_metadata(
+                    offset,
+                    Some(SchematicMetadata {
+                        vars: metadata.vars,
+                        inventory: metadata.inventory,
+                    }),
+                );
+            }
+        }
+        Ok(schematic)
+    }
+
+    /// Returns the extent of the schematic in each dimension
+    pub fn size(&self) -> U16Vec3 {
+        self.size
+    }
+
+    fn index(&self, pos: U16Vec3) -> Option<usize> {
+        pos.cmplt(self.size).all().then(|| {
+            pos.x as usize
+                + self.size.x as usize * (pos.y as usize + self.size.y as usize * pos.z as usize)
+        })
+    }
+
+    /// Returns the node at `pos`, relative to the schematic's origin
+    ///
+    /// `None` means that the node in the world is kept when placing the schematic.
+    pub fn get_node(&self, pos: U16Vec3) -> Option<&Node> {
+        self.index(pos).and_then(|i| self.nodes[i].as_ref())
+    }
+
+    /// Sets the node at `pos`, relative to the schematic's origin
+    ///
+    /// Positions outside of the schematic are ignored.
+    pub fn set_node(&mut self, pos: U16Vec3, node: Option<Node>) {
+        if let Some(i) = self.index(pos) {
+            self.nodes[i] = node;
+        }
+    }
+
+    /// Returns the metadata at `pos`, relative to the schematic's origin
+    pub fn get_metadata(&self, pos: U16Vec3) -> Option<&SchematicMetadata> {
+        self.index(pos).and_then(|i| self.metadata.get(&i))
+    }
+
+    /// Sets the metadata at `pos`, relative to the schematic's origin
+    ///
+    /// When placing the schematic, the metadata replaces the metadata in the world,
+    /// provided that there is a node at `pos`. Positions outside of the schematic are ignored.
+    pub fn set_metadata(&mut self, pos: U16Vec3, metadata: Option<SchematicMetadata>) {
+        if let Some(i) = self.index(pos) {
+            match metadata {
+                Some(metadata) => self.metadata.insert(i, metadata),
+                None => self.metadata.remove(&i),
+            };
+        }
+    }
+
+    /// Returns a copy of the schematic, turned around the y axis by `quarter_turns` steps of 90°
+    ///
+    /// The schematic is turned clockwise as seen from above, i.e. from +z towards +x.
+    /// The `param2` of directional nodes is turned along, as far as their `paramtype2`
+    /// is known to `node_defs`, see [`ParamType2::rotate_y`](`crate::node_def::ParamType2::rotate_y`).
+    pub fn rotated_y(&self, quarter_turns: u8, node_defs: &dyn NodeDefProvider) -> Schematic {
+        let turns = quarter_turns % 4;
+        let turn = |pos: U16Vec3, size: U16Vec3| U16Vec3::new(pos.z, pos.y, size.x - 1 - pos.x);
+        let mut size = self.size;
+        for _ in 0..turns {
+            size = U16Vec3::new(size.z, size.y, size.x);
+        }
+        let mut rotated = Schematic::new(size);
+        for z in 0..self.size.z {
+            for y in 0..self.size.y {
+                for x in 0..self.size.x {
+                    let pos = U16Vec3::new(x, y, z);
+                    let (mut new_pos, mut new_size) = (pos, self.size);
+                    for _ in 0..turns {
+                        new_pos = turn(new_pos, new_size);
+                        new_size = U16Vec3::new(new_size.z, new_size.y, new_size.x);
+                    }
+                    let Some(node) = self.get_node(pos) else {
+                        continue;
+                    };
+                    let mut node = node.clone();
+                    if let Some(def) = node_defs.node_def(&node.param0) {
+                        node.param2 = def.paramtype2.rotate_y(node.param2, turns);
+                    }
+                    rotated.set_node(new_pos, Some(node));
+                    rotated.set_metadata(new_pos, self.get_metadata(pos).cloned());
+                }
+            }
+        }
+        rotated
+    }
+
+    /// Returns a copy of the schematic, mirrored along `axis`
+    ///
+    /// The `param2` of directional nodes is mirrored along, as far as their `paramtype2`
+    /// is known to `node_defs`, see [`ParamType2::mirror`](`crate::node_def::ParamType2::mirror`).
+    pub fn mirrored(&self, axis: MirrorAxis, node_defs: &dyn NodeDefProvider) -> Schematic {
+        let mut mirrored = Schematic::new(self.size);
+        for z in 0..self.size.z {
+            for y in 0..self.size.y {
+                for x in 0..self.size.x {
+                    let pos = U16Vec3::new(x, y, z);
+                    let new_pos = match axis {
+                        MirrorAxis::X => U16Vec3::new(self.size.x - 1 - x, y, z),
+                        MirrorAxis::Z => U16Vec3::new(x, y, self.size.z - 1 - z),
+                    };
+                    let Some(node) = self.get_node(pos) else {
+                        continue;
+                    };
+                    let mut node = node.clone();
+                    if let Some(def) = node_defs.node_def(&node.param0) {
+                        node.param2 = def.paramtype2.mirror(node.param2, axis);
+                    }
+                    mirrored.set_node(new_pos, Some(node));
+                    mirrored.set_metadata(new_pos, self.get_metadata(pos).cloned());
+                }
+            }
+        }
+        mirrored
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Operation {
+    Fill {
+        area: NodeBox,
+        content: Vec<u8>,
+    },
+    Place {
+        area: NodeBox,
+        schematic: Schematic,
+    },
+    Replace {
+        area: NodeBox,
+        from: Vec<u8>,
+        to: Vec<u8>,
+    },
+}
+
+impl Operation {
+    fn area(&self) -> &NodeBox {
+        match self {
+            Operation::Fill { area, .. }
+            | Operation::Place { area, .. }
+            | Operation::Replace { area, .. } => area,
+        }
+    }
+
+    /// Applies the part of this operation that lies within `block`
+    ///
+    /// Returns true if the mapblock has been modified.
+    fn apply(&self, block: &mut MapBlock, blockpos: BlockPos) -> bool {
+        let Some(part) = self.area().intersection(&NodeBox::of_block(blockpos)) else {
+            return false;
+        };
+        match self {
+            Operation::Fill { area, content } => fill_block(block, blockpos, area, content),
+            Operation::Place { area, schematic } => {
+                let mut content_ids = HashMap::new();
+                let mut modified = false;
+                for pos in part.iter() {
+                    let Some(node) = schematic.get_node((pos - area.min).as_u16vec3()) else {
+                        continue;
+                    };
+                    let content_id = *content_ids
+                        .entry(&node.param0)
+                        .or_insert_with(|| block.get_or_create_content_id(&node.param0));
+                    let node_pos = pos.split().1;
+                    block.set_content(node_pos, content_id);
+                    block.set_param1(node_pos, node.param1);
+                    block.set_param2(node_pos, node.param2);
+                    if let Some(metadata) = schematic.get_metadata((pos - area.min).as_u16vec3()) {
+                        block.remove_node_metadata(node_pos);
+                        let block_metadata = block.node_metadata_mut(node_pos);
+                        block_metadata.vars = metadata.vars.clone();
+                        block_metadata.inventory = metadata.inventory.clone();
+                    }
+                    modified = true;
+                }
+                modified
+            }
+            Operation::Replace { from, to, .. } => {
+                let Some(from_id) = block.get_content_id(from) else {
+                    return false;
+                };
+                let mut to_id = None;
+                for pos in part.iter() {
+                    let node_pos = pos.split().1;
+                    if block.param0[usize::from(node_pos)] == from_id {
+                        let to_id =
+                            *to_id.get_or_insert_with(|| block.get_or_create_content_id(to));
+                        block.set_content(node_pos, to_id);
+                    }
+                }
+                to_id.is_some()
+            }
+        }
+    }
+}
+
+/// A queue of edit operations that are applied together
+///
+/// The operations are applied in the order they were queued,
+/// so later operations overwrite earlier ones.
+///
+/// ```no_run
+/// use minetestworld::edit_plan::EditPlan;
+/// use minetestworld::World;
+/// use glam::I16Vec3;
+/// use async_std::task;
+///
+/// task::block_on(async {
+///     let vm = World::open("TestWorld").get_voxel_manip(true).await.unwrap();
+///     EditPlan::new()
+///         .fill(I16Vec3::new(0, 0, 0), I16Vec3::new(40, 3, 40), b"default:stone")
+///         .replace(I16Vec3::new(-100, -100, -100), I16Vec3::new(100, 100, 100), b"default:dirt", b"air")
+///         .execute(&vm)
+///         .await
+///         .unwrap();
+/// });
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EditPlan {
+    operations: Vec<Operation>,
+}
+
+impl EditPlan {
+    /// Creates an empty plan
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues setting the content of all nodes in the box between `a` and `b` to `content`
+    ///
+    /// The box includes both corners. param1 and param2 are kept.
+    pub fn fill(mut self, a: I16Vec3, b: I16Vec3, content: &[u8]) -> Self {
+        self.operations.push(Operation::Fill {
+            area: NodeBox::new(a, b),
+            content: content.to_vec(),
+        });
+        self
+    }
+
+    /// Queues placing `schematic` with its origin at `pos`
+    pub fn place_schematic(mut self, pos: I16Vec3, schematic: Schematic) -> Self {
+        if schematic.size.cmpgt(U16Vec3::ZERO).all() {
+            let max = pos.saturating_add((schematic.size - U16Vec3::ONE).as_i16vec3());
+            self.operations.push(Operation::Place {
+                area: NodeBox::new(pos, max),
+                schematic,
+            });
+        }
+        self
+    }
+
+    /// Queues replacing the content `from` by `to` in the box between `a` and `b`
+    pub fn replace(mut self, a: I16Vec3, b: I16Vec3, from: &[u8], to: &[u8]) -> Self {
+        self.operations.push(Operation::Replace {
+            area: NodeBox::new(a, b),
+            from: from.to_vec(),
+            to: to.to_vec(),
+        });
+        self
+    }
+
+    /// Applies all queued operations and commits `vm`
+    ///
+    /// Every affected mapblock is visited once, applying all operations that touch it.
+    /// Missing mapblocks are created. Returns the number of visited mapblocks.
+    pub async fn execute(self, vm: &MapEdit) -> Result<usize, MapDataError> {
+        let mut blocks: Vec<BlockPos> = self
+            .operations
+            .iter()
+            .flat_map(|op| op.area().blocks())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        blocks.sort_unstable_by_key(|pos| BlockKey::from(*pos));
+
+        for &blockpos in &blocks {
+            vm.edit_mapblock(blockpos, |block| {
+                self.operations
+                    .iter()
+                    .fold(false, |modified, op| op.apply(block, blockpos) | modified)
+            })
+            .await?;
+        }
+        vm.commit().await?;
+        Ok(blocks.len())
+    }
+}
@@ -0,0 +1,345 @@
+//! Functions to move map data between a backend and other storage formats
+//!
+//! A region can be exported into a directory, where every mapblock is stored verbatim
+//! as `x.y.z.bin` next to a `manifest.txt`. Such a directory is well suited for being
+//! version-controlled, and can be imported into any world again.
+//!
+//! For search engines, [`index_records`] flattens a region into one record per node.
+//!
+//! With the `checksums` feature, the manifest records a SHA-256 checksum per block,
+//! which is verified on import. The `signatures` feature additionally allows
+//! signing the manifest with an ed25519 key, so that such a directory can be
+//! distributed as a trusted map update.
+
+use async_std::fs;
+#[cfg(feature = "signatures")]
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use futures::stream::{self, BoxStream, StreamExt};
+use futures::TryStreamExt;
+use glam::I16Vec3;
+#[cfg(feature = "checksums")]
+use sha2::{Digest, Sha256};
+use std::io;
+use std::path::Path;
+
+use crate::map_block::CONTENT_IGNORE;
+use crate::positions::{BlockArea, BlockKey, BlockPos, NodeIndex, NodePos};
+use crate::{MapBlock, MapData, MapDataError};
+
+const MANIFEST: &str = "manifest.txt";
+#[cfg(feature = "signatures")]
+const SIGNATURE_KEY: &str = "signature = ";
+
+fn invalid_manifest(message: impl Into<String>) -> MapDataError {
+    MapDataError::IoError(io::Error::new(io::ErrorKind::InvalidData, message.into()))
+}
+
+fn block_file_stem(pos: BlockPos) -> String {
+    let index = pos.into_index_vec();
+    format!("{}.{}.{}", index.x, index.y, index.z)
+}
+
+fn parse_index_vec(s: &str) -> Result<I16Vec3, MapDataError> {
+    let mut components = s.trim().split('.').map(|c| c.trim().parse::<i16>());
+    match (
+        components.next(),
+        components.next(),
+        components.next(),
+        components.next(),
+    ) {
+        (Some(Ok(x)), Some(Ok(y)), Some(Ok(z)), None) => Ok(I16Vec3::new(x, y, z)),
+        _ => Err(invalid_manifest(format!("Malformed block position '{s}'"))),
+    }
+}
+
+#[cfg(feature = "checksums")]
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(feature = "signatures")]
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(feature = "checksums")]
+fn checksum(data: &[u8]) -> String {
+    to_hex(&Sha256::digest(data))
+}
+
+/// The content of a parsed manifest
+struct Manifest {
+    region: BlockArea,
+    /// Block positions with their checksum, if recorded
+    blocks: Vec<(BlockPos, Option<String>)>,
+}
+
+fn parse_manifest(manifest: &str) -> Result<Manifest, MapDataError> {
+    let mut region_min = None;
+    let mut region_max = None;
+    let mut blocks = vec![];
+    for line in manifest.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        match key.trim() {
+            "region_min" => region_min = Some(parse_index_vec(value)?),
+            "region_max" => region_max = Some(parse_index_vec(value)?),
+            "block" => {
+                let mut parts = value.split_whitespace();
+                let index = parse_index_vec(parts.next().unwrap_or_default())?;
+                let pos = BlockPos::try_from_index_vec(index)
+                    .map_err(|_| invalid_manifest(format!("Block {index} is out of range")))?;
+                let checksum = parts.next().map(String::from);
+                blocks.push((pos, checksum));
+            }
+            "signature" => {}
+            other => return Err(invalid_manifest(format!("Unknown manifest key '{other}'"))),
+        }
+    }
+    let (Some(region_min), Some(region_max)) = (region_min, region_max) else {
+        return Err(invalid_manifest("The manifest lacks the region"));
+    };
+    Ok(Manifest {
+        region: BlockArea::new(
+            BlockPos::from_index_vec(region_min),
+            BlockPos::from_index_vec(region_max),
+        ),
+        blocks,
+    })
+}
+
+/// Writes all mapblocks of `region` into `dir`
+///
+/// Each block blob is stored unaltered as `x.y.z.bin`, where `x`, `y` and `z`
+/// are the block indices. A `manifest.txt` lists the region and all written blocks.
+/// With the `checksums` feature, each block is listed along with its SHA-256 checksum.
+/// `dir` is created if it does not exist yet.
+///
+/// Returns the number of exported blocks.
+pub async fn blocks_to_dir(
+    map: &MapData,
+    region: BlockArea,
+    dir: impl AsRef<Path>,
+) -> Result<usize, MapDataError> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir).await?;
+
+    // Sorted, so that the manifest stays stable across exports
+    let mut positions: Vec<_> = map
+        .mapblock_positions_in(region)
+        .await
+        .try_collect()
+        .await?;
+    positions.sort_unstable_by_key(|pos| BlockKey::from(*pos));
+
+    let mut manifest = format!(
+        "region_min = {}\nregion_max = {}\n",
+        block_file_stem(region.min()),
+        block_file_stem(region.max())
+    );
+    for &pos in &positions {
+        let data = map.get_block_data(pos).await?;
+        let stem = block_file_stem(pos);
+        #[cfg(feature = "checksums")]
+        manifest.push_str(&format!("block = {stem} {}\n", checksum(&data)));
+        #[cfg(not(feature = "checksums"))]
+        manifest.push_str(&format!("block = {stem}\n"));
+        fs::write(dir.join(format!("{stem}.bin")), data).await?;
+    }
+    fs::write(dir.join(MANIFEST), manifest).await?;
+
+    Ok(positions.len())
+}
+
+/// Writes all mapblocks exported by [`blocks_to_dir`] back into `map`
+///
+/// Existing blocks at the same positions are replaced.
+/// All blocks are read into memory first and written at once,
+/// see [`MapData::set_mapblocks_data`], so a failure leaves `map` untouched.
+/// With the `checksums` feature, all recorded checksums are verified
+/// before the first block is written.
+/// Returns the region recorded in the manifest.
+pub async fn blocks_from_dir(
+    map: &MapData,
+    dir: impl AsRef<Path>,
+) -> Result<BlockArea, MapDataError> {
+    let dir = dir.as_ref();
+    let manifest = parse_manifest(&fs::read_to_string(dir.join(MANIFEST)).await?)?;
+    import_blocks(map, dir, manifest).await
+}
+
+async fn import_blocks(
+    map: &MapData,
+    dir: &Path,
+    manifest: Manifest,
+) -> Result<BlockArea, MapDataError> {
+    // Every file is read exactly once, so that the verified bytes are the written ones
+    let mut blocks = Vec::with_capacity(manifest.blocks.len());
+    for (pos, expected) in manifest.blocks {
+        let data = fs::read(dir.join(format!("{}.bin", block_file_stem(pos)))).await?;
+        #[cfg(not(feature = "checksums"))]
+        let _ = expected;
+        #[cfg(feature = "checksums")]
+        if expected.is_some_and(|expected| checksum(&data) != expected) {
+            return Err(invalid_manifest(format!(
+                "Checksum mismatch for block {}",
+                block_file_stem(pos)
+            )));
+        }
+        blocks.push((pos, data));
+    }
+
+    map.set_mapblocks_data(blocks.iter().map(|(pos, data)| (*pos, data.as_slice())))
+        .await?;
+    Ok(manifest.region)
+}
+
+/// Signs the manifest in `dir` with `key`
+///
+/// The signature covers the manifest, so the exported blocks are only
+/// protected if the manifest contains their checksums.
+/// A previous signature is replaced.
+#[cfg(feature = "signatures")]
+pub async fn sign_dir(dir: impl AsRef<Path>, key: &SigningKey) -> Result<(), MapDataError> {
+    let path = dir.as_ref().join(MANIFEST);
+    let mut manifest = unsigned_part(&fs::read_to_string(&path).await?).to_string();
+    let signature = key.sign(manifest.as_bytes());
+    manifest.push_str(&format!(
+        "{SIGNATURE_KEY}{}\n",
+        to_hex(&signature.to_bytes())
+    ));
+    Ok(fs::write(path, manifest).await?)
+}
+
+/// Returns the manifest without its signature line
+///
+/// Only a signature on the last line counts, so that the signed part can't contain
+/// text that looks like a signature.
+#[cfg(feature = "signatures")]
+fn unsigned_part(manifest: &str) -> &str {
+    let trimmed = manifest.trim_end_matches('\n');
+    let last_line = trimmed.rfind('\n').map_or(0, |newline| newline + 1);
+    if trimmed[last_line..].starts_with(SIGNATURE_KEY) {
+        &manifest[..last_line]
+    } else {
+        manifest
+    }
+}
+
+/// Like [`blocks_from_dir`], but only imports blocks signed by `key`
+///
+/// Fails before modifying `map` if the signature is missing or invalid,
+/// or if a block is listed without a checksum or with a wrong one.
+#[cfg(feature = "signatures")]
+pub async fn verified_blocks_from_dir(
+    map: &MapData,
+    dir: impl AsRef<Path>,
+    key: &VerifyingKey,
+) -> Result<BlockArea, MapDataError> {
+    let dir = dir.as_ref();
+    let content = fs::read_to_string(dir.join(MANIFEST)).await?;
+    let unsigned = unsigned_part(&content);
+    let signature = content[unsigned.len()..]
+        .strip_prefix(SIGNATURE_KEY)
+        .and_then(|hex| from_hex(hex.trim()))
+        .and_then(|bytes| Signature::from_slice(&bytes).ok())
+        .ok_or_else(|| invalid_manifest("The manifest is not signed"))?;
+    key.verify(unsigned.as_bytes(), &signature)
+        .map_err(|_| invalid_manifest("The manifest signature is invalid"))?;
+
+    let manifest = parse_manifest(unsigned)?;
+    if manifest
+        .blocks
+        .iter()
+        .any(|(_, checksum)| checksum.is_none())
+    {
+        return Err(invalid_manifest(
+            "A signed manifest lists a block without checksum",
+        ));
+    }
+    import_blocks(map, dir, manifest).await
+}
+
+/// A single node, flattened for bulk-loading into a search index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexRecord {
+    /// The world position of the node
+    pub pos: I16Vec3,
+    /// The content name, e.g. `default:chest`
+    pub content: String,
+    /// The `param1` of the node
+    ///
+    /// For lit nodes, this is the day light in the lower
+    /// and the night light in the upper four bits.
+    pub light: u8,
+    /// Whether the node has metadata, like the inventory of a chest
+    pub has_meta: bool,
+    /// The mod that defines the content, i.e. the part of the name before the `:`
+    ///
+    /// Empty for contents without a mod prefix.
+    pub mod_prefix: String,
+}
+
+/// Decodes the records of all nodes in a serialized mapblock, except air and ignore
+fn block_records(pos: BlockPos, data: &[u8]) -> Result<Vec<IndexRecord>, MapDataError> {
+    let block = MapBlock::from_data(data)?;
+    let mut records = vec![];
+    for (index, &content_id) in block.param0.iter().enumerate() {
+        let content = block.content_from_id(content_id);
+        if content == b"air" || content == CONTENT_IGNORE {
+            continue;
+        }
+        // There are only 4096 nodes in a mapblock
+        let node_pos = NodePos::from(NodeIndex::try_from(index as u16).unwrap());
+        let content = String::from_utf8_lossy(content).into_owned();
+        records.push(IndexRecord {
+            pos: pos.join(node_pos),
+            mod_prefix: content
+                .split_once(':')
+                .map(|(prefix, _)| prefix.to_string())
+                .unwrap_or_default(),
+            content,
+            light: block.param1[index],
+            has_meta: block.node_metadata_at(node_pos).is_some(),
+        });
+    }
+    Ok(records)
+}
+
+/// Yields a record for every node within `region`, except for air and ignore
+///
+/// The records are grouped by mapblock, in no particular order.
+///
+/// ```
+/// use minetestworld::{export, World};
+/// use minetestworld::positions::{BlockArea, BlockPos};
+/// use futures::TryStreamExt;
+/// use glam::I16Vec3;
+/// use async_std::task;
+///
+/// task::block_on(async {
+///     let map = World::open("TestWorld").get_map_data().await.unwrap();
+///     let pos = BlockPos::from_index_vec(I16Vec3::new(-13, -8, 2));
+///     let records: Vec<_> = export::index_records(&map, BlockArea::new(pos, pos))
+///         .await
+///         .try_collect()
+///         .await
+///         .unwrap();
+///     assert!(records.iter().all(|record| record.content != "air"));
+/// });
+/// ```
+pub async fn index_records(
+    map: &MapData,
+    region: BlockArea,
+) -> BoxStream<'_, Result<IndexRecord, MapDataError>> {
+    map.mapblock_positions_in(region)
+        .await
+        .and_then(move |pos| async move { block_records(pos, &map.get_block_data(pos).await?) })
+        .map_ok(|records| stream::iter(records.into_iter().map(Ok)))
+        .try_flatten()
+        .boxed()
+}
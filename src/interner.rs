@@ -0,0 +1,110 @@
+//! Contains an opt-in interner for content names
+//!
+//! Every [`Node`](`crate::Node`) owns a copy of its content name. Analyses that keep
+//! many nodes around end up storing the same few names millions of times.
+//! A [`ContentInterner`] hands out one shared allocation per name instead,
+//! and [`SharedNode`] is a node that refers to such a shared name.
+//!
+//! ```
+//! use minetestworld::interner::ContentInterner;
+//! use std::sync::Arc;
+//!
+//! let interner = ContentInterner::new();
+//! let a = interner.intern(b"default:stone");
+//! let b = interner.intern(b"default:stone");
+//! assert!(Arc::ptr_eq(&a, &b));
+//! assert_eq!(interner.len(), 1);
+//! ```
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use crate::map_block::NodeRef;
+use crate::Node;
+
+/// A content name shared between all nodes of the same content
+pub type ContentName = Arc<[u8]>;
+
+/// A set of content names that are stored only once
+///
+/// The interner can be shared between threads, e.g. by wrapping it into an [`Arc`].
+/// Names are never removed, so it should only live as long as the analysis using it.
+#[derive(Debug, Default)]
+pub struct ContentInterner {
+    names: Mutex<HashSet<ContentName>>,
+}
+
+impl ContentInterner {
+    /// Creates an empty interner
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the shared name that is equal to `content`
+    ///
+    /// Only the first call for a name allocates.
+    pub fn intern(&self, content: &[u8]) -> ContentName {
+        // A poisoned set is still consistent, as inserting cannot be interrupted halfway
+        let mut names = self.names.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(name) = names.get(content) {
+            return name.clone();
+        }
+        let name: ContentName = content.into();
+        names.insert(name.clone());
+        name
+    }
+
+    /// Returns the number of distinct names
+    pub fn len(&self) -> usize {
+        self.names.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// Returns true if no name has been interned yet
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A [`Node`] whose content name is shared
+///
+/// Cloning a `SharedNode` does not copy the content name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SharedNode {
+    /// The content name, see [`Node::param0`]
+    pub param0: ContentName,
+    /// See [`Node::param1`]
+    pub param1: u8,
+    /// See [`Node::param2`]
+    pub param2: u8,
+}
+
+impl SharedNode {
+    /// Interns the content name of `node`
+    pub fn from_node(node: &Node, interner: &ContentInterner) -> Self {
+        SharedNode {
+            param0: interner.intern(&node.param0),
+            param1: node.param1,
+            param2: node.param2,
+        }
+    }
+
+    /// Copies the node into an owned [`Node`]
+    pub fn to_node(&self) -> Node {
+        Node {
+            param0: self.param0.to_vec(),
+            param1: self.param1,
+            param2: self.param2,
+        }
+    }
+}
+
+impl<const LENGTH: u16> NodeRef<'_, LENGTH> {
+    /// Converts the node into a [`SharedNode`], using `interner` for the content name
+    pub fn to_shared_node(&self, interner: &ContentInterner) -> SharedNode {
+        SharedNode {
+            param0: interner.intern(self.content),
+            param1: self.param1,
+            param2: self.param2,
+        }
+    }
+}
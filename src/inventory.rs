@@ -0,0 +1,353 @@
+//! Contains the inventory types shared by players and node metadata
+//!
+//! Inventories are stored in a line-based text format, both in player files
+//! and in the metadata of nodes like chests.
+//! Each slot holds an [`ItemStack`] in its serialized form.
+
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::iter::Peekable;
+use std::str::{Chars, FromStr};
+
+use crate::json;
+
+/// Starts the serialized metadata of an item stack
+const ITEM_METADATA_START: char = '\u{1}';
+/// Separates a key from its value in item stack metadata
+const ITEM_METADATA_KV_DELIM: char = '\u{2}';
+/// Terminates a key-value pair in item stack metadata
+const ITEM_METADATA_PAIR_DELIM: char = '\u{3}';
+
+/// An error while parsing an [`ItemStack`]
+#[derive(thiserror::Error, Debug)]
+pub enum ItemStackError {
+    /// The item stack could not be parsed
+    #[error("'{0}' is not a valid item stack")]
+    Malformed(String),
+}
+
+/// A named inventory list, like `main` or `craft`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InventoryList {
+    /// The name of this list
+    pub name: String,
+    /// The width of this list when displayed, 0 if unspecified
+    pub width: u32,
+    /// The serialized item stacks, one per slot
+    ///
+    /// Empty slots are represented by an empty string.
+    /// Use [`InventoryList::stacks`] to parse them.
+    pub items: Vec<String>,
+}
+
+impl InventoryList {
+    /// Returns the parsed item stacks, one per slot
+    pub fn stacks(&self) -> impl Iterator<Item = Result<ItemStack, ItemStackError>> + '_ {
+        self.items.iter().map(|item| item.parse())
+    }
+}
+
+/// A stack of items in an inventory slot
+///
+/// Its serialized form is `<name> [<count> [<wear> [<metadata>]]]`,
+/// where trailing default values are omitted.
+///
+/// ```
+/// use minetestworld::inventory::ItemStack;
+///
+/// let stack: ItemStack = "default:pick_steel 1 3000".parse().unwrap();
+/// assert_eq!(stack.name, "default:pick_steel");
+/// assert_eq!(stack.wear, 3000);
+/// assert_eq!(stack.to_string(), "default:pick_steel 1 3000");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ItemStack {
+    /// The [itemstring](https://wiki.minetest.net/Itemstrings) of the item,
+    /// empty for an empty slot
+    pub name: String,
+    /// The number of items in this stack
+    pub count: u16,
+    /// The wear of a tool, from 0 (new) to 65535
+    pub wear: u16,
+    /// Custom per-stack data, e.g. the text of a book or a description
+    pub metadata: HashMap<String, String>,
+}
+
+impl ItemStack {
+    /// Creates a stack of `count` items without wear and metadata
+    pub fn new(name: impl Into<String>, count: u16) -> Self {
+        ItemStack {
+            name: name.into(),
+            count,
+            ..Default::default()
+        }
+    }
+
+    /// Returns true if there are no items in this stack
+    pub fn is_empty(&self) -> bool {
+        self.name.is_empty() || self.count == 0
+    }
+}
+
+/// Reads a space-separated field, which is quoted as a JSON string if necessary
+///
+/// Returns `Ok(None)` at the end of the input.
+fn parse_item_field(chars: &mut Peekable<Chars>) -> Result<Option<String>, ()> {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+    match chars.peek() {
+        None => Ok(None),
+        Some('"') => parse_item_string(chars).map(Some).ok_or(()),
+        Some(_) => {
+            let mut field = String::new();
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                field.push(c);
+            }
+            Ok(Some(field))
+        }
+    }
+}
+
+fn parse_item_metadata(serialized: &str) -> HashMap<String, String> {
+    match serialized.strip_prefix(ITEM_METADATA_START) {
+        Some(pairs) => pairs
+            .split(ITEM_METADATA_PAIR_DELIM)
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair
+                    .split_once(ITEM_METADATA_KV_DELIM)
+                    .unwrap_or((pair, ""));
+                (key.to_string(), value.to_string())
+            })
+            .collect(),
+        // Legacy metadata is a single string, which the engine stores under the empty key
+        None if !serialized.is_empty() => HashMap::from([(String::new(), serialized.to_string())]),
+        None => HashMap::new(),
+    }
+}
+
+impl FromStr for ItemStack {
+    type Err = ItemStackError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |_| ItemStackError::Malformed(s.to_string());
+        let mut chars = s.chars().peekable();
+        let Some(name) = parse_item_field(&mut chars).map_err(error)? else {
+            return Ok(ItemStack::default());
+        };
+        let mut stack = ItemStack::new(name, 1);
+        if let Some(count) = parse_item_field(&mut chars).map_err(error)? {
+            stack.count = count.parse().map_err(|_| error(()))?;
+        }
+        if let Some(wear) = parse_item_field(&mut chars).map_err(error)? {
+            stack.wear = wear.parse().map_err(|_| error(()))?;
+        }
+        if let Some(metadata) = parse_item_field(&mut chars).map_err(error)? {
+            stack.metadata = parse_item_metadata(&metadata);
+        }
+        if parse_item_field(&mut chars).map_err(error)?.is_some() {
+            return Err(error(()));
+        }
+        Ok(stack)
+    }
+}
+
+/// Writes `field`, quoted as a JSON string if it contains special characters
+fn serialize_item_field(field: &str, dest: &mut String) {
+    // Same condition as the engine's `serializeJsonStringIfNeeded`,
+    // except that an empty field has to be quoted to be read back
+    if field.is_empty() || field.bytes().any(|b| b <= b' ' || b >= 0x7f || b == b'"') {
+        serialize_item_string(field, dest);
+    } else {
+        dest.push_str(field);
+    }
+}
+
+/// Parses a quoted item field, including the enclosing quotes
+///
+/// The engine escapes each byte outside of printable ASCII on its own,
+/// so `\u00XX` stands for a single byte rather than a code point.
+fn parse_item_string(chars: &mut Peekable<Chars>) -> Option<String> {
+    if chars.next() != Some('"') {
+        return None;
+    }
+    let mut bytes = vec![];
+    loop {
+        match chars.next()? {
+            '"' => return String::from_utf8(bytes).ok(),
+            '\\' => match chars.next()? {
+                'u' => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    // Like the engine, only keep the lowest byte
+                    bytes.push(u16::from_str_radix(&hex, 16).ok()? as u8);
+                }
+                // All other escapes stand for ASCII characters
+                c => bytes.push(json::unescape(c)? as u8),
+            },
+            c => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+}
+
+/// Writes a quoted item field like the engine's `serializeJsonString`
+fn serialize_item_string(field: &str, dest: &mut String) {
+    dest.push('"');
+    for byte in field.bytes() {
+        match byte {
+            b'"' => dest.push_str("\\\""),
+            b'\\' => dest.push_str("\\\\"),
+            8 => dest.push_str("\\b"),
+            12 => dest.push_str("\\f"),
+            b'\n' => dest.push_str("\\n"),
+            b'\r' => dest.push_str("\\r"),
+            b'\t' => dest.push_str("\\t"),
+            b' '..=b'~' => dest.push(byte as char),
+            byte => {
+                let _ = write!(dest, "\\u{byte:04x}");
+            }
+        }
+    }
+    dest.push('"');
+}
+
+impl fmt::Display for ItemStack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let mut serialized = String::new();
+        serialize_item_field(&self.name, &mut serialized);
+        let has_metadata = !self.metadata.is_empty();
+        if self.count != 1 || self.wear != 0 || has_metadata {
+            let _ = write!(serialized, " {}", self.count);
+        }
+        if self.wear != 0 || has_metadata {
+            let _ = write!(serialized, " {}", self.wear);
+        }
+        if has_metadata {
+            let mut entries: Vec<_> = self.metadata.iter().collect();
+            entries.sort_unstable();
+            let mut metadata = String::from(ITEM_METADATA_START);
+            for (key, value) in entries {
+                metadata.push_str(key);
+                metadata.push(ITEM_METADATA_KV_DELIM);
+                metadata.push_str(value);
+                metadata.push(ITEM_METADATA_PAIR_DELIM);
+            }
+            serialized.push(' ');
+            serialize_item_field(&metadata, &mut serialized);
+        }
+        f.write_str(&serialized)
+    }
+}
+
+/// Turns a serialized item stack with invalid UTF-8 into an equivalent string
+///
+/// The engine takes the bytes of an unquoted field verbatim and copies raw bytes
+/// inside a quoted field, so quoting such fields and escaping their non-ASCII
+/// bytes as `\u00XX` yields the same item stack when the engine reads it back.
+pub(crate) fn item_from_bytes(item: &[u8]) -> String {
+    let mut escaped = String::new();
+    let mut rest = item;
+    while !rest.is_empty() {
+        let field_len = if rest[0] == b'"' {
+            let mut escape = false;
+            rest.iter()
+                .skip(1)
+                .position(|&b| {
+                    let end = !escape && b == b'"';
+                    escape = !escape && b == b'\\';
+                    end
+                })
+                .map_or(rest.len(), |end| end + 2)
+        } else {
+            rest.iter()
+                .position(|b| b.is_ascii_whitespace())
+                .unwrap_or(rest.len())
+                .max(1)
+        };
+        let (field, tail) = rest.split_at(field_len);
+        match std::str::from_utf8(field) {
+            Ok(field) => escaped.push_str(field),
+            Err(_) => {
+                let (quoted, inner) = match field.strip_prefix(b"\"") {
+                    Some(inner) => (true, inner.strip_suffix(b"\"").unwrap_or(inner)),
+                    None => (false, field),
+                };
+                escaped.push('"');
+                for &byte in inner {
+                    match byte {
+                        // Inside quotes, these already start or are part of an escape sequence
+                        b'"' | b'\\' if !quoted => {
+                            escaped.push('\\');
+                            escaped.push(byte as char);
+                        }
+                        byte if byte.is_ascii() => escaped.push(byte as char),
+                        byte => {
+                            let _ = write!(escaped, "\\u{byte:04x}");
+                        }
+                    }
+                }
+                escaped.push('"');
+            }
+        }
+        rest = tail;
+    }
+    escaped
+}
+
+/// Parses inventory lists up to and including the `EndInventory` line
+///
+/// On failure, a description of the problem is returned.
+pub(crate) fn parse_inventory<'a>(
+    lines: impl Iterator<Item = &'a str>,
+) -> Result<Vec<InventoryList>, String> {
+    let mut inventory = vec![];
+    let mut current_list: Option<InventoryList> = None;
+    for line in lines {
+        let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+        match (keyword, current_list.as_mut()) {
+            ("List", None) => {
+                let (name, _size) = rest.split_once(' ').unwrap_or((rest, "0"));
+                current_list = Some(InventoryList {
+                    name: name.to_string(),
+                    width: 0,
+                    items: vec![],
+                });
+            }
+            ("Width", Some(list)) => {
+                list.width = rest
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("'{rest}' is not a valid width"))?
+            }
+            ("Item", Some(list)) => list.items.push(rest.to_string()),
+            ("Empty", Some(list)) => list.items.push(String::new()),
+            ("EndInventoryList", Some(_)) => inventory.extend(current_list.take()),
+            ("EndInventory", None) => return Ok(inventory),
+            _ => return Err(format!("unexpected line '{line}' in inventory")),
+        }
+    }
+    Err(String::from("the inventory is not terminated"))
+}
+
+/// Serializes inventory lists, including the final `EndInventory` line
+pub(crate) fn serialize_inventory(inventory: &[InventoryList]) -> String {
+    let mut content = String::new();
+    // Writing to a String can't fail
+    for list in inventory {
+        let _ = writeln!(content, "List {} {}", list.name, list.items.len());
+        let _ = writeln!(content, "Width {}", list.width);
+        for item in &list.items {
+            if item.is_empty() {
+                content.push_str("Empty\n");
+            } else {
+                let _ = writeln!(content, "Item {item}");
+            }
+        }
+        content.push_str("EndInventoryList\n");
+    }
+    content.push_str("EndInventory\n");
+    content
+}
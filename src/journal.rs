@@ -0,0 +1,245 @@
+//! Contains an intent log that makes multi-block commits recoverable
+//!
+//! Before a [`MapEdit`](`crate::MapEdit`) with a [journal](`crate::MapEdit::set_journal`)
+//! writes its modified mapblocks, it records both their previous and their new data
+//! in a journal file. The file is only removed once all blocks have been written.
+//! If the process gets killed in between, the journal is still present when the map
+//! is opened next time. Opening the map checks for the journal at its usual location,
+//! see [`Journal::for_map_file`], and completes the interrupted commit.
+//! [`Journal::recover`] does the same for other locations, and can also roll it back.
+//!
+//! ```
+//! use minetestworld::journal::{Journal, Recovery};
+//! use minetestworld::World;
+//! use async_std::task;
+//!
+//! task::block_on(async {
+//!     let map = World::open("TestWorld").get_map_data().await.unwrap();
+//!     let journal = Journal::new("TestWorld/map.journal");
+//!     // Without a journal file, there is nothing to recover
+//!     assert_eq!(journal.recover(&map, Recovery::Complete).await.unwrap(), 0);
+//! });
+//! ```
+
+use async_std::fs;
+use async_std::io::WriteExt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::positions::{BlockKey, BlockPos};
+use crate::{MapData, MapDataError};
+
+const MAGIC: &[u8] = b"MTJOURNAL\x01";
+
+/// How to deal with a commit that has been interrupted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recovery {
+    /// Writes the new data of all journaled mapblocks
+    Complete,
+    /// Restores the previous data of all journaled mapblocks
+    ///
+    /// Mapblocks that did not exist before the commit are removed again.
+    RollBack,
+}
+
+/// A single mapblock write recorded in the journal
+pub(crate) struct JournalEntry {
+    pub pos: BlockPos,
+    /// The data before the commit, `None` if the mapblock did not exist
+    pub old_data: Option<Vec<u8>>,
+    pub new_data: Vec<u8>,
+}
+
+fn malformed_journal() -> MapDataError {
+    MapDataError::IoError(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "The commit journal is malformed",
+    ))
+}
+
+fn encode(entries: &[JournalEntry]) -> Vec<u8> {
+    let mut buffer = MAGIC.to_vec();
+    buffer.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+    for entry in entries {
+        buffer.extend_from_slice(&i64::from(BlockKey::from(entry.pos)).to_be_bytes());
+        match &entry.old_data {
+            Some(data) => {
+                buffer.push(1);
+                buffer.extend_from_slice(&(data.len() as u32).to_be_bytes());
+                buffer.extend_from_slice(data);
+            }
+            None => buffer.push(0),
+        }
+        buffer.extend_from_slice(&(entry.new_data.len() as u32).to_be_bytes());
+        buffer.extend_from_slice(&entry.new_data);
+    }
+    buffer
+}
+
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8], MapDataError> {
+    if data.len() < len {
+        return Err(malformed_journal());
+    }
+    let (head, tail) = data.split_at(len);
+    *data = tail;
+    Ok(head)
+}
+
+fn take_u32(data: &mut &[u8]) -> Result<u32, MapDataError> {
+    // take() guarantees the length
+    Ok(u32::from_be_bytes(take(data, 4)?.try_into().unwrap()))
+}
+
+fn take_blob(data: &mut &[u8]) -> Result<Vec<u8>, MapDataError> {
+    let len = take_u32(data)? as usize;
+    Ok(take(data, len)?.to_vec())
+}
+
+fn decode(mut data: &[u8]) -> Result<Vec<JournalEntry>, MapDataError> {
+    if take(&mut data, MAGIC.len())? != MAGIC {
+        return Err(malformed_journal());
+    }
+    let count = take_u32(&mut data)?;
+    let mut entries = vec![];
+    for _ in 0..count {
+        let key = i64::from_be_bytes(take(&mut data, 8)?.try_into().unwrap());
+        let pos = BlockKey::try_from(key)
+            .map(BlockPos::from)
+            .map_err(|_| malformed_journal())?;
+        let old_data = match take(&mut data, 1)?[0] {
+            0 => None,
+            1 => Some(take_blob(&mut data)?),
+            _ => return Err(malformed_journal()),
+        };
+        entries.push(JournalEntry {
+            pos,
+            old_data,
+            new_data: take_blob(&mut data)?,
+        });
+    }
+    if !data.is_empty() {
+        return Err(malformed_journal());
+    }
+    Ok(entries)
+}
+
+/// A commit journal stored in a file
+///
+/// The journal should live next to the map it protects,
+/// and the same path has to be used when recovering.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Journal {
+    path: PathBuf,
+}
+
+impl Journal {
+    /// Creates a journal stored at `path`
+    ///
+    /// The file is only created while a commit is in progress.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Journal {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Returns the journal that belongs to the map stored in `map_file`
+    ///
+    /// It is stored next to the map, e.g. `map.journal` for `map.sqlite`.
+    /// [`MapData::from_sqlite_file`] and [`World`](`crate::World`) check this journal
+    /// when they open a map.
+    pub fn for_map_file(map_file: impl AsRef<Path>) -> Self {
+        Self::new(map_file.as_ref().with_extension("journal"))
+    }
+
+    /// Returns the path of the journal file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns true if the journal file exists, i.e. a commit has been interrupted
+    pub async fn is_pending(&self) -> bool {
+        async_std::path::Path::new(&self.path).exists().await
+    }
+
+    /// Records `entries` durably, before any of them is written to the map
+    ///
+    /// The journal is written to a temporary file first and then renamed,
+    /// so that a crash never leaves a partially written journal behind.
+    pub(crate) async fn write(&self, entries: &[JournalEntry]) -> Result<(), MapDataError> {
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let mut file = fs::File::create(&tmp_path).await?;
+        file.write_all(&encode(entries)).await?;
+        file.sync_all().await?;
+        fs::rename(&tmp_path, &self.path).await?;
+        // The rename is only durable once the directory has been synced as well
+        #[cfg(unix)]
+        {
+            let dir = match self.path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir,
+                _ => Path::new("."),
+            };
+            fs::File::open(dir).await?.sync_all().await?;
+        }
+        Ok(())
+    }
+
+    /// Removes the journal after all entries have been written
+    pub(crate) async fn clear(&self) -> Result<(), MapDataError> {
+        Ok(fs::remove_file(&self.path).await?)
+    }
+
+    /// Repairs `map` after an interrupted commit
+    ///
+    /// Does nothing if there is no journal. Otherwise, all journaled mapblocks
+    /// are written according to `recovery` and the journal is removed.
+    /// Recovering is idempotent, so it may be interrupted and repeated.
+    ///
+    /// Returns the number of repaired mapblocks.
+    pub async fn recover(&self, map: &MapData, recovery: Recovery) -> Result<usize, MapDataError> {
+        let data = match fs::read(&self.path).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+        let entries = decode(&data)?;
+        for entry in &entries {
+            match (recovery, &entry.old_data) {
+                (Recovery::Complete, _) => map.set_block_data(entry.pos, &entry.new_data).await?,
+                (Recovery::RollBack, Some(old_data)) => {
+                    map.set_block_data(entry.pos, old_data).await?
+                }
+                (Recovery::RollBack, None) => {
+                    map.delete_mapblock(entry.pos).await?;
+                }
+            }
+        }
+        self.clear().await?;
+        Ok(entries.len())
+    }
+
+    /// Deals with an interrupted commit while `map` is being opened
+    ///
+    /// A writable map is repaired by completing the commit, because the journal is written
+    /// in full before any of its mapblocks. A read-only map cannot be repaired, so opening
+    /// it fails with [`MapDataError::PendingJournal`]. If completing fails, the journal
+    /// is kept and the error is returned.
+    pub(crate) async fn check_on_open(
+        &self,
+        map: &MapData,
+        read_only: bool,
+    ) -> Result<(), MapDataError> {
+        if !self.is_pending().await {
+            return Ok(());
+        }
+        if read_only {
+            return Err(MapDataError::PendingJournal(self.path.clone()));
+        }
+        let count = self.recover(map, Recovery::Complete).await?;
+        log::warn!(
+            "Completed an interrupted commit of {count} mapblocks from {}",
+            self.path.display()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,85 @@
+//! Escaping and unescaping of JSON string literals
+//!
+//! Used for the extended attributes of players, the JSON report and the
+//! JSON variant of WorldEdit schematics. Item stacks are quoted slightly differently
+//! by the engine, see [`ItemStack`](`crate::inventory::ItemStack`).
+
+use std::fmt::Write;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Returns the character of a single-character escape sequence like `\n`
+pub(crate) fn unescape(escaped: char) -> Option<char> {
+    Some(match escaped {
+        '"' => '"',
+        '\\' => '\\',
+        '/' => '/',
+        'b' => '\u{8}',
+        'f' => '\u{c}',
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        _ => return None,
+    })
+}
+
+/// Decodes a `\u` escape sequence, whose `\u` has already been consumed
+///
+/// `next` yields the following characters. Characters outside of the
+/// basic multilingual plane are escaped as a UTF-16 surrogate pair, i.e. as two
+/// consecutive `\u` escapes, which are combined here. Lone surrogates are rejected.
+pub(crate) fn unescape_unicode(mut next: impl FnMut() -> Option<char>) -> Option<char> {
+    let code = read_hex(&mut next)?;
+    if !(0xd800..0xdc00).contains(&code) {
+        return char::from_u32(code);
+    }
+    if next()? != '\\' || next()? != 'u' {
+        return None;
+    }
+    let low = read_hex(&mut next)?;
+    if !(0xdc00..0xe000).contains(&low) {
+        return None;
+    }
+    char::from_u32(0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00))
+}
+
+/// Reads the four hexadecimal digits of a `\u` escape sequence
+fn read_hex(next: &mut impl FnMut() -> Option<char>) -> Option<u32> {
+    let hex: String = (0..4).map(|_| next()).collect::<Option<_>>()?;
+    u32::from_str_radix(&hex, 16).ok()
+}
+
+/// Parses a JSON string literal, including the enclosing quotes
+pub(crate) fn parse_string(chars: &mut Peekable<Chars>) -> Option<String> {
+    if chars.next() != Some('"') {
+        return None;
+    }
+    let mut string = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(string),
+            '\\' => match chars.next()? {
+                'u' => string.push(unescape_unicode(|| chars.next())?),
+                escaped => string.push(unescape(escaped)?),
+            },
+            c => string.push(c),
+        }
+    }
+}
+
+/// Writes `string` as a JSON string literal
+pub(crate) fn serialize_string(string: &str, dest: &mut String) {
+    dest.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => dest.push_str("\\\""),
+            '\\' => dest.push_str("\\\\"),
+            '\n' => dest.push_str("\\n"),
+            c if c.is_control() => {
+                let _ = write!(dest, "\\u{:04x}", c as u32);
+            }
+            c => dest.push(c),
+        }
+    }
+    dest.push('"');
+}
@@ -47,11 +47,38 @@ extern crate async_std;
 #[cfg(feature = "smartstring")]
 extern crate smartstring;
 
+pub mod analysis;
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+pub mod auth;
+pub mod aux_file;
+pub mod edit_plan;
+pub mod export;
+pub mod interner;
+pub mod inventory;
+pub mod journal;
+mod json;
+pub mod lighting;
+pub mod liquids;
 pub mod map_block;
 pub mod map_data;
+pub mod mapgen;
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+pub mod mod_storage;
+pub mod node_def;
+pub mod param2;
+pub mod pathfinding;
+pub mod players;
 pub mod positions;
+#[cfg(feature = "render")]
+pub mod render;
+pub mod report;
+pub mod scrub;
+pub mod shapes;
+pub mod sponge;
+pub mod sync;
 pub mod voxel_manip;
 pub mod world;
+pub mod worldedit;
 
 use std::ops::Range;
 
@@ -132,6 +159,26 @@ pub const WORLD_BLOCKS_MAX: i16 = (1 << (BLOCK_BITS_1D - 1)) - 1;
 /// Valid block index range for all dimensions
 pub const WORLD_BLOCKS_RANGE: Range<i16> = WORLD_BLOCKS_MIN..(1 << (BLOCK_BITS_1D - 1));
 
+/// The engine does not generate nodes beyond this distance from the origin by default
+///
+/// This is the default of the `mapgen_limit` setting.
+pub const MAP_GENERATION_LIMIT: i16 = 31000;
+
+/// The engine never loads mapblocks that reach beyond this distance from the origin
+///
+/// This is the upper bound of the `mapgen_limit` setting.
+pub const MAX_MAP_GENERATION_LIMIT: i16 = 31007;
+
+/// Positions of players and objects are stored in this unit, which is a tenth of a node
+pub(crate) const BS: f32 = 10.0;
+
+/// Range of block indices that the engine loads, see [`MAX_MAP_GENERATION_LIMIT`]
+///
+/// Like in the engine, a block is within the limit if its index is,
+/// after dividing the limit by the block size.
+pub const LIMIT_BLOCKS_RANGE: Range<i16> = -(MAX_MAP_GENERATION_LIMIT / BLOCK_NODES_1D as i16)
+    ..(MAX_MAP_GENERATION_LIMIT / BLOCK_NODES_1D as i16 + 1);
+
 const DIAGONAL_KEY_STRIDE: i64 =
     1 + WORLD_BLOCKS_1D as i64 + WORLD_BLOCKS_1D as i64 * WORLD_BLOCKS_1D as i64;
 
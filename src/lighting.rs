@@ -0,0 +1,131 @@
+//! Contains the computation of the light that the engine keeps in `param1`
+//!
+//! The engine stores two kinds of light per node, see [`LightBank`](`crate::map_block::LightBank`):
+//! sunlight and the light emitted by nodes. It does not relight mapblocks that claim
+//! to be [lit completely](`crate::MapBlock::is_lighting_complete`), so edits have to
+//! be relit to avoid dark or glowing spots.
+
+use std::collections::VecDeque;
+
+use glam::I16Vec3;
+
+use crate::edit_plan::NodeBox;
+use crate::map_block::CONTENT_IGNORE;
+use crate::node_def::{NodeDef, NodeDefProvider};
+use crate::voxel_manip::VoxelData;
+
+/// The light of direct sunlight, which nodes cannot emit
+pub const LIGHT_SUN: u8 = 15;
+
+/// The strongest light a node can emit
+pub const LIGHT_MAX: u8 = 14;
+
+const NEIGHBORS: [I16Vec3; 6] = [
+    I16Vec3::X,
+    I16Vec3::NEG_X,
+    I16Vec3::Y,
+    I16Vec3::NEG_Y,
+    I16Vec3::Z,
+    I16Vec3::NEG_Z,
+];
+
+/// Returns the definition relevant for light, treating unknown nodes as opaque
+fn light_def(node_defs: &dyn NodeDefProvider, content: &[u8]) -> NodeDef {
+    node_defs.node_def(content).unwrap_or(if content == b"air" {
+        NodeDef::AIR
+    } else {
+        NodeDef::default()
+    })
+}
+
+/// Recomputes the light of all nodes of `data` within the box between `a` and `b`
+///
+/// Sunlight falls down through nodes that [propagate it](`NodeDef::sunlight_propagates`)
+/// without being dimmed. Sunlight and the light of [light sources](`NodeDef::light_source`)
+/// spread through all nodes that [store light](`NodeDef::uses_param1`), losing one level
+/// per node. Nodes without a definition are opaque, except for `air`.
+///
+/// The nodes of `data` outside of the box keep their light, which shines into the box.
+/// The box is sunlit from above where the node above it has sunlight or is not generated,
+/// or if `data` ends at the top of the box.
+/// Only the `param1` of nodes within the box that store light is changed.
+pub fn calc_lighting(
+    data: &mut VoxelData,
+    a: I16Vec3,
+    b: I16Vec3,
+    node_defs: &dyn NodeDefProvider,
+) {
+    let Some(inner) = NodeBox::new(a, b).intersection(&NodeBox::new(data.min, data.max)) else {
+        return;
+    };
+    let defs: Vec<NodeDef> = data
+        .palette
+        .iter()
+        .map(|content| light_def(node_defs, content))
+        .collect();
+    let ignore = data.content_id(CONTENT_IGNORE);
+    let def_at = |data: &VoxelData, index: usize| defs[data.content[index] as usize];
+    let is_inner = |pos: I16Vec3| pos.cmpge(inner.min).all() && pos.cmple(inner.max).all();
+
+    // The nodes outside of the box keep their light, the box is lit from scratch
+    let mut day = vec![0; data.volume()];
+    let mut night = vec![0; data.volume()];
+    for index in 0..data.volume() {
+        let def = def_at(data, index);
+        let stored = if def.uses_param1() && !is_inner(data.position(index)) {
+            data.param1[index]
+        } else {
+            0
+        };
+        let source = def.light_source.min(LIGHT_MAX);
+        day[index] = (stored & 0x0f).max(source);
+        night[index] = (stored >> 4).max(source);
+    }
+
+    for z in inner.min.z..=inner.max.z {
+        for x in inner.min.x..=inner.max.x {
+            let above = inner.max.y.checked_add(1).map(|y| I16Vec3::new(x, y, z));
+            let mut sunlit = match above.and_then(|above| data.index(above)) {
+                Some(index) => Some(data.content[index]) == ignore || day[index] == LIGHT_SUN,
+                None => true,
+            };
+            for y in (inner.min.y..=inner.max.y).rev() {
+                // The position is within the box
+                let index = data.index(I16Vec3::new(x, y, z)).unwrap();
+                let def = def_at(data, index);
+                sunlit &= def.sunlight_propagates && def.uses_param1();
+                if !sunlit {
+                    break;
+                }
+                day[index] = LIGHT_SUN;
+            }
+        }
+    }
+
+    for light in [&mut day, &mut night] {
+        let mut queue: VecDeque<usize> = (0..data.volume()).filter(|&i| light[i] > 1).collect();
+        while let Some(index) = queue.pop_front() {
+            let pos = data.position(index);
+            let spread = light[index] - 1;
+            for neighbor in NEIGHBORS.map(|dir| pos.saturating_add(dir)) {
+                if !is_inner(neighbor) {
+                    continue;
+                }
+                // The neighbor is within the box
+                let neighbor = data.index(neighbor).unwrap();
+                if light[neighbor] < spread && def_at(data, neighbor).uses_param1() {
+                    light[neighbor] = spread;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+    }
+
+    for pos in inner.iter() {
+        // The position is within the box
+        let index = data.index(pos).unwrap();
+        if def_at(data, index).uses_param1() {
+            data.param1[index] = day[index] | (night[index] << 4);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Contains the settling of liquids around edited areas
+//!
+//! The engine only lets liquids flow where something changed while the map was loaded.
+//! Liquids next to air that was carved into the map offline stay as they are, e.g. as
+//! walls of water at the border of a dug out ocean floor.
+
+use std::collections::BinaryHeap;
+
+use glam::I16Vec3;
+
+use crate::edit_plan::NodeBox;
+use crate::voxel_manip::VoxelData;
+
+/// The level of a liquid source, one above the highest level of a flowing liquid
+pub const LIQUID_LEVEL_SOURCE: u8 = 8;
+
+/// The bits of `param2` that hold the level of a flowing liquid
+pub const LIQUID_LEVEL_MASK: u8 = 0x07;
+
+/// The bit of `param2` that marks a flowing liquid as falling down
+pub const LIQUID_FLOW_DOWN_MASK: u8 = 0x08;
+
+const HORIZONTAL: [I16Vec3; 4] = [I16Vec3::X, I16Vec3::NEG_X, I16Vec3::Z, I16Vec3::NEG_Z];
+
+/// A liquid as defined by the game, e.g. with `liquid_alternative_source`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Liquid {
+    /// The content name of the source nodes
+    pub source: Vec<u8>,
+    /// The content name of the flowing nodes
+    pub flowing: Vec<u8>,
+    /// How many nodes the liquid flows away from a source, see `liquid_range` in Lua
+    pub range: u8,
+}
+
+impl Liquid {
+    /// Creates a liquid with the default range of 8
+    pub fn new(source: &[u8], flowing: &[u8]) -> Self {
+        Liquid {
+            source: source.to_vec(),
+            flowing: flowing.to_vec(),
+            range: 8,
+        }
+    }
+}
+
+/// Lets `liquids` flow into the air of `data` within the box between `a` and `b`
+///
+/// The result is what the engine ends up with once the liquids have settled:
+/// Flowing nodes within the box are recomputed from the sources and the flowing nodes
+/// around them, so that they fill adjacent air and dry up where nothing feeds them.
+/// Liquids fall down through air, and only spread sideways on top of other nodes
+/// or from a source. The level and the falling flag are kept in `param2`,
+/// see [`LIQUID_LEVEL_MASK`] and [`LIQUID_FLOW_DOWN_MASK`].
+///
+/// Liquids only flow into `air`. Nodes of `data` outside of the box are left as they are.
+pub fn update_liquids(data: &mut VoxelData, a: I16Vec3, b: I16Vec3, liquids: &[Liquid]) {
+    let Some(inner) = NodeBox::new(a, b).intersection(&NodeBox::new(data.min, data.max)) else {
+        return;
+    };
+    let is_inner = |pos: I16Vec3| pos.cmpge(inner.min).all() && pos.cmple(inner.max).all();
+    let air = data.get_or_create_content_id(b"air");
+
+    for liquid in liquids {
+        let source = data.content_id(&liquid.source);
+        let flowing = data.get_or_create_content_id(&liquid.flowing);
+        let min_level = LIQUID_LEVEL_SOURCE.saturating_sub(liquid.range);
+
+        // The flowing liquid within the box is recomputed from scratch
+        let mut levels = vec![None; data.volume()];
+        let mut queue = BinaryHeap::new();
+        for index in 0..data.volume() {
+            let content = data.content[index];
+            let level = if Some(content) == source {
+                LIQUID_LEVEL_SOURCE
+            } else if content != flowing {
+                continue;
+            } else if is_inner(data.position(index)) {
+                data.content[index] = air;
+                data.param2[index] = 0;
+                continue;
+            } else if data.param2[index] & LIQUID_FLOW_DOWN_MASK != 0 {
+                LIQUID_LEVEL_SOURCE - 1
+            } else {
+                data.param2[index] & LIQUID_LEVEL_MASK
+            };
+            levels[index] = Some(level);
+            queue.push((level, index));
+        }
+
+        let floodable = |data: &VoxelData, pos: I16Vec3| {
+            data.index(pos)
+                .filter(|&index| is_inner(pos) && data.content[index] == air)
+        };
+        while let Some((level, index)) = queue.pop() {
+            if levels[index] != Some(level) {
+                continue;
+            }
+            let pos = data.position(index);
+            let below = pos.y.checked_sub(1).map(|y| I16Vec3::new(pos.x, y, pos.z));
+            let falls = below.and_then(|below| floodable(data, below));
+            let falls = falls.or_else(|| {
+                below.and_then(|below| data.index(below)).filter(|&below| {
+                    data.content[below] == flowing
+                        && levels[below].is_some_and(|level| level < LIQUID_LEVEL_SOURCE - 1)
+                        && is_inner(data.position(below))
+                })
+            });
+            if let Some(below) = falls {
+                data.content[below] = flowing;
+                data.param2[below] = LIQUID_FLOW_DOWN_MASK | (LIQUID_LEVEL_SOURCE - 1);
+                levels[below] = Some(LIQUID_LEVEL_SOURCE - 1);
+                queue.push((LIQUID_LEVEL_SOURCE - 1, below));
+            }
+            // Liquid on top of air or flowing liquid keeps falling instead of spreading
+            let resting = !below
+                .and_then(|below| data.index(below))
+                .is_some_and(|below| data.content[below] == air || data.content[below] == flowing);
+            let Some(spread) = level.checked_sub(1).filter(|&spread| spread >= min_level) else {
+                continue;
+            };
+            if level != LIQUID_LEVEL_SOURCE && !resting {
+                continue;
+            }
+            for dir in HORIZONTAL {
+                let neighbor = pos.saturating_add(dir);
+                if !is_inner(neighbor) {
+                    continue;
+                }
+                // The neighbor is within the box
+                let neighbor = data.index(neighbor).unwrap();
+                let content = data.content[neighbor];
+                let improves = content == air
+                    || (content == flowing && levels[neighbor].is_some_and(|level| level < spread));
+                if improves {
+                    data.content[neighbor] = flowing;
+                    data.param2[neighbor] = spread;
+                    levels[neighbor] = Some(spread);
+                    queue.push((spread, neighbor));
+                }
+            }
+        }
+    }
+}
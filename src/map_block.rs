@@ -1,23 +1,44 @@
 //! Contains data types and constants to work with MapBlocks
 
+use std::borrow::Cow;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::io::{Read, Write};
 
-use glam::I16Vec3;
+use flate2::bufread::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use glam::{I16Vec3, Vec3};
 
-use crate::positions::{BlockPos, NodeIndex, NodePos, SplitPos};
-use crate::BLOCK_NODES_3D_U;
+use crate::inventory::{item_from_bytes, parse_inventory, serialize_inventory, InventoryList};
+use crate::positions::{BlockPos, BlockSize, NodeIndex, SizedNodeIndex, SizedNodePos, SplitPos};
+use crate::{BLOCK_NODES_1D, BLOCK_NODES_3D_U, BS};
 
 #[cfg(feature = "smartstring")]
 type String = smartstring::SmartString<smartstring::LazyCompact>;
 
+/// The map format version written by [`MapBlock::to_binary`]
+pub const SERIALIZE_VERSION_LATEST: u8 = 29;
+
+/// The oldest map format version that can be read and written, used by servers before 5.5
+pub const SERIALIZE_VERSION_OLDEST: u8 = 28;
+
 /// This content type string refers to an unknown content type
 pub const CONTENT_UNKNOWN: &[u8] = b"unknown";
 
 /// This content type string refers to a node that has not yet been generated
 pub const CONTENT_IGNORE: &[u8] = b"ignore";
 
+/// The [`MapBlock::timestamp`] of mapblocks that have never been saved by the engine
+pub const TIMESTAMP_UNDEFINED: u32 = u32::MAX;
+
+const FLAG_IS_UNDERGROUND: u8 = 0x01;
+const FLAG_DAY_NIGHT_DIFFERS: u8 = 0x02;
+const FLAG_NOT_GENERATED: u8 = 0x08;
+
+/// The [`StaticObject::type_id`] of Lua entities, e.g. mobs or dropped items
+pub const OBJECT_TYPE_LUA_ENTITY: u8 = 7;
+
 fn read_u8(r: &mut impl Read) -> std::io::Result<u8> {
     let mut buf = [0; 1];
     r.read_exact(&mut buf)?;
@@ -42,8 +63,8 @@ fn read_i32_be(r: &mut impl Read) -> std::io::Result<i32> {
     Ok(i32::from_be_bytes(buffer))
 }
 
-fn read_param0(r: &mut impl Read) -> std::io::Result<[u16; BLOCK_NODES_3D_U]> {
-    let mut array = [0; BLOCK_NODES_3D_U];
+fn read_param0<const NODES: usize>(r: &mut impl Read) -> std::io::Result<[u16; NODES]> {
+    let mut array = [0; NODES];
 
     for p0 in array.iter_mut() {
         *p0 = read_u16_be(r)?;
@@ -52,16 +73,267 @@ fn read_param0(r: &mut impl Read) -> std::io::Result<[u16; BLOCK_NODES_3D_U]> {
     Ok(array)
 }
 
-fn read_nodeparams(r: &mut impl Read) -> std::io::Result<[u8; BLOCK_NODES_3D_U]> {
-    let mut params = [0; BLOCK_NODES_3D_U];
+/// Splits a mapblock into its version byte and the decompressed remainder
+///
+/// Version 28 is rearranged into the layout of version 29, see [`recompose_v28`].
+fn decompress(mut data: impl Read) -> Result<(u8, Vec<u8>), MapBlockError> {
+    let map_format_version = read_u8(&mut data)?;
+    let mut buffer = vec![];
+    match map_format_version {
+        SERIALIZE_VERSION_LATEST => {
+            zstd::stream::Decoder::new(data)?.read_to_end(&mut buffer)?;
+        }
+        SERIALIZE_VERSION_OLDEST => buffer = recompose_v28(data)?,
+        _ => return Err(MapBlockError::MapVersionError(map_format_version)),
+    }
+    Ok((map_format_version, buffer))
+}
+
+/// Like [`decompress`], but keeps what was decompressed before an error and returns the error
+///
+/// Version 28 is not salvaged, it has to be intact.
+fn decompress_partially(
+    mut data: impl Read,
+) -> Result<(u8, Vec<u8>, Option<std::io::Error>), MapBlockError> {
+    let map_format_version = read_u8(&mut data)?;
+    match map_format_version {
+        SERIALIZE_VERSION_LATEST => {
+            let mut buffer = vec![];
+            // `read_to_end` keeps everything that was read before the error
+            let error = zstd::stream::Decoder::new(data)?
+                .read_to_end(&mut buffer)
+                .err();
+            Ok((map_format_version, buffer, error))
+        }
+        SERIALIZE_VERSION_OLDEST => Ok((map_format_version, recompose_v28(data)?, None)),
+        _ => Err(MapBlockError::MapVersionError(map_format_version)),
+    }
+}
+
+/// Like [`decompress`], but only decompresses as much as is read
+///
+/// Version 28 is decompressed at once, since its sections have to be rearranged.
+fn decompress_lazily<'a>(
+    mut data: impl Read + 'a,
+) -> Result<(u8, Box<dyn Read + 'a>), MapBlockError> {
+    let map_format_version = read_u8(&mut data)?;
+    match map_format_version {
+        SERIALIZE_VERSION_LATEST => Ok((
+            map_format_version,
+            Box::new(zstd::stream::Decoder::new(data)?),
+        )),
+        SERIALIZE_VERSION_OLDEST => Ok((
+            map_format_version,
+            Box::new(std::io::Cursor::new(recompose_v28(data)?)),
+        )),
+        _ => Err(MapBlockError::MapVersionError(map_format_version)),
+    }
+}
+
+/// Rearranges a version 28 mapblock (after its version byte) into the layout of version 29
+///
+/// Version 28 compresses only the node arrays and the node metadata, each on its own with zlib,
+/// and stores the timestamp and the name-id mappings after the static objects.
+fn recompose_v28(mut data: impl Read) -> Result<Vec<u8>, MapBlockError> {
+    let mut input = vec![];
+    data.read_to_end(&mut input)?;
+    let mut rest = input.as_slice();
+
+    // flags, lighting_complete, content_width and params_width
+    let mut header = [0; 5];
+    rest.read_exact(&mut header)?;
+    let mut nodes = vec![];
+    ZlibDecoder::new(&mut rest).read_to_end(&mut nodes)?;
+    let mut metadata = vec![];
+    ZlibDecoder::new(&mut rest).read_to_end(&mut metadata)?;
+
+    let objects = rest;
+    read_static_objects(&mut rest)?;
+    let objects = &objects[..objects.len() - rest.len()];
+    let timestamp = read_u32_be(&mut rest)?;
+    let mappings = rest;
+    read_name_id_mappings(&mut rest)?;
+    let mappings = &mappings[..mappings.len() - rest.len()];
+    // The node timers are the same in both versions
+
+    let mut buffer = header[..3].to_vec();
+    buffer.extend_from_slice(&timestamp.to_be_bytes());
+    buffer.extend_from_slice(mappings);
+    buffer.extend_from_slice(&header[3..]);
+    buffer.extend_from_slice(&nodes);
+    buffer.extend_from_slice(&metadata);
+    buffer.extend_from_slice(objects);
+    buffer.extend_from_slice(rest);
+    Ok(buffer)
+}
+
+fn read_nodeparams<const NODES: usize>(r: &mut impl Read) -> std::io::Result<[u8; NODES]> {
+    let mut params = [0; NODES];
     r.read_exact(&mut params)?;
     Ok(params)
 }
 
+/// (De)serializes the node arrays, which are too large for serde's built-in array support
+///
+/// Human-readable formats like JSON get one string of space-separated values per row of nodes,
+/// which keeps them compact and line-based. Other formats get a flat sequence of numbers.
+/// Both forms are accepted when reading.
+#[cfg(feature = "serde")]
+mod serde_array {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::fmt::Display;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Cell {
+        Value(u64),
+        Row(std::string::String),
+    }
+
+    pub fn serialize<S: Serializer, T: Serialize + Display, const N: usize>(
+        array: &[T; N],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        if !serializer.is_human_readable() {
+            return serializer.collect_seq(array);
+        }
+        // A row along the x axis has the side length of the mapblock
+        let rows = array.chunks(num_integer::cbrt(N)).map(|row| {
+            row.iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(" ")
+        });
+        serializer.collect_seq(rows)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, T: TryFrom<u64>, const N: usize>(
+        deserializer: D,
+    ) -> Result<[T; N], D::Error> {
+        let mut values = Vec::with_capacity(N);
+        for cell in Vec::<Cell>::deserialize(deserializer)? {
+            match cell {
+                Cell::Value(value) => values.push(value),
+                Cell::Row(row) => {
+                    for value in row.split_ascii_whitespace() {
+                        values.push(value.parse().map_err(D::Error::custom)?);
+                    }
+                }
+            }
+        }
+        let len = values.len();
+        let values = values
+            .into_iter()
+            .map(|value| {
+                T::try_from(value)
+                    .map_err(|_| D::Error::custom(format!("node value {value} is out of range")))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        values
+            .try_into()
+            .map_err(|_| D::Error::invalid_length(len, &N.to_string().as_str()))
+    }
+}
+
+/// (De)serializes content names and metadata as strings
+///
+/// Human-readable formats like JSON fall back to an array of bytes for invalid UTF-8,
+/// other formats always get a byte string. All three forms are accepted when reading.
+#[cfg(feature = "serde")]
+mod serde_text {
+    use serde::de::{SeqAccess, Visitor};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::{BTreeMap, HashMap};
+    use std::fmt;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        if !serializer.is_human_readable() {
+            return serializer.serialize_bytes(bytes);
+        }
+        match std::str::from_utf8(bytes) {
+            Ok(text) => serializer.serialize_str(text),
+            Err(_) => serializer.collect_seq(bytes),
+        }
+    }
+
+    struct BytesVisitor;
+
+    impl<'de> Visitor<'de> for BytesVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a string, a byte string or an array of bytes")
+        }
+
+        fn visit_str<E>(self, text: &str) -> Result<Vec<u8>, E> {
+            Ok(text.as_bytes().to_vec())
+        }
+
+        fn visit_bytes<E>(self, bytes: &[u8]) -> Result<Vec<u8>, E> {
+            Ok(bytes.to_vec())
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+            let mut bytes = vec![];
+            while let Some(byte) = seq.next_element()? {
+                bytes.push(byte);
+            }
+            Ok(bytes)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        deserializer.deserialize_any(BytesVisitor)
+    }
+
+    struct Text<'a>(&'a [u8]);
+
+    impl Serialize for Text<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serialize(self.0, serializer)
+        }
+    }
+
+    struct TextBuf(Vec<u8>);
+
+    impl<'de> Deserialize<'de> for TextBuf {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserialize(deserializer).map(TextBuf)
+        }
+    }
+
+    /// The same for the content names of the name-id mappings
+    pub mod mappings {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(
+            mappings: &HashMap<u16, Vec<u8>>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            // Sorted by ID, so that the output is deterministic
+            let sorted: BTreeMap<_, _> =
+                mappings.iter().map(|(id, name)| (id, Text(name))).collect();
+            serializer.collect_map(sorted)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<HashMap<u16, Vec<u8>>, D::Error> {
+            let mappings = HashMap::<u16, TextBuf>::deserialize(deserializer)?;
+            Ok(mappings
+                .into_iter()
+                .map(|(id, TextBuf(name))| (id, name))
+                .collect())
+        }
+    }
+}
+
 /// The physical composition of the world at a specific voxel
 ///
 /// Nodes are the voxel-shaped 1 m³ blocks that the world consists of.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Node {
     /// Content type string
     ///
@@ -72,6 +344,7 @@ pub struct Node {
     /// * [`vec![b"default:stone"]`](https://wiki.minetest.net/Stone)
     /// * [`vec![b"air"]`](https://wiki.minetest.net/Air)
     /// * [`vec![b"ignore"]`](https://wiki.minetest.net/Ignore)
+    #[cfg_attr(feature = "serde", serde(with = "serde_text"))]
     pub param0: Vec<u8>,
     /// Lighting data
     pub param1: u8,
@@ -103,17 +376,58 @@ pub enum MapBlockError {
     /// Node metadata version is not 2, hence unsupported
     #[error("Node metadata version {0} is not supported")]
     UnsupportedNodeMetadataVersion(u8),
+
+    /// Re-serializing the mapblock did not reproduce the original data.
+    ///
+    /// The offset of the first difference within the decompressed data is contained.
+    #[error("Re-serialized mapblock differs at byte {0}")]
+    RoundtripMismatch(usize),
+
+    /// The JSON representation could not be converted
+    #[cfg(feature = "json")]
+    #[error("JSON error: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    /// The CBOR representation could not be converted
+    ///
+    /// This variant contains a more detailed error message.
+    #[cfg(feature = "cbor")]
+    #[error("CBOR error: {0}")]
+    CborError(std::string::String),
+}
+
+/// The outcome of checking a serialized mapblock, see [`MapBlock::check_data`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockIntegrity {
+    /// The mapblock decodes without errors
+    Ok,
+    /// The map format version is not supported, see [`MapBlockError::MapVersionError`]
+    UnknownVersion(u8),
+    /// The data is empty or could not be decompressed
+    ///
+    /// This variant contains the error message.
+    DecompressFailed(std::string::String),
+    /// The decompressed data does not follow the expected binary structure
+    ParseFailed {
+        /// The position within the decompressed data where parsing stopped
+        offset: usize,
+        /// The error message
+        error: std::string::String,
+    },
 }
 
 /// Maps mapblock-local content IDs to content types
 pub type NameIdMappings = HashMap<u16, Vec<u8>>;
 
 /// A single node metadata variable, consisting of a key and a value
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NodeVar {
     /// The 'name' of this variable
+    #[cfg_attr(feature = "serde", serde(with = "serde_text"))]
     pub key: Vec<u8>,
     /// The value for this variable
+    #[cfg_attr(feature = "serde", serde(with = "serde_text"))]
     pub value: Vec<u8>,
     /// Whether this is a private variable
     pub is_private: bool,
@@ -122,20 +436,88 @@ pub struct NodeVar {
 /// Metadata of a node
 ///
 /// In game, this is used for e.g. the inventory of a chest or the text of a sign
-#[derive(Debug)]
-pub struct NodeMetadata {
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeMetadata<const LENGTH: u16 = BLOCK_NODES_1D> {
     /// The mapblock-relative node position of this item
-    pub position: NodePos,
+    pub position: SizedNodePos<LENGTH>,
     /// Metadata variables
     pub vars: Vec<NodeVar>,
-    /// Serialized inventory
-    pub inventory: Vec<u8>,
+    /// The inventory lists of this node, e.g. `main` for a chest
+    ///
+    /// Up to version 0.5, this held the serialized inventory as `Vec<u8>`.
+    /// Items that are not valid UTF-8 are kept in an escaped form, which the engine
+    /// reads back as the original bytes, but which [`ItemStack`](`crate::inventory::ItemStack`)
+    /// cannot parse.
+    pub inventory: Vec<InventoryList>,
+}
+
+impl<const LENGTH: u16> NodeMetadata<LENGTH> {
+    /// Returns the value of the variable `key`, if present
+    ///
+    /// ```
+    /// use minetestworld::MapBlock;
+    ///
+    /// let data = std::fs::read("TestWorld/testmapblock").unwrap();
+    /// let block = MapBlock::from_data(data.as_slice()).unwrap();
+    /// for metadata in &block.node_metadata {
+    ///     if let Some(text) = metadata.get(b"infotext") {
+    ///         println!("{:?}: {}", metadata.position, String::from_utf8_lossy(text));
+    ///     }
+    /// }
+    /// ```
+    pub fn get(&self, key: &[u8]) -> Option<&[u8]> {
+        self.vars
+            .iter()
+            .find(|var| var.key == key)
+            .map(|var| var.value.as_slice())
+    }
+
+    /// Returns the inventory list called `name`, if present
+    pub fn inventory_list(&self, name: &str) -> Option<&InventoryList> {
+        self.inventory.iter().find(|list| list.name == name)
+    }
+
+    /// Sets the variable `key` to `value`
+    ///
+    /// An existing variable keeps its private flag.
+    /// Like in the engine, an empty value removes the variable.
+    pub fn set(&mut self, key: &[u8], value: &[u8]) {
+        match self.vars.iter().position(|var| var.key == key) {
+            Some(i) if value.is_empty() => {
+                self.vars.remove(i);
+            }
+            Some(i) => self.vars[i].value = value.to_vec(),
+            None if value.is_empty() => {}
+            None => self.vars.push(NodeVar {
+                key: key.to_vec(),
+                value: value.to_vec(),
+                is_private: false,
+            }),
+        }
+    }
+
+    /// Inserts `list`, replacing a list of the same name
+    pub fn set_inventory_list(&mut self, list: InventoryList) {
+        match self.inventory.iter_mut().find(|l| l.name == list.name) {
+            Some(existing) => *existing = list,
+            None => self.inventory.push(list),
+        }
+    }
+
+    /// Returns true if there are neither variables nor inventory lists
+    ///
+    /// Empty metadata is not serialized.
+    pub fn is_empty(&self) -> bool {
+        self.vars.is_empty() && self.inventory.is_empty()
+    }
 }
 
 /// Objects in the world that are not nodes
 ///
 /// For example a LuaEntity
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StaticObject {
     /// Type ID
     pub type_id: u8,
@@ -149,36 +531,389 @@ pub struct StaticObject {
     pub data: Vec<u8>,
 }
 
+impl StaticObject {
+    /// Creates a Lua entity object at `position`, given in node coordinates
+    pub fn from_lua_entity(position: Vec3, entity: &LuaEntity) -> Self {
+        let mut object = StaticObject {
+            type_id: OBJECT_TYPE_LUA_ENTITY,
+            x: 0,
+            y: 0,
+            z: 0,
+            data: vec![],
+        };
+        object.set_position(position);
+        object.set_lua_entity(entity);
+        object
+    }
+
+    /// Returns the position of this object in node coordinates
+    pub fn position(&self) -> Vec3 {
+        Vec3::new(self.x as f32, self.y as f32, self.z as f32) / 1000.0 / BS
+    }
+
+    /// Moves this object to `position`, given in node coordinates
+    ///
+    /// The object should stay within the bounds of its mapblock,
+    /// otherwise the engine moves it on the next activation.
+    pub fn set_position(&mut self, position: Vec3) {
+        let position = (position * BS * 1000.0).round().as_ivec3();
+        (self.x, self.y, self.z) = (position.x, position.y, position.z);
+    }
+
+    /// Turns this object into the Lua entity `entity`
+    pub fn set_lua_entity(&mut self, entity: &LuaEntity) {
+        let mut data = vec![1];
+        data.extend((entity.name.len() as u16).to_be_bytes());
+        data.extend(entity.name.as_bytes());
+        data.extend((entity.staticdata.len() as u32).to_be_bytes());
+        data.extend(&entity.staticdata);
+        data.extend(entity.hp.to_be_bytes());
+        write_v3f1000(entity.velocity * BS, &mut data);
+        write_f1000(entity.rotation.y, &mut data);
+        data.push(1);
+        write_f1000(entity.rotation.x, &mut data);
+        write_f1000(entity.rotation.z, &mut data);
+        self.type_id = OBJECT_TYPE_LUA_ENTITY;
+        self.data = data;
+    }
+
+    /// Decodes the data of this object if it is a Lua entity
+    ///
+    /// Returns `Ok(None)` for other object types.
+    pub fn lua_entity(&self) -> Result<Option<LuaEntity>, MapBlockError> {
+        if self.type_id != OBJECT_TYPE_LUA_ENTITY {
+            return Ok(None);
+        }
+        let mut data = self.data.as_slice();
+        let version = read_u8(&mut data)?;
+        if version != 1 {
+            return Err(MapBlockError::BlobMalformed(format!(
+                "lua entity version should be 1, is {version}"
+            )));
+        }
+        let name_length = read_u16_be(&mut data)?;
+        let mut name = vec![0; name_length as usize];
+        data.read_exact(&mut name)?;
+        let name = std::str::from_utf8(&name).map_err(|_| {
+            MapBlockError::BlobMalformed("lua entity name is not valid UTF-8".into())
+        })?;
+        let staticdata_length = read_u32_be(&mut data)?;
+        let mut staticdata = vec![0; staticdata_length as usize];
+        data.read_exact(&mut staticdata)?;
+        let hp = read_u16_be(&mut data)?;
+        let velocity = read_v3f1000(&mut data)? / BS;
+        let yaw = read_f1000(&mut data)?;
+        // Pitch and roll were added later, along with another version byte
+        let (pitch, roll) = if data.is_empty() {
+            (0.0, 0.0)
+        } else {
+            read_u8(&mut data)?;
+            (read_f1000(&mut data)?, read_f1000(&mut data)?)
+        };
+        Ok(Some(LuaEntity {
+            name: String::from(name),
+            staticdata,
+            hp,
+            velocity,
+            rotation: Vec3::new(pitch, yaw, roll),
+        }))
+    }
+}
+
+/// The decoded data of a [`StaticObject`] that is a Lua entity
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LuaEntity {
+    /// The registered name of the entity, e.g. `__builtin:item` for dropped items
+    pub name: String,
+    /// The data returned by the entity's `get_staticdata`, often a serialized Lua table
+    pub staticdata: Vec<u8>,
+    /// Hit points
+    pub hp: u16,
+    /// Velocity in nodes per second
+    pub velocity: Vec3,
+    /// Pitch, yaw and roll in degrees
+    pub rotation: Vec3,
+}
+
 /// Represents a running node timer
-#[derive(Debug)]
-pub struct NodeTimer {
+///
+/// When the elapsed time reaches the timeout, the engine calls the
+/// `on_timer` callback of the node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeTimer<const LENGTH: u16 = BLOCK_NODES_1D> {
     /// The mapblock-relative node position of this timer
-    pub position: NodePos,
+    pub position: SizedNodePos<LENGTH>,
     /// Timeout in milliseconds
     pub timeout: i32,
     /// Elapsed time in milliseconds
     pub elapsed: i32,
 }
 
+impl<const LENGTH: u16> NodeTimer<LENGTH> {
+    /// Creates a timer that fires after `timeout` milliseconds
+    pub fn new(position: SizedNodePos<LENGTH>, timeout: i32) -> Self {
+        NodeTimer {
+            position,
+            timeout,
+            elapsed: 0,
+        }
+    }
+
+    /// Returns the milliseconds until this timer fires
+    pub fn remaining(&self) -> i32 {
+        (self.timeout - self.elapsed).max(0)
+    }
+}
+
 /// A 'chunk' of voxels; the data unit saved in a backend
 ///
 /// Refer to <https://github.com/minetest/minetest/blob/master/doc/world_format.txt>
-#[derive(Debug)]
-pub struct MapBlock {
-    /// The format version of the mapblock. Currently supported is only version 29.
+pub type MapBlock = SizedMapBlock<BLOCK_NODES_1D, BLOCK_NODES_3D_U>;
+
+/// One of the two kinds of light stored in [`MapBlock::param1`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LightBank {
+    /// Sunlight, stored in the lower four bits
+    Day,
+    /// Light emitted by nodes, stored in the upper four bits
+    Night,
+}
+
+/// A side of a mapblock, in the order the engine uses for [`MapBlock::lighting_complete`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlockFace {
+    /// The side facing east
+    PosX,
+    /// The top side
+    PosY,
+    /// The side facing north
+    PosZ,
+    /// The side facing south
+    NegZ,
+    /// The bottom side
+    NegY,
+    /// The side facing west
+    NegX,
+}
+
+/// How a modified mapblock is prepared for the engine before it is written
+///
+/// See [`MapBlock::apply_maintenance`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WriteMaintenance {
+    /// The game time to record as the time of the last save
+    ///
+    /// The current game time of a world is returned by
+    /// [`World::get_game_time`](`crate::World::get_game_time`).
+    pub timestamp: Option<u32>,
+    /// Whether to clear [`MapBlock::lighting_complete`],
+    /// so that the engine recomputes the light when loading the block
+    pub invalidate_lighting: bool,
+}
+
+/// A node borrowed from a mapblock, as yielded by [`MapBlock::nodes`]
+///
+/// Unlike [`Node`], this does not allocate a copy of the content name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeRef<'a, const LENGTH: u16 = BLOCK_NODES_1D> {
+    /// The mapblock-relative node position
+    pub position: SizedNodePos<LENGTH>,
+    /// The content name, see [`Node::param0`]
+    pub content: &'a [u8],
+    /// See [`Node::param1`]
+    pub param1: u8,
+    /// See [`Node::param2`]
+    pub param2: u8,
+}
+
+impl NodeRef<'_> {
+    /// Copies the node into an owned [`Node`]
+    pub fn to_node(&self) -> Node {
+        Node {
+            param0: self.content.to_vec(),
+            param1: self.param1,
+            param2: self.param2,
+        }
+    }
+}
+
+/// How much of a mapblock [`MapBlock::from_data_mode`] decodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DecodeMode {
+    /// Everything is decoded
+    #[default]
+    Full,
+    /// The header and the nodes are decoded, but node metadata,
+    /// static objects and node timers are left empty
+    ///
+    /// ⚠️ Writing such a mapblock back into the map removes them.
+    NodesOnly,
+    /// Like [`Full`](`Self::Full`), but sections after the node arrays that cannot be
+    /// decoded are left empty instead of failing
+    ///
+    /// See [`MapBlock::from_data_lenient`] to find out what was dropped.
+    Lenient,
+}
+
+/// A part of a serialized mapblock that follows the node arrays
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockSection {
+    /// See [`MapBlock::node_metadata`]
+    NodeMetadata,
+    /// See [`MapBlock::static_objects`]
+    StaticObjects,
+    /// See [`MapBlock::node_timers`]
+    NodeTimers,
+}
+
+/// What [`MapBlock::from_data_lenient`] had to leave out of a damaged mapblock
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LenientReport {
+    /// The sections that could not be decoded and were left empty
+    pub dropped: Vec<BlockSection>,
+    /// The first error that was encountered, if any
+    pub error: Option<std::string::String>,
+}
+
+impl LenientReport {
+    /// Returns true if the mapblock was decoded without any errors
+    pub fn is_intact(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// The header of a serialized mapblock, as decoded by [`MapBlock::header_from_data`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockHeader {
+    /// See [`MapBlock::map_format_version`]
+    pub map_format_version: u8,
+    /// See [`MapBlock::flags`]
+    pub flags: u8,
+    /// See [`MapBlock::lighting_complete`]
+    pub lighting_complete: u16,
+    /// See [`MapBlock::timestamp`]
+    pub timestamp: u32,
+    /// See [`MapBlock::name_id_mappings`]
+    pub name_id_mappings: NameIdMappings,
+}
+
+/// A node that differs between two versions of a mapblock
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeChange<const LENGTH: u16 = BLOCK_NODES_1D> {
+    /// The mapblock-relative node position
+    pub position: SizedNodePos<LENGTH>,
+    /// The node in the old version
+    pub before: Node,
+    /// The node in the new version
+    pub after: Node,
+}
+
+/// The differences between two versions of a mapblock, as found by [`MapBlock::diff`]
+///
+/// Nodes are compared by their content name, so the two versions may use different content IDs.
+/// All position lists are sorted by node index.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BlockDelta<const LENGTH: u16 = BLOCK_NODES_1D> {
+    /// Nodes whose content, param1 or param2 differ
+    pub nodes: Vec<NodeChange<LENGTH>>,
+    /// Content names that only the new version contains, sorted
+    pub added_contents: Vec<Vec<u8>>,
+    /// Content names that only the old version contains, sorted
+    pub removed_contents: Vec<Vec<u8>>,
+    /// Positions whose node metadata has been added, removed or changed
+    pub metadata: Vec<SizedNodePos<LENGTH>>,
+    /// Static objects that only the new version contains
+    pub added_objects: Vec<StaticObject>,
+    /// Static objects that only the old version contains
+    pub removed_objects: Vec<StaticObject>,
+    /// Positions whose node timer has been added, removed or changed
+    pub timers: Vec<SizedNodePos<LENGTH>>,
+}
+
+impl<const LENGTH: u16> BlockDelta<LENGTH> {
+    /// Returns true if both versions are equal, apart from the header and the content IDs
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+            && self.added_contents.is_empty()
+            && self.removed_contents.is_empty()
+            && self.metadata.is_empty()
+            && self.added_objects.is_empty()
+            && self.removed_objects.is_empty()
+            && self.timers.is_empty()
+    }
+}
+
+/// Returns the elements of `a` that are missing in `b`, respecting duplicates
+fn missing_objects(a: &[StaticObject], b: &[StaticObject]) -> Vec<StaticObject> {
+    let mut remaining: Vec<_> = b.iter().collect();
+    a.iter()
+        .filter(
+            |object| match remaining.iter().position(|other| other == object) {
+                Some(i) => {
+                    remaining.swap_remove(i);
+                    false
+                }
+                None => true,
+            },
+        )
+        .cloned()
+        .collect()
+}
+
+/// Returns the sorted positions at which the items of `a` and `b` differ
+fn changed_positions<T: PartialEq, const LENGTH: u16>(
+    a: &[T],
+    b: &[T],
+    position: impl Fn(&T) -> SizedNodePos<LENGTH>,
+) -> Vec<SizedNodePos<LENGTH>> {
+    let mut changed: Vec<_> = a
+        .iter()
+        .filter(|item| !b.contains(item))
+        .chain(b.iter().filter(|item| !a.contains(item)))
+        .map(position)
+        .collect();
+    changed.sort_unstable_by_key(|&pos| SizedNodeIndex::from(pos));
+    changed.dedup();
+    changed
+}
+
+/// A [`MapBlock`] with a side length of `LENGTH` nodes
+///
+/// This allows decoding, editing and encoding single blocks of engine forks that use
+/// another block size. [`MapData`](`crate::MapData`) and [`MapEdit`](`crate::MapEdit`)
+/// only deal with [`MapBlock`], but the raw data of such blocks can be stored and loaded with
+/// [`set_block_data`](`crate::MapData::set_block_data`) and
+/// [`get_block_data`](`crate::MapData::get_block_data`).
+/// `NODES` has to be the cube of `LENGTH`, which is checked at compile time.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SizedMapBlock<const LENGTH: u16 = BLOCK_NODES_1D, const NODES: usize = BLOCK_NODES_3D_U>
+{
+    /// The format version of the mapblock. Versions 28 and 29 are supported.
     ///
-    /// An attempt to read a block of a previous version will result in a
-    /// [`MapBlockError::MapVersionError`].
+    /// An attempt to read a block of another version will result in a
+    /// [`MapBlockError::MapVersionError`]. The bulk operations of
+    /// [`MapData`](`crate::MapData`) write modified mapblocks back in this version.
     pub map_format_version: u8,
     /// Flags telling if this chunk is underground etc.
+    ///
+    /// See [`is_underground`](`Self::is_underground`), [`day_night_differs`](`Self::day_night_differs`)
+    /// and [`is_generated`](`Self::is_generated`).
     pub flags: u8,
     /// Flags that indicate if the lighting is complete at each side.
+    ///
+    /// See [`is_lighting_complete`](`Self::is_lighting_complete`).
     pub lighting_complete: u16,
     /// Timestamp of last save , in seconds from game start
+    ///
+    /// [`TIMESTAMP_UNDEFINED`] if the block has never been saved by the engine.
     pub timestamp: u32,
     /// Maps each numeric content ID to the content name.
     ///
     /// This is used to efficiently store nodes.
+    #[cfg_attr(feature = "serde", serde(with = "serde_text::mappings"))]
     pub name_id_mappings: NameIdMappings,
     /// Number bytes used for the content (param0) field of the nodes
     pub content_width: u8,
@@ -187,113 +922,560 @@ pub struct MapBlock {
     /// The content ID of each node in the mapblock.
     ///
     /// It can be mapped to names via [`MapBlock::name_id_mappings`]
-    pub param0: [u16; 4096],
+    #[cfg_attr(feature = "serde", serde(with = "serde_array"))]
+    pub param0: [u16; NODES],
     /// The param1 field of every node
-    pub param1: [u8; 4096],
+    #[cfg_attr(feature = "serde", serde(with = "serde_array"))]
+    pub param1: [u8; NODES],
     /// The param2 field of every node
-    pub param2: [u8; 4096],
+    #[cfg_attr(feature = "serde", serde(with = "serde_array"))]
+    pub param2: [u8; NODES],
     /// Node metadata
-    pub node_metadata: Vec<NodeMetadata>,
+    pub node_metadata: Vec<NodeMetadata<LENGTH>>,
     /// Objects that are no nodes
     pub static_objects: Vec<StaticObject>,
     /// Node timers
-    pub node_timers: Vec<NodeTimer>,
+    pub node_timers: Vec<NodeTimer<LENGTH>>,
 }
 
-impl MapBlock {
+impl<const LENGTH: u16, const NODES: usize> SizedMapBlock<LENGTH, NODES> {
+    const VALID_SIZE: () = assert!(
+        BlockSize::<LENGTH>::NODES_3D == NODES,
+        "NODES has to be the cube of LENGTH"
+    );
+
     /// Constructs a Mapblock from its binary representation
-    pub fn from_data(mut data: impl Read) -> Result<MapBlock, MapBlockError> {
-        let map_format_version = read_u8(&mut data)?;
-        if map_format_version != 29 {
-            return Err(MapBlockError::MapVersionError(map_format_version));
-        }
-        // Read all into a vector
-        let mut buffer = vec![];
-        zstd::stream::Decoder::new(data)?.read_to_end(&mut buffer)?;
-        let mut data = buffer.as_slice();
-
-        let flags = read_u8(&mut data)?;
-        let lighting_complete = read_u16_be(&mut data)?;
-        let timestamp = read_u32_be(&mut data)?;
-        let name_id_mappings = read_name_id_mappings(&mut data)?;
-
-        let content_width = read_u8(&mut data)?;
+    pub fn from_data(data: impl Read) -> Result<Self, MapBlockError> {
+        Self::from_data_mode(data, DecodeMode::Full)
+    }
+
+    /// Constructs a Mapblock from its binary representation, decoding only what `mode` asks for
+    ///
+    /// With [`DecodeMode::NodesOnly`], the data is only decompressed up to the end of the node
+    /// arrays. See [`header_from_data`](`Self::header_from_data`) to skip the nodes as well.
+    ///
+    /// ```
+    /// use minetestworld::map_block::DecodeMode;
+    /// use minetestworld::MapBlock;
+    ///
+    /// let data = std::fs::read("TestWorld/testmapblock").unwrap();
+    /// let block = MapBlock::from_data_mode(data.as_slice(), DecodeMode::NodesOnly).unwrap();
+    /// assert!(block.node_metadata.is_empty());
+    /// ```
+    pub fn from_data_mode(data: impl Read, mode: DecodeMode) -> Result<Self, MapBlockError> {
+        let () = Self::VALID_SIZE;
+        let () = BlockSize::<LENGTH>::VALID;
+        match mode {
+            DecodeMode::Full => {
+                // Decompressing at once is faster than parsing from the decompressor
+                let (map_format_version, buffer) = decompress(data)?;
+                Self::parse(map_format_version, &mut buffer.as_slice(), mode)
+            }
+            DecodeMode::NodesOnly => {
+                let (map_format_version, mut data) = decompress_lazily(data)?;
+                Self::parse(map_format_version, &mut data, mode)
+            }
+            DecodeMode::Lenient => Self::from_data_lenient(data).map(|(mapblock, _)| mapblock),
+        }
+    }
+
+    /// Constructs a Mapblock from a possibly damaged binary representation
+    ///
+    /// The header and the node arrays have to be intact, but everything after them is salvaged
+    /// as far as possible: If the data is truncated or a section is malformed, that section
+    /// and all following ones are left empty. The returned [`LenientReport`] lists them.
+    ///
+    /// ```
+    /// use minetestworld::MapBlock;
+    ///
+    /// let data = std::fs::read("TestWorld/testmapblock").unwrap();
+    /// let (block, report) = MapBlock::from_data_lenient(data.as_slice()).unwrap();
+    /// assert!(report.is_intact());
+    /// assert_eq!(block.node_timers, MapBlock::from_data(data.as_slice()).unwrap().node_timers);
+    /// ```
+    pub fn from_data_lenient(data: impl Read) -> Result<(Self, LenientReport), MapBlockError> {
+        let () = Self::VALID_SIZE;
+        let () = BlockSize::<LENGTH>::VALID;
+        let (map_format_version, buffer, error) = decompress_partially(data)?;
+        let mut report = LenientReport {
+            dropped: vec![],
+            error: error.map(|e| MapBlockError::from(e).to_string()),
+        };
+
+        let data = &mut buffer.as_slice();
+        let mut mapblock = Self::parse_nodes(map_format_version, data)?;
+        if let Err((section, e)) = mapblock.read_trailer(data) {
+            let sections = [
+                BlockSection::NodeMetadata,
+                BlockSection::StaticObjects,
+                BlockSection::NodeTimers,
+            ];
+            // The sections after the failed one cannot be located anymore
+            let failed = sections
+                .iter()
+                .position(|s| *s == section)
+                .unwrap_or_default();
+            report.dropped = sections[failed..].to_vec();
+            report.error.get_or_insert_with(|| e.to_string());
+        }
+
+        Ok((mapblock, report))
+    }
+
+    /// Reads the sections after the node arrays, stopping at the first one that fails
+    fn read_trailer(&mut self, data: &mut impl Read) -> Result<(), (BlockSection, MapBlockError)> {
+        self.node_metadata =
+            read_node_metadata(data).map_err(|e| (BlockSection::NodeMetadata, e))?;
+        self.static_objects =
+            read_static_objects(data).map_err(|e| (BlockSection::StaticObjects, e))?;
+        self.node_timers = read_timers(data).map_err(|e| (BlockSection::NodeTimers, e))?;
+        Ok(())
+    }
+
+    /// Parses the decompressed part of a mapblock
+    fn parse(
+        map_format_version: u8,
+        data: &mut impl Read,
+        mode: DecodeMode,
+    ) -> Result<Self, MapBlockError> {
+        let mut mapblock = Self::parse_nodes(map_format_version, data)?;
+        if mode == DecodeMode::Full {
+            mapblock.node_metadata = read_node_metadata(data)?;
+            mapblock.static_objects = read_static_objects(data)?;
+            mapblock.node_timers = read_timers(data)?;
+        }
+
+        Ok(mapblock)
+    }
+
+    /// Parses the header and the node arrays, leaving the remaining sections empty
+    fn parse_nodes(map_format_version: u8, data: &mut impl Read) -> Result<Self, MapBlockError> {
+        let header = read_header(map_format_version, data)?;
+
+        let content_width = read_u8(data)?;
         if content_width != 2 {
             return Err(MapBlockError::BlobMalformed(format!(
                 "\"{content_width}\" is not the expected content_width"
             )));
         }
 
-        let params_width = read_u8(&mut data)?;
+        let params_width = read_u8(data)?;
         if params_width != 2 {
             return Err(MapBlockError::BlobMalformed(format!(
                 "\"{params_width}\" is not the expected params_width"
             )));
         }
 
-        let mapblock = MapBlock {
+        Ok(SizedMapBlock {
             map_format_version,
-            flags,
-            lighting_complete,
-            timestamp,
-            name_id_mappings,
+            flags: header.flags,
+            lighting_complete: header.lighting_complete,
+            timestamp: header.timestamp,
+            name_id_mappings: header.name_id_mappings,
             content_width,
             params_width,
-            param0: read_param0(&mut data)?,
-            param1: read_nodeparams(&mut data)?,
-            param2: read_nodeparams(&mut data)?,
-            node_metadata: read_node_metadata(&mut data)?,
-            static_objects: read_static_objects(&mut data)?,
-            node_timers: read_timers(&mut data)?,
-        };
+            param0: read_param0(data)?,
+            param1: read_nodeparams(data)?,
+            param2: read_nodeparams(data)?,
+            node_metadata: vec![],
+            static_objects: vec![],
+            node_timers: vec![],
+        })
+    }
 
-        Ok(mapblock)
+    /// Decodes only the header and the name-id mappings of a serialized mapblock
+    ///
+    /// Only the beginning of the data is decompressed, so this is much cheaper than
+    /// [`from_data`](`Self::from_data`) for scans that only need to know
+    /// which contents exist where.
+    ///
+    /// ```
+    /// use minetestworld::MapBlock;
+    ///
+    /// let data = std::fs::read("TestWorld/testmapblock").unwrap();
+    /// let header = MapBlock::header_from_data(data.as_slice()).unwrap();
+    /// assert_eq!(header.map_format_version, 29);
+    /// assert!(header.name_id_mappings.values().any(|name| name == b"air"));
+    /// ```
+    pub fn header_from_data(data: impl Read) -> Result<BlockHeader, MapBlockError> {
+        let (map_format_version, mut data) = decompress_lazily(data)?;
+        read_header(map_format_version, &mut data)
+    }
+
+    /// Converts the mapblock into pretty-printed JSON
+    ///
+    /// Every field is represented. Content names and metadata are strings, unless they are
+    /// not valid UTF-8, and the node arrays have one line per row of nodes. Being line-based,
+    /// the result works well with text-based diff tools.
+    ///
+    /// ```
+    /// use minetestworld::MapBlock;
+    ///
+    /// let data = std::fs::read("TestWorld/testmapblock").unwrap();
+    /// let block = MapBlock::from_data(data.as_slice()).unwrap();
+    /// let json = block.to_json().unwrap();
+    /// assert!(MapBlock::from_json(&json).unwrap().diff(&block).is_empty());
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> Result<std::string::String, MapBlockError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Reads a mapblock from the JSON representation of [`to_json`](`Self::to_json`)
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> Result<Self, MapBlockError> {
+        let block: Self = serde_json::from_str(json)?;
+        block.check_imported()?;
+        Ok(block)
+    }
+
+    /// Converts the mapblock into CBOR, a compact binary alternative to JSON
+    #[cfg(feature = "cbor")]
+    pub fn to_cbor(&self) -> Result<Vec<u8>, MapBlockError> {
+        let mut dest = vec![];
+        ciborium::into_writer(self, &mut dest)
+            .map_err(|e| MapBlockError::CborError(e.to_string()))?;
+        Ok(dest)
+    }
+
+    /// Reads a mapblock from the CBOR representation of [`to_cbor`](`Self::to_cbor`)
+    #[cfg(feature = "cbor")]
+    pub fn from_cbor(data: impl Read) -> Result<Self, MapBlockError> {
+        let block: Self =
+            ciborium::from_reader(data).map_err(|e| MapBlockError::CborError(e.to_string()))?;
+        block.check_imported()?;
+        Ok(block)
+    }
+
+    /// Checks what the binary format guarantees, but imported data does not
+    ///
+    /// These are the map format version, the widths and a content name for every node.
+    #[cfg(any(feature = "json", feature = "cbor"))]
+    fn check_imported(&self) -> Result<(), MapBlockError> {
+        if !(SERIALIZE_VERSION_OLDEST..=SERIALIZE_VERSION_LATEST).contains(&self.map_format_version)
+        {
+            return Err(MapBlockError::MapVersionError(self.map_format_version));
+        }
+        if self.content_width != 2 {
+            return Err(MapBlockError::BlobMalformed(format!(
+                "\"{}\" is not the expected content_width",
+                self.content_width
+            )));
+        }
+        if self.params_width != 2 {
+            return Err(MapBlockError::BlobMalformed(format!(
+                "\"{}\" is not the expected params_width",
+                self.params_width
+            )));
+        }
+        match self
+            .param0
+            .iter()
+            .find(|id| !self.name_id_mappings.contains_key(id))
+        {
+            Some(id) => Err(MapBlockError::BlobMalformed(format!(
+                "content ID {id} has no name"
+            ))),
+            None => Ok(()),
+        }
     }
 
     /// Serializes the map block into the binary format
+    ///
+    /// This always writes the latest version, regardless of
+    /// [`map_format_version`](`Self::map_format_version`), see
+    /// [`to_binary_version`](`Self::to_binary_version`).
     pub fn to_binary(&self) -> std::io::Result<Vec<u8>> {
-        let mut encoder = zstd::stream::Encoder::new(vec![29], 0)?;
+        let mut encoder = zstd::stream::Encoder::new(vec![SERIALIZE_VERSION_LATEST], 0)?;
+        self.write_uncompressed(&mut encoder)?;
+        encoder.finish()
+    }
 
-        encoder.write_all(&self.flags.to_be_bytes())?;
-        encoder.write_all(&self.lighting_complete.to_be_bytes())?;
-        encoder.write_all(&self.timestamp.to_be_bytes())?;
-        write_name_id_mappings(&self.name_id_mappings, &mut encoder)?;
+    /// Serializes the map block into the binary format of `serialize_version`
+    ///
+    /// Version 29 is what [`to_binary`](`Self::to_binary`) writes.
+    /// Version 28 uses zlib instead of zstd and is needed for servers before 5.5.
+    /// Both versions can be read by [`from_data`](`Self::from_data`).
+    /// Other versions result in a [`MapBlockError::MapVersionError`].
+    pub fn to_binary_version(&self, serialize_version: u8) -> Result<Vec<u8>, MapBlockError> {
+        match serialize_version {
+            SERIALIZE_VERSION_LATEST => Ok(self.to_binary()?),
+            SERIALIZE_VERSION_OLDEST => Ok(self.to_binary_v28()?),
+            _ => Err(MapBlockError::MapVersionError(serialize_version)),
+        }
+    }
 
-        encoder.write_all(&[2])?; // content_width
-        encoder.write_all(&[2])?; // params_width
+    /// Writes version 28, where only the node data and metadata are compressed
+    fn to_binary_v28(&self) -> std::io::Result<Vec<u8>> {
+        let mut dest = vec![28];
+        dest.write_all(&self.flags.to_be_bytes())?;
+        dest.write_all(&self.lighting_complete.to_be_bytes())?;
+        dest.write_all(&[2])?; // content_width
+        dest.write_all(&[2])?; // params_width
 
+        let mut encoder = ZlibEncoder::new(dest, Compression::default());
         for value in self.param0 {
             encoder.write_all(&value.to_be_bytes())?;
         }
         encoder.write_all(&self.param1)?;
         encoder.write_all(&self.param2)?;
-
+        let mut encoder = ZlibEncoder::new(encoder.finish()?, Compression::default());
         write_node_metadata(&self.node_metadata, &mut encoder)?;
-        write_static_objects(&self.static_objects, &mut encoder)?;
-        write_node_timers(&self.node_timers, &mut encoder)?;
+        let mut dest = encoder.finish()?;
 
-        encoder.finish()
+        write_static_objects(&self.static_objects, &mut dest)?;
+        dest.write_all(&self.timestamp.to_be_bytes())?;
+        write_name_id_mappings(&self.name_id_mappings, &mut dest)?;
+        write_node_timers(&self.node_timers, &mut dest)?;
+        Ok(dest)
+    }
+
+    /// Writes everything that follows the version byte, before compression
+    fn write_uncompressed(&self, dest: &mut impl Write) -> std::io::Result<()> {
+        dest.write_all(&self.flags.to_be_bytes())?;
+        dest.write_all(&self.lighting_complete.to_be_bytes())?;
+        dest.write_all(&self.timestamp.to_be_bytes())?;
+        write_name_id_mappings(&self.name_id_mappings, dest)?;
+
+        dest.write_all(&[2])?; // content_width
+        dest.write_all(&[2])?; // params_width
+
+        for value in self.param0 {
+            dest.write_all(&value.to_be_bytes())?;
+        }
+        dest.write_all(&self.param1)?;
+        dest.write_all(&self.param2)?;
+
+        write_node_metadata(&self.node_metadata, dest)?;
+        write_static_objects(&self.static_objects, dest)?;
+        write_node_timers(&self.node_timers, dest)
+    }
+
+    /// Verifies that decoding and re-encoding `data` does not alter the mapblock
+    ///
+    /// `data` is a mapblock in its binary representation, as returned by
+    /// [`MapData::get_block_data`](`crate::MapData::get_block_data`).
+    /// It is decoded and serialized again, and both versions are compared byte by byte.
+    ///
+    /// The comparison happens on the decompressed data, because the zstd settings
+    /// of the engine may yield a different, yet equivalent, compressed stream.
+    /// Besides that, the only accepted difference is the order of the name-id mappings,
+    /// which are always written sorted by content ID.
+    ///
+    /// A difference is reported as [`MapBlockError::RoundtripMismatch`].
+    ///
+    /// ```
+    /// use minetestworld::MapBlock;
+    ///
+    /// let data = std::fs::read("TestWorld/testmapblock").unwrap();
+    /// MapBlock::roundtrip_check(&data).unwrap();
+    /// ```
+    pub fn roundtrip_check(data: &[u8]) -> Result<(), MapBlockError> {
+        let mapblock = Self::from_data(data)?;
+        let (_, original) = decompress(data)?;
+
+        // Rewrite the original's name-id mappings in sorted order to make it comparable
+        let mappings_start = 7; // flags, lighting_complete and timestamp
+        let mut rest = original.get(mappings_start..).unwrap_or_default();
+        let mappings = read_name_id_mappings(&mut rest)?;
+        let mappings_end = original.len() - rest.len();
+        let mut canonical = original[..mappings_start].to_vec();
+        write_name_id_mappings(&mappings, &mut canonical)?;
+        canonical.extend_from_slice(&original[mappings_end..]);
+
+        let mut reencoded = vec![];
+        mapblock.write_uncompressed(&mut reencoded)?;
+        match canonical.iter().zip(&reencoded).position(|(a, b)| a != b) {
+            Some(offset) => Err(MapBlockError::RoundtripMismatch(offset)),
+            None if canonical.len() != reencoded.len() => Err(MapBlockError::RoundtripMismatch(
+                canonical.len().min(reencoded.len()),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks whether a serialized mapblock can be decoded, and where it fails otherwise
+    ///
+    /// Unlike [`from_data`](`Self::from_data`), this tells apart the stages of decoding
+    /// and reports the offset of a parse error within the decompressed data.
+    ///
+    /// ```
+    /// use minetestworld::map_block::BlockIntegrity;
+    /// use minetestworld::MapBlock;
+    ///
+    /// let data = std::fs::read("TestWorld/testmapblock").unwrap();
+    /// assert_eq!(MapBlock::check_data(&data), BlockIntegrity::Ok);
+    /// assert_eq!(MapBlock::check_data(&[25]), BlockIntegrity::UnknownVersion(25));
+    /// ```
+    pub fn check_data(data: &[u8]) -> BlockIntegrity {
+        let (map_format_version, buffer) = match decompress(data) {
+            Ok(decompressed) => decompressed,
+            Err(MapBlockError::MapVersionError(version)) => {
+                return BlockIntegrity::UnknownVersion(version)
+            }
+            Err(e) => return BlockIntegrity::DecompressFailed(e.to_string()),
+        };
+        let mut rest = buffer.as_slice();
+        match Self::parse(map_format_version, &mut rest, DecodeMode::Full) {
+            Ok(_) => BlockIntegrity::Ok,
+            Err(e) => BlockIntegrity::ParseFailed {
+                offset: buffer.len() - rest.len(),
+                error: e.to_string(),
+            },
+        }
     }
 
     /// Creates a not-yet-generated map block that only contains [`CONTENT_IGNORE`]
     pub fn unloaded() -> Self {
-        MapBlock {
+        let () = Self::VALID_SIZE;
+        let () = BlockSize::<LENGTH>::VALID;
+        SizedMapBlock {
             map_format_version: 29,
             flags: 0,
             lighting_complete: 0,
-            timestamp: 0xffffffff,
+            timestamp: TIMESTAMP_UNDEFINED,
             name_id_mappings: HashMap::from([(0, Vec::from(CONTENT_IGNORE))]),
             content_width: 2,
             params_width: 2,
-            param0: [0; BLOCK_NODES_3D_U],
-            param1: [0; BLOCK_NODES_3D_U],
-            param2: [0; BLOCK_NODES_3D_U],
+            param0: [0; NODES],
+            param1: [0; NODES],
+            param2: [0; NODES],
             node_metadata: vec![],
             node_timers: vec![],
             static_objects: vec![],
         }
     }
 
+    fn set_flag(&mut self, flag: u8, value: bool) {
+        if value {
+            self.flags |= flag;
+        } else {
+            self.flags &= !flag;
+        }
+    }
+
+    /// Returns true if the engine considers this mapblock to be below the surface
+    ///
+    /// Sunlight does not propagate into underground mapblocks from above.
+    pub fn is_underground(&self) -> bool {
+        self.flags & FLAG_IS_UNDERGROUND != 0
+    }
+
+    /// Marks this mapblock as underground, see [`is_underground`](`Self::is_underground`)
+    pub fn set_underground(&mut self, underground: bool) {
+        self.set_flag(FLAG_IS_UNDERGROUND, underground);
+    }
+
+    /// Returns true if the mapblock looks different by day than by night
+    ///
+    /// Mapblocks without this flag can be lit by either light bank.
+    pub fn day_night_differs(&self) -> bool {
+        self.flags & FLAG_DAY_NIGHT_DIFFERS != 0
+    }
+
+    /// Sets whether the mapblock looks different by day than by night
+    pub fn set_day_night_differs(&mut self, differs: bool) {
+        self.set_flag(FLAG_DAY_NIGHT_DIFFERS, differs);
+    }
+
+    /// Returns true if the mapgen has finished this mapblock
+    ///
+    /// The engine regenerates mapblocks without this flag when they are loaded.
+    pub fn is_generated(&self) -> bool {
+        self.flags & FLAG_NOT_GENERATED == 0
+    }
+
+    /// Sets whether the mapgen has finished this mapblock
+    pub fn set_generated(&mut self, generated: bool) {
+        self.set_flag(FLAG_NOT_GENERATED, !generated);
+    }
+
+    /// Returns true if the light of `bank` is complete at `face`
+    ///
+    /// ```
+    /// use minetestworld::map_block::{BlockFace, LightBank};
+    /// use minetestworld::MapBlock;
+    ///
+    /// let mut block = MapBlock::unloaded();
+    /// block.set_lighting_complete(LightBank::Night, BlockFace::NegY, true);
+    /// assert!(block.is_lighting_complete(LightBank::Night, BlockFace::NegY));
+    /// assert!(!block.is_lighting_complete(LightBank::Day, BlockFace::NegY));
+    /// ```
+    pub fn is_lighting_complete(&self, bank: LightBank, face: BlockFace) -> bool {
+        self.lighting_complete & lighting_bit(bank, face) != 0
+    }
+
+    /// Sets whether the light of `bank` is complete at `face`
+    pub fn set_lighting_complete(&mut self, bank: LightBank, face: BlockFace, complete: bool) {
+        if complete {
+            self.lighting_complete |= lighting_bit(bank, face);
+        } else {
+            self.lighting_complete &= !lighting_bit(bank, face);
+        }
+    }
+
+    /// Returns the time of the last save in seconds since the world's creation
+    ///
+    /// Mapblocks that have never been saved by the engine have no timestamp.
+    pub fn saved_at(&self) -> Option<u32> {
+        (self.timestamp != TIMESTAMP_UNDEFINED).then_some(self.timestamp)
+    }
+
+    /// Sets the time of the last save, see [`saved_at`](`Self::saved_at`)
+    pub fn set_saved_at(&mut self, timestamp: Option<u32>) {
+        self.timestamp = timestamp.unwrap_or(TIMESTAMP_UNDEFINED);
+    }
+
+    /// Updates the header of a modified mapblock according to `maintenance`
+    pub fn apply_maintenance(&mut self, maintenance: &WriteMaintenance) {
+        if let Some(timestamp) = maintenance.timestamp {
+            self.timestamp = timestamp;
+        }
+        if maintenance.invalidate_lighting {
+            self.lighting_complete = 0;
+        }
+    }
+
+    /// Reads only the name-id mappings of a serialized mapblock
+    ///
+    /// This skips decoding the nodes, metadata, objects and timers,
+    /// which makes it a cheap way to check which contents a mapblock contains.
+    /// Bulk operations use it to rule out most mapblocks before decoding them.
+    /// See [`header_from_data`](`Self::header_from_data`).
+    ///
+    /// ```
+    /// use minetestworld::MapBlock;
+    ///
+    /// let data = std::fs::read("TestWorld/testmapblock").unwrap();
+    /// let palette = MapBlock::palette_from_data(data.as_slice()).unwrap();
+    /// assert!(palette.values().any(|name| name == b"air"));
+    /// ```
+    pub fn palette_from_data(data: impl Read) -> Result<NameIdMappings, MapBlockError> {
+        Ok(Self::header_from_data(data)?.name_id_mappings)
+    }
+
+    /// Iterates over the name-id mappings as `(content_id, content_name)`, in no particular order
+    ///
+    /// The engine only saves the contents that are actually used in a mapblock,
+    /// so this is a cheap alternative to iterating all nodes.
+    pub fn palette(&self) -> impl Iterator<Item = (u16, &[u8])> {
+        self.name_id_mappings
+            .iter()
+            .map(|(&id, name)| (id, name.as_slice()))
+    }
+
+    /// Returns the content name of a content ID, if present
+    pub fn content_name(&self, content_id: u16) -> Option<&[u8]> {
+        self.name_id_mappings.get(&content_id).map(Vec::as_slice)
+    }
+
+    /// Returns true if `content` is part of the name-id mappings
+    ///
+    /// For mapblocks saved by the engine, this means that at least one node has this content.
+    pub fn has_content(&self, content: &[u8]) -> bool {
+        self.get_content_id(content).is_some()
+    }
+
     /// Gets the content type string from a content ID
     ///
     /// If the ID is not present, [`CONTENT_UNKNOWN`] is returned.
@@ -304,8 +1486,65 @@ impl MapBlock {
             .unwrap_or(CONTENT_UNKNOWN)
     }
 
+    /// Iterates over all nodes in the order of their node index
+    ///
+    /// The content names are borrowed from the name-id mappings,
+    /// so this does not allocate per node.
+    ///
+    /// ```
+    /// use minetestworld::MapBlock;
+    ///
+    /// let data = std::fs::read("TestWorld/testmapblock").unwrap();
+    /// let block = MapBlock::from_data(data.as_slice()).unwrap();
+    /// let air = block.nodes().filter(|node| node.content == b"air").count();
+    /// assert!(air > 0);
+    /// ```
+    pub fn nodes(&self) -> impl Iterator<Item = NodeRef<'_, LENGTH>> + '_ {
+        (0..NODES).map(move |index| NodeRef {
+            // NODES is the node count of a block, so the index fits
+            position: SizedNodePos::from(SizedNodeIndex::try_from(index as u16).unwrap()),
+            content: self.content_from_id(self.param0[index]),
+            param1: self.param1[index],
+            param2: self.param2[index],
+        })
+    }
+
+    /// Iterates over all nodes as `(position, content_id, param1, param2)`
+    ///
+    /// Comparing content IDs is cheaper than comparing names, so analysis code can look up
+    /// the IDs of interest once per block via [`get_content_id`](`Self::get_content_id`),
+    /// or resolve IDs to names via [`palette`](`Self::palette`) or
+    /// [`content_name`](`Self::content_name`).
+    ///
+    /// ```
+    /// use minetestworld::MapBlock;
+    ///
+    /// let data = std::fs::read("TestWorld/testmapblock").unwrap();
+    /// let block = MapBlock::from_data(data.as_slice()).unwrap();
+    /// if let Some(air) = block.get_content_id(b"air") {
+    ///     let lit_air = block
+    ///         .iter_content_ids()
+    ///         .filter(|&(_, content_id, param1, _)| content_id == air && param1 > 0)
+    ///         .count();
+    ///     println!("{lit_air} air nodes are lit");
+    /// }
+    /// ```
+    pub fn iter_content_ids(
+        &self,
+    ) -> impl Iterator<Item = (SizedNodePos<LENGTH>, u16, u8, u8)> + '_ {
+        (0..NODES).map(move |index| {
+            (
+                // NODES is the node count of a block, so the index fits
+                SizedNodePos::from(SizedNodeIndex::try_from(index as u16).unwrap()),
+                self.param0[index],
+                self.param1[index],
+                self.param2[index],
+            )
+        })
+    }
+
     /// Queries the mapblock for a node on the given mapblock-relative coordinates
-    pub fn get_node_at(&self, node_pos: NodePos) -> Node {
+    pub fn get_node_at(&self, node_pos: SizedNodePos<LENGTH>) -> Node {
         let index = usize::from(node_pos);
         let param0 = self.content_from_id(self.param0[index]);
         Node {
@@ -348,21 +1587,173 @@ impl MapBlock {
             .unwrap_or_else(|| self.add_content(content.to_vec()))
     }
 
+    /// Drops all content names that no node refers to anymore
+    ///
+    /// Like the engine does when saving a mapblock, the content IDs are
+    /// renumbered densely in the order of their first occurrence.
+    /// Nodes whose ID lacks a name are mapped to [`CONTENT_UNKNOWN`].
+    ///
+    /// Returns the number of dropped content names.
+    ///
+    /// ```
+    /// use minetestworld::MapBlock;
+    ///
+    /// let mut block = MapBlock::unloaded();
+    /// block.get_or_create_content_id(b"default:stone");
+    /// assert_eq!(block.compact_palette(), 1);
+    /// assert_eq!(block.content_names().collect::<Vec<_>>(), vec![b"ignore"]);
+    /// ```
+    pub fn compact_palette(&mut self) -> usize {
+        let previous_len = self.name_id_mappings.len();
+        let mut id_map = HashMap::new();
+        let mut compacted = HashMap::new();
+        for content_id in self.param0.iter_mut() {
+            let new_id = *id_map.entry(*content_id).or_insert_with(|| {
+                let new_id = compacted.len() as u16;
+                compacted.insert(
+                    new_id,
+                    self.name_id_mappings
+                        .get(content_id)
+                        .cloned()
+                        .unwrap_or_else(|| CONTENT_UNKNOWN.to_vec()),
+                );
+                new_id
+            });
+            *content_id = new_id;
+        }
+        self.name_id_mappings = compacted;
+        previous_len.saturating_sub(self.name_id_mappings.len())
+    }
+
+    /// Replaces the content `from` by `to` in all nodes of this mapblock
+    ///
+    /// If `to` is not part of the name-id mappings yet, only the mapping of `from`
+    /// is renamed. Otherwise, the nodes are changed to the content ID of `to`
+    /// and `from` is dropped from the mappings.
+    ///
+    /// Returns true if the mapblock has been modified.
+    ///
+    /// ```
+    /// use minetestworld::MapBlock;
+    ///
+    /// let mut block = MapBlock::unloaded();
+    /// assert!(block.replace_content(b"ignore", b"air"));
+    /// assert_eq!(block.content_names().collect::<Vec<_>>(), vec![b"air"]);
+    /// ```
+    pub fn replace_content(&mut self, from: &[u8], to: &[u8]) -> bool {
+        let Some(from_id) = self.get_content_id(from) else {
+            return false;
+        };
+        if from == to {
+            return false;
+        }
+        match self.get_content_id(to) {
+            Some(to_id) => {
+                for content_id in self.param0.iter_mut().filter(|id| **id == from_id) {
+                    *content_id = to_id;
+                }
+                self.name_id_mappings.remove(&from_id);
+            }
+            None => {
+                self.name_id_mappings.insert(from_id, to.to_vec());
+            }
+        }
+        true
+    }
+
     /// Sets the content type of this node
-    pub fn set_content(&mut self, node_pos: NodePos, content_id: u16) {
+    pub fn set_content(&mut self, node_pos: SizedNodePos<LENGTH>, content_id: u16) {
         self.param0[usize::from(node_pos)] = content_id
     }
 
     /// Sets the param1 of this node
-    pub fn set_param1(&mut self, node_pos: NodePos, param1: u8) {
+    pub fn set_param1(&mut self, node_pos: SizedNodePos<LENGTH>, param1: u8) {
         self.param1[usize::from(node_pos)] = param1
     }
 
     /// Sets the param2 of this node
-    pub fn set_param2(&mut self, node_pos: NodePos, param2: u8) {
+    pub fn set_param2(&mut self, node_pos: SizedNodePos<LENGTH>, param2: u8) {
         self.param2[usize::from(node_pos)] = param2
     }
 
+    /// Returns the metadata of the node at `node_pos`, if it has any
+    pub fn node_metadata_at(
+        &self,
+        node_pos: SizedNodePos<LENGTH>,
+    ) -> Option<&NodeMetadata<LENGTH>> {
+        self.node_metadata
+            .iter()
+            .find(|metadata| metadata.position == node_pos)
+    }
+
+    /// Returns the metadata of the node at `node_pos` for modification
+    ///
+    /// Empty metadata is created if the node has none yet.
+    pub fn node_metadata_mut(
+        &mut self,
+        node_pos: SizedNodePos<LENGTH>,
+    ) -> &mut NodeMetadata<LENGTH> {
+        let index = match self
+            .node_metadata
+            .iter()
+            .position(|metadata| metadata.position == node_pos)
+        {
+            Some(index) => index,
+            None => {
+                self.node_metadata.push(NodeMetadata {
+                    position: node_pos,
+                    vars: vec![],
+                    inventory: vec![],
+                });
+                self.node_metadata.len() - 1
+            }
+        };
+        &mut self.node_metadata[index]
+    }
+
+    /// Removes the metadata of the node at `node_pos`, returning it
+    pub fn remove_node_metadata(
+        &mut self,
+        node_pos: SizedNodePos<LENGTH>,
+    ) -> Option<NodeMetadata<LENGTH>> {
+        let index = self
+            .node_metadata
+            .iter()
+            .position(|metadata| metadata.position == node_pos)?;
+        Some(self.node_metadata.remove(index))
+    }
+
+    /// Returns the timer of the node at `node_pos`, if it has one
+    pub fn node_timer_at(&self, node_pos: SizedNodePos<LENGTH>) -> Option<&NodeTimer<LENGTH>> {
+        self.node_timers
+            .iter()
+            .find(|timer| timer.position == node_pos)
+    }
+
+    /// Installs `timer`, replacing a timer at the same position
+    pub fn set_node_timer(&mut self, timer: NodeTimer<LENGTH>) {
+        match self
+            .node_timers
+            .iter_mut()
+            .find(|t| t.position == timer.position)
+        {
+            Some(existing) => *existing = timer,
+            None => self.node_timers.push(timer),
+        }
+    }
+
+    /// Removes the timer of the node at `node_pos`, returning it
+    pub fn remove_node_timer(
+        &mut self,
+        node_pos: SizedNodePos<LENGTH>,
+    ) -> Option<NodeTimer<LENGTH>> {
+        let index = self
+            .node_timers
+            .iter()
+            .position(|timer| timer.position == node_pos)?;
+        Some(self.node_timers.remove(index))
+    }
+
     /// Returns an iterator over all content types that appear in name-id-mapping
     ///
     /// Example:
@@ -376,10 +1767,114 @@ impl MapBlock {
     pub fn content_names(&self) -> impl Iterator<Item = &[u8]> {
         self.name_id_mappings.values().map(Vec::as_slice)
     }
+
+    /// Removes all static objects for which `remove` returns true
+    ///
+    /// Returns the number of removed objects.
+    pub fn remove_static_objects(
+        &mut self,
+        mut remove: impl FnMut(&StaticObject) -> bool,
+    ) -> usize {
+        let count = self.static_objects.len();
+        self.static_objects.retain(|object| !remove(object));
+        count - self.static_objects.len()
+    }
+
+    /// Returns the position in node coordinates and the decoded data
+    /// of all static objects that are Lua entities
+    pub fn lua_entities(
+        &self,
+    ) -> impl Iterator<Item = Result<(Vec3, LuaEntity), MapBlockError>> + '_ {
+        self.static_objects.iter().filter_map(|object| {
+            object
+                .lua_entity()
+                .map(|entity| entity.map(|entity| (object.position(), entity)))
+                .transpose()
+        })
+    }
+
+    /// Lists the differences from this version of a mapblock to `other`
+    ///
+    /// The header, i.e. flags, lighting and timestamp, is not compared.
+    ///
+    /// ```
+    /// use minetestworld::MapBlock;
+    /// use minetestworld::positions::NodePos;
+    /// use glam::U16Vec3;
+    ///
+    /// let old = MapBlock::unloaded();
+    /// let mut new = MapBlock::unloaded();
+    /// let stone = new.get_or_create_content_id(b"default:stone");
+    /// let pos = NodePos::try_from(U16Vec3::new(1, 2, 3)).unwrap();
+    /// new.set_content(pos, stone);
+    ///
+    /// let delta = old.diff(&new);
+    /// assert_eq!(delta.nodes.len(), 1);
+    /// assert_eq!(delta.nodes[0].after.param0, b"default:stone");
+    /// assert_eq!(delta.added_contents, vec![b"default:stone".to_vec()]);
+    /// ```
+    pub fn diff(&self, other: &Self) -> BlockDelta<LENGTH> {
+        let mut delta = BlockDelta::default();
+        for index in 0..NODES {
+            let (id, other_id) = (self.param0[index], other.param0[index]);
+            if self.content_from_id(id) != other.content_from_id(other_id)
+                || self.param1[index] != other.param1[index]
+                || self.param2[index] != other.param2[index]
+            {
+                // NODES is the node count of a block, so the index fits
+                let position = SizedNodePos::from(SizedNodeIndex::try_from(index as u16).unwrap());
+                delta.nodes.push(NodeChange {
+                    position,
+                    before: self.get_node_at(position),
+                    after: other.get_node_at(position),
+                });
+            }
+        }
+
+        let contents: Vec<&[u8]> = self.content_names().collect();
+        let other_contents: Vec<&[u8]> = other.content_names().collect();
+        delta.added_contents = other_contents
+            .iter()
+            .filter(|name| !contents.contains(name))
+            .map(|name| name.to_vec())
+            .collect();
+        delta.added_contents.sort_unstable();
+        delta.removed_contents = contents
+            .iter()
+            .filter(|name| !other_contents.contains(name))
+            .map(|name| name.to_vec())
+            .collect();
+        delta.removed_contents.sort_unstable();
+
+        delta.metadata =
+            changed_positions(&self.node_metadata, &other.node_metadata, |m| m.position);
+        delta.added_objects = missing_objects(&other.static_objects, &self.static_objects);
+        delta.removed_objects = missing_objects(&self.static_objects, &other.static_objects);
+        delta.timers = changed_positions(&self.node_timers, &other.node_timers, |t| t.position);
+        delta
+    }
+}
+
+fn lighting_bit(bank: LightBank, face: BlockFace) -> u16 {
+    let bank_offset = match bank {
+        LightBank::Day => 0,
+        LightBank::Night => 6,
+    };
+    1 << (bank_offset + face as u16)
 }
 
 // Helper functions to read and write smaller chunks of binary data
 
+fn read_header(map_format_version: u8, data: &mut impl Read) -> Result<BlockHeader, MapBlockError> {
+    Ok(BlockHeader {
+        map_format_version,
+        flags: read_u8(data)?,
+        lighting_complete: read_u16_be(data)?,
+        timestamp: read_u32_be(data)?,
+        name_id_mappings: read_name_id_mappings(data)?,
+    })
+}
+
 fn read_name_id_mappings(data: &mut impl Read) -> Result<NameIdMappings, MapBlockError> {
     if read_u8(data)? != 0 {
         return Err(MapBlockError::BlobMalformed(
@@ -406,9 +1901,12 @@ fn read_name_id_mappings(data: &mut impl Read) -> Result<NameIdMappings, MapBloc
 }
 
 fn write_name_id_mappings(mappings: &NameIdMappings, dest: &mut impl Write) -> std::io::Result<()> {
+    // Sorted by ID, so that the output is deterministic
+    let mut sorted: Vec<_> = mappings.iter().collect();
+    sorted.sort_unstable_by_key(|(&key, _)| key);
     dest.write_all(&[0])?; // Version byte
     dest.write_all(&(mappings.len() as u16).to_be_bytes())?; // TODO handle length greater than 65k
-    for (key, value) in mappings {
+    for (key, value) in sorted {
         dest.write_all(&key.to_be_bytes())?;
         dest.write_all(&(value.len() as u16).to_be_bytes())?;
         dest.write_all(value)?;
@@ -416,29 +1914,39 @@ fn write_name_id_mappings(mappings: &NameIdMappings, dest: &mut impl Write) -> s
     Ok(())
 }
 
-fn read_inventory(data: &mut impl Read) -> std::io::Result<Vec<u8>> {
-    let mut result = vec![];
+fn read_inventory(data: &mut impl Read) -> Result<Vec<InventoryList>, MapBlockError> {
+    let mut serialized = vec![];
     let mut line = vec![];
 
-    for byte in data.bytes() {
-        let byte = byte?;
+    loop {
+        let byte = read_u8(data)?;
         line.push(byte);
         if byte == 10 {
-            result.extend_from_slice(&line);
+            serialized.extend_from_slice(&line);
             if line == b"EndInventory\n" {
-                return Ok(result);
+                break;
             }
             line.clear();
         }
     }
-
-    Err(std::io::Error::new(
-        std::io::ErrorKind::UnexpectedEof,
-        "inventory",
-    ))
+    // Item stacks are arbitrary bytes to the engine, so don't reject the whole block over them
+    let lines: Vec<Cow<str>> = serialized
+        .split_inclusive(|&byte| byte == b'\n')
+        .map(|line| line.strip_suffix(b"\n").unwrap_or(line))
+        .map(
+            |line| match (std::str::from_utf8(line), line.strip_prefix(b"Item ")) {
+                (Ok(line), _) => Cow::Borrowed(line),
+                (Err(_), Some(item)) => Cow::Owned(format!("Item {}", item_from_bytes(item))),
+                (Err(_), None) => String::from_utf8_lossy(line),
+            },
+        )
+        .collect();
+    parse_inventory(lines.iter().map(AsRef::as_ref)).map_err(MapBlockError::BlobMalformed)
 }
 
-fn read_node_metadata(data: &mut impl Read) -> Result<Vec<NodeMetadata>, MapBlockError> {
+fn read_node_metadata<const LENGTH: u16>(
+    data: &mut impl Read,
+) -> Result<Vec<NodeMetadata<LENGTH>>, MapBlockError> {
     let metadata_version = read_u8(data)?;
     if metadata_version == 0 {
         return Ok(vec![]);
@@ -449,13 +1957,13 @@ fn read_node_metadata(data: &mut impl Read) -> Result<Vec<NodeMetadata>, MapBloc
         ));
     }
     let metadata_count = read_u16_be(data)?;
-    let metadata = Vec::with_capacity(metadata_count as usize);
+    let mut metadata = Vec::with_capacity(metadata_count as usize);
 
     for _ in 0..metadata_count {
         let mut metadatum = NodeMetadata {
-            position: NodePos::from(NodeIndex::try_from(read_u16_be(data)?).map_err(|_| {
-                MapBlockError::BlobMalformed("node index exceeds valid range".into())
-            })?),
+            position: SizedNodePos::from(SizedNodeIndex::try_from(read_u16_be(data)?).map_err(
+                |_| MapBlockError::BlobMalformed("node index exceeds valid range".into()),
+            )?),
             vars: Default::default(),
             inventory: vec![],
         };
@@ -480,19 +1988,29 @@ fn read_node_metadata(data: &mut impl Read) -> Result<Vec<NodeMetadata>, MapBloc
             });
         }
         metadatum.inventory = read_inventory(data)?;
+        metadata.push(metadatum);
     }
 
     Ok(metadata)
 }
 
-fn write_node_metadata(data: &[NodeMetadata], dest: &mut impl Write) -> std::io::Result<()> {
+fn write_node_metadata<const LENGTH: u16>(
+    data: &[NodeMetadata<LENGTH>],
+    dest: &mut impl Write,
+) -> std::io::Result<()> {
+    // Like the engine, skip empty metadata
+    let data: Vec<_> = data
+        .iter()
+        .filter(|metadatum| !metadatum.is_empty())
+        .collect();
     if data.is_empty() {
         dest.write_all(&[0])?;
     } else {
         dest.write_all(&[2])?;
         dest.write_all(&(data.len() as u16).to_be_bytes())?; // TODO handle count greater than 65k
         for metadatum in data {
-            dest.write_all(&u16::from(NodeIndex::from(metadatum.position)).to_be_bytes())?;
+            dest.write_all(&u16::from(SizedNodeIndex::from(metadatum.position)).to_be_bytes())?;
+            dest.write_all(&(metadatum.vars.len() as u32).to_be_bytes())?;
             for var in &metadatum.vars {
                 dest.write_all(&(var.key.len() as u16).to_be_bytes())?;
                 dest.write_all(&var.key)?;
@@ -500,7 +2018,7 @@ fn write_node_metadata(data: &[NodeMetadata], dest: &mut impl Write) -> std::io:
                 dest.write_all(&var.value)?;
                 dest.write_all(&[var.is_private as u8])?;
             }
-            dest.write_all(&metadatum.inventory)?;
+            dest.write_all(serialize_inventory(&metadatum.inventory).as_bytes())?;
         }
     }
 
@@ -518,6 +2036,28 @@ fn read_object_pos(data: &mut impl Read) -> std::io::Result<(i32, i32, i32)> {
     ))
 }
 
+fn read_f1000(data: &mut impl Read) -> std::io::Result<f32> {
+    Ok(read_i32_be(data)? as f32 / 1000.0)
+}
+
+fn read_v3f1000(data: &mut impl Read) -> std::io::Result<Vec3> {
+    Ok(Vec3::new(
+        read_f1000(data)?,
+        read_f1000(data)?,
+        read_f1000(data)?,
+    ))
+}
+
+fn write_f1000(value: f32, dest: &mut Vec<u8>) {
+    dest.extend(((value * 1000.0).round() as i32).to_be_bytes());
+}
+
+fn write_v3f1000(value: Vec3, dest: &mut Vec<u8>) {
+    for component in value.to_array() {
+        write_f1000(component, dest);
+    }
+}
+
 fn read_static_objects(source: &mut impl Read) -> Result<Vec<StaticObject>, MapBlockError> {
     let version = read_u8(source)?;
     if version != 0 {
@@ -551,6 +2091,7 @@ fn write_static_objects(data: &[StaticObject], dest: &mut impl Write) -> std::io
     dest.write_all(&[0])?;
     dest.write_all(&(data.len() as u16).to_be_bytes())?;
     for object in data {
+        dest.write_all(&[object.type_id])?;
         for i in [object.x, object.y, object.z] {
             dest.write_all(&i.to_be_bytes())?;
         }
@@ -560,7 +2101,9 @@ fn write_static_objects(data: &[StaticObject], dest: &mut impl Write) -> std::io
     Ok(())
 }
 
-fn read_timers(data: &mut impl Read) -> Result<Vec<NodeTimer>, MapBlockError> {
+fn read_timers<const LENGTH: u16>(
+    data: &mut impl Read,
+) -> Result<Vec<NodeTimer<LENGTH>>, MapBlockError> {
     let timer_size = read_u8(data)?;
     if timer_size != 10 {
         return Err(MapBlockError::BlobMalformed(format!(
@@ -573,7 +2116,7 @@ fn read_timers(data: &mut impl Read) -> Result<Vec<NodeTimer>, MapBlockError> {
     let mut timers = Vec::with_capacity(count as usize);
 
     for _ in 0..count {
-        let position = NodeIndex::try_from(read_u16_be(data)?)
+        let position = SizedNodeIndex::try_from(read_u16_be(data)?)
             .map_err(|_| MapBlockError::BlobMalformed("Node index ot of range".into()))?
             .into();
         let timeout = read_i32_be(data)?;
@@ -588,11 +2131,14 @@ fn read_timers(data: &mut impl Read) -> Result<Vec<NodeTimer>, MapBlockError> {
     Ok(timers)
 }
 
-fn write_node_timers(data: &[NodeTimer], dest: &mut impl Write) -> std::io::Result<()> {
+fn write_node_timers<const LENGTH: u16>(
+    data: &[NodeTimer<LENGTH>],
+    dest: &mut impl Write,
+) -> std::io::Result<()> {
     dest.write_all(&[10])?; // Data length of node timers
     dest.write_all(&(data.len() as u16).to_be_bytes())?;
     for timer in data {
-        dest.write_all(&(u16::from(NodeIndex::from(timer.position))).to_be_bytes())?;
+        dest.write_all(&(u16::from(SizedNodeIndex::from(timer.position))).to_be_bytes())?;
         dest.write_all(&timer.timeout.to_be_bytes())?;
         dest.write_all(&timer.elapsed.to_be_bytes())?;
     }
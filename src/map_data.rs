@@ -5,8 +5,9 @@ use futures::future;
 use futures::stream;
 use futures::stream::BoxStream;
 use futures::stream::StreamExt;
-use futures::TryStreamExt;
-use glam::I16Vec3;
+use futures::Stream;
+use futures::{TryFutureExt, TryStreamExt};
+use glam::{I16Vec2, I16Vec3};
 #[cfg(feature = "experimental-leveldb")]
 use leveldb_rs::{LevelDBError, DB as LevelDb};
 use log::LevelFilter;
@@ -18,25 +19,308 @@ use sqlx::sqlite::{SqliteConnectOptions, SqlitePool};
 use sqlx::{postgres::PgConnectOptions, PgPool};
 #[cfg(any(feature = "sqlite", feature = "postgres"))]
 use sqlx::{prelude::*, ConnectOptions};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
 #[cfg(any(feature = "sqlite", feature = "experimental-leveldb"))]
 use std::path::Path;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 #[cfg(feature = "redis")]
 use url::Host;
 
-use crate::map_block::{MapBlock, MapBlockError, Node, NodeIter};
+#[cfg(feature = "sqlite")]
+use crate::journal::Journal;
+use crate::map_block::{
+    BlockHeader, BlockIntegrity, DecodeMode, MapBlock, MapBlockError, NameIdMappings, Node,
+    NodeIter, NodeRef, StaticObject, WriteMaintenance,
+};
+use crate::positions::BlockArea;
 use crate::positions::BlockKey;
 use crate::positions::BlockPos;
+use crate::positions::MortonKey;
+#[cfg(feature = "sqlite")]
+use crate::{BLOCK_BITS_1D, BLOCK_KEY_MIN};
+use crate::{BLOCK_NODES_1D, NODE_BITS_1D, WORLD_BLOCKS_MAX, WORLD_BLOCKS_MIN};
 
 const POSTGRES_QUERY: &str = "SELECT data FROM blocks
  WHERE (posx = $1 AND posy = $2 AND posz = $3)";
 
+const SQLITE_POSITIONS_PAGE: &str = "SELECT pos FROM blocks
+ WHERE pos > ? ORDER BY pos LIMIT ?";
+
+const POSTGRES_POSITIONS_PAGE: &str = "SELECT posx, posy, posz FROM blocks
+ WHERE (posz, posy, posx) > ($3, $2, $1) ORDER BY posz, posy, posx LIMIT $4";
+
+// The key span narrows the search by the primary key index,
+// the coordinates decoded from the shifted key select the box within it
+const SQLITE_POSITIONS_IN: &str = "SELECT pos FROM blocks WHERE pos BETWEEN ?1 AND ?2
+ AND (pos - ?3) % ?4 BETWEEN ?5 AND ?6 AND (pos - ?3) / ?4 % ?4 BETWEEN ?7 AND ?8";
+
+const POSTGRES_POSITIONS_IN: &str = "SELECT posx, posy, posz FROM blocks
+ WHERE posx BETWEEN $1 AND $2 AND posy BETWEEN $3 AND $4 AND posz BETWEEN $5 AND $6";
+
+/// Splits the block keys into coordinates, like the engine's `getIntegerAsBlock`
+const SQLITE_BOUNDS: &str = "SELECT MIN(x), MAX(x), MIN(y), MAX(y),
+ MIN((rest - y) / 4096), MAX((rest - y) / 4096) FROM
+ (SELECT x, rest, (rest % 4096 + 6144) % 4096 - 2048 AS y FROM
+ (SELECT x, (pos - x) / 4096 AS rest FROM
+ (SELECT pos, (pos % 4096 + 6144) % 4096 - 2048 AS x FROM blocks)))";
+
+const POSTGRES_BOUNDS: &str = "SELECT MIN(posx), MAX(posx), MIN(posy), MAX(posy),
+ MIN(posz), MAX(posz) FROM blocks";
+
 const SQLITE_UPSERT: &str = "INSERT INTO blocks VALUES (?, ?)
  ON CONFLICT(pos) DO UPDATE SET data=excluded.data";
 
 const POSTGRES_UPSERT: &str = "INSERT INTO blocks VALUES($1, $2, $3, $4)
  ON CONFLICT(posx,posy,posz) DO UPDATE SET data=excluded.data";
 
+/// A callback that modifies a mapblock while it is being copied
+pub type BlockTransform<'a> = &'a mut dyn FnMut(BlockPos, &mut MapBlock);
+
+/// What [`MapData::rewrite_blocks`] does with a mapblock
+pub(crate) enum Rewrite {
+    /// The mapblock is left as it is
+    Keep,
+    /// The mapblock is replaced by this serialized mapblock
+    Replace(Vec<u8>),
+    /// The mapblock is removed
+    Delete,
+}
+
+/// A step of [`MapData::optimize`], reported before it starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptimizeStep {
+    /// Unused space is released, so that the database file shrinks
+    Vacuum,
+    /// The statistics of the query planner are updated
+    Analyze,
+}
+
+/// How far a long-running operation has come, as passed to its progress callback
+///
+/// The callback is called once before the first unit of work, and after every unit.
+/// A unit is usually a mapblock.
+#[derive(Debug, Clone, Copy)]
+pub struct Progress {
+    /// Number of units that are done
+    pub done: usize,
+    /// Number of units in total
+    pub total: usize,
+    started: Instant,
+}
+
+impl Progress {
+    pub(crate) fn new(total: usize) -> Self {
+        Progress {
+            done: 0,
+            total,
+            started: Instant::now(),
+        }
+    }
+
+    /// Counts one more unit as done and returns the new state
+    pub(crate) fn advance(&mut self) -> Self {
+        self.done += 1;
+        *self
+    }
+
+    /// Returns the share of units that are done, between 0 and 1
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.done as f64 / self.total as f64
+        }
+    }
+
+    /// Returns the time since the operation started
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Estimates the remaining time from the average time per unit so far
+    ///
+    /// Returns `None` as long as no unit is done.
+    pub fn eta(&self) -> Option<Duration> {
+        let remaining = self.total.saturating_sub(self.done);
+        (self.done > 0).then(|| self.elapsed().mul_f64(remaining as f64 / self.done as f64))
+    }
+}
+
+/// Stops long-running operations from another task or thread
+///
+/// All clones of a token share their state, so one clone can be handed to the operation
+/// while another one is kept to cancel it, e.g. from a GUI's cancel button.
+/// Operations check the token between mapblocks, so a mapblock is never written partially.
+/// Every mapblock is written on its own, so the ones written before the cancellation are kept.
+///
+/// ```
+/// use minetestworld::map_data::CancellationToken;
+/// use minetestworld::{MapData, MapDataError};
+/// use futures::StreamExt;
+/// use async_std::task;
+///
+/// task::block_on(async {
+///     let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true).await.unwrap();
+///     let token = CancellationToken::new();
+///     let mut results = token.guard(map.check_integrity().await);
+///     results.next().await.unwrap().unwrap();
+///     token.cancel();
+///     assert!(matches!(results.next().await, Some(Err(MapDataError::Cancelled))));
+///     assert!(results.next().await.is_none());
+/// });
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(std::sync::Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Creates a token that is not cancelled yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels all operations that observe this token or one of its clones
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true if [`cancel`](`Self::cancel`) has been called
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Returns [`MapDataError::Cancelled`] if the token has been cancelled
+    pub fn check(&self) -> Result<(), MapDataError> {
+        if self.is_cancelled() {
+            Err(MapDataError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Makes a stream stop once this token is cancelled
+    ///
+    /// Before each item, the token is checked. After cancellation, the stream yields
+    /// [`MapDataError::Cancelled`] once and ends, without polling `stream` again.
+    /// This works with all streams of [`MapData`], e.g. [`MapData::find_nodes`]
+    /// and [`MapData::check_integrity`].
+    pub fn guard<'a, T: Send + 'a>(
+        &self,
+        stream: BoxStream<'a, Result<T, MapDataError>>,
+    ) -> BoxStream<'a, Result<T, MapDataError>> {
+        let token = self.clone();
+        stream::unfold(Some(stream), move |stream| {
+            let token = token.clone();
+            async move {
+                let mut stream = stream?;
+                if token.is_cancelled() {
+                    return Some((Err(MapDataError::Cancelled), None));
+                }
+                let item = stream.next().await?;
+                Some((item, Some(stream)))
+            }
+        })
+        .boxed()
+    }
+}
+
+/// Returns the index of the node column at `x`, `z` within the area between `min` and `max`
+fn column_index(min: I16Vec2, max: I16Vec2, x: i16, z: i16) -> Option<usize> {
+    let pos = I16Vec2::new(x, z);
+    (pos.cmpge(min).all() && pos.cmple(max).all()).then(|| {
+        let width = (i32::from(max.x) - i32::from(min.x) + 1) as usize;
+        (i32::from(x) - i32::from(min.x)) as usize
+            + width * (i32::from(z) - i32::from(min.y)) as usize
+    })
+}
+
+/// Iterates over the node columns between `min` and `max` as `(x, z)`, with x changing fastest
+fn columns(min: I16Vec2, max: I16Vec2) -> impl Iterator<Item = I16Vec2> {
+    (min.y..=max.y).flat_map(move |z| (min.x..=max.x).map(move |x| I16Vec2::new(x, z)))
+}
+
+/// The highest matching node of every node column in an area,
+/// as found by [`MapData::surface`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Surface {
+    min: I16Vec2,
+    max: I16Vec2,
+    nodes: Vec<Option<(i16, Node)>>,
+}
+
+impl Surface {
+    /// Returns the corner with the smallest `(x, z)` coordinates
+    pub fn min(&self) -> I16Vec2 {
+        self.min
+    }
+
+    /// Returns the corner with the largest `(x, z)` coordinates
+    pub fn max(&self) -> I16Vec2 {
+        self.max
+    }
+
+    /// Returns the y coordinate and the node of the highest matching node in the column at `x`, `z`
+    ///
+    /// `None` if no node in the column matched, or if the column lies outside of the area.
+    pub fn get(&self, x: i16, z: i16) -> Option<&(i16, Node)> {
+        column_index(self.min, self.max, x, z).and_then(|i| self.nodes[i].as_ref())
+    }
+
+    /// Iterates over all columns as `((x, z), surface)`, with x changing fastest
+    pub fn iter(&self) -> impl Iterator<Item = (I16Vec2, Option<&(i16, Node)>)> + '_ {
+        columns(self.min, self.max).zip(self.nodes.iter().map(Option::as_ref))
+    }
+}
+
+/// The height of the highest matching node of every node column in an area,
+/// as computed by [`MapData::heightmap`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heightmap {
+    min: I16Vec2,
+    max: I16Vec2,
+    heights: Vec<Option<i16>>,
+}
+
+impl Heightmap {
+    /// Returns the corner with the smallest `(x, z)` coordinates
+    pub fn min(&self) -> I16Vec2 {
+        self.min
+    }
+
+    /// Returns the corner with the largest `(x, z)` coordinates
+    pub fn max(&self) -> I16Vec2 {
+        self.max
+    }
+
+    /// Returns the y coordinate of the highest matching node in the column at `x`, `z`
+    ///
+    /// `None` if no node in the column matched, or if the column lies outside of the area.
+    pub fn get(&self, x: i16, z: i16) -> Option<i16> {
+        column_index(self.min, self.max, x, z).and_then(|i| self.heights[i])
+    }
+
+    /// Iterates over all columns as `((x, z), height)`, with x changing fastest
+    pub fn iter(&self) -> impl Iterator<Item = (I16Vec2, Option<i16>)> + '_ {
+        columns(self.min, self.max).zip(self.heights.iter().copied())
+    }
+}
+
+impl From<Surface> for Heightmap {
+    fn from(surface: Surface) -> Self {
+        Heightmap {
+            min: surface.min,
+            max: surface.max,
+            heights: surface
+                .nodes
+                .into_iter()
+                .map(|node| node.map(|(y, _)| y))
+                .collect(),
+        }
+    }
+}
+
 /// An error in the underlying database or in the map block binary format
 #[derive(thiserror::Error, Debug)]
 pub enum MapDataError {
@@ -63,9 +347,33 @@ pub enum MapDataError {
     #[error("MapBlock {0:?} does not exist")]
     MapBlockNonexistent(BlockPos),
 
+    /// This mapblock lies beyond the generation limit
+    ///
+    /// Only returned with the `strict-bounds` feature.
+    #[error("MapBlock {0:?} lies beyond the generation limit")]
+    BeyondGenerationLimit(BlockPos),
+
+    /// A stored block key lies outside of the world
+    #[error("Block key {0} is out of range")]
+    InvalidBlockKey(i64),
+
     /// An IO related error
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
+
+    /// An error that occurred while processing a specific mapblock
+    #[error("MapBlock {0:?}: {1}")]
+    AtBlock(BlockPos, Box<MapDataError>),
+
+    /// A commit has been interrupted and the map has been opened read-only
+    ///
+    /// Open the map writable to complete the commit, see [`Journal`](`crate::journal::Journal`).
+    #[error("The commit journal {0:?} is pending")]
+    PendingJournal(std::path::PathBuf),
+
+    /// The operation was stopped by a [`CancellationToken`]
+    #[error("Operation cancelled")]
+    Cancelled,
 }
 
 impl MapDataError {
@@ -113,6 +421,8 @@ impl MapData {
     /// Connects to the "map.sqlite" database.
     ///
     /// If the `blocks` table does not exist, tries to create it.
+    /// A pending [journal](`Journal::for_map_file`) next to the database is recovered,
+    /// or reported as [`MapDataError::PendingJournal`] if `read_only` is set.
     ///
     /// ```
     /// use minetestworld::MapData;
@@ -126,6 +436,7 @@ impl MapData {
         filename: impl AsRef<Path>,
         read_only: bool,
     ) -> Result<MapData, MapDataError> {
+        let filename = filename.as_ref();
         let opts = SqliteConnectOptions::new()
             .immutable(read_only)
             .filename(filename)
@@ -134,7 +445,11 @@ impl MapData {
         match SqlitePool::connect_with(opts).await {
             Ok(pool) => {
                 sqlx::query("CREATE TABLE IF NOT EXISTS blocks (`pos` INT NOT NULL PRIMARY KEY,`data` BLOB)").execute(&pool).await?;
-                Ok(MapData::Sqlite(pool))
+                let map = MapData::Sqlite(pool);
+                Journal::for_map_file(filename)
+                    .check_on_open(&map, read_only)
+                    .await?;
+                Ok(map)
             }
             Err(e) => Err(MapDataError::SqlError(e)),
         }
@@ -176,7 +491,7 @@ impl MapData {
     ///
     /// Note that the unit of the coordinates will be
     /// [MAPBLOCK_LENGTH][`crate::map_block::MAPBLOCK_LENGTH`].
-    pub async fn all_mapblock_positions(&self) -> BoxStream<Result<BlockPos, MapDataError>> {
+    pub async fn all_mapblock_positions(&self) -> BoxStream<'_, Result<BlockPos, MapDataError>> {
         match self {
             #[cfg(feature = "sqlite")]
             MapData::Sqlite(pool) => sqlx::query_as("SELECT pos FROM blocks")
@@ -228,7 +543,264 @@ impl MapData {
         }
     }
 
+    /// Fetches and decodes all mapblocks, in no particular order
+    ///
+    /// With SQLite and PostgreSQL, the positions and the data are read by a single
+    /// query, instead of one query per mapblock. Like with
+    /// [`all_mapblock_positions`](`Self::all_mapblock_positions`), no mapblock may be
+    /// written while the stream is running, as SQLite does not tolerate concurrent
+    /// read and write access. To modify mapblocks, collect their positions first.
+    ///
+    /// ```
+    /// use minetestworld::MapData;
+    /// use futures::TryStreamExt;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true).await.unwrap();
+    ///     let mut blocks = map.all_mapblocks().await;
+    ///     while let Some((pos, block)) = blocks.try_next().await.unwrap() {
+    ///         assert_eq!(block.param0.len(), 4096, "{pos}");
+    ///     }
+    /// });
+    /// ```
+    pub async fn all_mapblocks(&self) -> BoxStream<'_, Result<(BlockPos, MapBlock), MapDataError>> {
+        let decode = |(pos, data): (BlockPos, Vec<u8>)| -> Result<_, MapDataError> {
+            Ok((pos, MapBlock::from_data(&data)?))
+        };
+        match self {
+            #[cfg(feature = "sqlite")]
+            MapData::Sqlite(pool) => sqlx::query("SELECT pos, data FROM blocks")
+                .fetch(pool)
+                .map(|row| -> Result<(BlockPos, Vec<u8>), MapDataError> {
+                    let row = row?;
+                    Ok((BlockPos::from_row(&row)?, row.try_get("data")?))
+                })
+                .and_then(move |block| future::ready(decode(block)))
+                .boxed(),
+            #[cfg(feature = "postgres")]
+            MapData::Postgres(pool) => sqlx::query("SELECT posx, posy, posz, data FROM blocks")
+                .fetch(pool)
+                .map(|row| -> Result<(BlockPos, Vec<u8>), MapDataError> {
+                    let row = row?;
+                    Ok((BlockPos::from_row(&row)?, row.try_get("data")?))
+                })
+                .and_then(move |block| future::ready(decode(block)))
+                .boxed(),
+            #[cfg(feature = "redis")]
+            MapData::Redis { connection, hash } => {
+                // Like the positions, the blocks can't be streamed
+                let blocks: Result<Vec<(i64, Vec<u8>)>, _> =
+                    connection.clone().hgetall(hash.to_string()).await;
+                match blocks {
+                    Ok(blocks) => stream::iter(blocks.into_iter().map(move |(key, data)| {
+                        let key = BlockKey::try_from(key)
+                            .map_err(|_| MapDataError::InvalidBlockKey(key))?;
+                        decode((BlockPos::from(key), data))
+                    }))
+                    .boxed(),
+                    Err(e) => stream::once(future::ready(Err(MapDataError::RedisError(e)))).boxed(),
+                }
+            }
+            #[cfg(feature = "experimental-leveldb")]
+            MapData::LevelDb(_) => self
+                .all_mapblock_positions()
+                .await
+                .and_then(move |pos| self.get_mapblock(pos).map_ok(move |block| (pos, block)))
+                .boxed(),
+        }
+    }
+
+    /// Returns the positions of all mapblocks within `region`
+    ///
+    /// SQLite and PostgreSQL filter the positions in the database and stream them,
+    /// so that only the positions of `region` are transferred.
+    /// The other backends filter all positions.
+    ///
+    /// ```
+    /// use minetestworld::MapData;
+    /// use minetestworld::positions::{BlockArea, BlockPos};
+    /// use futures::TryStreamExt;
+    /// use glam::I16Vec3;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true).await.unwrap();
+    ///     let region = BlockArea::new(
+    ///         BlockPos::from_index_vec(I16Vec3::new(-13, -3, 3)),
+    ///         BlockPos::from_index_vec(I16Vec3::new(-11, -1, 5)),
+    ///     );
+    ///     let positions: Vec<_> = map.mapblock_positions_in(region).await.try_collect().await.unwrap();
+    ///     assert!(positions.iter().all(|pos| region.contains(*pos)));
+    /// });
+    /// ```
+    pub async fn mapblock_positions_in(
+        &self,
+        region: BlockArea,
+    ) -> BoxStream<'_, Result<BlockPos, MapDataError>> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            MapData::Sqlite(pool) => {
+                // The shifted key is never negative
+                let min = region.min().into_index_vec() - I16Vec3::splat(WORLD_BLOCKS_MIN);
+                let max = region.max().into_index_vec() - I16Vec3::splat(WORLD_BLOCKS_MIN);
+                sqlx::query_as(SQLITE_POSITIONS_IN)
+                    .bind(i64::from(BlockKey::from(region.min())))
+                    .bind(i64::from(BlockKey::from(region.max())))
+                    .bind(BLOCK_KEY_MIN)
+                    .bind(1_i64 << BLOCK_BITS_1D)
+                    .bind(min.x)
+                    .bind(max.x)
+                    .bind(min.y)
+                    .bind(max.y)
+                    .fetch(pool)
+                    .map_err(MapDataError::SqlError)
+                    .boxed()
+            }
+            #[cfg(feature = "postgres")]
+            MapData::Postgres(pool) => {
+                let min = region.min().into_index_vec();
+                let max = region.max().into_index_vec();
+                sqlx::query_as(POSTGRES_POSITIONS_IN)
+                    .bind(min.x)
+                    .bind(max.x)
+                    .bind(min.y)
+                    .bind(max.y)
+                    .bind(min.z)
+                    .bind(max.z)
+                    .fetch(pool)
+                    .map_err(MapDataError::SqlError)
+                    .boxed()
+            }
+            // These backends can't filter, so all positions are scanned
+            #[cfg(any(feature = "redis", feature = "experimental-leveldb"))]
+            _ => self
+                .all_mapblock_positions()
+                .await
+                .try_filter(move |pos| future::ready(region.contains(*pos)))
+                .boxed(),
+        }
+    }
+
+    /// Returns up to `limit` mapblock positions whose key is greater than `after`
+    ///
+    /// The positions are ordered by their [`BlockKey`], so that a scan can be resumed
+    /// by passing the key of the last returned position as `after`.
+    /// Passing `None` starts at the beginning. An empty result marks the end of the scan.
+    ///
+    /// ```
+    /// use minetestworld::MapData;
+    /// use minetestworld::positions::BlockKey;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let mapdata = MapData::from_sqlite_file("TestWorld/map.sqlite", true).await.unwrap();
+    ///     let first = mapdata.mapblock_positions_page(None, 10).await.unwrap();
+    ///     let cursor = BlockKey::from(*first.last().unwrap());
+    ///     let second = mapdata.mapblock_positions_page(Some(cursor), 10).await.unwrap();
+    ///     assert!(BlockKey::from(second[0]) > cursor);
+    /// });
+    /// ```
+    pub async fn mapblock_positions_page(
+        &self,
+        after: Option<BlockKey>,
+        limit: usize,
+    ) -> Result<Vec<BlockPos>, MapDataError> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            MapData::Sqlite(pool) => Ok(sqlx::query_as(SQLITE_POSITIONS_PAGE)
+                .bind(after.map_or(BLOCK_KEY_MIN - 1, i64::from))
+                .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+                .fetch_all(pool)
+                .await?),
+            #[cfg(feature = "postgres")]
+            MapData::Postgres(pool) => {
+                let after = after.map_or(I16Vec3::MIN, |key| BlockPos::from(key).into_index_vec());
+                Ok(sqlx::query_as(POSTGRES_POSITIONS_PAGE)
+                    .bind(after.x)
+                    .bind(after.y)
+                    .bind(after.z)
+                    .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+                    .fetch_all(pool)
+                    .await?)
+            }
+            // These backends can't seek, so the page is cut out of the complete key set
+            #[cfg(any(feature = "redis", feature = "experimental-leveldb"))]
+            _ => {
+                let mut keys: Vec<BlockKey> = self
+                    .all_mapblock_positions()
+                    .await
+                    .map_ok(BlockKey::from)
+                    .try_filter(|key| future::ready(after.is_none_or(|after| *key > after)))
+                    .try_collect()
+                    .await?;
+                keys.sort_unstable();
+                keys.truncate(limit);
+                Ok(keys.into_iter().map(BlockPos::from).collect())
+            }
+        }
+    }
+
+    /// Returns the smallest box that contains all stored mapblocks, or `None` if there are none
+    ///
+    /// With SQLite and PostgreSQL, this is computed by the database
+    /// without transferring the positions.
+    ///
+    /// ```
+    /// use minetestworld::MapData;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let mapdata = MapData::from_sqlite_file("TestWorld/map.sqlite", true).await.unwrap();
+    ///     let bounds = mapdata.generated_bounds().await.unwrap().unwrap();
+    ///     println!("The map spans from {:?} to {:?}", bounds.min(), bounds.max());
+    /// });
+    /// ```
+    pub async fn generated_bounds(&self) -> Result<Option<BlockArea>, MapDataError> {
+        let corners = match self {
+            #[cfg(feature = "sqlite")]
+            MapData::Sqlite(pool) => {
+                let row = sqlx::query(SQLITE_BOUNDS).fetch_one(pool).await?;
+                let mut columns = [None; 6];
+                for (i, column) in columns.iter_mut().enumerate() {
+                    *column = row.try_get::<Option<i64>, _>(i)?.map(|value| value as i16);
+                }
+                corners(columns)
+            }
+            #[cfg(feature = "postgres")]
+            MapData::Postgres(pool) => {
+                let row = sqlx::query(POSTGRES_BOUNDS).fetch_one(pool).await?;
+                let mut columns = [None; 6];
+                for (i, column) in columns.iter_mut().enumerate() {
+                    *column = row.try_get::<Option<i32>, _>(i)?.map(|value| value as i16);
+                }
+                corners(columns)
+            }
+            // These backends can't aggregate, so all positions are scanned
+            #[cfg(any(feature = "redis", feature = "experimental-leveldb"))]
+            _ => {
+                self.all_mapblock_positions()
+                    .await
+                    .try_fold(None, |corners, pos| {
+                        let index = pos.into_index_vec();
+                        future::ready(Ok(Some(match corners {
+                            Some((min, max)) => (index.min(min), index.max(max)),
+                            None => (index, index),
+                        })))
+                    })
+                    .await?
+            }
+        };
+        Ok(corners.map(|(min, max)| {
+            BlockArea::new(BlockPos::from_index_vec(min), BlockPos::from_index_vec(max))
+        }))
+    }
+
     /// Queries the backend for the data of a single mapblock
+    ///
+    /// The data is returned as stored, i.e. compressed and without being decoded.
+    /// Together with [`set_block_data`](`Self::set_block_data`), this allows copying
+    /// or archiving mapblocks losslessly and without the cost of recompression.
     pub async fn get_block_data(&self, pos: BlockPos) -> Result<Vec<u8>, MapDataError> {
         let block_key = i64::from(BlockKey::from(pos));
         let pos_vec = pos.into_index_vec();
@@ -264,6 +836,90 @@ impl MapData {
         }
     }
 
+    /// Returns the serialized mapblocks of `region` that exist, in no particular order
+    ///
+    /// SQLite, PostgreSQL and Redis fetch all of them with a single query,
+    /// which is much faster than calling [`get_block_data`](`Self::get_block_data`)
+    /// for each position of the region.
+    ///
+    /// ```
+    /// use minetestworld::MapData;
+    /// use minetestworld::positions::{BlockArea, BlockPos};
+    /// use glam::I16Vec3;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true).await.unwrap();
+    ///     let region = BlockArea::new(
+    ///         BlockPos::from_index_vec(I16Vec3::new(-12, -2, 4)),
+    ///         BlockPos::from_index_vec(I16Vec3::new(-11, -1, 4)),
+    ///     );
+    ///     let blocks = map.get_region_data(region).await.unwrap();
+    ///     assert!(blocks.iter().all(|(pos, _)| region.contains(*pos)));
+    /// });
+    /// ```
+    pub async fn get_region_data(
+        &self,
+        region: BlockArea,
+    ) -> Result<Vec<(BlockPos, Vec<u8>)>, MapDataError> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            MapData::Sqlite(pool) => {
+                let query = format!(
+                    "SELECT pos, data FROM blocks WHERE {}",
+                    region.sqlite_predicate("pos")
+                );
+                let rows = sqlx::query(&query).fetch_all(pool).await?;
+                Ok(rows
+                    .iter()
+                    .map(|row| Ok((BlockPos::from_row(row)?, row.try_get("data")?)))
+                    .collect::<Result<_, sqlx::Error>>()?)
+            }
+            #[cfg(feature = "postgres")]
+            MapData::Postgres(pool) => {
+                let query = format!(
+                    "SELECT posx, posy, posz, data FROM blocks WHERE {}",
+                    region.postgres_predicate()
+                );
+                let rows = sqlx::query(&query).fetch_all(pool).await?;
+                Ok(rows
+                    .iter()
+                    .map(|row| Ok((BlockPos::from_row(row)?, row.try_get("data")?)))
+                    .collect::<Result<_, sqlx::Error>>()?)
+            }
+            #[cfg(feature = "redis")]
+            MapData::Redis { connection, hash } => {
+                let positions: Vec<_> = region.iter().collect();
+                let keys: Vec<_> = positions
+                    .iter()
+                    .map(|&pos| i64::from(BlockKey::from(pos)))
+                    .collect();
+                let values: Vec<Option<Vec<u8>>> = redis::cmd("HMGET")
+                    .arg(hash.to_string())
+                    .arg(keys)
+                    .query_async(&mut connection.clone())
+                    .await?;
+                Ok(positions
+                    .into_iter()
+                    .zip(values)
+                    .filter_map(|(pos, data)| Some((pos, data?)))
+                    .collect())
+            }
+            #[cfg(feature = "experimental-leveldb")]
+            MapData::LevelDb(_) => {
+                let mut blocks = vec![];
+                for pos in region.iter() {
+                    match self.get_block_data(pos).await {
+                        Ok(data) => blocks.push((pos, data)),
+                        Err(MapDataError::MapBlockNonexistent(_)) => {}
+                        Err(e) => return Err(e),
+                    }
+                }
+                Ok(blocks)
+            }
+        }
+    }
+
     /// Queries the backend for a specific map block
     ///
     /// `pos` is a map block position; this means that every dimension is divided
@@ -275,7 +931,25 @@ impl MapData {
     }
 
     /// Sets the backend's mapblock data for position `pos` to `data`
+    ///
+    /// This is the same as [`set_block_data`](`Self::set_block_data`).
+    #[deprecated(note = "use `set_block_data` instead")]
     pub async fn set_mapblock_data(&self, pos: BlockPos, data: &[u8]) -> Result<(), MapDataError> {
+        self.set_block_data(pos, data).await
+    }
+
+    /// Stores `data` verbatim as the mapblock at `pos`
+    ///
+    /// `data` has to be a serialized mapblock, e.g. as returned by
+    /// [`get_block_data`](`Self::get_block_data`). It is neither decoded nor validated.
+    ///
+    /// With the `strict-bounds` feature, writing a block beyond the
+    /// [generation limit](`BlockPos::is_within_generation_limit`) is refused.
+    pub async fn set_block_data(&self, pos: BlockPos, data: &[u8]) -> Result<(), MapDataError> {
+        #[cfg(feature = "strict-bounds")]
+        if !pos.is_within_generation_limit() {
+            return Err(MapDataError::BeyondGenerationLimit(pos));
+        }
         let block_key = i64::from(BlockKey::from(pos));
         let pos_vec = pos.into_index_vec();
         match self {
@@ -306,9 +980,736 @@ impl MapData {
         }
     }
 
+    /// Writes many serialized mapblocks at once
+    ///
+    /// With SQLite and PostgreSQL, all mapblocks are written in a single transaction,
+    /// which is much faster than writing them one by one. Redis writes them in one
+    /// atomic pipeline. Either all mapblocks are written or none of them.
+    ///
+    /// ```no_run
+    /// use minetestworld::World;
+    /// use minetestworld::positions::BlockPos;
+    /// use glam::I16Vec3;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let source = World::open("TestWorld").get_map_data().await.unwrap();
+    ///     let dest = World::open("MyWorld").get_map_data_backend(false).await.unwrap();
+    ///     let pos = BlockPos::from_index_vec(I16Vec3::new(-12, 2, 4));
+    ///     let data = source.get_block_data(pos).await.unwrap();
+    ///     let copies: Vec<_> = (0..16)
+    ///         .map(|x| (BlockPos::from_index_vec(I16Vec3::new(x, 2, 4)), data.as_slice()))
+    ///         .collect();
+    ///     dest.set_mapblocks_data(copies).await.unwrap();
+    /// });
+    /// ```
+    pub async fn set_mapblocks_data<'d>(
+        &self,
+        blocks: impl IntoIterator<Item = (BlockPos, &'d [u8])>,
+    ) -> Result<(), MapDataError> {
+        let blocks: Vec<_> = blocks.into_iter().collect();
+        #[cfg(feature = "strict-bounds")]
+        if let Some(&(pos, _)) = blocks
+            .iter()
+            .find(|(pos, _)| !pos.is_within_generation_limit())
+        {
+            return Err(MapDataError::BeyondGenerationLimit(pos));
+        }
+        match self {
+            #[cfg(feature = "sqlite")]
+            MapData::Sqlite(pool) => {
+                let mut tx = pool.begin().await?;
+                for (pos, data) in blocks {
+                    sqlx::query(SQLITE_UPSERT)
+                        .bind(i64::from(BlockKey::from(pos)))
+                        .bind(data)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                tx.commit().await?;
+            }
+            #[cfg(feature = "postgres")]
+            MapData::Postgres(pool) => {
+                let mut tx = pool.begin().await?;
+                for (pos, data) in blocks {
+                    let pos_vec = pos.into_index_vec();
+                    sqlx::query(POSTGRES_UPSERT)
+                        .bind(pos_vec.x)
+                        .bind(pos_vec.y)
+                        .bind(pos_vec.z)
+                        .bind(data)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                tx.commit().await?;
+            }
+            #[cfg(feature = "redis")]
+            MapData::Redis { connection, hash } => {
+                let mut pipe = redis::pipe();
+                pipe.atomic();
+                for (pos, data) in blocks {
+                    pipe.hset(hash, i64::from(BlockKey::from(pos)), data)
+                        .ignore();
+                }
+                pipe.query_async::<_, ()>(&mut connection.clone()).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Inserts or replaces many mapblocks at once
+    ///
+    /// All mapblocks are serialized first, then they are written like with
+    /// [`set_mapblocks_data`](`Self::set_mapblocks_data`).
+    pub async fn set_mapblocks<'b>(
+        &self,
+        blocks: impl IntoIterator<Item = (BlockPos, &'b MapBlock)>,
+    ) -> Result<(), MapDataError> {
+        let blocks = blocks
+            .into_iter()
+            .map(|(pos, block)| Ok((pos, block.to_binary_version(block.map_format_version)?)))
+            .collect::<Result<Vec<_>, MapBlockError>>()?;
+        self.set_mapblocks_data(blocks.iter().map(|(pos, data)| (*pos, data.as_slice())))
+            .await
+    }
+
+    /// Removes the mapblock at `pos` from the backend
+    ///
+    /// The engine generates the mapblock anew when it is needed.
+    /// Returns false if there was no such mapblock.
+    pub async fn delete_mapblock(&self, pos: BlockPos) -> Result<bool, MapDataError> {
+        let block_key = i64::from(BlockKey::from(pos));
+        let pos_vec = pos.into_index_vec();
+        match self {
+            #[cfg(feature = "sqlite")]
+            MapData::Sqlite(pool) => Ok(sqlx::query("DELETE FROM blocks WHERE pos = ?")
+                .bind(block_key)
+                .execute(pool)
+                .await?
+                .rows_affected()
+                > 0),
+            #[cfg(feature = "postgres")]
+            MapData::Postgres(pool) => Ok(sqlx::query(
+                "DELETE FROM blocks WHERE (posx = $1 AND posy = $2 AND posz = $3)",
+            )
+            .bind(pos_vec.x)
+            .bind(pos_vec.y)
+            .bind(pos_vec.z)
+            .execute(pool)
+            .await?
+            .rows_affected()
+                > 0),
+            #[cfg(feature = "redis")]
+            MapData::Redis { connection, hash } => {
+                let removed: i64 = connection.clone().hdel(hash, block_key).await?;
+                Ok(removed > 0)
+            }
+        }
+    }
+
+    /// Removes all mapblocks within `region` from the backend, like `/deleteblocks` does in game
+    ///
+    /// The engine generates the mapblocks anew when they are needed,
+    /// which makes this the way to force regenerating a broken area.
+    /// With SQLite, every row of the box is deleted by a range of block keys,
+    /// all within one transaction. The server must not be running meanwhile.
+    ///
+    /// Returns the number of removed mapblocks.
+    ///
+    /// ```no_run
+    /// use minetestworld::World;
+    /// use minetestworld::positions::{BlockArea, BlockPos};
+    /// use glam::I16Vec3;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = World::open("MyWorld").get_map_data_backend(false).await.unwrap();
+    ///     let region = BlockArea::new(
+    ///         BlockPos::from_index_vec(I16Vec3::new(-4, -2, -4)),
+    ///         BlockPos::from_index_vec(I16Vec3::new(4, 2, 4)),
+    ///     );
+    ///     let removed = map.delete_blocks(region).await.unwrap();
+    ///     println!("Removed {removed} mapblocks");
+    /// });
+    /// ```
+    pub async fn delete_blocks(&self, region: BlockArea) -> Result<usize, MapDataError> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            MapData::Sqlite(pool) => {
+                let mut tx = pool.begin().await?;
+                let mut removed = 0;
+                for range in region.key_ranges() {
+                    removed += sqlx::query("DELETE FROM blocks WHERE pos BETWEEN ? AND ?")
+                        .bind(i64::from(*range.start()))
+                        .bind(i64::from(*range.end()))
+                        .execute(&mut *tx)
+                        .await?
+                        .rows_affected();
+                }
+                tx.commit().await?;
+                Ok(removed as usize)
+            }
+            #[cfg(feature = "postgres")]
+            MapData::Postgres(pool) => Ok(sqlx::query(&format!(
+                "DELETE FROM blocks WHERE {}",
+                region.postgres_predicate()
+            ))
+            .execute(pool)
+            .await?
+            .rows_affected() as usize),
+            #[cfg(feature = "redis")]
+            MapData::Redis { .. } => {
+                let positions: Vec<_> = self
+                    .mapblock_positions_in(region)
+                    .await
+                    .try_collect()
+                    .await?;
+                for &pos in &positions {
+                    self.delete_mapblock(pos).await?;
+                }
+                Ok(positions.len())
+            }
+        }
+    }
+
+    /// Removes all mapblocks whose block index on the y axis lies between `min_y` and `max_y`
+    ///
+    /// This drops whole layers of the map, e.g. a broken sky island or everything
+    /// below a certain depth. Both bounds are included and given in mapblocks,
+    /// so the node height `y` lies in the layer `y >> 4`.
+    /// See [`delete_blocks`](`Self::delete_blocks`).
+    ///
+    /// Returns the number of removed mapblocks.
+    ///
+    /// ```no_run
+    /// use minetestworld::World;
+    /// use minetestworld::WORLD_BLOCKS_MIN;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = World::open("MyWorld").get_map_data_backend(false).await.unwrap();
+    ///     // Everything below y = -2000
+    ///     let removed = map.delete_y_range(WORLD_BLOCKS_MIN, (-2000 >> 4) - 1).await.unwrap();
+    ///     println!("Removed {removed} mapblocks");
+    /// });
+    /// ```
+    pub async fn delete_y_range(&self, min_y: i16, max_y: i16) -> Result<usize, MapDataError> {
+        let layers = BlockArea::new(
+            BlockPos::from_index_vec(I16Vec3::new(WORLD_BLOCKS_MIN, min_y, WORLD_BLOCKS_MIN)),
+            BlockPos::from_index_vec(I16Vec3::new(WORLD_BLOCKS_MAX, max_y, WORLD_BLOCKS_MAX)),
+        );
+        self.delete_blocks(layers).await
+    }
+
+    /// Reads the mapblocks at `positions` from `source` and writes them into this map,
+    /// as decided by `rewrite`
+    ///
+    /// The positions are collected beforehand, because SQLite does not tolerate
+    /// concurrent read and write access. `progress` is reported over the mapblocks.
+    ///
+    /// Returns the number of visited mapblocks.
+    pub(crate) async fn rewrite_blocks<F>(
+        &self,
+        source: &MapData,
+        positions: impl Stream<Item = Result<BlockPos, MapDataError>>,
+        mut progress: impl FnMut(Progress),
+        mut rewrite: impl FnMut(BlockPos, Vec<u8>) -> F,
+    ) -> Result<usize, MapDataError>
+    where
+        F: Future<Output = Result<Rewrite, MapDataError>>,
+    {
+        let positions: Vec<_> = positions.try_collect().await?;
+        let mut state = Progress::new(positions.len());
+        progress(state);
+        for &pos in &positions {
+            let data = source.get_block_data(pos).await?;
+            match rewrite(pos, data).await? {
+                Rewrite::Keep => {}
+                Rewrite::Replace(data) => self.set_block_data(pos, &data).await?,
+                Rewrite::Delete => {
+                    self.delete_mapblock(pos).await?;
+                }
+            }
+            progress(state.advance());
+        }
+        Ok(positions.len())
+    }
+
+    /// Removes all mapblocks that consist of air only and hold no further data
+    ///
+    /// Mapblocks with node metadata, node timers or static objects are kept.
+    /// The engine generates the removed mapblocks anew when they are needed,
+    /// so this shrinks the database without losing anything that a player built,
+    /// provided that mapgen fills these areas with air again.
+    /// Only the name-id mappings of mapblocks that contain anything but air are decoded.
+    /// The server must not be running meanwhile.
+    ///
+    /// Returns the number of removed mapblocks.
+    pub async fn trim_air_blocks(&self) -> Result<usize, MapDataError> {
+        let mut removed = 0;
+        let positions = self.all_mapblock_positions().await;
+        self.rewrite_blocks(
+            self,
+            positions,
+            |_| (),
+            |_, data| {
+                future::ready(is_air_only(&data).map(|air_only| {
+                    removed += usize::from(air_only);
+                    if air_only {
+                        Rewrite::Delete
+                    } else {
+                        Rewrite::Keep
+                    }
+                }))
+            },
+        )
+        .await?;
+        Ok(removed)
+    }
+
+    /// Releases unused space and updates the query planner statistics of the database
+    ///
+    /// After deleting many mapblocks, the database file keeps its size until it is vacuumed.
+    /// `progress` is called before each step, as vacuuming a large map can take minutes.
+    /// With SQLite, the write-ahead log is checkpointed as well, so that the file
+    /// shrinks right away. Redis and LevelDB manage their storage themselves,
+    /// so nothing is done for them.
+    /// The server must not be running meanwhile.
+    ///
+    /// ```no_run
+    /// use minetestworld::World;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = World::open("MyWorld").get_map_data_backend(false).await.unwrap();
+    ///     map.optimize(|step| println!("{step:?}…")).await.unwrap();
+    /// });
+    /// ```
+    pub async fn optimize(
+        &self,
+        mut progress: impl FnMut(OptimizeStep),
+    ) -> Result<(), MapDataError> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            MapData::Sqlite(pool) => {
+                progress(OptimizeStep::Vacuum);
+                sqlx::query("VACUUM").execute(pool).await?;
+                sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+                    .execute(pool)
+                    .await?;
+                progress(OptimizeStep::Analyze);
+                sqlx::query("ANALYZE").execute(pool).await?;
+            }
+            #[cfg(feature = "postgres")]
+            MapData::Postgres(pool) => {
+                progress(OptimizeStep::Vacuum);
+                sqlx::query("VACUUM blocks").execute(pool).await?;
+                progress(OptimizeStep::Analyze);
+                sqlx::query("ANALYZE blocks").execute(pool).await?;
+            }
+            #[cfg(any(feature = "redis", feature = "experimental-leveldb"))]
+            _ => {}
+        }
+        Ok(())
+    }
+
     /// Inserts or replaces the map block at `pos`
+    ///
+    /// The mapblock is written in its [`map_format_version`](`MapBlock::map_format_version`),
+    /// so that mapblocks read from an older world stay readable by older engines.
     pub async fn set_mapblock(&self, pos: BlockPos, block: &MapBlock) -> Result<(), MapDataError> {
-        self.set_mapblock_data(pos, &block.to_binary()?).await
+        self.set_block_data(pos, &block.to_binary_version(block.map_format_version)?)
+            .await
+    }
+
+    /// Like [`set_mapblock`](`Self::set_mapblock`), but updates the header of `block` first
+    ///
+    /// See [`MapBlock::apply_maintenance`].
+    pub async fn set_mapblock_maintained(
+        &self,
+        pos: BlockPos,
+        block: &mut MapBlock,
+        maintenance: &WriteMaintenance,
+    ) -> Result<(), MapDataError> {
+        block.apply_maintenance(maintenance);
+        self.set_mapblock(pos, block).await
+    }
+
+    /// Copies all mapblocks within `region` from `source` into this map
+    ///
+    /// Without a `transform`, the blocks are copied verbatim.
+    /// Otherwise, every block is decoded and handed to `transform` before it is written,
+    /// which allows e.g. stripping metadata or replacing content in the same pass.
+    ///
+    /// Returns the number of copied blocks.
+    pub async fn copy_region_from(
+        &self,
+        source: &MapData,
+        region: BlockArea,
+        transform: Option<BlockTransform<'_>>,
+    ) -> Result<usize, MapDataError> {
+        self.copy_region_from_cancellable(source, region, transform, &CancellationToken::new())
+            .await
+    }
+
+    /// Like [`copy_region_from`](`Self::copy_region_from`), but stops with
+    /// [`MapDataError::Cancelled`] once `cancel` is cancelled
+    ///
+    /// The mapblocks copied until then are kept.
+    pub async fn copy_region_from_cancellable(
+        &self,
+        source: &MapData,
+        region: BlockArea,
+        mut transform: Option<BlockTransform<'_>>,
+        cancel: &CancellationToken,
+    ) -> Result<usize, MapDataError> {
+        let positions = source.mapblock_positions_in(region).await;
+        self.rewrite_blocks(
+            source,
+            positions,
+            |_| (),
+            |pos, data| {
+                future::ready(cancel.check().and_then(|()| match transform.as_mut() {
+                    Some(transform) => {
+                        let mut block = MapBlock::from_data(data.as_slice())?;
+                        transform(pos, &mut block);
+                        Ok(Rewrite::Replace(
+                            block.to_binary_version(block.map_format_version)?,
+                        ))
+                    }
+                    None => Ok(Rewrite::Replace(data)),
+                }))
+            },
+        )
+        .await
+    }
+
+    /// Returns the name-id mappings of the mapblock at `pos`, without decoding its nodes
+    ///
+    /// See [`MapBlock::palette_from_data`].
+    pub async fn get_palette(&self, pos: BlockPos) -> Result<NameIdMappings, MapDataError> {
+        Ok(MapBlock::palette_from_data(
+            self.get_block_data(pos).await?.as_slice(),
+        )?)
+    }
+
+    /// Returns the header of the mapblock at `pos`, without decoding its nodes
+    ///
+    /// See [`MapBlock::header_from_data`].
+    pub async fn get_block_header(&self, pos: BlockPos) -> Result<BlockHeader, MapDataError> {
+        Ok(MapBlock::header_from_data(
+            self.get_block_data(pos).await?.as_slice(),
+        )?)
+    }
+
+    /// Removes static objects from the whole map, like `/clearobjects` does in game
+    ///
+    /// `remove` decides for each object whether it is removed.
+    /// Only mapblocks that lost an object are rewritten.
+    /// The server must not be running meanwhile.
+    ///
+    /// Returns the number of removed objects.
+    ///
+    /// ```no_run
+    /// use minetestworld::World;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = World::open("MyWorld").get_map_data_backend(false).await.unwrap();
+    ///     // Remove all dropped items
+    ///     let removed = map
+    ///         .clear_objects(|object| {
+    ///             matches!(object.lua_entity(), Ok(Some(entity)) if entity.name == "__builtin:item")
+    ///         })
+    ///         .await
+    ///         .unwrap();
+    ///     println!("Removed {removed} items");
+    /// });
+    /// ```
+    pub async fn clear_objects(
+        &self,
+        mut remove: impl FnMut(&StaticObject) -> bool,
+    ) -> Result<usize, MapDataError> {
+        let mut removed = 0;
+        let positions = self.all_mapblock_positions().await;
+        self.rewrite_blocks(
+            self,
+            positions,
+            |_| (),
+            |_, data| {
+                future::ready(
+                    remove_objects(&data, &mut remove).map(|result| match result {
+                        Some((data, count)) => {
+                            removed += count;
+                            Rewrite::Replace(data)
+                        }
+                        None => Rewrite::Keep,
+                    }),
+                )
+            },
+        )
+        .await?;
+        Ok(removed)
+    }
+
+    /// Sets the timeout of all node timers whose node content matches `content_filter`
+    ///
+    /// This is useful after changing e.g. the cooking time of furnaces or the growth time
+    /// of crops. The elapsed time of the timers is kept, so timers that have already
+    /// run longer than `new_timeout` fire as soon as their mapblock is loaded.
+    /// Only mapblocks with a rescheduled timer are rewritten.
+    /// The server must not be running meanwhile.
+    ///
+    /// Returns the number of rescheduled timers.
+    ///
+    /// ```no_run
+    /// use minetestworld::World;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = World::open("MyWorld").get_map_data_backend(false).await.unwrap();
+    ///     let rescheduled = map
+    ///         .reschedule_timers(|content| content.starts_with(b"farming:wheat_"), 30_000)
+    ///         .await
+    ///         .unwrap();
+    ///     println!("Rescheduled {rescheduled} timers");
+    /// });
+    /// ```
+    pub async fn reschedule_timers(
+        &self,
+        mut content_filter: impl FnMut(&[u8]) -> bool,
+        new_timeout: i32,
+    ) -> Result<usize, MapDataError> {
+        let mut rescheduled = 0;
+        let positions = self.all_mapblock_positions().await;
+        self.rewrite_blocks(
+            self,
+            positions,
+            |_| (),
+            |_, data| {
+                let result = reschedule(&data, &mut content_filter, new_timeout);
+                future::ready(result.map(|result| match result {
+                    Some((data, count)) => {
+                        rescheduled += count;
+                        Rewrite::Replace(data)
+                    }
+                    None => Rewrite::Keep,
+                }))
+            },
+        )
+        .await?;
+        Ok(rescheduled)
+    }
+
+    /// Replaces the content `from` by `to` in all mapblocks within `region`
+    ///
+    /// This is meant for mass replacements like migrating from one mod to another.
+    /// Mapblocks without `from` in their name-id mappings are skipped without decoding
+    /// their nodes. Usually, the mapping of `from` is simply renamed,
+    /// see [`MapBlock::replace_content`]. param1, param2 and metadata are kept.
+    /// The server must not be running meanwhile.
+    ///
+    /// Returns the number of rewritten mapblocks.
+    ///
+    /// ```no_run
+    /// use minetestworld::World;
+    /// use minetestworld::positions::{BlockArea, BlockPos};
+    /// use glam::I16Vec3;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = World::open("MyWorld").get_map_data_backend(false).await.unwrap();
+    ///     let region = BlockArea::new(
+    ///         BlockPos::from_index_vec(I16Vec3::splat(-10)),
+    ///         BlockPos::from_index_vec(I16Vec3::splat(10)),
+    ///     );
+    ///     let rewritten = map
+    ///         .replace_content(b"moreores:mineral_copper", b"default:stone_with_copper", region)
+    ///         .await
+    ///         .unwrap();
+    ///     println!("Rewrote {rewritten} mapblocks");
+    /// });
+    /// ```
+    pub async fn replace_content(
+        &self,
+        from: &[u8],
+        to: &[u8],
+        region: BlockArea,
+    ) -> Result<usize, MapDataError> {
+        self.replace_content_with_progress(from, to, region, |_| ())
+            .await
+    }
+
+    /// Like [`replace_content`](`Self::replace_content`), but reports its [`Progress`]
+    /// over the mapblocks of `region`
+    ///
+    /// ```no_run
+    /// use minetestworld::World;
+    /// use minetestworld::positions::{BlockArea, BlockPos};
+    /// use glam::I16Vec3;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = World::open("MyWorld").get_map_data_backend(false).await.unwrap();
+    ///     let region = BlockArea::new(
+    ///         BlockPos::from_index_vec(I16Vec3::splat(-100)),
+    ///         BlockPos::from_index_vec(I16Vec3::splat(100)),
+    ///     );
+    ///     map.replace_content_with_progress(b"default:dirt", b"default:sand", region, |p| {
+    ///         eprint!("\r{}/{} mapblocks, {:?} left", p.done, p.total, p.eta());
+    ///     })
+    ///     .await
+    ///     .unwrap();
+    /// });
+    /// ```
+    pub async fn replace_content_with_progress(
+        &self,
+        from: &[u8],
+        to: &[u8],
+        region: BlockArea,
+        progress: impl FnMut(Progress),
+    ) -> Result<usize, MapDataError> {
+        let mut rewritten = 0;
+        let positions = self.mapblock_positions_in(region).await;
+        self.rewrite_blocks(self, positions, progress, |_, data| {
+            future::ready(
+                replace_in_block(&data, from, to).map(|result| match result {
+                    Some(data) => {
+                        rewritten += 1;
+                        Rewrite::Replace(data)
+                    }
+                    None => Rewrite::Keep,
+                }),
+            )
+        })
+        .await?;
+        Ok(rewritten)
+    }
+
+    /// Checks whether every mapblock of the map can be decoded
+    ///
+    /// Yields the outcome of [`MapBlock::check_data`] for each mapblock, in no particular order.
+    /// Errors of the backend itself are yielded as errors.
+    ///
+    /// ```
+    /// use minetestworld::map_block::BlockIntegrity;
+    /// use minetestworld::World;
+    /// use futures::TryStreamExt;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = World::open("TestWorld").get_map_data().await.unwrap();
+    ///     let damaged: Vec<_> = map
+    ///         .check_integrity()
+    ///         .await
+    ///         .try_filter(|(_, integrity)| futures::future::ready(*integrity != BlockIntegrity::Ok))
+    ///         .try_collect()
+    ///         .await
+    ///         .unwrap();
+    ///     for (pos, integrity) in damaged {
+    ///         println!("{pos:?}: {integrity:?}");
+    ///     }
+    /// });
+    /// ```
+    pub async fn check_integrity(
+        &self,
+    ) -> BoxStream<'_, Result<(BlockPos, BlockIntegrity), MapDataError>> {
+        self.all_mapblock_positions()
+            .await
+            .and_then(move |pos| async move {
+                let data = self.get_block_data(pos).await?;
+                Ok((pos, MapBlock::check_data(&data)))
+            })
+            .boxed()
+    }
+
+    /// Like [`check_integrity`](`Self::check_integrity`), but reports its [`Progress`]
+    ///
+    /// All positions are listed before the first mapblock is checked, to know the total.
+    /// `progress` is called whenever the stream yields.
+    pub async fn check_integrity_with_progress<'a>(
+        &'a self,
+        mut progress: impl FnMut(Progress) + Send + 'a,
+    ) -> BoxStream<'a, Result<(BlockPos, BlockIntegrity), MapDataError>> {
+        let positions: Vec<_> = match self.all_mapblock_positions().await.try_collect().await {
+            Ok(positions) => positions,
+            Err(e) => return stream::once(future::ready(Err(e))).boxed(),
+        };
+        let mut state = Progress::new(positions.len());
+        progress(state);
+        stream::iter(positions)
+            .then(move |pos| async move {
+                let data = self.get_block_data(pos).await?;
+                Ok((pos, MapBlock::check_data(&data)))
+            })
+            .inspect(move |_| progress(state.advance()))
+            .boxed()
+    }
+
+    /// Fetches and decodes all mapblocks, with up to `concurrency` of them in flight
+    ///
+    /// The mapblocks are yielded in no particular order, as soon as they are decoded.
+    /// Unlike fetching every position at once, at most `concurrency` mapblocks are held
+    /// in memory, so this scales to big worlds. A `concurrency` of 0 is treated as 1.
+    ///
+    /// ```
+    /// use minetestworld::MapData;
+    /// use futures::TryStreamExt;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true).await.unwrap();
+    ///     let mut blocks = map.stream_mapblocks(8).await;
+    ///     while let Some((pos, block)) = blocks.try_next().await.unwrap() {
+    ///         assert_eq!(block.param0.len(), 4096, "{pos:?}");
+    ///     }
+    /// });
+    /// ```
+    pub async fn stream_mapblocks(
+        &self,
+        concurrency: usize,
+    ) -> BoxStream<'_, Result<(BlockPos, MapBlock), MapDataError>> {
+        self.all_mapblock_positions()
+            .await
+            .map_ok(move |pos| self.get_mapblock(pos).map_ok(move |block| (pos, block)))
+            .try_buffer_unordered(concurrency.max(1))
+            .boxed()
+    }
+
+    /// Fetches and decodes all mapblocks along the Z-order curve, see [`MortonKey`]
+    ///
+    /// Like [`stream_mapblocks`](`Self::stream_mapblocks`), but mapblocks that are close
+    /// to each other in the world are mostly yielded close together. This requires
+    /// collecting all positions before the first mapblock is fetched.
+    ///
+    /// ```
+    /// use minetestworld::MapData;
+    /// use futures::TryStreamExt;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true).await.unwrap();
+    ///     let mut blocks = map.stream_mapblocks_morton(8).await;
+    ///     while let Some((pos, block)) = blocks.try_next().await.unwrap() {
+    ///         assert_eq!(block.param0.len(), 4096, "{pos:?}");
+    ///     }
+    /// });
+    /// ```
+    pub async fn stream_mapblocks_morton(
+        &self,
+        concurrency: usize,
+    ) -> BoxStream<'_, Result<(BlockPos, MapBlock), MapDataError>> {
+        let positions: Result<Vec<BlockPos>, _> =
+            self.all_mapblock_positions().await.try_collect().await;
+        let mut positions = match positions {
+            Ok(positions) => positions,
+            Err(e) => return stream::once(future::ready(Err(e))).boxed(),
+        };
+        positions.sort_unstable_by_key(|&pos| MortonKey::from(pos));
+        stream::iter(positions)
+            .map(move |pos| self.get_mapblock(pos).map_ok(move |block| (pos, block)))
+            .buffered(concurrency.max(1))
+            .boxed()
     }
 
     /// Enumerate all nodes from the mapblock at `pos`
@@ -321,4 +1722,482 @@ impl MapData {
         let mapblock = self.get_mapblock(mapblock_pos).await?;
         Ok(NodeIter::from(mapblock, mapblock_pos))
     }
+
+    /// Finds the highest node matching `predicate` in every node column between `a` and `b`
+    ///
+    /// The corners are given as `(x, z)` and are both included.
+    /// `predicate` is called with content names, e.g. `|content| content != b"air"`.
+    /// It is called once per content and mapblock, not per node.
+    ///
+    /// Mapblocks are scanned from the top of each column downwards, until every
+    /// node column has a match. Mapblocks without a matching content, like those
+    /// consisting of air only, are not decoded beyond their palette.
+    ///
+    /// ```
+    /// use minetestworld::World;
+    /// use glam::I16Vec2;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = World::open("TestWorld").get_map_data().await.unwrap();
+    ///     let surface = map
+    ///         .surface(I16Vec2::new(-20, -20), I16Vec2::new(20, 20), |content| {
+    ///             content != b"air"
+    ///         })
+    ///         .await
+    ///         .unwrap();
+    ///     if let Some((y, node)) = surface.get(0, 0) {
+    ///         let content = String::from_utf8_lossy(&node.param0);
+    ///         println!("The origin is covered by {content} at y = {y}");
+    ///     }
+    /// });
+    /// ```
+    pub async fn surface(
+        &self,
+        a: I16Vec2,
+        b: I16Vec2,
+        mut predicate: impl FnMut(&[u8]) -> bool,
+    ) -> Result<Surface, MapDataError> {
+        let (min, max) = (a.min(b), a.max(b));
+        let (block_min, block_max) = (min >> NODE_BITS_1D, max >> NODE_BITS_1D);
+        let area = BlockArea::new(
+            BlockPos::from_index_vec(I16Vec3::new(block_min.x, WORLD_BLOCKS_MIN, block_min.y)),
+            BlockPos::from_index_vec(I16Vec3::new(block_max.x, WORLD_BLOCKS_MAX, block_max.y)),
+        );
+        let positions: Vec<BlockPos> = self.mapblock_positions_in(area).await.try_collect().await?;
+        let mut columns: HashMap<(i16, i16), Vec<i16>> = HashMap::new();
+        for pos in positions {
+            let index = pos.into_index_vec();
+            columns.entry((index.x, index.z)).or_default().push(index.y);
+        }
+
+        let size = (max.as_ivec2() - min.as_ivec2() + 1).as_uvec2();
+        let mut surface = Surface {
+            min,
+            max,
+            nodes: vec![None; size.x as usize * size.y as usize],
+        };
+        let mut nodes = vec![None; BLOCK_NODES_1D as usize * BLOCK_NODES_1D as usize];
+        for ((block_x, block_z), mut ys) in columns {
+            ys.sort_unstable_by(|a, b| b.cmp(a));
+            nodes.fill(None);
+            for block_y in ys {
+                let origin = I16Vec3::new(block_x, block_y, block_z) << NODE_BITS_1D;
+                let data = self
+                    .get_block_data(BlockPos::from_index_vec(origin >> NODE_BITS_1D))
+                    .await?;
+                column_surface(&data, origin, min, max, &mut predicate, &mut nodes)?;
+                if nodes.iter().all(Option::is_some) {
+                    break;
+                }
+            }
+            for (index, node) in nodes.iter_mut().enumerate() {
+                let x = (block_x << NODE_BITS_1D) + (index % BLOCK_NODES_1D as usize) as i16;
+                let z = (block_z << NODE_BITS_1D) + (index / BLOCK_NODES_1D as usize) as i16;
+                if let Some(i) = column_index(min, max, x, z) {
+                    surface.nodes[i] = node.take();
+                }
+            }
+        }
+        Ok(surface)
+    }
+
+    /// Finds the highest node matching `predicate` in the node column at `x`, `z`
+    ///
+    /// Returns its y coordinate along with the node, or `None` if no node matched.
+    /// This is [`surface`](`Self::surface`) for a single column.
+    ///
+    /// ```
+    /// use minetestworld::World;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = World::open("TestWorld").get_map_data().await.unwrap();
+    ///     let ground = map.surface_at(0, 0, |content| content != b"air").await.unwrap();
+    ///     if let Some((y, _)) = ground {
+    ///         println!("The ground at the origin is at y = {y}");
+    ///     }
+    /// });
+    /// ```
+    pub async fn surface_at(
+        &self,
+        x: i16,
+        z: i16,
+        predicate: impl FnMut(&[u8]) -> bool,
+    ) -> Result<Option<(i16, Node)>, MapDataError> {
+        let column = I16Vec2::new(x, z);
+        let mut surface = self.surface(column, column, predicate).await?;
+        Ok(surface.nodes.pop().flatten())
+    }
+
+    /// Finds the height of the highest node matching `predicate` in every node column
+    /// between `a` and `b`
+    ///
+    /// This is [`surface`](`Self::surface`) without the nodes.
+    ///
+    /// ```
+    /// use minetestworld::World;
+    /// use glam::I16Vec2;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = World::open("TestWorld").get_map_data().await.unwrap();
+    ///     let heightmap = map
+    ///         .heightmap(I16Vec2::new(-20, -20), I16Vec2::new(20, 20), |content| {
+    ///             content != b"air"
+    ///         })
+    ///         .await
+    ///         .unwrap();
+    ///     if let Some(y) = heightmap.get(0, 0) {
+    ///         println!("The ground at the origin is at y = {y}");
+    ///     }
+    /// });
+    /// ```
+    pub async fn heightmap(
+        &self,
+        a: I16Vec2,
+        b: I16Vec2,
+        predicate: impl FnMut(&[u8]) -> bool,
+    ) -> Result<Heightmap, MapDataError> {
+        Ok(self.surface(a, b, predicate).await?.into())
+    }
+
+    /// Yields all nodes between `a` and `b` whose content is one of `content_names`
+    ///
+    /// This is the offline counterpart of `minetest.find_nodes_in_area`.
+    /// The nodes are yielded along with their world position, grouped by mapblock
+    /// and in no particular order. Mapblocks without a matching content are not
+    /// decoded beyond their name-id mappings.
+    ///
+    /// ```
+    /// use minetestworld::World;
+    /// use futures::TryStreamExt;
+    /// use glam::I16Vec3;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = World::open("TestWorld").get_map_data().await.unwrap();
+    ///     let chests: Vec<_> = map
+    ///         .find_nodes(
+    ///             ["default:chest", "default:chest_locked"],
+    ///             I16Vec3::splat(-100),
+    ///             I16Vec3::splat(100),
+    ///         )
+    ///         .await
+    ///         .try_collect()
+    ///         .await
+    ///         .unwrap();
+    ///     for (pos, node) in chests {
+    ///         println!("{} at {pos}", String::from_utf8_lossy(&node.param0));
+    ///     }
+    /// });
+    /// ```
+    pub async fn find_nodes(
+        &self,
+        content_names: impl IntoIterator<Item = impl AsRef<[u8]>>,
+        a: I16Vec3,
+        b: I16Vec3,
+    ) -> BoxStream<'_, Result<(I16Vec3, Node), MapDataError>> {
+        let content_names: HashSet<Vec<u8>> = content_names
+            .into_iter()
+            .map(|content| content.as_ref().to_vec())
+            .collect();
+        let (min, max) = (a.min(b), a.max(b));
+        let region = BlockArea::new(
+            BlockPos::from_index_vec(min >> NODE_BITS_1D),
+            BlockPos::from_index_vec(max >> NODE_BITS_1D),
+        );
+        self.mapblock_positions_in(region)
+            .await
+            .and_then(move |pos| async move { Ok((pos, self.get_block_data(pos).await?)) })
+            .and_then(move |(pos, data)| {
+                future::ready(find_in_block(&data, pos, &content_names, min, max))
+            })
+            .map_ok(|found| stream::iter(found.into_iter().map(Ok)))
+            .try_flatten()
+            .boxed()
+    }
+
+    /// Counts the nodes within `region`, or within the whole map if it is `None`, by content
+    ///
+    /// The nodes of each mapblock are counted by content ID, so that every content name
+    /// is looked up only once per mapblock. Nodes whose content ID lacks a name are
+    /// counted as [`CONTENT_UNKNOWN`](`crate::map_block::CONTENT_UNKNOWN`).
+    ///
+    /// ```
+    /// use minetestworld::World;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = World::open("TestWorld").get_map_data().await.unwrap();
+    ///     let histogram = map.content_histogram(None).await.unwrap();
+    ///     let diamonds = histogram
+    ///         .get(&b"default:stone_with_diamond"[..])
+    ///         .copied()
+    ///         .unwrap_or_default();
+    ///     println!("{diamonds} diamond ores are left");
+    /// });
+    /// ```
+    pub async fn content_histogram(
+        &self,
+        region: Option<BlockArea>,
+    ) -> Result<HashMap<Vec<u8>, u64>, MapDataError> {
+        let positions: Vec<_> = match region {
+            Some(region) => self.mapblock_positions_in(region).await,
+            None => self.all_mapblock_positions().await,
+        }
+        .try_collect()
+        .await?;
+        let mut histogram = HashMap::new();
+        for pos in positions {
+            let data = self.get_block_data(pos).await?;
+            count_contents(&data, &mut histogram)?;
+        }
+        Ok(histogram)
+    }
+
+    /// Calls `visit` for every node of the mapblock at `pos`
+    ///
+    /// Unlike [`iter_mapblock_nodes`](`Self::iter_mapblock_nodes`), this does not
+    /// allocate per node, which matters when scanning millions of nodes.
+    /// `visit` receives the world position along with the borrowed node.
+    ///
+    /// ```
+    /// use minetestworld::World;
+    /// use minetestworld::positions::BlockPos;
+    /// use glam::I16Vec3;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = World::open("TestWorld").get_map_data().await.unwrap();
+    ///     let pos = BlockPos::from_index_vec(I16Vec3::new(-13, -8, 2));
+    ///     let mut stone = 0;
+    ///     map.visit_mapblock_nodes(pos, |_, node| {
+    ///         if node.content == b"default:stone" {
+    ///             stone += 1;
+    ///         }
+    ///     })
+    ///     .await
+    ///     .unwrap();
+    /// });
+    /// ```
+    pub async fn visit_mapblock_nodes(
+        &self,
+        pos: BlockPos,
+        visit: impl FnMut(I16Vec3, NodeRef),
+    ) -> Result<(), MapDataError> {
+        let data = self.get_block_data(pos).await?;
+        visit_nodes(&data, pos, visit)
+    }
+}
+
+/// Turns the block index columns `MIN(x), MAX(x), MIN(y), MAX(y), MIN(z), MAX(z)`
+/// into the corners of a box
+///
+/// The aggregates are `NULL` if there are no mapblocks.
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+fn corners(columns: [Option<i16>; 6]) -> Option<(I16Vec3, I16Vec3)> {
+    let [min_x, max_x, min_y, max_y, min_z, max_z] = columns;
+    Some((
+        I16Vec3::new(min_x?, min_y?, min_z?),
+        I16Vec3::new(max_x?, max_y?, max_z?),
+    ))
+}
+
+/// Updates `nodes` with the highest matching nodes of a serialized mapblock
+///
+/// `nodes` holds the node columns of the whole mapblock, indexed by `x + 16 * z`.
+/// Columns that already have a node are skipped, so mapblocks have to be
+/// passed from top to bottom. Only columns between `min` and `max` are considered.
+fn column_surface(
+    data: &[u8],
+    origin: I16Vec3,
+    min: I16Vec2,
+    max: I16Vec2,
+    predicate: &mut impl FnMut(&[u8]) -> bool,
+    nodes: &mut [Option<(i16, Node)>],
+) -> Result<(), MapDataError> {
+    let palette = MapBlock::palette_from_data(data)?;
+    if !palette.values().any(|content| predicate(content)) {
+        return Ok(());
+    }
+    let block = MapBlock::from_data_mode(data, DecodeMode::NodesOnly)?;
+    let matching: HashMap<u16, bool> = block
+        .name_id_mappings
+        .iter()
+        .map(|(&id, content)| (id, predicate(content)))
+        .collect();
+    let last = BLOCK_NODES_1D as i16 - 1;
+    for z in (min.y - origin.z).max(0)..=(max.y - origin.z).min(last) {
+        for x in (min.x - origin.x).max(0)..=(max.x - origin.x).min(last) {
+            let node = &mut nodes[(x + z * BLOCK_NODES_1D as i16) as usize];
+            if node.is_some() {
+                continue;
+            }
+            *node = (0..=last).rev().find_map(|y| {
+                let index = (x + (y << NODE_BITS_1D) + (z << (2 * NODE_BITS_1D))) as usize;
+                let content_id = block.param0[index];
+                matching.get(&content_id).is_some_and(|&m| m).then(|| {
+                    let node = Node {
+                        param0: block.content_from_id(content_id).to_vec(),
+                        param1: block.param1[index],
+                        param2: block.param2[index],
+                    };
+                    (origin.y + y, node)
+                })
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Returns true if a serialized mapblock consists of air only and holds no further data
+fn is_air_only(data: &[u8]) -> Result<bool, MapDataError> {
+    let palette = MapBlock::palette_from_data(data)?;
+    if palette.values().any(|content| content != b"air") {
+        return Ok(false);
+    }
+    let block = MapBlock::from_data(data)?;
+    Ok(block
+        .param0
+        .iter()
+        .all(|&content_id| block.content_from_id(content_id) == b"air")
+        && block.node_metadata.is_empty()
+        && block.node_timers.is_empty()
+        && block.static_objects.is_empty())
+}
+
+/// Replaces the content `from` by `to` in a serialized mapblock
+///
+/// Returns the modified mapblock, or `None` if it does not contain `from`.
+pub(crate) fn replace_in_block(
+    data: &[u8],
+    from: &[u8],
+    to: &[u8],
+) -> Result<Option<Vec<u8>>, MapDataError> {
+    let palette = MapBlock::palette_from_data(data)?;
+    if !palette.values().any(|content| content == from) {
+        return Ok(None);
+    }
+    let mut block = MapBlock::from_data(data)?;
+    if !block.replace_content(from, to) {
+        return Ok(None);
+    }
+    Ok(Some(block.to_binary_version(block.map_format_version)?))
+}
+
+/// Returns the nodes of a serialized mapblock between `min` and `max`
+/// whose content is one of `content_names`
+fn find_in_block(
+    data: &[u8],
+    pos: BlockPos,
+    content_names: &HashSet<Vec<u8>>,
+    min: I16Vec3,
+    max: I16Vec3,
+) -> Result<Vec<(I16Vec3, Node)>, MapDataError> {
+    let palette = MapBlock::palette_from_data(data)?;
+    if !palette
+        .values()
+        .any(|content| content_names.contains(content))
+    {
+        return Ok(Vec::new());
+    }
+    let block = MapBlock::from_data_mode(data, DecodeMode::NodesOnly)?;
+    let matching: HashSet<u16> = block
+        .name_id_mappings
+        .iter()
+        .filter(|(_, content)| content_names.contains(*content))
+        .map(|(&id, _)| id)
+        .collect();
+    Ok(block
+        .iter_content_ids()
+        .filter(|(_, content_id, _, _)| matching.contains(content_id))
+        .map(|(node_pos, ..)| (pos.join(node_pos), node_pos))
+        .filter(|(world_pos, _)| world_pos.cmpge(min).all() && world_pos.cmple(max).all())
+        .map(|(world_pos, node_pos)| (world_pos, block.get_node_at(node_pos)))
+        .collect())
+}
+
+/// Adds the nodes of a serialized mapblock to `histogram`, by content name
+pub(crate) fn count_contents(
+    data: &[u8],
+    histogram: &mut HashMap<Vec<u8>, u64>,
+) -> Result<(), MapDataError> {
+    let block = MapBlock::from_data_mode(data, DecodeMode::NodesOnly)?;
+    let mut counts: HashMap<u16, u64> = HashMap::new();
+    for &content_id in &block.param0 {
+        *counts.entry(content_id).or_default() += 1;
+    }
+    for (content_id, count) in counts {
+        *histogram
+            .entry(block.content_from_id(content_id).to_vec())
+            .or_default() += count;
+    }
+    Ok(())
+}
+
+/// Calls `visit` for every node of a serialized mapblock
+fn visit_nodes(
+    data: &[u8],
+    pos: BlockPos,
+    mut visit: impl FnMut(I16Vec3, NodeRef),
+) -> Result<(), MapDataError> {
+    let block = MapBlock::from_data(data)?;
+    for node in block.nodes() {
+        visit(pos.join(node.position), node);
+    }
+    Ok(())
+}
+
+/// Removes static objects from a serialized mapblock
+///
+/// Returns the re-serialized mapblock along with the number of removed objects,
+/// or `None` if nothing was removed.
+/// This is not inlined into [`MapData::clear_objects`], to keep the decoded
+/// mapblock out of its future.
+fn remove_objects(
+    data: &[u8],
+    remove: impl FnMut(&StaticObject) -> bool,
+) -> Result<Option<(Vec<u8>, usize)>, MapDataError> {
+    let mut block = MapBlock::from_data(data)?;
+    let count = block.remove_static_objects(remove);
+    if count == 0 {
+        return Ok(None);
+    }
+    Ok(Some((
+        block.to_binary_version(block.map_format_version)?,
+        count,
+    )))
+}
+
+/// Sets the timeout of all timers of the matching nodes in a serialized mapblock
+///
+/// Returns the modified mapblock along with the number of rescheduled timers,
+/// or `None` if there was no such timer.
+fn reschedule(
+    data: &[u8],
+    mut content_filter: impl FnMut(&[u8]) -> bool,
+    new_timeout: i32,
+) -> Result<Option<(Vec<u8>, usize)>, MapDataError> {
+    let palette = MapBlock::palette_from_data(data)?;
+    if !palette.values().any(|content| content_filter(content)) {
+        return Ok(None);
+    }
+    let mut block = MapBlock::from_data(data)?;
+    let mut count = 0;
+    for index in 0..block.node_timers.len() {
+        let position = block.node_timers[index].position;
+        let content_id = block.param0[usize::from(position)];
+        if content_filter(block.content_from_id(content_id)) {
+            block.node_timers[index].timeout = new_timeout;
+            count += 1;
+        }
+    }
+    if count == 0 {
+        return Ok(None);
+    }
+    Ok(Some((
+        block.to_binary_version(block.map_format_version)?,
+        count,
+    )))
 }
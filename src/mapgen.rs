@@ -0,0 +1,59 @@
+//! Helpers for generating content in a world, e.g. decoration passes
+//!
+//! Everything here is deterministic: the same seed always yields the same result,
+//! independent of the order in which a region is processed. A decoration pass that got
+//! interrupted can thus be resumed, and running it again does not place anything new.
+
+use glam::I16Vec3;
+
+use crate::positions::{BlockArea, NodeIndex, NodePos};
+use crate::BLOCK_NODES_3D;
+
+/// Mixes the bits of `value`, using the finalizer of SplitMix64
+fn mix(mut value: u64) -> u64 {
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d049bb133111eb);
+    value ^ (value >> 31)
+}
+
+/// Returns a pseudo-random number that only depends on `seed` and `pos`
+fn node_hash(seed: u64, pos: I16Vec3) -> u64 {
+    let coordinates =
+        u64::from(pos.x as u16) | u64::from(pos.y as u16) << 16 | u64::from(pos.z as u16) << 32;
+    mix(mix(seed) ^ coordinates)
+}
+
+/// Yields pseudo-randomly chosen node positions within `region`
+///
+/// Every node is chosen with the probability `density`, which is clamped to `0.0..=1.0`.
+/// Whether a node is chosen only depends on `seed` and its position,
+/// so the result is reproducible and does not change if the region is split up.
+/// Using the [world's seed](`crate::World::get_seed`) makes different worlds
+/// look different.
+///
+/// The positions are yielded block by block.
+///
+/// ```
+/// use minetestworld::mapgen;
+/// use minetestworld::positions::{BlockArea, BlockPos};
+/// use glam::I16Vec3;
+///
+/// let block = BlockPos::from_index_vec(I16Vec3::new(0, 0, 0));
+/// let region = BlockArea::new(block, block);
+/// let flowers: Vec<_> = mapgen::scatter(region, 0.01, 1234).collect();
+/// assert_eq!(flowers, mapgen::scatter(region, 0.01, 1234).collect::<Vec<_>>());
+/// ```
+pub fn scatter(region: BlockArea, density: f64, seed: u64) -> impl Iterator<Item = I16Vec3> {
+    // The threshold cannot exceed u64::MAX, so a density of 1.0 is special-cased
+    let density = density.clamp(0.0, 1.0);
+    let threshold = (density * u64::MAX as f64) as u64;
+    region
+        .iter()
+        .flat_map(|block| {
+            (0..BLOCK_NODES_3D).map(move |index| {
+                // The index is below the node count of a mapblock
+                block.join(NodePos::from(NodeIndex::try_from(index).unwrap()))
+            })
+        })
+        .filter(move |&pos| density >= 1.0 || node_hash(seed, pos) < threshold)
+}
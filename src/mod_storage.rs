@@ -0,0 +1,211 @@
+//! Contains a type to read and modify the key-value storage of mods
+
+use futures::stream::BoxStream;
+use futures::stream::StreamExt;
+use futures::TryStreamExt;
+use log::LevelFilter;
+#[cfg(feature = "postgres")]
+use sqlx::postgres::{PgConnectOptions, PgPool};
+#[cfg(feature = "sqlite")]
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool};
+use sqlx::ConnectOptions;
+#[cfg(feature = "sqlite")]
+use std::path::Path;
+#[cfg(feature = "postgres")]
+use std::str::FromStr;
+
+/// An error in the underlying mod storage database
+#[derive(thiserror::Error, Debug)]
+pub enum ModStorageError {
+    #[error("Database error: {0}")]
+    /// sqlx based error. This covers Sqlite and Postgres errors.
+    SqlError(#[from] sqlx::Error),
+}
+
+/// A key and its value
+pub type ModStorageEntry = (Vec<u8>, Vec<u8>);
+
+/// A handle to the mod storage database of a world
+///
+/// Every mod has its own namespace of keys, which is selected by the mod name.
+/// Keys and values are arbitrary byte strings, although most mods store text
+/// or serialized Lua tables.
+///
+/// Modifications require a handle obtained via
+/// [`World::mutable_mod_storage`](`crate::World::mutable_mod_storage`).
+/// The server should not be running while the mod storage is modified.
+///
+/// ```
+/// use minetestworld::World;
+/// use async_std::task;
+///
+/// task::block_on(async {
+///     let storage = World::open("TestWorld").mod_storage().await.unwrap();
+///     let value = storage.get("areas", b"count").await.unwrap();
+///     assert_eq!(value.as_deref(), Some(&b"2"[..]));
+/// });
+/// ```
+pub enum ModStorage {
+    /// This variant covers the SQLite database backend
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqlitePool),
+
+    /// This variant supports PostgreSQL as a backend
+    #[cfg(feature = "postgres")]
+    Postgres(PgPool),
+}
+
+impl ModStorage {
+    #[cfg(feature = "sqlite")]
+    /// Connects to the "mod_storage.sqlite" database.
+    ///
+    /// `read_only` has to be false to be able to modify the storage.
+    pub async fn from_sqlite_file(
+        filename: impl AsRef<Path>,
+        read_only: bool,
+    ) -> Result<ModStorage, ModStorageError> {
+        let opts = SqliteConnectOptions::new()
+            .immutable(read_only)
+            .filename(filename)
+            .log_statements(LevelFilter::Debug);
+        Ok(ModStorage::Sqlite(SqlitePool::connect_with(opts).await?))
+    }
+
+    #[cfg(feature = "postgres")]
+    /// Connects to a Postgres database
+    pub async fn from_pg_connection_params(url: &str) -> Result<ModStorage, ModStorageError> {
+        let opts = PgConnectOptions::from_str(url)?.log_statements(LevelFilter::Debug);
+        Ok(ModStorage::Postgres(PgPool::connect_with(opts).await?))
+    }
+
+    /// Returns the names of all mods that have stored at least one entry
+    pub fn mod_names(&self) -> BoxStream<'_, Result<String, ModStorageError>> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            ModStorage::Sqlite(pool) => {
+                sqlx::query_scalar("SELECT DISTINCT modname FROM entries ORDER BY modname")
+                    .fetch(pool)
+                    .map_err(ModStorageError::SqlError)
+                    .boxed()
+            }
+            #[cfg(feature = "postgres")]
+            ModStorage::Postgres(pool) => {
+                sqlx::query_scalar("SELECT DISTINCT modname FROM mod_storage ORDER BY modname")
+                    .fetch(pool)
+                    .map_err(ModStorageError::SqlError)
+                    .boxed()
+            }
+        }
+    }
+
+    /// Returns all key-value pairs stored by the mod `modname`
+    pub fn entries<'a>(
+        &'a self,
+        modname: &'a str,
+    ) -> BoxStream<'a, Result<ModStorageEntry, ModStorageError>> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            ModStorage::Sqlite(pool) => {
+                sqlx::query_as("SELECT key, value FROM entries WHERE modname = ? ORDER BY key")
+                    .bind(modname)
+                    .fetch(pool)
+                    .map_err(ModStorageError::SqlError)
+                    .boxed()
+            }
+            #[cfg(feature = "postgres")]
+            ModStorage::Postgres(pool) => {
+                sqlx::query_as("SELECT key, value FROM mod_storage WHERE modname = $1 ORDER BY key")
+                    .bind(modname)
+                    .fetch(pool)
+                    .map_err(ModStorageError::SqlError)
+                    .boxed()
+            }
+        }
+    }
+
+    /// Returns the value stored by the mod `modname` under `key`, if any
+    pub async fn get(&self, modname: &str, key: &[u8]) -> Result<Option<Vec<u8>>, ModStorageError> {
+        Ok(match self {
+            #[cfg(feature = "sqlite")]
+            ModStorage::Sqlite(pool) => {
+                sqlx::query_scalar("SELECT value FROM entries WHERE modname = ? AND key = ?")
+                    .bind(modname)
+                    .bind(key)
+                    .fetch_optional(pool)
+                    .await?
+            }
+            #[cfg(feature = "postgres")]
+            ModStorage::Postgres(pool) => {
+                sqlx::query_scalar("SELECT value FROM mod_storage WHERE modname = $1 AND key = $2")
+                    .bind(modname)
+                    .bind(key)
+                    .fetch_optional(pool)
+                    .await?
+            }
+        })
+    }
+
+    /// Stores `value` under `key` for the mod `modname`
+    ///
+    /// A previous value is replaced.
+    pub async fn set(
+        &self,
+        modname: &str,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(), ModStorageError> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            ModStorage::Sqlite(pool) => {
+                sqlx::query(
+                    "INSERT OR REPLACE INTO entries (modname, key, value) VALUES (?, ?, ?)",
+                )
+                .bind(modname)
+                .bind(key)
+                .bind(value)
+                .execute(pool)
+                .await?;
+            }
+            #[cfg(feature = "postgres")]
+            ModStorage::Postgres(pool) => {
+                sqlx::query(
+                    "INSERT INTO mod_storage (modname, key, value) VALUES ($1, $2, $3)
+                    ON CONFLICT (modname, key) DO UPDATE SET value = EXCLUDED.value",
+                )
+                .bind(modname)
+                .bind(key)
+                .bind(value)
+                .execute(pool)
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the entry `key` of the mod `modname`
+    ///
+    /// Returns false if there was no such entry.
+    pub async fn delete(&self, modname: &str, key: &[u8]) -> Result<bool, ModStorageError> {
+        let result = match self {
+            #[cfg(feature = "sqlite")]
+            ModStorage::Sqlite(pool) => {
+                sqlx::query("DELETE FROM entries WHERE modname = ? AND key = ?")
+                    .bind(modname)
+                    .bind(key)
+                    .execute(pool)
+                    .await?
+                    .rows_affected()
+            }
+            #[cfg(feature = "postgres")]
+            ModStorage::Postgres(pool) => {
+                sqlx::query("DELETE FROM mod_storage WHERE modname = $1 AND key = $2")
+                    .bind(modname)
+                    .bind(key)
+                    .execute(pool)
+                    .await?
+                    .rows_affected()
+            }
+        };
+        Ok(result > 0)
+    }
+}
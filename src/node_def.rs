@@ -0,0 +1,302 @@
+//! Contains types that describe how the game defines nodes
+//!
+//! The map data only stores content names. How a node makes use of its
+//! `param1` and `param2` is defined by the game's Lua code, which this crate
+//! cannot run. A [`NodeDefProvider`] fills this gap, e.g. with definitions
+//! exported from a running server.
+
+use glam::I16Vec3;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::param2::{FACEDIR_AXES, WALLMOUNTED_DIRS};
+
+/// What the engine stores in the `param1` of a node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ParamType {
+    /// `param1` is not used
+    #[default]
+    None,
+    /// `param1` holds the light of the node
+    Light,
+}
+
+/// What the engine stores in the `param2` of a node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ParamType2 {
+    /// `param2` is not used by the engine
+    #[default]
+    None,
+    /// `param2` is used by the game in an arbitrary way
+    Full,
+    /// The level of a flowing liquid
+    FlowingLiquid,
+    /// The side the node is attached to
+    WallMounted,
+    /// The facing direction and the rotation around it
+    FaceDir,
+    /// The level of a nodebox or liquid
+    Leveled,
+    /// The rotation around the vertical axis, in steps of 1.5°
+    DegRotate,
+    /// The shape and size of plantlike meshes
+    MeshOptions,
+    /// The index into the palette
+    Color,
+    /// A palette index combined with [`FaceDir`](`Self::FaceDir`)
+    ColorFaceDir,
+    /// A palette index combined with [`WallMounted`](`Self::WallMounted`)
+    ColorWallMounted,
+    /// The liquid level of a glasslike node
+    GlasslikeLiquidLevel,
+    /// A palette index combined with [`DegRotate`](`Self::DegRotate`)
+    ColorDegRotate,
+    /// One of the four horizontal facing directions
+    FourDir,
+    /// A palette index combined with [`FourDir`](`Self::FourDir`)
+    ColorFourDir,
+}
+
+impl FromStr for ParamType {
+    type Err = String;
+
+    /// Parses the `paramtype` field of a Lua node definition
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(ParamType::None),
+            "light" => Ok(ParamType::Light),
+            _ => Err(format!("Unknown paramtype '{s}'")),
+        }
+    }
+}
+
+impl FromStr for ParamType2 {
+    type Err = String;
+
+    /// Parses the `paramtype2` field of a Lua node definition
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "none" => ParamType2::None,
+            "full" => ParamType2::Full,
+            "flowingliquid" => ParamType2::FlowingLiquid,
+            "wallmounted" => ParamType2::WallMounted,
+            "facedir" => ParamType2::FaceDir,
+            "leveled" => ParamType2::Leveled,
+            "degrotate" => ParamType2::DegRotate,
+            "meshoptions" => ParamType2::MeshOptions,
+            "color" => ParamType2::Color,
+            "colorfacedir" => ParamType2::ColorFaceDir,
+            "colorwallmounted" => ParamType2::ColorWallMounted,
+            "glasslikeliquidlevel" => ParamType2::GlasslikeLiquidLevel,
+            "colordegrotate" => ParamType2::ColorDegRotate,
+            "4dir" => ParamType2::FourDir,
+            "color4dir" => ParamType2::ColorFourDir,
+            _ => return Err(format!("Unknown paramtype2 '{s}'")),
+        })
+    }
+}
+
+/// Turns `dir` clockwise around the y axis as seen from above, i.e. from +z towards +x
+fn turn_y(dir: I16Vec3, quarter_turns: u8) -> I16Vec3 {
+    (0..quarter_turns % 4).fold(dir, |dir, _| I16Vec3::new(dir.z, dir.y, -dir.x))
+}
+
+/// The horizontal coordinate that is negated when mirroring
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MirrorAxis {
+    /// Mirror across a plane of constant x, swapping east and west
+    X,
+    /// Mirror across a plane of constant z, swapping north and south
+    Z,
+}
+
+impl MirrorAxis {
+    fn mirror(self, dir: I16Vec3) -> I16Vec3 {
+        match self {
+            MirrorAxis::X => I16Vec3::new(-dir.x, dir.y, dir.z),
+            MirrorAxis::Z => I16Vec3::new(dir.x, dir.y, -dir.z),
+        }
+    }
+}
+
+impl ParamType2 {
+    /// Returns the `param2` of a node of this type after turning it around the y axis
+    ///
+    /// The node is turned by `quarter_turns` steps of 90°, clockwise as seen from above,
+    /// i.e. from +z towards +x. Palette indices are kept, and so are values that do not
+    /// describe a rotation. Nodes mounted on the floor or ceiling alternate between
+    /// the wallmounted values 0/1 and their rotated variants 6/7.
+    ///
+    /// ```
+    /// use minetestworld::node_def::ParamType2;
+    ///
+    /// // A chest facing +z now faces +x
+    /// assert_eq!(ParamType2::FaceDir.rotate_y(0, 1), 1);
+    /// // A torch on a wall in the +x direction is now on a wall in the -z direction
+    /// assert_eq!(ParamType2::WallMounted.rotate_y(2, 1), 5);
+    /// ```
+    pub fn rotate_y(self, param2: u8, quarter_turns: u8) -> u8 {
+        let turns = quarter_turns % 4;
+        match self {
+            ParamType2::FaceDir | ParamType2::ColorFaceDir => {
+                let Some(&(top, front)) = FACEDIR_AXES.get(usize::from(param2 & 0x1f)) else {
+                    return param2;
+                };
+                let turned = (turn_y(top, turns), turn_y(front, turns));
+                let facedir = FACEDIR_AXES
+                    .iter()
+                    .position(|&axes| axes == turned)
+                    .expect("all orientations have a facedir") as u8;
+                (param2 & !0x1f) | facedir
+            }
+            ParamType2::FourDir | ParamType2::ColorFourDir => {
+                (param2 & !0x03) | ((param2 & 0x03) + turns) % 4
+            }
+            ParamType2::WallMounted | ParamType2::ColorWallMounted => {
+                let wallmounted = match param2 & 0x07 {
+                    // Floor and ceiling
+                    wallmounted @ (0 | 1 | 6 | 7) if turns % 2 == 1 => wallmounted ^ 0x06,
+                    wallmounted @ 2..=5 => {
+                        let dir = turn_y(WALLMOUNTED_DIRS[usize::from(wallmounted)], turns);
+                        WALLMOUNTED_DIRS.iter().position(|&d| d == dir).unwrap() as u8
+                    }
+                    wallmounted => wallmounted,
+                };
+                (param2 & !0x07) | wallmounted
+            }
+            ParamType2::DegRotate if param2 < 240 => {
+                ((u16::from(param2) + 60 * u16::from(turns)) % 240) as u8
+            }
+            ParamType2::ColorDegRotate if param2 & 0x1f < 24 => {
+                (param2 & !0x1f) | ((param2 & 0x1f) + 6 * turns) % 24
+            }
+            _ => param2,
+        }
+    }
+
+    /// Returns the `param2` of a node of this type after mirroring it along `axis`
+    ///
+    /// Nodes cannot be mirrored themselves, so the directions that their top and front
+    /// face are mirrored instead, which is what keeps stairs, chests and torches attached
+    /// to the mirrored build. Palette indices are kept, and so are values that do not
+    /// describe a rotation.
+    ///
+    /// ```
+    /// use minetestworld::node_def::{MirrorAxis, ParamType2};
+    ///
+    /// // A chest facing +x now faces -x
+    /// assert_eq!(ParamType2::FaceDir.mirror(1, MirrorAxis::X), 3);
+    /// // A torch on a wall in the +z direction is now on a wall in the -z direction
+    /// assert_eq!(ParamType2::WallMounted.mirror(4, MirrorAxis::Z), 5);
+    /// ```
+    pub fn mirror(self, param2: u8, axis: MirrorAxis) -> u8 {
+        match self {
+            ParamType2::FaceDir | ParamType2::ColorFaceDir => {
+                let Some(&(top, front)) = FACEDIR_AXES.get(usize::from(param2 & 0x1f)) else {
+                    return param2;
+                };
+                let mirrored = (axis.mirror(top), axis.mirror(front));
+                let facedir = FACEDIR_AXES
+                    .iter()
+                    .position(|&axes| axes == mirrored)
+                    .expect("all orientations have a facedir") as u8;
+                (param2 & !0x1f) | facedir
+            }
+            ParamType2::FourDir | ParamType2::ColorFourDir => {
+                let fourdir = match axis {
+                    MirrorAxis::X => (4 - (param2 & 0x03)) % 4,
+                    MirrorAxis::Z => (6 - (param2 & 0x03)) % 4,
+                };
+                (param2 & !0x03) | fourdir
+            }
+            ParamType2::WallMounted | ParamType2::ColorWallMounted => {
+                let wallmounted = match param2 & 0x07 {
+                    wallmounted @ 2..=5 => {
+                        let dir = axis.mirror(WALLMOUNTED_DIRS[usize::from(wallmounted)]);
+                        WALLMOUNTED_DIRS.iter().position(|&d| d == dir).unwrap() as u8
+                    }
+                    // Floor and ceiling stay aligned with the axes
+                    wallmounted => wallmounted,
+                };
+                (param2 & !0x07) | wallmounted
+            }
+            ParamType2::DegRotate if param2 < 240 => {
+                let half_turn = match axis {
+                    MirrorAxis::X => 0,
+                    MirrorAxis::Z => 120,
+                };
+                ((240 + half_turn - u16::from(param2)) % 240) as u8
+            }
+            ParamType2::ColorDegRotate if param2 & 0x1f < 24 => {
+                let half_turn = match axis {
+                    MirrorAxis::X => 0,
+                    MirrorAxis::Z => 12,
+                };
+                (param2 & !0x1f) | (24 + half_turn - (param2 & 0x1f)) % 24
+            }
+            _ => param2,
+        }
+    }
+}
+
+/// The parts of a node definition that matter for editing the map
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct NodeDef {
+    /// How `param1` is used
+    pub paramtype: ParamType,
+    /// How `param2` is used
+    pub paramtype2: ParamType2,
+    /// The light the node emits, up to [`LIGHT_MAX`](`crate::lighting::LIGHT_MAX`)
+    pub light_source: u8,
+    /// Whether sunlight passes the node downwards without being dimmed
+    pub sunlight_propagates: bool,
+}
+
+impl NodeDef {
+    /// The definition the engine uses for `air`
+    pub const AIR: NodeDef = NodeDef {
+        paramtype: ParamType::Light,
+        paramtype2: ParamType2::None,
+        light_source: 0,
+        sunlight_propagates: true,
+    };
+
+    /// Returns true if the engine keeps light values in `param1`
+    ///
+    /// Light only spreads through such nodes.
+    pub fn uses_param1(&self) -> bool {
+        self.paramtype == ParamType::Light
+    }
+
+    /// Returns true if `param2` has a meaning for this node
+    pub fn uses_param2(&self) -> bool {
+        self.paramtype2 != ParamType2::None
+    }
+}
+
+/// A source of node definitions, looked up by content name
+///
+/// ```
+/// use minetestworld::node_def::{NodeDef, NodeDefProvider, ParamType2};
+/// use std::collections::HashMap;
+///
+/// let defs = HashMap::from([(
+///     b"default:chest".to_vec(),
+///     NodeDef {
+///         paramtype2: "facedir".parse().unwrap(),
+///         ..Default::default()
+///     },
+/// )]);
+/// assert_eq!(defs.node_def(b"default:chest").unwrap().paramtype2, ParamType2::FaceDir);
+/// assert!(defs.node_def(b"default:stone").is_none());
+/// ```
+pub trait NodeDefProvider {
+    /// Returns the definition of `content`, if it is known
+    fn node_def(&self, content: &[u8]) -> Option<NodeDef>;
+}
+
+impl NodeDefProvider for HashMap<Vec<u8>, NodeDef> {
+    fn node_def(&self, content: &[u8]) -> Option<NodeDef> {
+        self.get(content).copied()
+    }
+}
@@ -0,0 +1,301 @@
+//! Contains helpers to decode and encode the `param2` of nodes
+//!
+//! How `param2` is used depends on the [`ParamType2`] of a node,
+//! see [`NodeDef::paramtype2`](`crate::node_def::NodeDef::paramtype2`).
+//! Directions are given as unit vectors.
+
+use glam::I16Vec3;
+
+use crate::node_def::ParamType2;
+
+/// The directions that the top and the front of a node face, indexed by facedir
+///
+/// The front is the side that faces +z with a facedir of 0.
+pub(crate) const FACEDIR_AXES: [(I16Vec3, I16Vec3); 24] = {
+    use I16Vec3 as V;
+    [
+        (V::Y, V::Z),
+        (V::Y, V::X),
+        (V::Y, V::NEG_Z),
+        (V::Y, V::NEG_X),
+        (V::Z, V::NEG_Y),
+        (V::Z, V::X),
+        (V::Z, V::Y),
+        (V::Z, V::NEG_X),
+        (V::NEG_Z, V::Y),
+        (V::NEG_Z, V::X),
+        (V::NEG_Z, V::NEG_Y),
+        (V::NEG_Z, V::NEG_X),
+        (V::X, V::Z),
+        (V::X, V::NEG_Y),
+        (V::X, V::NEG_Z),
+        (V::X, V::Y),
+        (V::NEG_X, V::Z),
+        (V::NEG_X, V::Y),
+        (V::NEG_X, V::NEG_Z),
+        (V::NEG_X, V::NEG_Y),
+        (V::NEG_Y, V::Z),
+        (V::NEG_Y, V::NEG_X),
+        (V::NEG_Y, V::NEG_Z),
+        (V::NEG_Y, V::X),
+    ]
+};
+
+/// The directions of the surface a node is attached to, indexed by wallmounted value
+pub(crate) const WALLMOUNTED_DIRS: [I16Vec3; 6] = [
+    I16Vec3::Y,
+    I16Vec3::NEG_Y,
+    I16Vec3::X,
+    I16Vec3::NEG_X,
+    I16Vec3::Z,
+    I16Vec3::NEG_Z,
+];
+
+/// The bits of a `leveled` `param2` that hold the level
+pub const LEVELED_MASK: u8 = 0x7f;
+
+/// An orientation of a node with paramtype2 `facedir` or `colorfacedir`
+///
+/// ```
+/// use glam::I16Vec3;
+/// use minetestworld::param2::FaceDir;
+///
+/// let facedir = FaceDir::from_param2(13).unwrap();
+/// assert_eq!(facedir.top(), I16Vec3::X);
+/// assert_eq!(facedir.front(), I16Vec3::NEG_Y);
+/// assert_eq!(FaceDir::from_dirs(I16Vec3::X, I16Vec3::NEG_Y), Some(facedir));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct FaceDir {
+    /// The direction the top of the node faces, in the order +y, +z, -z, +x, -x, -y
+    pub axis: u8,
+    /// The number of quarter turns around `axis`
+    pub rotation: u8,
+}
+
+impl FaceDir {
+    /// Decodes the lower five bits of `param2`, or returns `None` if they are out of range
+    ///
+    /// The upper bits, e.g. the [palette index](`palette_index`), are ignored.
+    pub fn from_param2(param2: u8) -> Option<Self> {
+        let facedir = param2 & 0x1f;
+        (facedir < 24).then_some(FaceDir {
+            axis: facedir / 4,
+            rotation: facedir % 4,
+        })
+    }
+
+    /// Returns the facedir value, without any palette index
+    pub fn to_param2(self) -> u8 {
+        (self.axis % 6) * 4 + self.rotation % 4
+    }
+
+    /// Returns the orientation whose top and front face the given directions
+    ///
+    /// Returns `None` if they are no perpendicular unit vectors along the axes.
+    pub fn from_dirs(top: I16Vec3, front: I16Vec3) -> Option<Self> {
+        FACEDIR_AXES
+            .iter()
+            .position(|&axes| axes == (top, front))
+            .and_then(|facedir| Self::from_param2(facedir as u8))
+    }
+
+    /// Returns the direction the top of the node faces
+    pub fn top(self) -> I16Vec3 {
+        FACEDIR_AXES[usize::from(self.to_param2())].0
+    }
+
+    /// Returns the direction the front of the node faces
+    ///
+    /// With a facedir of 0, the front faces +z.
+    pub fn front(self) -> I16Vec3 {
+        FACEDIR_AXES[usize::from(self.to_param2())].1
+    }
+
+    /// Returns the rotation as an axis and an angle in degrees
+    ///
+    /// The axis is the direction of the [top](`Self::top`).
+    pub fn axis_angle(self) -> (I16Vec3, u16) {
+        (self.top(), u16::from(self.rotation % 4) * 90)
+    }
+}
+
+/// The side of a node with paramtype2 `4dir` or `color4dir` that faces forward
+///
+/// This is a [`FaceDir`] whose top always faces +y.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct FourDir {
+    /// The number of quarter turns around the y axis, clockwise as seen from above
+    pub rotation: u8,
+}
+
+impl FourDir {
+    /// Decodes the lower two bits of `param2`
+    pub fn from_param2(param2: u8) -> Self {
+        FourDir {
+            rotation: param2 & 0x03,
+        }
+    }
+
+    /// Returns the 4dir value, without any palette index
+    pub fn to_param2(self) -> u8 {
+        self.rotation & 0x03
+    }
+
+    /// Returns the direction the front of the node faces
+    pub fn front(self) -> I16Vec3 {
+        self.to_facedir().front()
+    }
+
+    /// Returns the same orientation as a facedir
+    pub fn to_facedir(self) -> FaceDir {
+        FaceDir {
+            axis: 0,
+            rotation: self.to_param2(),
+        }
+    }
+}
+
+/// The surface a node with paramtype2 `wallmounted` or `colorwallmounted` is attached to
+///
+/// ```
+/// use glam::I16Vec3;
+/// use minetestworld::param2::WallMounted;
+///
+/// let torch = WallMounted::from_param2(3);
+/// assert_eq!(torch, WallMounted::NegX);
+/// assert_eq!(torch.dir(), I16Vec3::NEG_X);
+/// assert_eq!(WallMounted::from_dir(I16Vec3::NEG_X), Some(torch));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum WallMounted {
+    /// Attached to the ceiling
+    #[default]
+    PosY,
+    /// Attached to the floor
+    NegY,
+    /// Attached to a wall in the +x direction
+    PosX,
+    /// Attached to a wall in the -x direction
+    NegX,
+    /// Attached to a wall in the +z direction
+    PosZ,
+    /// Attached to a wall in the -z direction
+    NegZ,
+    /// Attached to the ceiling, turned by 90°
+    PosYRotated,
+    /// Attached to the floor, turned by 90°
+    NegYRotated,
+}
+
+impl WallMounted {
+    const ALL: [WallMounted; 8] = [
+        WallMounted::PosY,
+        WallMounted::NegY,
+        WallMounted::PosX,
+        WallMounted::NegX,
+        WallMounted::PosZ,
+        WallMounted::NegZ,
+        WallMounted::PosYRotated,
+        WallMounted::NegYRotated,
+    ];
+
+    /// Decodes the lower three bits of `param2`
+    pub fn from_param2(param2: u8) -> Self {
+        Self::ALL[usize::from(param2 & 0x07)]
+    }
+
+    /// Returns the wallmounted value, without any palette index
+    pub fn to_param2(self) -> u8 {
+        self as u8
+    }
+
+    /// Returns the unturned variant that is attached to the surface in direction `dir`
+    pub fn from_dir(dir: I16Vec3) -> Option<Self> {
+        WALLMOUNTED_DIRS
+            .iter()
+            .position(|&d| d == dir)
+            .map(|wallmounted| Self::ALL[wallmounted])
+    }
+
+    /// Returns the direction of the surface the node is attached to
+    pub fn dir(self) -> I16Vec3 {
+        match self {
+            WallMounted::PosYRotated => I16Vec3::Y,
+            WallMounted::NegYRotated => I16Vec3::NEG_Y,
+            wallmounted => WALLMOUNTED_DIRS[usize::from(wallmounted.to_param2())],
+        }
+    }
+}
+
+/// Returns the number of lower bits of `param2` that are not part of the palette index
+fn rotation_bits(paramtype2: ParamType2) -> Option<u8> {
+    match paramtype2 {
+        ParamType2::Color => Some(0),
+        ParamType2::ColorFourDir => Some(2),
+        ParamType2::ColorWallMounted => Some(3),
+        ParamType2::ColorFaceDir | ParamType2::ColorDegRotate => Some(5),
+        _ => None,
+    }
+}
+
+/// Returns the palette index of a node, or `None` if `paramtype2` has no palette
+///
+/// ```
+/// use minetestworld::node_def::ParamType2;
+/// use minetestworld::param2::{palette_index, with_palette_index};
+///
+/// assert_eq!(palette_index(ParamType2::ColorWallMounted, 0b10011_010), Some(19));
+/// assert_eq!(with_palette_index(ParamType2::ColorWallMounted, 0b10011_010, 1), 0b00001_010);
+/// assert_eq!(palette_index(ParamType2::FaceDir, 7), None);
+/// ```
+pub fn palette_index(paramtype2: ParamType2, param2: u8) -> Option<u8> {
+    rotation_bits(paramtype2).map(|bits| param2.checked_shr(bits.into()).unwrap_or(0))
+}
+
+/// Returns `param2` with its palette index set to `index`, keeping the other bits
+///
+/// Indices that do not fit are truncated. If `paramtype2` has no palette,
+/// `param2` is returned as is.
+pub fn with_palette_index(paramtype2: ParamType2, param2: u8, index: u8) -> u8 {
+    let Some(bits) = rotation_bits(paramtype2) else {
+        return param2;
+    };
+    let rotation_mask = (1u16 << bits) as u8 - 1;
+    (param2 & rotation_mask) | (u16::from(index) << bits) as u8
+}
+
+/// Returns the rotation around the y axis in degrees, for `degrotate` and `colordegrotate`
+///
+/// `degrotate` turns in steps of 1.5°, `colordegrotate` in steps of 15°.
+/// Returns `None` for other types and for values out of range.
+pub fn degrotate(paramtype2: ParamType2, param2: u8) -> Option<f32> {
+    match paramtype2 {
+        ParamType2::DegRotate if param2 < 240 => Some(f32::from(param2) * 1.5),
+        ParamType2::ColorDegRotate if param2 & 0x1f < 24 => Some(f32::from(param2 & 0x1f) * 15.0),
+        _ => None,
+    }
+}
+
+/// Returns `param2` turned to `degrees` around the y axis, rounded to the nearest step
+///
+/// The palette index of `colordegrotate` is kept. For other types than
+/// `degrotate` and `colordegrotate`, `param2` is returned as is.
+pub fn with_degrotate(paramtype2: ParamType2, param2: u8, degrees: f32) -> u8 {
+    let steps = |step: f32, count: f32| ((degrees / step).round().rem_euclid(count)) as u8;
+    match paramtype2 {
+        ParamType2::DegRotate => steps(1.5, 240.0),
+        ParamType2::ColorDegRotate => (param2 & !0x1f) | steps(15.0, 24.0),
+        _ => param2,
+    }
+}
+
+/// Returns the level of a node with paramtype2 `leveled`
+pub fn leveled(param2: u8) -> u8 {
+    param2 & LEVELED_MASK
+}
+
+/// Returns `param2` with the level of a `leveled` node set to `level`, at most 127
+pub fn with_leveled(param2: u8, level: u8) -> u8 {
+    (param2 & !LEVELED_MASK) | level.min(LEVELED_MASK)
+}
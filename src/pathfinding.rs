@@ -0,0 +1,97 @@
+//! Contains a search for paths through the nodes of the world
+//!
+//! ```
+//! use minetestworld::{MapData, MapEdit};
+//! use minetestworld::pathfinding::find_path;
+//! use glam::I16Vec3;
+//! use async_std::task;
+//!
+//! task::block_on(async {
+//!     let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true).await.unwrap();
+//!     let vm = MapEdit::new(map);
+//!     let (start, goal) = (I16Vec3::new(-185, -15, 65), I16Vec3::new(-180, -15, 65));
+//!     // Dig through everything, but prefer air
+//!     let cost = |content: &[u8]| Some(if content == b"air" { 1 } else { 5 });
+//!     let path = find_path(&vm, start, goal, |node| cost(&node.param0), 10_000)
+//!         .await
+//!         .unwrap()
+//!         .unwrap();
+//!     assert_eq!(path.first(), Some(&start));
+//!     assert_eq!(path.last(), Some(&goal));
+//! });
+//! ```
+
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap};
+
+use glam::I16Vec3;
+
+use crate::positions::neighbors6;
+use crate::{MapDataError, MapEdit, Node};
+
+/// Searches the cheapest path from `start` to `goal` with A*
+///
+/// A path moves between nodes that share a face. `cost` returns the cost of entering
+/// a node, or `None` if it cannot be entered. Nodes of mapblocks that have not been
+/// generated cannot be entered either. The path is the cheapest one as long as every
+/// cost is at least 1. Mapblocks are loaded through `vm` as the search reaches them.
+///
+/// Returns the nodes of the path, including `start` and `goal`, or `None` if there is
+/// no path, or if none has been found after visiting `max_visited` nodes.
+pub async fn find_path(
+    vm: &MapEdit,
+    start: I16Vec3,
+    goal: I16Vec3,
+    mut cost: impl FnMut(&Node) -> Option<u32>,
+    max_visited: usize,
+) -> Result<Option<Vec<I16Vec3>>, MapDataError> {
+    let estimate = |pos: I16Vec3| {
+        let distance = (goal.as_ivec3() - pos.as_ivec3()).abs();
+        u64::from((distance.x + distance.y + distance.z).unsigned_abs())
+    };
+    // The cheapest known cost to reach each node, and the node it is reached from
+    let mut reached: HashMap<I16Vec3, (u64, I16Vec3)> = HashMap::from([(start, (0, start))]);
+    let mut queue = BinaryHeap::from([(Reverse(estimate(start)), start.to_array())]);
+    let mut visited = 0;
+    while let Some((Reverse(estimated), pos)) = queue.pop() {
+        let pos = I16Vec3::from_array(pos);
+        let spent = reached[&pos].0;
+        // A cheaper way to this node has been queued later
+        if spent + estimate(pos) != estimated {
+            continue;
+        }
+        if pos == goal {
+            let mut path = vec![goal];
+            while let Some(&pos) = path.last().filter(|&&pos| pos != start) {
+                path.push(reached[&pos].1);
+            }
+            path.reverse();
+            return Ok(Some(path));
+        }
+        visited += 1;
+        if visited > max_visited {
+            break;
+        }
+        for neighbor in neighbors6(pos) {
+            let Some(node) = vm.get_node_option(neighbor).await? else {
+                continue;
+            };
+            let Some(step) = cost(&node) else {
+                continue;
+            };
+            let total = spent + u64::from(step);
+            match reached.entry(neighbor) {
+                Entry::Occupied(entry) if entry.get().0 <= total => continue,
+                Entry::Occupied(mut entry) => {
+                    entry.insert((total, pos));
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert((total, pos));
+                }
+            }
+            queue.push((Reverse(total + estimate(neighbor)), neighbor.to_array()));
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,533 @@
+//! Contains a type to read the player data of a world
+//!
+//! Players are either stored in `players.sqlite`, or, in older worlds,
+//! as one text file per player in the `players/` directory.
+
+use async_std::fs;
+use futures::stream;
+use futures::stream::BoxStream;
+use futures::stream::StreamExt;
+use futures::TryStreamExt;
+use glam::Vec3;
+#[cfg(feature = "sqlite")]
+use log::LevelFilter;
+#[cfg(feature = "sqlite")]
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqliteRow};
+#[cfg(feature = "sqlite")]
+use sqlx::{ConnectOptions, FromRow, Row};
+use std::collections::HashMap;
+
+pub use crate::inventory::InventoryList;
+use crate::inventory::{parse_inventory, serialize_inventory};
+use crate::json;
+use crate::BS;
+use std::fmt::Write;
+#[cfg(feature = "sqlite")]
+use std::path::Path;
+use std::path::PathBuf;
+
+#[cfg(feature = "sqlite")]
+// The NUMERIC columns may hold integers, which sqlx refuses to decode as floats
+const SQLITE_PLAYER_COLUMNS: &str = "SELECT name,
+ CAST(pitch AS REAL) AS pitch, CAST(yaw AS REAL) AS yaw,
+ CAST(posX AS REAL) AS posX, CAST(posY AS REAL) AS posY, CAST(posZ AS REAL) AS posZ,
+ hp, breath FROM player";
+
+/// An error in the underlying player database
+#[derive(thiserror::Error, Debug)]
+pub enum PlayerError {
+    #[cfg(feature = "sqlite")]
+    #[error("Database error: {0}")]
+    /// sqlx based error
+    SqlError(#[from] sqlx::Error),
+
+    /// An IO related error
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    /// A player file did not follow the expected format
+    ///
+    /// This variant contains a more detailed error message.
+    #[error("Player file malformed: {0}")]
+    FileMalformed(String),
+
+    /// There is no player with this name
+    #[error("Player '{0}' does not exist")]
+    PlayerNonexistent(String),
+}
+
+#[cfg(feature = "sqlite")]
+impl PlayerError {
+    /// Converts an SQL error to a player error
+    ///
+    /// while converting `RowNotFound` to `PlayerNonexistent(name)`
+    fn from_sqlx_error(e: sqlx::Error, name: &str) -> PlayerError {
+        if let sqlx::Error::RowNotFound = e {
+            PlayerError::PlayerNonexistent(name.to_string())
+        } else {
+            PlayerError::SqlError(e)
+        }
+    }
+}
+
+/// The saved state of a player
+#[derive(Debug, Clone, PartialEq)]
+pub struct Player {
+    /// The player name
+    pub name: String,
+    /// The position of the player's feet in node coordinates
+    pub position: Vec3,
+    /// Vertical look angle in degrees
+    pub pitch: f32,
+    /// Horizontal look angle in degrees
+    pub yaw: f32,
+    /// Hit points
+    pub hp: i32,
+    /// Remaining breath
+    pub breath: i32,
+    /// The player's inventory lists, in storage order
+    pub inventory: Vec<InventoryList>,
+    /// Player metadata key/value pairs set by mods
+    pub metadata: HashMap<String, String>,
+}
+
+impl Player {
+    /// Returns the inventory list called `name`, if present
+    pub fn inventory_list(&self, name: &str) -> Option<&InventoryList> {
+        self.inventory.iter().find(|list| list.name == name)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl FromRow<'_, SqliteRow> for Player {
+    fn from_row(row: &SqliteRow) -> sqlx::Result<Self> {
+        let position = Vec3::new(
+            row.try_get::<f64, _>("posX")? as f32,
+            row.try_get::<f64, _>("posY")? as f32,
+            row.try_get::<f64, _>("posZ")? as f32,
+        );
+        Ok(Player {
+            name: row.try_get("name")?,
+            position: position / BS,
+            pitch: row.try_get::<f64, _>("pitch")? as f32,
+            yaw: row.try_get::<f64, _>("yaw")? as f32,
+            hp: row.try_get("hp")?,
+            breath: row.try_get("breath")?,
+            inventory: vec![],
+            metadata: HashMap::new(),
+        })
+    }
+}
+
+/// A handle to the player database of a world
+///
+/// Modifications require a handle obtained via
+/// [`World::get_mutable_players`](`crate::World::get_mutable_players`).
+/// The server should not be running while players are modified.
+///
+/// ```
+/// use minetestworld::World;
+/// use futures::TryStreamExt;
+/// use async_std::task;
+///
+/// task::block_on(async {
+///     let players = World::open("TestWorld").get_players().await.unwrap();
+///     let players: Vec<_> = players.all_players().try_collect().await.unwrap();
+///     assert_eq!(players.len(), 2);
+/// });
+/// ```
+pub enum PlayerData {
+    /// This variant covers the SQLite database backend
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqlitePool),
+
+    /// This variant covers the legacy `players/` directory with one file per player
+    Files(PathBuf),
+}
+
+impl PlayerData {
+    #[cfg(feature = "sqlite")]
+    /// Connects to the "players.sqlite" database.
+    ///
+    /// `read_only` has to be false to be able to modify players.
+    pub async fn from_sqlite_file(
+        filename: impl AsRef<Path>,
+        read_only: bool,
+    ) -> Result<PlayerData, PlayerError> {
+        let opts = SqliteConnectOptions::new()
+            .immutable(read_only)
+            .filename(filename)
+            .log_statements(LevelFilter::Debug);
+        Ok(PlayerData::Sqlite(SqlitePool::connect_with(opts).await?))
+    }
+
+    /// Returns the saved state of all players
+    pub fn all_players(&self) -> BoxStream<'_, Result<Player, PlayerError>> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            PlayerData::Sqlite(pool) => sqlx::query_as(SQLITE_PLAYER_COLUMNS)
+                .fetch(pool)
+                .map_err(PlayerError::SqlError)
+                .and_then(|player| self.with_details(player))
+                .boxed(),
+            PlayerData::Files(dir) => stream::once(fs::read_dir(dir.clone()))
+                .try_flatten()
+                .map_err(PlayerError::IoError)
+                .and_then(|entry| async move {
+                    parse_player_file(&fs::read_to_string(entry.path()).await?)
+                })
+                .boxed(),
+        }
+    }
+
+    /// Returns the saved state of the player called `name`
+    pub async fn get_player(&self, name: &str) -> Result<Player, PlayerError> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            PlayerData::Sqlite(pool) => {
+                let player = sqlx::query_as(&format!("{SQLITE_PLAYER_COLUMNS} WHERE name = ?"))
+                    .bind(name)
+                    .fetch_one(pool)
+                    .await
+                    .map_err(|e| PlayerError::from_sqlx_error(e, name))?;
+                self.with_details(player).await
+            }
+            PlayerData::Files(dir) => {
+                let path = find_player_file(dir, name)
+                    .await?
+                    .ok_or_else(|| PlayerError::PlayerNonexistent(name.to_string()))?;
+                parse_player_file(&fs::read_to_string(path).await?)
+            }
+        }
+    }
+
+    /// Loads the inventory and the metadata of `player`
+    #[cfg(feature = "sqlite")]
+    async fn with_details(&self, mut player: Player) -> Result<Player, PlayerError> {
+        match self {
+            PlayerData::Sqlite(pool) => {
+                let lists = sqlx::query(
+                    "SELECT inv_id, inv_width, inv_name, inv_size FROM player_inventories
+                     WHERE player = ? ORDER BY inv_id",
+                )
+                .bind(&player.name)
+                .fetch_all(pool)
+                .await?;
+                let mut ids = Vec::with_capacity(lists.len());
+                for row in lists {
+                    ids.push(row.try_get::<i64, _>("inv_id")?);
+                    player.inventory.push(InventoryList {
+                        name: row.try_get("inv_name")?,
+                        width: row.try_get("inv_width")?,
+                        items: vec![String::new(); row.try_get::<u32, _>("inv_size")? as usize],
+                    });
+                }
+
+                let items = sqlx::query(
+                    "SELECT inv_id, slot_id, item FROM player_inventory_items WHERE player = ?",
+                )
+                .bind(&player.name)
+                .fetch_all(pool)
+                .await?;
+                for row in items {
+                    let inv_id: i64 = row.try_get("inv_id")?;
+                    let slot_id: i64 = row.try_get("slot_id")?;
+                    let list = ids.iter().position(|&id| id == inv_id);
+                    let slot = list.and_then(|list| {
+                        player.inventory[list]
+                            .items
+                            .get_mut(usize::try_from(slot_id).ok()?)
+                    });
+                    match slot {
+                        Some(slot) => *slot = row.try_get("item")?,
+                        None => log::warn!(
+                            "Dropping item of {} in nonexistent slot {inv_id}/{slot_id}",
+                            player.name
+                        ),
+                    }
+                }
+
+                let metadata =
+                    sqlx::query("SELECT metadata, value FROM player_metadata WHERE player = ?")
+                        .bind(&player.name)
+                        .fetch_all(pool)
+                        .await?;
+                for row in metadata {
+                    let value: Option<String> = row.try_get("value")?;
+                    player
+                        .metadata
+                        .insert(row.try_get("metadata")?, value.unwrap_or_default());
+                }
+            }
+            PlayerData::Files(_) => {}
+        }
+        Ok(player)
+    }
+
+    /// Writes the complete state of `player` back, creating the player if necessary
+    ///
+    /// Like the engine, this replaces all inventory lists and metadata of the player.
+    ///
+    /// ```ignore
+    /// // Rescue a stuck player
+    /// let mut player = players.get_player("sam").await?;
+    /// player.position = Vec3::new(0.0, 10.0, 0.0);
+    /// player.hp = 20;
+    /// players.save_player(&player).await?;
+    /// ```
+    pub async fn save_player(&self, player: &Player) -> Result<(), PlayerError> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            PlayerData::Sqlite(pool) => {
+                let position = player.position * BS;
+                let mut tx = pool.begin().await?;
+                sqlx::query(
+                    "INSERT INTO player (name, pitch, yaw, posX, posY, posZ, hp, breath)
+                     VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                     ON CONFLICT(name) DO UPDATE SET pitch = excluded.pitch, yaw = excluded.yaw,
+                     posX = excluded.posX, posY = excluded.posY, posZ = excluded.posZ,
+                     hp = excluded.hp, breath = excluded.breath,
+                     modification_date = CURRENT_TIMESTAMP",
+                )
+                .bind(&player.name)
+                .bind(f64::from(player.pitch))
+                .bind(f64::from(player.yaw))
+                .bind(f64::from(position.x))
+                .bind(f64::from(position.y))
+                .bind(f64::from(position.z))
+                .bind(player.hp)
+                .bind(player.breath)
+                .execute(&mut *tx)
+                .await?;
+
+                for table in [
+                    "player_inventories",
+                    "player_inventory_items",
+                    "player_metadata",
+                ] {
+                    sqlx::query(&format!("DELETE FROM {table} WHERE player = ?"))
+                        .bind(&player.name)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+
+                for (inv_id, list) in player.inventory.iter().enumerate() {
+                    sqlx::query(
+                        "INSERT INTO player_inventories (player, inv_id, inv_width, inv_name, inv_size)
+                         VALUES (?, ?, ?, ?, ?)",
+                    )
+                    .bind(&player.name)
+                    .bind(inv_id as i64)
+                    .bind(list.width)
+                    .bind(&list.name)
+                    .bind(list.items.len() as i64)
+                    .execute(&mut *tx)
+                    .await?;
+                    for (slot_id, item) in list.items.iter().enumerate() {
+                        sqlx::query(
+                            "INSERT INTO player_inventory_items (player, inv_id, slot_id, item)
+                             VALUES (?, ?, ?, ?)",
+                        )
+                        .bind(&player.name)
+                        .bind(inv_id as i64)
+                        .bind(slot_id as i64)
+                        .bind(item)
+                        .execute(&mut *tx)
+                        .await?;
+                    }
+                }
+
+                for (key, value) in &player.metadata {
+                    sqlx::query(
+                        "INSERT INTO player_metadata (player, metadata, value) VALUES (?, ?, ?)",
+                    )
+                    .bind(&player.name)
+                    .bind(key)
+                    .bind(value)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+
+                Ok(tx.commit().await?)
+            }
+            PlayerData::Files(dir) => {
+                let path = match find_player_file(dir, &player.name).await? {
+                    Some(path) => path,
+                    None => dir.join(&player.name),
+                };
+                // Write to a temporary file first, so that a crash can't leave a truncated file
+                let temp_path = path.with_extension("tmp");
+                fs::write(&temp_path, serialize_player_file(player)).await?;
+                Ok(fs::rename(temp_path, path).await?)
+            }
+        }
+    }
+}
+
+// Helper functions to read and write the legacy player file format
+
+/// Finds the file of the player called `name`
+///
+/// The file is usually named after the player, but the engine picks another name
+/// if that is taken, so all files have to be checked in that case.
+async fn find_player_file(dir: &PathBuf, name: &str) -> Result<Option<PathBuf>, PlayerError> {
+    let candidate = dir.join(name);
+    if let Ok(content) = fs::read_to_string(&candidate).await {
+        if parse_player_file(&content)?.name == name {
+            return Ok(Some(candidate));
+        }
+    }
+    let mut entries = fs::read_dir(dir).await?;
+    while let Some(entry) = entries.try_next().await? {
+        let path = PathBuf::from(entry.path().into_os_string());
+        if path.extension().is_some_and(|ext| ext == "tmp") {
+            continue;
+        }
+        if parse_player_file(&fs::read_to_string(&path).await?)?.name == name {
+            return Ok(Some(path));
+        }
+    }
+    Ok(None)
+}
+
+fn malformed(message: impl Into<String>) -> PlayerError {
+    PlayerError::FileMalformed(message.into())
+}
+
+fn parse_number<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, PlayerError> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| malformed(format!("'{value}' is not a valid {key}")))
+}
+
+/// Parses a vector in the `(x,y,z)` notation
+fn parse_v3f(value: &str) -> Result<Vec3, PlayerError> {
+    let components: Vec<f32> = value
+        .trim()
+        .trim_start_matches('(')
+        .trim_end_matches(')')
+        .split(',')
+        .map(|c| parse_number("position", c))
+        .collect::<Result<_, _>>()?;
+    match components[..] {
+        [x, y, z] => Ok(Vec3::new(x, y, z)),
+        _ => Err(malformed(format!("'{value}' is not a valid position"))),
+    }
+}
+
+fn parse_player_file(content: &str) -> Result<Player, PlayerError> {
+    let mut player = Player {
+        name: String::new(),
+        position: Vec3::ZERO,
+        pitch: 0.0,
+        yaw: 0.0,
+        hp: 20,
+        breath: 11,
+        inventory: vec![],
+        metadata: HashMap::new(),
+    };
+    let mut lines = content.lines();
+
+    for line in lines.by_ref() {
+        if line == "PlayerArgsEnd" {
+            break;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "name" => player.name = value.to_string(),
+            "pitch" => player.pitch = parse_number("pitch", value)?,
+            "yaw" => player.yaw = parse_number("yaw", value)?,
+            "position" => player.position = parse_v3f(value)? / BS,
+            "hp" => player.hp = parse_number("hp", value)?,
+            "breath" => player.breath = parse_number("breath", value)?,
+            "extended_attributes" => player.metadata = parse_json_string_map(value)?,
+            _ => {}
+        }
+    }
+    if player.name.is_empty() {
+        return Err(malformed("the player has no name"));
+    }
+
+    player.inventory = parse_inventory(lines).map_err(PlayerError::FileMalformed)?;
+    Ok(player)
+}
+
+fn serialize_player_file(player: &Player) -> String {
+    let position = player.position * BS;
+    let mut content = String::new();
+    // Writing to a String can't fail
+    let _ = writeln!(content, "name = {}", player.name);
+    let _ = writeln!(content, "pitch = {}", player.pitch);
+    let _ = writeln!(content, "yaw = {}", player.yaw);
+    let _ = writeln!(
+        content,
+        "position = ({},{},{})",
+        position.x, position.y, position.z
+    );
+    let _ = writeln!(content, "hp = {}", player.hp);
+    let _ = writeln!(content, "breath = {}", player.breath);
+    let _ = writeln!(
+        content,
+        "extended_attributes = {}",
+        serialize_json_string_map(&player.metadata)
+    );
+    content.push_str("version = 1\nPlayerArgsEnd\n");
+    content.push_str(&serialize_inventory(&player.inventory));
+    content
+}
+
+/// Parses a flat JSON object whose values are all strings
+fn parse_json_string_map(json: &str) -> Result<HashMap<String, String>, PlayerError> {
+    let mut chars = json.trim().chars().peekable();
+    let mut map = HashMap::new();
+    let error = || malformed(format!("'{json}' is not a JSON object of strings"));
+
+    let skip_whitespace = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+    };
+    if chars.next() != Some('{') {
+        return Err(error());
+    }
+    skip_whitespace(&mut chars);
+    if chars.next_if_eq(&'}').is_some() {
+        return Ok(map);
+    }
+    loop {
+        skip_whitespace(&mut chars);
+        let key = json::parse_string(&mut chars).ok_or_else(error)?;
+        skip_whitespace(&mut chars);
+        if chars.next() != Some(':') {
+            return Err(error());
+        }
+        skip_whitespace(&mut chars);
+        let value = json::parse_string(&mut chars).ok_or_else(error)?;
+        map.insert(key, value);
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            Some(',') => {}
+            Some('}') => return Ok(map),
+            _ => return Err(error()),
+        }
+    }
+}
+
+/// Serializes a flat JSON object with sorted keys
+fn serialize_json_string_map(map: &HashMap<String, String>) -> String {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_unstable();
+    let mut json = String::from("{");
+    for (i, (key, value)) in entries.into_iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        json::serialize_string(key, &mut json);
+        json.push(':');
+        json::serialize_string(value, &mut json);
+    }
+    json.push('}');
+    json
+}
@@ -7,11 +7,13 @@ use sqlx::postgres::PgRow;
 use sqlx::sqlite::SqliteRow;
 #[cfg(any(feature = "sqlite", feature = "postgres"))]
 use sqlx::{FromRow, Row};
+use std::ops::{Add, AddAssign, Mul, RangeInclusive, Sub, SubAssign};
+use std::str::FromStr;
 use std::{fmt::Display, io};
 
 use crate::{
-    BLOCK_BITS_1D, BLOCK_KEY_MIN, BLOCK_KEY_RANGE, BLOCK_MASK, BLOCK_NODES_1D, BLOCK_NODES_3D,
-    NODE_BITS_1D, NODE_MASK, NODE_STRIDE, WORLD_BLOCKS_RANGE,
+    BLOCK_BITS_1D, BLOCK_KEY_MIN, BLOCK_KEY_RANGE, BLOCK_MASK, BLOCK_NODES_1D, LIMIT_BLOCKS_RANGE,
+    NODE_BITS_1D, NODE_MASK, WORLD_BLOCKS_MIN, WORLD_BLOCKS_RANGE,
 };
 
 fn invalid_data_error<E>(error: E) -> sqlx::Error
@@ -26,7 +28,7 @@ where
 /// This type is used for addressing one of the following:
 /// * voxels ([nodes](`crate::Node`), node timers, metadata, ...).
 /// * [MapBlocks](`crate::MapBlock`). In this case, all three dimensions are divided by the
-/// MapBlock [side length](`crate::MAPBLOCK_LENGTH`).
+///   MapBlock [side length](`crate::MAPBLOCK_LENGTH`).
 ///
 /// A voxel position may either be absolute or relative to a mapblock root.
 ///
@@ -36,7 +38,6 @@ where
 // #[repr(transparent)]
 // #[derive(Debug, PartialEq, Copy, Clone, Eq, Hash)]
 // pub struct WorldPos(pub I16Vec3);
-
 #[repr(transparent)]
 #[derive(Debug, PartialEq, Copy, Clone, Eq, Hash)]
 pub struct BlockPos(I16Vec3);
@@ -68,6 +69,92 @@ impl TryFrom<i64> for BlockKey {
 #[derive(Debug)]
 pub struct BlockKeyOutOfRange;
 
+/// A key that orders blocks along a Z-order curve, also known as Morton code
+///
+/// Sorting by [`BlockKey`] visits the world in rows along the x axis, so neighbors
+/// along the y and z axes are far apart. Along the Z-order curve, blocks that are
+/// close to each other in the world are mostly close in the order as well.
+/// Processing blocks in this order keeps caches like the one of
+/// [`MapEdit`](`crate::MapEdit`) effective.
+///
+/// ```
+/// use minetestworld::positions::{BlockPos, MortonKey};
+/// use glam::I16Vec3;
+///
+/// let pos = BlockPos::from_index_vec(I16Vec3::new(-12, -2, 4));
+/// assert_eq!(BlockPos::from(MortonKey::from(pos)), pos);
+/// ```
+#[repr(transparent)]
+#[derive(Debug, PartialEq, Copy, Clone, Eq, Hash, PartialOrd, Ord)]
+pub struct MortonKey(u64);
+
+impl MortonKey {
+    /// Spreads the bits of `value` so that two zero bits follow each of them
+    fn spread(value: u16) -> u64 {
+        (0..BLOCK_BITS_1D).fold(0, |key, bit| {
+            key | (u64::from((value >> bit) & 1) << (3 * bit))
+        })
+    }
+
+    /// Reverts [`spread`](`Self::spread`) for the bits at `offset`
+    fn compact(key: u64, offset: u32) -> u16 {
+        (0..BLOCK_BITS_1D).fold(0, |value, bit| {
+            value | ((((key >> (3 * bit + offset)) & 1) as u16) << bit)
+        })
+    }
+}
+
+impl From<MortonKey> for u64 {
+    fn from(value: MortonKey) -> Self {
+        value.0
+    }
+}
+
+impl From<BlockPos> for MortonKey {
+    fn from(value: BlockPos) -> Self {
+        // Move the indices into the positive range, like it is done for the block key
+        let index = value.into_index_vec() - I16Vec3::splat(WORLD_BLOCKS_MIN);
+        let index = index.as_u16vec3();
+        Self(Self::spread(index.x) | (Self::spread(index.y) << 1) | (Self::spread(index.z) << 2))
+    }
+}
+
+impl From<MortonKey> for BlockPos {
+    fn from(value: MortonKey) -> Self {
+        let index = U16Vec3::new(
+            MortonKey::compact(value.0, 0),
+            MortonKey::compact(value.0, 1),
+            MortonKey::compact(value.0, 2),
+        );
+        BlockPos::from_index_vec(index.as_i16vec3() + I16Vec3::splat(WORLD_BLOCKS_MIN))
+    }
+}
+
+impl From<BlockKey> for MortonKey {
+    fn from(value: BlockKey) -> Self {
+        BlockPos::from(value).into()
+    }
+}
+
+impl From<MortonKey> for BlockKey {
+    fn from(value: MortonKey) -> Self {
+        BlockPos::from(value).into()
+    }
+}
+
+/// Returns true if a [`BlockPos`] may be constructed from `index` with a bounds check
+///
+/// With the `strict-bounds` feature, these are the blocks within the
+/// [generation limit](`crate::MAX_MAP_GENERATION_LIMIT`), otherwise the entire world.
+fn index_in_bounds(index: I16Vec3) -> bool {
+    let range = if cfg!(feature = "strict-bounds") {
+        LIMIT_BLOCKS_RANGE
+    } else {
+        WORLD_BLOCKS_RANGE
+    };
+    range.contains(&index.x) && range.contains(&index.y) && range.contains(&index.z)
+}
+
 impl BlockPos {
     /// Combines this block's position and a node position to form a world coordinate.
     #[must_use]
@@ -82,10 +169,398 @@ impl BlockPos {
     }
 
     /// Creates a new block position from the 3D-index of the block.
+    ///
+    /// The index is not checked, see [`try_from_index_vec`](`Self::try_from_index_vec`).
     #[must_use]
     pub fn from_index_vec(vec: I16Vec3) -> Self {
         Self(vec << NODE_BITS_1D)
     }
+
+    /// Creates a new block position from the 3D-index of the block, if it lies within the world
+    ///
+    /// With the `strict-bounds` feature, blocks beyond the
+    /// [generation limit](`Self::is_within_generation_limit`) are rejected as well.
+    pub fn try_from_index_vec(vec: I16Vec3) -> Result<Self, NodeIndexOutOfRange> {
+        if index_in_bounds(vec) {
+            Ok(Self::from_index_vec(vec))
+        } else {
+            Err(NodeIndexOutOfRange)
+        }
+    }
+
+    /// Returns true if the engine can load this block
+    ///
+    /// The engine refuses blocks that reach beyond
+    /// [`MAX_MAP_GENERATION_LIMIT`](`crate::MAX_MAP_GENERATION_LIMIT`).
+    #[must_use]
+    pub fn is_within_generation_limit(self) -> bool {
+        let index = self.into_index_vec();
+        LIMIT_BLOCKS_RANGE.contains(&index.x)
+            && LIMIT_BLOCKS_RANGE.contains(&index.y)
+            && LIMIT_BLOCKS_RANGE.contains(&index.z)
+    }
+
+    /// Returns the block `offset` blocks away, or `None` if it lies outside of the world
+    #[must_use]
+    pub fn checked_add(self, offset: I16Vec3) -> Option<Self> {
+        Self::checked_from_index_vec(self.into_index_vec().checked_add(offset)?)
+    }
+
+    /// Returns the block `offset` blocks back, or `None` if it lies outside of the world
+    #[must_use]
+    pub fn checked_sub(self, offset: I16Vec3) -> Option<Self> {
+        Self::checked_from_index_vec(self.into_index_vec().checked_sub(offset)?)
+    }
+
+    /// Returns the block with the index vector scaled by `factor`,
+    /// or `None` if it lies outside of the world
+    #[must_use]
+    pub fn checked_mul(self, factor: i16) -> Option<Self> {
+        Self::checked_from_index_vec(self.into_index_vec().checked_mul(I16Vec3::splat(factor))?)
+    }
+
+    /// Iterates over the up to six blocks sharing a face with this one
+    ///
+    /// Blocks outside of the world are left out.
+    pub fn neighbors6(self) -> impl Iterator<Item = Self> {
+        neighbors6(self.into_index_vec()).filter_map(Self::checked_from_index_vec)
+    }
+
+    /// Iterates over the up to 26 blocks sharing a face, an edge or a corner with this one
+    ///
+    /// Blocks outside of the world are left out.
+    pub fn neighbors26(self) -> impl Iterator<Item = Self> {
+        neighbors26(self.into_index_vec()).filter_map(Self::checked_from_index_vec)
+    }
+
+    /// Iterates over the blocks containing nodes of the box between the world positions `a` and `b`
+    ///
+    /// The blocks are ordered by their [`BlockKey`], which is the order the backends store them in.
+    ///
+    /// ```
+    /// use minetestworld::positions::BlockPos;
+    /// use glam::I16Vec3;
+    ///
+    /// let blocks: Vec<_> = BlockPos::iter_box(I16Vec3::new(-1, 0, 0), I16Vec3::new(16, 15, 0)).collect();
+    /// assert_eq!(blocks.len(), 3);
+    /// ```
+    pub fn iter_box(a: I16Vec3, b: I16Vec3) -> impl Iterator<Item = Self> {
+        BlockArea::new(
+            BlockPos::from_index_vec(a >> NODE_BITS_1D),
+            BlockPos::from_index_vec(b >> NODE_BITS_1D),
+        )
+        .iter()
+    }
+
+    /// Iterates over the blocks containing nodes within `radius` nodes of the world position `center`
+    ///
+    /// The blocks are ordered like in [`iter_box`](`Self::iter_box`).
+    ///
+    /// ```
+    /// use minetestworld::positions::BlockPos;
+    /// use glam::I16Vec3;
+    ///
+    /// // The sphere touches the three neighbors of the corner's block
+    /// let blocks: Vec<_> = BlockPos::iter_sphere(I16Vec3::new(15, 15, 15), 1).collect();
+    /// assert_eq!(blocks.len(), 4);
+    /// ```
+    pub fn iter_sphere(center: I16Vec3, radius: u16) -> impl Iterator<Item = Self> {
+        let center = center.as_ivec3();
+        let radius = i32::from(radius);
+        let clamp = |pos: IVec3| {
+            pos.clamp(IVec3::splat(i16::MIN.into()), IVec3::splat(i16::MAX.into()))
+                .as_i16vec3()
+        };
+        let last = i32::from(BLOCK_NODES_1D) - 1;
+        Self::iter_box(clamp(center - radius), clamp(center + radius)).filter(move |block| {
+            let min = block.0.as_ivec3();
+            let nearest = center.clamp(min, min + last);
+            (nearest - center).as_i64vec3().length_squared() <= i64::from(radius).pow(2)
+        })
+    }
+
+    fn checked_from_index_vec(index: I16Vec3) -> Option<Self> {
+        Self::try_from_index_vec(index).ok()
+    }
+}
+
+/// Moves the block by `offset` blocks
+///
+/// Panics if the result lies outside of the world, see [`BlockPos::checked_add`].
+impl Add<I16Vec3> for BlockPos {
+    type Output = Self;
+
+    fn add(self, offset: I16Vec3) -> Self {
+        self.checked_add(offset)
+            .expect("block position out of range")
+    }
+}
+
+/// Moves the block by `offset` blocks back
+///
+/// Panics if the result lies outside of the world, see [`BlockPos::checked_sub`].
+impl Sub<I16Vec3> for BlockPos {
+    type Output = Self;
+
+    fn sub(self, offset: I16Vec3) -> Self {
+        self.checked_sub(offset)
+            .expect("block position out of range")
+    }
+}
+
+/// Scales the index vector of the block by `factor`
+///
+/// Panics if the result lies outside of the world, see [`BlockPos::checked_mul`].
+impl Mul<i16> for BlockPos {
+    type Output = Self;
+
+    fn mul(self, factor: i16) -> Self {
+        self.checked_mul(factor)
+            .expect("block position out of range")
+    }
+}
+
+impl AddAssign<I16Vec3> for BlockPos {
+    fn add_assign(&mut self, offset: I16Vec3) {
+        *self = *self + offset;
+    }
+}
+
+impl SubAssign<I16Vec3> for BlockPos {
+    fn sub_assign(&mut self, offset: I16Vec3) {
+        *self = *self - offset;
+    }
+}
+
+/// The offset between two blocks, in blocks
+impl Sub for BlockPos {
+    type Output = I16Vec3;
+
+    fn sub(self, other: Self) -> I16Vec3 {
+        // Both indices lie within the world, so the difference fits into an i16
+        self.into_index_vec() - other.into_index_vec()
+    }
+}
+
+/// The offsets of the six positions sharing a face with a position
+pub const FACE_OFFSETS: [I16Vec3; 6] = [
+    I16Vec3::X,
+    I16Vec3::NEG_X,
+    I16Vec3::Y,
+    I16Vec3::NEG_Y,
+    I16Vec3::Z,
+    I16Vec3::NEG_Z,
+];
+
+/// Iterates over the up to six world positions sharing a face with `pos`
+///
+/// Positions beyond the range of an `i16` are left out.
+pub fn neighbors6(pos: I16Vec3) -> impl Iterator<Item = I16Vec3> {
+    FACE_OFFSETS
+        .into_iter()
+        .filter_map(move |offset| pos.checked_add(offset))
+}
+
+/// Iterates over the up to 26 world positions sharing a face, an edge or a corner with `pos`
+///
+/// Positions beyond the range of an `i16` are left out.
+pub fn neighbors26(pos: I16Vec3) -> impl Iterator<Item = I16Vec3> {
+    (-1..=1)
+        .flat_map(|z| (-1..=1).flat_map(move |y| (-1..=1).map(move |x| I16Vec3::new(x, y, z))))
+        .filter(|&offset| offset != I16Vec3::ZERO)
+        .filter_map(move |offset| pos.checked_add(offset))
+}
+
+/// An axis-aligned box of mapblocks, including both corners
+#[derive(Debug, PartialEq, Copy, Clone, Eq, Hash)]
+pub struct BlockArea {
+    min: BlockPos,
+    max: BlockPos,
+}
+
+impl BlockArea {
+    /// Creates the smallest box containing both corners
+    #[must_use]
+    pub fn new(a: BlockPos, b: BlockPos) -> Self {
+        Self {
+            min: BlockPos(a.0.min(b.0)),
+            max: BlockPos(a.0.max(b.0)),
+        }
+    }
+
+    /// Returns the corner with the smallest coordinates
+    #[must_use]
+    pub fn min(&self) -> BlockPos {
+        self.min
+    }
+
+    /// Returns the corner with the largest coordinates
+    #[must_use]
+    pub fn max(&self) -> BlockPos {
+        self.max
+    }
+
+    /// Returns true if `pos` lies within this box
+    #[must_use]
+    pub fn contains(&self, pos: BlockPos) -> bool {
+        pos.0.cmpge(self.min.0).all() && pos.0.cmple(self.max.0).all()
+    }
+
+    /// Iterates all block positions within this box, with x changing fastest
+    pub fn iter(&self) -> impl Iterator<Item = BlockPos> {
+        let min = self.min.into_index_vec();
+        let max = self.max.into_index_vec();
+        (min.z..=max.z).flat_map(move |z| {
+            (min.y..=max.y).flat_map(move |y| {
+                (min.x..=max.x).map(move |x| BlockPos::from_index_vec(I16Vec3::new(x, y, z)))
+            })
+        })
+    }
+
+    /// Iterates all block positions within this box along the Z-order curve, see [`MortonKey`]
+    pub fn iter_morton(&self) -> impl Iterator<Item = BlockPos> {
+        let mut positions: Vec<BlockPos> = self.iter().collect();
+        positions.sort_unstable_by_key(|&pos| MortonKey::from(pos));
+        positions.into_iter()
+    }
+
+    /// Returns the ascending, disjoint ranges of block keys that make up this box
+    ///
+    /// Every row of blocks along the x axis forms one range.
+    /// Adjacent rows are merged, which happens if the box spans the whole world on the x axis.
+    pub fn key_ranges(&self) -> Vec<RangeInclusive<BlockKey>> {
+        let min = self.min.into_index_vec();
+        let max = self.max.into_index_vec();
+        let mut ranges: Vec<RangeInclusive<BlockKey>> = vec![];
+        for z in min.z..=max.z {
+            for y in min.y..=max.y {
+                let start = BlockKey::from(BlockPos::from_index_vec(I16Vec3::new(min.x, y, z)));
+                let end = BlockKey::from(BlockPos::from_index_vec(I16Vec3::new(max.x, y, z)));
+                match ranges.last_mut() {
+                    Some(last) if last.end().0 + 1 == start.0 => *last = *last.start()..=end,
+                    _ => ranges.push(start..=end),
+                }
+            }
+        }
+        ranges
+    }
+
+    /// The largest number of key ranges that [`sqlite_predicate`](`Self::sqlite_predicate`)
+    /// lists one by one
+    pub const MAX_SQLITE_KEY_RANGES: usize = 64;
+
+    /// Returns an SQL condition selecting the blocks of this box from an SQLite map database
+    ///
+    /// `column` is the block key column, i.e. `pos` in the engine's `blocks` table.
+    /// It is inserted verbatim, so it must not be taken from untrusted input.
+    /// The outer `BETWEEN` narrows the search to the key span of the box,
+    /// so that the primary key index can be used, while the nested ones select
+    /// the rows of the box. Boxes of more than [`MAX_SQLITE_KEY_RANGES`](`Self::MAX_SQLITE_KEY_RANGES`)
+    /// rows select them by decoding the x and y coordinates from the key instead,
+    /// as SQLite limits the depth of expressions.
+    ///
+    /// ```
+    /// use minetestworld::positions::{BlockArea, BlockPos};
+    /// use glam::I16Vec3;
+    ///
+    /// let area = BlockArea::new(
+    ///     BlockPos::from_index_vec(I16Vec3::new(0, 0, 0)),
+    ///     BlockPos::from_index_vec(I16Vec3::new(1, 1, 0)),
+    /// );
+    /// assert_eq!(
+    ///     area.sqlite_predicate("pos"),
+    ///     "(pos BETWEEN 0 AND 4097 AND (pos BETWEEN 0 AND 1 OR pos BETWEEN 4096 AND 4097))"
+    /// );
+    ///
+    /// // Listing the rows of a tall box would exceed SQLite's expression depth
+    /// let tall = BlockArea::new(
+    ///     BlockPos::from_index_vec(I16Vec3::new(0, -100, 0)),
+    ///     BlockPos::from_index_vec(I16Vec3::new(1, 100, 0)),
+    /// );
+    /// assert!(!tall.sqlite_predicate("pos").contains(" OR "));
+    /// ```
+    pub fn sqlite_predicate(&self, column: &str) -> String {
+        let ranges = self.key_ranges();
+        let between = |range: &RangeInclusive<BlockKey>| {
+            format!("{column} BETWEEN {} AND {}", range.start(), range.end())
+        };
+        // There is at least one row in every box
+        let span = *ranges[0].start()..=*ranges[ranges.len() - 1].end();
+        if ranges.len() <= Self::MAX_SQLITE_KEY_RANGES {
+            let rows: Vec<_> = ranges.iter().map(between).collect();
+            return format!("({} AND ({}))", between(&span), rows.join(" OR "));
+        }
+        // The key span already limits z, and the shifted key is never negative
+        let min = self.min.into_index_vec() - I16Vec3::splat(WORLD_BLOCKS_MIN);
+        let max = self.max.into_index_vec() - I16Vec3::splat(WORLD_BLOCKS_MIN);
+        let blocks_1d = 1_i64 << BLOCK_BITS_1D;
+        format!(
+            "({} AND ({column} - {BLOCK_KEY_MIN}) % {blocks_1d} BETWEEN {} AND {} \
+             AND ({column} - {BLOCK_KEY_MIN}) / {blocks_1d} % {blocks_1d} BETWEEN {} AND {})",
+            between(&span),
+            min.x,
+            max.x,
+            min.y,
+            max.y,
+        )
+    }
+
+    /// Returns an SQL condition selecting the blocks of this box from a PostgreSQL map database
+    ///
+    /// The engine's `blocks` table stores the block position in the columns
+    /// `posx`, `posy` and `posz`.
+    pub fn postgres_predicate(&self) -> String {
+        let min = self.min.into_index_vec();
+        let max = self.max.into_index_vec();
+        format!(
+            "(posx BETWEEN {} AND {} AND posy BETWEEN {} AND {} AND posz BETWEEN {} AND {})",
+            min.x, max.x, min.y, max.y, min.z, max.z
+        )
+    }
+}
+
+/// Iterates the block positions around `center`, ordered by increasing distance
+///
+/// The blocks are visited in cubic shells, i.e. by their Chebyshev distance to `center`,
+/// up to and including `max_radius`. This allows prioritizing the area around e.g. the spawn
+/// and stopping early once enough blocks have been found.
+/// Positions outside of the world are skipped.
+///
+/// ```
+/// use minetestworld::positions::{spiral_outward, BlockPos};
+/// use glam::I16Vec3;
+///
+/// let center = BlockPos::from_index_vec(I16Vec3::new(0, 0, 0));
+/// let positions: Vec<_> = spiral_outward(center, 1).collect();
+/// assert_eq!(positions.len(), 27);
+/// assert_eq!(positions[0], center);
+/// ```
+pub fn spiral_outward(center: BlockPos, max_radius: u16) -> impl Iterator<Item = BlockPos> {
+    let center = center.into_index_vec().as_ivec3();
+    let world_range = i32::from(WORLD_BLOCKS_RANGE.start)..i32::from(WORLD_BLOCKS_RANGE.end);
+    (0..=i32::from(max_radius))
+        .flat_map(|radius| {
+            (-radius..=radius).flat_map(move |z| {
+                (-radius..=radius).flat_map(move |y| {
+                    // Inside of the shell, only the two faces along the x axis are visited
+                    let step = if z.abs() == radius || y.abs() == radius {
+                        1
+                    } else {
+                        (2 * radius).max(1)
+                    };
+                    (-radius..=radius)
+                        .step_by(step as usize)
+                        .map(move |x| IVec3::new(x, y, z))
+                })
+            })
+        })
+        .map(move |offset| center + offset)
+        .filter(move |pos| {
+            world_range.contains(&pos.x)
+                && world_range.contains(&pos.y)
+                && world_range.contains(&pos.z)
+        })
+        .map(|pos| BlockPos::from_index_vec(pos.as_i16vec3()))
 }
 
 impl From<BlockKey> for BlockPos {
@@ -122,14 +597,13 @@ impl From<BlockKey> for BlockPos {
     }
 }
 
+/// With the `strict-bounds` feature, this also rejects blocks beyond the
+/// [generation limit](`BlockPos::is_within_generation_limit`).
 impl TryFrom<I16Vec3> for BlockPos {
     type Error = NodeIndexOutOfRange;
 
     fn try_from(value: I16Vec3) -> Result<Self, Self::Error> {
-        if WORLD_BLOCKS_RANGE.contains(&value.x)
-            && WORLD_BLOCKS_RANGE.contains(&value.y)
-            && WORLD_BLOCKS_RANGE.contains(&value.z)
-        {
+        if index_in_bounds(value) {
             Ok(Self(value))
         } else {
             Err(NodeIndexOutOfRange)
@@ -137,6 +611,23 @@ impl TryFrom<I16Vec3> for BlockPos {
     }
 }
 
+/// A block position is serialized as its block index vector, see [`BlockPos::into_index_vec`]
+#[cfg(feature = "serde")]
+impl serde::Serialize for BlockPos {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.into_index_vec().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for BlockPos {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let index = I16Vec3::deserialize(deserializer)?;
+        BlockPos::try_from_index_vec(index)
+            .map_err(|_| serde::de::Error::custom(format!("block index {index} is out of range")))
+    }
+}
+
 impl From<BlockPos> for BlockKey {
     fn from(value: BlockPos) -> Self {
         let temp = (value.0 >> NODE_BITS_1D).as_i64vec3();
@@ -151,25 +642,135 @@ impl FromRow<'_, SqliteRow> for BlockPos {
     }
 }
 
-/// It is guaranteed that only the lowest `NODE_BITS_1D` bits are set
+/// The node math of mapblocks with a side length of `LENGTH` nodes
+///
+/// The engine uses 16, which is what [`NodePos`], [`NodeIndex`] and
+/// [`MapBlock`](`crate::MapBlock`) are fixed to. Engine forks with other block sizes
+/// can use the `Sized*` types with their own `LENGTH` to decode, edit and encode
+/// single mapblocks.
+/// `LENGTH` has to be a power of two, and at most 32 so that node indices fit into an u16.
+///
+/// Only the math within a mapblock is generic. Positions in the world, i.e. [`BlockPos`],
+/// [`BlockKey`] and [`SplitPos`], as well as [`MapData`](`crate::MapData`) and
+/// [`MapEdit`](`crate::MapEdit`) assume the engine's block size.
+pub struct BlockSize<const LENGTH: u16>;
+
+impl<const LENGTH: u16> BlockSize<LENGTH> {
+    /// Fails to compile if `LENGTH` is no valid block size
+    pub const VALID: () = assert!(
+        LENGTH.is_power_of_two() && LENGTH <= 32,
+        "the block length has to be a power of two of at most 32"
+    );
+
+    /// Number of bits needed to address nodes within a block in each dimension
+    pub const NODE_BITS_1D: u32 = LENGTH.trailing_zeros();
+
+    /// The bits needed to address nodes within a block
+    pub const NODE_MASK: u16 = LENGTH - 1;
+
+    /// Number of nodes in an entire block
+    pub const NODES_3D: usize = LENGTH as usize * LENGTH as usize * LENGTH as usize;
+
+    /// Number of node index increments in all directions
+    pub const NODE_STRIDE: U16Vec3 = U16Vec3::new(1, LENGTH, LENGTH * LENGTH);
+}
+
+/// A node position relative to a block with a side length of `LENGTH`
+///
+/// It is guaranteed that only the lowest [`BlockSize::NODE_BITS_1D`] bits are set.
 #[repr(transparent)]
 #[derive(Debug, PartialEq, Copy, Clone, Eq, Hash)]
-pub struct NodePos(U16Vec3);
+pub struct SizedNodePos<const LENGTH: u16 = BLOCK_NODES_1D>(U16Vec3);
+
+/// A node position relative to a mapblock of the engine's size
+pub type NodePos = SizedNodePos<BLOCK_NODES_1D>;
 
-/// The index of a node within a block.
+/// The index of a node within a block with a side length of `LENGTH`
 #[repr(transparent)]
 #[derive(Debug, PartialEq, Copy, Clone, Eq, Hash, PartialOrd, Ord)]
-pub struct NodeIndex(u16);
+pub struct SizedNodeIndex<const LENGTH: u16 = BLOCK_NODES_1D>(u16);
+
+/// The index of a node within a mapblock of the engine's size
+pub type NodeIndex = SizedNodeIndex<BLOCK_NODES_1D>;
+
+impl<const LENGTH: u16> SizedNodePos<LENGTH> {
+    /// Returns the node `offset` nodes away, or `None` if it lies outside of the block
+    #[must_use]
+    pub fn checked_add(self, offset: I16Vec3) -> Option<Self> {
+        let pos = self.0.as_i16vec3().checked_add(offset)?;
+        if pos.cmplt(I16Vec3::ZERO).any() {
+            return None;
+        }
+        Self::try_from(pos.as_u16vec3()).ok()
+    }
+
+    /// Returns the node `offset` nodes back, or `None` if it lies outside of the block
+    #[must_use]
+    pub fn checked_sub(self, offset: I16Vec3) -> Option<Self> {
+        let pos = self.0.as_i16vec3().checked_sub(offset)?;
+        if pos.cmplt(I16Vec3::ZERO).any() {
+            return None;
+        }
+        Self::try_from(pos.as_u16vec3()).ok()
+    }
+
+    /// Iterates over the up to six nodes of the same block sharing a face with this one
+    pub fn neighbors6(self) -> impl Iterator<Item = Self> {
+        FACE_OFFSETS
+            .into_iter()
+            .filter_map(move |offset| self.checked_add(offset))
+    }
+
+    /// Iterates over the up to 26 nodes of the same block sharing a face,
+    /// an edge or a corner with this one
+    pub fn neighbors26(self) -> impl Iterator<Item = Self> {
+        neighbors26(I16Vec3::ZERO).filter_map(move |offset| self.checked_add(offset))
+    }
+}
+
+/// Moves the node by `offset` nodes
+///
+/// Panics if the result lies outside of the block, see [`SizedNodePos::checked_add`].
+impl<const LENGTH: u16> Add<I16Vec3> for SizedNodePos<LENGTH> {
+    type Output = Self;
+
+    fn add(self, offset: I16Vec3) -> Self {
+        self.checked_add(offset)
+            .expect("node position out of range")
+    }
+}
+
+/// Moves the node by `offset` nodes back
+///
+/// Panics if the result lies outside of the block, see [`SizedNodePos::checked_sub`].
+impl<const LENGTH: u16> Sub<I16Vec3> for SizedNodePos<LENGTH> {
+    type Output = Self;
+
+    fn sub(self, offset: I16Vec3) -> Self {
+        self.checked_sub(offset)
+            .expect("node position out of range")
+    }
+}
+
+/// The offset between two nodes of a block
+impl<const LENGTH: u16> Sub for SizedNodePos<LENGTH> {
+    type Output = I16Vec3;
+
+    fn sub(self, other: Self) -> I16Vec3 {
+        self.0.as_i16vec3() - other.0.as_i16vec3()
+    }
+}
 
 /// Returned whenever a conversion to a `NodeIndex` failed due to being out of range input values.
 #[derive(Debug)]
 pub struct NodeIndexOutOfRange;
 
-impl TryFrom<u16> for NodeIndex {
+impl<const LENGTH: u16> TryFrom<u16> for SizedNodeIndex<LENGTH> {
     type Error = NodeIndexOutOfRange;
 
     fn try_from(value: u16) -> Result<Self, Self::Error> {
-        if value < BLOCK_NODES_3D {
+        let () = BlockSize::<LENGTH>::VALID;
+        if usize::from(value) < BlockSize::<LENGTH>::NODES_3D {
             Ok(Self(value))
         } else {
             Err(NodeIndexOutOfRange)
@@ -177,11 +778,12 @@ impl TryFrom<u16> for NodeIndex {
     }
 }
 
-impl TryFrom<U16Vec3> for NodePos {
+impl<const LENGTH: u16> TryFrom<U16Vec3> for SizedNodePos<LENGTH> {
     type Error = NodeIndexOutOfRange;
 
     fn try_from(value: U16Vec3) -> Result<Self, Self::Error> {
-        if value.x < BLOCK_NODES_1D && value.y < BLOCK_NODES_1D && value.z < BLOCK_NODES_1D {
+        let () = BlockSize::<LENGTH>::VALID;
+        if value.x < LENGTH && value.y < LENGTH && value.z < LENGTH {
             Ok(Self(value))
         } else {
             Err(NodeIndexOutOfRange)
@@ -189,19 +791,35 @@ impl TryFrom<U16Vec3> for NodePos {
     }
 }
 
-impl From<NodeIndex> for u16 {
-    fn from(value: NodeIndex) -> Self {
+#[cfg(feature = "serde")]
+impl<const LENGTH: u16> serde::Serialize for SizedNodePos<LENGTH> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, const LENGTH: u16> serde::Deserialize<'de> for SizedNodePos<LENGTH> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = U16Vec3::deserialize(deserializer)?;
+        Self::try_from(value)
+            .map_err(|_| serde::de::Error::custom(format!("node position {value} is out of range")))
+    }
+}
+
+impl<const LENGTH: u16> From<SizedNodeIndex<LENGTH>> for u16 {
+    fn from(value: SizedNodeIndex<LENGTH>) -> Self {
         value.0
     }
 }
 
-impl From<NodePos> for U16Vec3 {
-    fn from(value: NodePos) -> Self {
+impl<const LENGTH: u16> From<SizedNodePos<LENGTH>> for U16Vec3 {
+    fn from(value: SizedNodePos<LENGTH>) -> Self {
         value.0
     }
 }
 
-impl Display for NodeIndex {
+impl<const LENGTH: u16> Display for SizedNodeIndex<LENGTH> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         self.0.fmt(f)
     }
@@ -213,36 +831,131 @@ impl Display for BlockKey {
     }
 }
 
+/// Returned whenever a position could not be parsed from the `(x,y,z)` notation
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum ParsePosError {
+    #[error("'{0}' is not a position in the (x,y,z) notation")]
+    /// The text is no triple of integers
+    Malformed(String),
+    #[error("'{0}' is out of range")]
+    /// The position does not fit into the target type
+    OutOfRange(String),
+}
+
+/// Parses a position in the `(x,y,z)` notation used by the engine,
+/// e.g. in chat commands, configuration files and `force_loaded` entries
+///
+/// Whitespace around the numbers and the parentheses are optional.
+///
+/// ```
+/// use minetestworld::positions::{display_pos, parse_pos};
+/// use glam::I16Vec3;
+///
+/// let pos = parse_pos("(-185, -15,65)").unwrap();
+/// assert_eq!(pos, I16Vec3::new(-185, -15, 65));
+/// assert_eq!(display_pos(pos).to_string(), "(-185,-15,65)");
+/// ```
+pub fn parse_pos(text: &str) -> Result<I16Vec3, ParsePosError> {
+    let malformed = || ParsePosError::Malformed(text.to_string());
+    let inner = text.trim();
+    let inner = inner
+        .strip_prefix('(')
+        .and_then(|inner| inner.strip_suffix(')'))
+        .unwrap_or(inner);
+    let mut components = [0; 3];
+    let mut parts = inner.split(',');
+    for component in &mut components {
+        let part = parts.next().ok_or_else(malformed)?.trim();
+        let value: i64 = part.parse().map_err(|_| malformed())?;
+        *component =
+            i16::try_from(value).map_err(|_| ParsePosError::OutOfRange(text.to_string()))?;
+    }
+    if parts.next().is_some() {
+        return Err(malformed());
+    }
+    Ok(I16Vec3::from_array(components))
+}
+
+/// Formats a position in the `(x,y,z)` notation used by the engine, see [`parse_pos`]
+pub fn display_pos(pos: I16Vec3) -> impl Display {
+    struct Pos(I16Vec3);
+
+    impl Display for Pos {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "({},{},{})", self.0.x, self.0.y, self.0.z)
+        }
+    }
+
+    Pos(pos)
+}
+
+/// A block position is written as its block index vector, see [`BlockPos::into_index_vec`]
+impl Display for BlockPos {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        display_pos(self.into_index_vec()).fmt(f)
+    }
+}
+
+/// A block position is read as its block index vector, see [`BlockPos::from_index_vec`]
+impl FromStr for BlockPos {
+    type Err = ParsePosError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let index = parse_pos(s)?;
+        BlockPos::try_from_index_vec(index).map_err(|_| ParsePosError::OutOfRange(s.to_string()))
+    }
+}
+
+impl<const LENGTH: u16> Display for SizedNodePos<LENGTH> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        display_pos(self.0.as_i16vec3()).fmt(f)
+    }
+}
+
+impl<const LENGTH: u16> FromStr for SizedNodePos<LENGTH> {
+    type Err = ParsePosError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let pos = parse_pos(s)?;
+        if pos.cmplt(I16Vec3::ZERO).any() {
+            return Err(ParsePosError::OutOfRange(s.to_string()));
+        }
+        Self::try_from(pos.as_u16vec3()).map_err(|_| ParsePosError::OutOfRange(s.to_string()))
+    }
+}
+
 /// Convert a nodex index (used in flat 16·16·16 arrays) into a node position
 ///
 /// The node position will be relative to the map block.
-impl From<NodeIndex> for NodePos {
-    fn from(node_index: NodeIndex) -> Self {
+impl<const LENGTH: u16> From<SizedNodeIndex<LENGTH>> for SizedNodePos<LENGTH> {
+    fn from(node_index: SizedNodeIndex<LENGTH>) -> Self {
+        let bits = BlockSize::<LENGTH>::NODE_BITS_1D;
+        let mask = BlockSize::<LENGTH>::NODE_MASK;
         // ....zzzzyyyyxxxx
         Self(U16Vec3::new(
-            node_index.0 & NODE_MASK,
-            (node_index.0 >> NODE_BITS_1D) & NODE_MASK,
-            (node_index.0 >> (NODE_BITS_1D * 2)) & NODE_MASK,
+            node_index.0 & mask,
+            (node_index.0 >> bits) & mask,
+            (node_index.0 >> (bits * 2)) & mask,
         ))
     }
 }
 
 /// Convert a MapBlock-relative node position into a flat array index
-impl From<NodePos> for NodeIndex {
-    fn from(value: NodePos) -> NodeIndex {
-        Self(value.0.dot(NODE_STRIDE))
+impl<const LENGTH: u16> From<SizedNodePos<LENGTH>> for SizedNodeIndex<LENGTH> {
+    fn from(value: SizedNodePos<LENGTH>) -> Self {
+        Self(value.0.dot(BlockSize::<LENGTH>::NODE_STRIDE))
     }
 }
 
-impl From<NodeIndex> for usize {
-    fn from(value: NodeIndex) -> usize {
+impl<const LENGTH: u16> From<SizedNodeIndex<LENGTH>> for usize {
+    fn from(value: SizedNodeIndex<LENGTH>) -> usize {
         usize::from(value.0)
     }
 }
 
-impl From<NodePos> for usize {
-    fn from(value: NodePos) -> usize {
-        NodeIndex::from(value).into()
+impl<const LENGTH: u16> From<SizedNodePos<LENGTH>> for usize {
+    fn from(value: SizedNodePos<LENGTH>) -> usize {
+        SizedNodeIndex::from(value).into()
     }
 }
 
@@ -329,10 +1042,30 @@ impl FromRow<'_, PgRow> for BlockPos {
 // }
 
 /// Enables splitting and joining world coordinates.
+///
+/// This uses the engine's block size, see [`BlockSize`].
 pub trait SplitPos {
     /// Splits a world coordinate into a block position and a node position.
+    ///
+    /// The block position is not checked, see [`try_split`](`Self::try_split`).
     fn split(self) -> (BlockPos, NodePos);
 
+    /// Like [`split`](`Self::split`), but fails if the block lies outside of the world
+    ///
+    /// With the `strict-bounds` feature, blocks beyond the
+    /// [generation limit](`BlockPos::is_within_generation_limit`) are rejected as well.
+    fn try_split(self) -> Result<(BlockPos, NodePos), NodeIndexOutOfRange>
+    where
+        Self: Sized,
+    {
+        let (block_pos, node_pos) = self.split();
+        if index_in_bounds(block_pos.into_index_vec()) {
+            Ok((block_pos, node_pos))
+        } else {
+            Err(NodeIndexOutOfRange)
+        }
+    }
+
     /// Joins a block position and a node position to form a new world coordinate.
     fn join(block_pos: BlockPos, node_pos: NodePos) -> Self;
 }
@@ -341,7 +1074,7 @@ impl SplitPos for I16Vec3 {
     fn split(self) -> (BlockPos, NodePos) {
         (
             BlockPos(self & I16Vec3::splat(BLOCK_MASK)),
-            NodePos(self.as_u16vec3() & U16Vec3::splat(NODE_MASK)),
+            SizedNodePos(self.as_u16vec3() & U16Vec3::splat(NODE_MASK)),
         )
     }
 
@@ -0,0 +1,478 @@
+//! Rendering of map images, like minetestmapper does
+//!
+//! [`render_top_down`] looks at a region from above and colors every node column
+//! by its topmost visible node. The colors of the contents are taken from a [`ColorMap`].
+//! [`render_slice`] cuts a vertical cross-section through the world instead.
+//! The resulting [`Image`] can be encoded as PNG.
+//! Color tables of minetestmapper (`colors.txt`) can be loaded with [`ColorMap::from_file`].
+//!
+//! ```no_run
+//! use minetestworld::render::{self, ColorMap};
+//! use minetestworld::World;
+//! use glam::I16Vec3;
+//! use async_std::task;
+//!
+//! task::block_on(async {
+//!     let map = World::open("TestWorld").get_map_data().await.unwrap();
+//!     let mut colors = ColorMap::new();
+//!     colors.insert(b"default:stone", [128, 128, 128, 255]);
+//!     colors.insert(b"default:water_source", [40, 80, 200, 160]);
+//!     let image = render::render_top_down(
+//!         &map,
+//!         I16Vec3::new(-256, -64, -256),
+//!         I16Vec3::new(255, 128, 255),
+//!         &colors,
+//!     )
+//!     .await
+//!     .unwrap();
+//!     std::fs::write("map.png", image.to_png().unwrap()).unwrap();
+//! });
+//! ```
+
+use futures::TryStreamExt;
+use glam::{I16Vec2, I16Vec3};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+
+use crate::map_data::Progress;
+use crate::positions::{BlockArea, BlockPos};
+use crate::{MapBlock, MapData, MapDataError, BLOCK_NODES_1D, NODE_BITS_1D};
+
+/// A color with red, green, blue and alpha channels
+pub type Color = [u8; 4];
+
+/// An error while loading a [`ColorMap`]
+#[derive(thiserror::Error, Debug)]
+pub enum ColorMapError {
+    /// An IO related error
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+    /// A line could not be parsed
+    ///
+    /// The line number, counted from 1, and a description are contained.
+    #[error("Syntax error in line {0}: {1}")]
+    Syntax(usize, String),
+}
+
+/// Maps content names to the colors they are rendered with
+///
+/// Contents without a color are invisible, unless a fallback color is set.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColorMap {
+    colors: HashMap<Vec<u8>, Color>,
+    fallback: Option<Color>,
+}
+
+impl ColorMap {
+    /// Creates an empty color map
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a color table in the `colors.txt` format of minetestmapper
+    ///
+    /// Every line contains a content name followed by red, green and blue
+    /// and optionally alpha, e.g. `default:water_source 39 66 106 128`.
+    /// Empty lines and lines starting with `#` are ignored, as is minetestmapper's
+    /// optional sixth column.
+    ///
+    /// ```
+    /// use minetestworld::render::ColorMap;
+    ///
+    /// let colors = ColorMap::parse("# Ores\ndefault:stone_with_coal 80 80 80\n").unwrap();
+    /// assert_eq!(colors.get(b"default:stone_with_coal"), Some([80, 80, 80, 255]));
+    /// ```
+    pub fn parse(data: &str) -> Result<Self, ColorMapError> {
+        let mut colors = ColorMap::new();
+        for (number, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let syntax_error =
+                |message: &str| ColorMapError::Syntax(number + 1, message.to_string());
+            let mut columns = line.split_whitespace();
+            let Some(content) = columns.next() else {
+                continue;
+            };
+            let channels = columns
+                .map(|channel| channel.parse::<u8>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| syntax_error("color channels have to be in 0..=255"))?;
+            let color = match channels[..] {
+                [r, g, b] => [r, g, b, 255],
+                [r, g, b, a] | [r, g, b, a, _] => [r, g, b, a],
+                _ => {
+                    return Err(syntax_error(
+                        "expected 3 to 5 numbers after the content name",
+                    ))
+                }
+            };
+            colors.insert(content.as_bytes(), color);
+        }
+        Ok(colors)
+    }
+
+    /// Reads a `colors.txt` file, see [`ColorMap::parse`]
+    pub async fn from_file(path: impl AsRef<Path>) -> Result<Self, ColorMapError> {
+        ColorMap::parse(&async_std::fs::read_to_string(path.as_ref()).await?)
+    }
+
+    /// Sets the color of `content`
+    ///
+    /// Colors with an alpha below 255 are translucent,
+    /// so that e.g. the ground below shallow water stays visible.
+    pub fn insert(&mut self, content: &[u8], color: Color) {
+        self.colors.insert(content.to_vec(), color);
+    }
+
+    /// Adds all colors of `other`, replacing the colors of contents that are in both
+    ///
+    /// This allows to load a general color table first and override it
+    /// e.g. with the colors of a specific game. The fallback color of `other`
+    /// takes precedence as well, if it has one.
+    pub fn merge(&mut self, other: &ColorMap) {
+        self.colors.extend(
+            other
+                .colors
+                .iter()
+                .map(|(content, &color)| (content.clone(), color)),
+        );
+        self.fallback = other.fallback.or(self.fallback);
+    }
+
+    /// Sets the color of all contents that have no color of their own
+    ///
+    /// `air` and `ignore` stay invisible nevertheless.
+    pub fn set_fallback(&mut self, color: Option<Color>) {
+        self.fallback = color;
+    }
+
+    /// Returns the fallback color, see [`ColorMap::set_fallback`]
+    pub fn fallback(&self) -> Option<Color> {
+        self.fallback
+    }
+
+    /// Returns the color of `content`, or the fallback color if it has none
+    pub fn get(&self, content: &[u8]) -> Option<Color> {
+        match self.colors.get(content) {
+            Some(&color) => Some(color),
+            None if content == b"air" || content == b"ignore" => None,
+            None => self.fallback,
+        }
+    }
+}
+
+/// An image with RGBA pixels
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<Color>,
+}
+
+impl Image {
+    /// Creates a transparent image
+    pub fn new(width: u32, height: u32) -> Self {
+        Image {
+            width,
+            height,
+            pixels: vec![[0; 4]; width as usize * height as usize],
+        }
+    }
+
+    /// Returns the width in pixels
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the height in pixels
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the color of the pixel at column `x` and row `y`, counted from the top left
+    ///
+    /// Panics if the pixel lies outside of the image.
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        assert!(x < self.width && y < self.height, "pixel out of bounds");
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// Sets the color of the pixel at column `x` and row `y`, counted from the top left
+    ///
+    /// Panics if the pixel lies outside of the image.
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: Color) {
+        assert!(x < self.width && y < self.height, "pixel out of bounds");
+        self.pixels[(y * self.width + x) as usize] = color;
+    }
+
+    /// Encodes the image as PNG into `dest`
+    pub fn write_png(&self, dest: impl Write) -> Result<(), png::EncodingError> {
+        let mut encoder = png::Encoder::new(dest, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(self.pixels.as_flattened())?;
+        writer.finish()
+    }
+
+    /// Encodes the image as PNG
+    pub fn to_png(&self) -> Result<Vec<u8>, png::EncodingError> {
+        let mut dest = vec![];
+        self.write_png(&mut dest)?;
+        Ok(dest)
+    }
+}
+
+/// The color of a node column, accumulated from top to bottom
+///
+/// The color channels are premultiplied with the alpha, all in `0.0..=1.0`.
+#[derive(Debug, Clone, Copy, Default)]
+struct Blend([f32; 4]);
+
+impl Blend {
+    fn is_opaque(&self) -> bool {
+        self.0[3] >= 0.999
+    }
+
+    /// Adds `color` below the colors seen so far
+    fn add_below(&mut self, color: Color) {
+        let weight = f32::from(color[3]) / 255.0 * (1.0 - self.0[3]);
+        for (channel, value) in self.0.iter_mut().zip(color).take(3) {
+            *channel += f32::from(value) / 255.0 * weight;
+        }
+        self.0[3] += weight;
+    }
+
+    fn to_color(self) -> Color {
+        let [r, g, b, a] = self.0;
+        if a <= 0.0 {
+            return [0; 4];
+        }
+        let channel = |value: f32| (value * 255.0).round().clamp(0.0, 255.0) as u8;
+        [channel(r / a), channel(g / a), channel(b / a), channel(a)]
+    }
+}
+
+/// Blends the nodes of a serialized mapblock into the node columns below `pixels`
+///
+/// `pixels` holds the columns of the whole mapblock, indexed by `x + 16 * z`.
+/// Only nodes within the box from `min` to `max` are considered.
+/// This keeps the decoded mapblock out of the rendering future.
+fn blend_block(
+    data: &[u8],
+    origin: I16Vec3,
+    min: I16Vec3,
+    max: I16Vec3,
+    colors: &ColorMap,
+    pixels: &mut [Blend],
+) -> Result<(), MapDataError> {
+    let block = MapBlock::from_data(data)?;
+    let block_colors: HashMap<u16, Color> = block
+        .name_id_mappings
+        .iter()
+        .filter_map(|(&id, content)| colors.get(content).map(|color| (id, color)))
+        .collect();
+    if block_colors.is_empty() {
+        return Ok(());
+    }
+    let local_min = (min - origin).max(I16Vec3::ZERO);
+    let local_max = (max - origin).min(I16Vec3::splat(BLOCK_NODES_1D as i16 - 1));
+    for z in local_min.z..=local_max.z {
+        for x in local_min.x..=local_max.x {
+            let pixel = &mut pixels[(x + z * BLOCK_NODES_1D as i16) as usize];
+            for y in (local_min.y..=local_max.y).rev() {
+                if pixel.is_opaque() {
+                    break;
+                }
+                let index = (x + (y << NODE_BITS_1D) + (z << (2 * NODE_BITS_1D))) as usize;
+                if let Some(&color) = block_colors.get(&block.param0[index]) {
+                    pixel.add_below(color);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Renders the box between `a` and `b` as seen from above
+///
+/// Every pixel shows a node column: the first opaque node from the top,
+/// with translucent nodes above it blended over it. Columns without any colored
+/// node stay transparent. The y range of the box limits which nodes are considered,
+/// e.g. to look below a ceiling.
+///
+/// The image has one pixel per node, with x growing to the right and z growing upwards,
+/// so north is at the top.
+pub async fn render_top_down(
+    map: &MapData,
+    a: I16Vec3,
+    b: I16Vec3,
+    colors: &ColorMap,
+) -> Result<Image, MapDataError> {
+    render_top_down_with_progress(map, a, b, colors, |_| ()).await
+}
+
+/// Like [`render_top_down`], but reports its [`Progress`]
+///
+/// The units are the mapblock columns, as the number of mapblocks that have to be
+/// decoded per column is not known beforehand.
+pub async fn render_top_down_with_progress(
+    map: &MapData,
+    a: I16Vec3,
+    b: I16Vec3,
+    colors: &ColorMap,
+    mut progress: impl FnMut(Progress),
+) -> Result<Image, MapDataError> {
+    let (min, max) = (a.min(b), a.max(b));
+    let area = BlockArea::new(
+        BlockPos::from_index_vec(min >> NODE_BITS_1D),
+        BlockPos::from_index_vec(max >> NODE_BITS_1D),
+    );
+    let positions: Vec<BlockPos> = map.mapblock_positions_in(area).await.try_collect().await?;
+    // Group the mapblocks by column, topmost first
+    let mut columns: HashMap<(i16, i16), Vec<i16>> = HashMap::new();
+    for pos in positions {
+        let index = pos.into_index_vec();
+        columns.entry((index.x, index.z)).or_default().push(index.y);
+    }
+
+    let size = (max - min).as_uvec3() + 1;
+    let mut image = Image::new(size.x, size.z);
+    let mut pixels = vec![Blend::default(); BLOCK_NODES_1D as usize * BLOCK_NODES_1D as usize];
+    let mut state = Progress::new(columns.len());
+    progress(state);
+    for ((block_x, block_z), mut ys) in columns {
+        ys.sort_unstable_by(|a, b| b.cmp(a));
+        pixels.fill(Blend::default());
+        for block_y in ys {
+            let pos = BlockPos::from_index_vec(I16Vec3::new(block_x, block_y, block_z));
+            let data = map.get_block_data(pos).await?;
+            let origin = I16Vec3::new(block_x, block_y, block_z) << NODE_BITS_1D;
+            blend_block(&data, origin, min, max, colors, &mut pixels)?;
+            if pixels.iter().all(Blend::is_opaque) {
+                break;
+            }
+        }
+        let origin = I16Vec3::new(block_x, 0, block_z) << NODE_BITS_1D;
+        for (index, pixel) in pixels.iter().enumerate() {
+            let x = origin.x + (index % BLOCK_NODES_1D as usize) as i16;
+            let z = origin.z + (index / BLOCK_NODES_1D as usize) as i16;
+            if (min.x..=max.x).contains(&x) && (min.z..=max.z).contains(&z) {
+                let column = (x - min.x) as u32;
+                let row = (max.z - z) as u32;
+                image.set_pixel(column, row, pixel.to_color());
+            }
+        }
+        progress(state.advance());
+    }
+    Ok(image)
+}
+
+/// A vertical plane through the world, for [`render_slice`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlicePlane {
+    /// The plane of all nodes with this x coordinate, with z growing to the right
+    X(i16),
+    /// The plane of all nodes with this z coordinate, with x growing to the right
+    Z(i16),
+}
+
+/// Paints the nodes of a serialized mapblock that lie in the box from `min` to `max`
+///
+/// The box is one node thick in the direction of `plane`.
+/// Like [`blend_block`], this keeps the decoded mapblock out of the rendering future.
+fn paint_slice(
+    data: &[u8],
+    origin: I16Vec3,
+    plane: SlicePlane,
+    (min, max): (I16Vec3, I16Vec3),
+    colors: &ColorMap,
+    image: &mut Image,
+) -> Result<(), MapDataError> {
+    let block = MapBlock::from_data(data)?;
+    let block_colors: HashMap<u16, Color> = block
+        .name_id_mappings
+        .iter()
+        .filter_map(|(&id, content)| colors.get(content).map(|color| (id, color)))
+        .collect();
+    let local_min = (min - origin).max(I16Vec3::ZERO);
+    let local_max = (max - origin).min(I16Vec3::splat(BLOCK_NODES_1D as i16 - 1));
+    for z in local_min.z..=local_max.z {
+        for y in local_min.y..=local_max.y {
+            for x in local_min.x..=local_max.x {
+                let index = (x + (y << NODE_BITS_1D) + (z << (2 * NODE_BITS_1D))) as usize;
+                let Some(&color) = block_colors.get(&block.param0[index]) else {
+                    continue;
+                };
+                let pos = origin + I16Vec3::new(x, y, z);
+                let column = match plane {
+                    SlicePlane::X(_) => pos.z - min.z,
+                    SlicePlane::Z(_) => pos.x - min.x,
+                };
+                image.set_pixel(column as u32, (max.y - pos.y) as u32, color);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Renders a vertical cross-section of the world, e.g. to inspect caves, ores and strata
+///
+/// The corners `a` and `b` are given as `(horizontal, y)` within `plane`,
+/// where horizontal is z for [`SlicePlane::X`] and x for [`SlicePlane::Z`].
+/// The image has one pixel per node with y growing upwards. Unlike
+/// [`render_top_down`], every pixel shows exactly one node, so translucent
+/// and uncolored nodes stay (partially) transparent.
+///
+/// ```no_run
+/// use minetestworld::render::{self, ColorMap, SlicePlane};
+/// use minetestworld::World;
+/// use glam::I16Vec2;
+/// use async_std::task;
+///
+/// task::block_on(async {
+///     let map = World::open("TestWorld").get_map_data().await.unwrap();
+///     let mut colors = ColorMap::new();
+///     colors.insert(b"default:stone_with_coal", [20, 20, 20, 255]);
+///     let image = render::render_slice(
+///         &map,
+///         SlicePlane::Z(0),
+///         I16Vec2::new(-256, -128),
+///         I16Vec2::new(255, 64),
+///         &colors,
+///     )
+///     .await
+///     .unwrap();
+///     std::fs::write("slice.png", image.to_png().unwrap()).unwrap();
+/// });
+/// ```
+pub async fn render_slice(
+    map: &MapData,
+    plane: SlicePlane,
+    a: I16Vec2,
+    b: I16Vec2,
+    colors: &ColorMap,
+) -> Result<Image, MapDataError> {
+    let (min, max) = (a.min(b), a.max(b));
+    let bounds = match plane {
+        SlicePlane::X(x) => (I16Vec3::new(x, min.y, min.x), I16Vec3::new(x, max.y, max.x)),
+        SlicePlane::Z(z) => (I16Vec3::new(min.x, min.y, z), I16Vec3::new(max.x, max.y, z)),
+    };
+    let area = BlockArea::new(
+        BlockPos::from_index_vec(bounds.0 >> NODE_BITS_1D),
+        BlockPos::from_index_vec(bounds.1 >> NODE_BITS_1D),
+    );
+    let positions: Vec<BlockPos> = map.mapblock_positions_in(area).await.try_collect().await?;
+
+    let size = (max.as_ivec2() - min.as_ivec2() + 1).as_uvec2();
+    let mut image = Image::new(size.x, size.y);
+    for pos in positions {
+        let data = map.get_block_data(pos).await?;
+        let origin = pos.into_index_vec() << NODE_BITS_1D;
+        paint_slice(&data, origin, plane, bounds, colors, &mut image)?;
+    }
+    Ok(image)
+}
@@ -0,0 +1,312 @@
+//! Contains a summary of a world's map data, as produced by
+//! [`World::generate_report`](`crate::World::generate_report`)
+//!
+//! A report bundles the figures server admins typically look at: how big the map is,
+//! which mods place the most nodes, whether there are unknown nodes,
+//! which mapblocks are unusually large, and a top-down overview of the generated area.
+//! It can be rendered as a self-contained HTML page or as JSON.
+
+use futures::TryStreamExt;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fmt::Write;
+
+use crate::analysis;
+use crate::json;
+use crate::map_block::{CONTENT_IGNORE, CONTENT_UNKNOWN};
+use crate::map_data::count_contents;
+use crate::positions::{BlockKey, BlockPos};
+use crate::{MapData, MapDataError};
+
+/// The output format of a report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReportFormat {
+    /// A single HTML page without external resources
+    #[default]
+    Html,
+    /// A JSON object, e.g. for feeding a monitoring system
+    Json,
+}
+
+/// Options for generating a report
+#[derive(Debug, Clone)]
+pub struct ReportOptions {
+    /// The output format
+    pub format: ReportFormat,
+    /// How many of the largest mapblocks to list
+    pub biggest_blocks: usize,
+    /// The content names registered by the game, if known
+    ///
+    /// Nodes with other content are reported as unknown, in addition to nodes
+    /// with the content [`CONTENT_UNKNOWN`] or without a content name.
+    /// `air` and `ignore` are always known.
+    pub known_contents: Option<HashSet<String>>,
+}
+
+impl Default for ReportOptions {
+    fn default() -> Self {
+        ReportOptions {
+            format: ReportFormat::default(),
+            biggest_blocks: 10,
+            known_contents: None,
+        }
+    }
+}
+
+/// The figures of a report
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorldReport {
+    /// The world name, as configured in `world.mt`
+    pub world_name: String,
+    /// The number of mapblocks
+    pub block_count: usize,
+    /// The size of all serialized mapblocks in bytes
+    pub total_size: usize,
+    /// The number of nodes per mod, see [`analysis::mod_usage`](`crate::analysis::mod_usage`)
+    pub mod_usage: HashMap<String, u64>,
+    /// The number of unknown nodes per content name
+    pub unknown_nodes: HashMap<String, u64>,
+    /// The largest mapblocks with their serialized size, largest first
+    pub biggest_blocks: Vec<(BlockPos, usize)>,
+    /// The top-down view: the highest block index of every generated block column,
+    /// keyed by the block indices `(x, z)`
+    pub top_layer: HashMap<(i16, i16), i16>,
+    /// Mapblocks that could not be decoded
+    ///
+    /// They count towards the number and size of the mapblocks,
+    /// but not towards the nodes.
+    pub undecodable: Vec<BlockPos>,
+}
+
+fn is_unknown(content: &str, known_contents: Option<&HashSet<String>>) -> bool {
+    match content.as_bytes() {
+        b"air" | CONTENT_IGNORE => false,
+        CONTENT_UNKNOWN => true,
+        _ => known_contents.is_some_and(|known| !known.contains(content)),
+    }
+}
+
+/// Gathers the figures of a report from `map`
+///
+/// Every mapblock is decoded once.
+pub async fn collect(
+    map: &MapData,
+    world_name: &str,
+    options: &ReportOptions,
+) -> Result<WorldReport, MapDataError> {
+    let mut report = WorldReport {
+        world_name: world_name.to_string(),
+        ..Default::default()
+    };
+    // A min-heap of the largest mapblocks so far,
+    // ties are broken by position, so that the report is deterministic
+    let mut biggest_blocks = BinaryHeap::with_capacity(options.biggest_blocks + 1);
+    let mut histogram = HashMap::new();
+    let positions: Vec<_> = map.all_mapblock_positions().await.try_collect().await?;
+    for pos in positions {
+        let data = map.get_block_data(pos).await?;
+        report.block_count += 1;
+        report.total_size += data.len();
+        biggest_blocks.push(Reverse((data.len(), Reverse(BlockKey::from(pos)))));
+        if biggest_blocks.len() > options.biggest_blocks {
+            biggest_blocks.pop();
+        }
+
+        let index = pos.into_index_vec();
+        report
+            .top_layer
+            .entry((index.x, index.z))
+            .and_modify(|y| *y = (*y).max(index.y))
+            .or_insert(index.y);
+
+        match count_contents(&data, &mut histogram) {
+            Ok(()) => {}
+            Err(MapDataError::MapBlockError(_)) => report.undecodable.push(pos),
+            Err(e) => return Err(e),
+        }
+    }
+    report.biggest_blocks = biggest_blocks
+        .into_sorted_vec()
+        .into_iter()
+        .map(|Reverse((size, Reverse(key)))| (BlockPos::from(key), size))
+        .collect();
+
+    report.mod_usage = analysis::mod_usage_of(&histogram);
+    for (content, count) in histogram {
+        let content = String::from_utf8_lossy(&content).into_owned();
+        if is_unknown(&content, options.known_contents.as_ref()) {
+            *report.unknown_nodes.entry(content).or_default() += count;
+        }
+    }
+    Ok(report)
+}
+
+/// Returns the entries of `counts` sorted by count, largest first
+fn sorted_counts(counts: &HashMap<String, u64>) -> Vec<(&str, u64)> {
+    let mut sorted: Vec<_> = counts
+        .iter()
+        .map(|(name, &count)| (name.as_str(), count))
+        .collect();
+    sorted.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    sorted
+}
+
+fn json_counts(counts: &HashMap<String, u64>, dest: &mut String) {
+    dest.push('{');
+    for (i, (name, count)) in sorted_counts(counts).into_iter().enumerate() {
+        if i > 0 {
+            dest.push(',');
+        }
+        json::serialize_string(name, dest);
+        let _ = write!(dest, ":{count}");
+    }
+    dest.push('}');
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn html_counts(title: &str, counts: &HashMap<String, u64>, dest: &mut String) {
+    let _ = write!(dest, "<h2>{title}</h2>\n<table>\n");
+    for (name, count) in sorted_counts(counts) {
+        let name = if name.is_empty() { "(none)" } else { name };
+        let _ = writeln!(
+            dest,
+            "<tr><td>{}</td><td>{count}</td></tr>",
+            html_escape(name)
+        );
+    }
+    dest.push_str("</table>\n");
+}
+
+impl WorldReport {
+    /// Renders the report in `format`
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Html => self.to_html(),
+            ReportFormat::Json => self.to_json(),
+        }
+    }
+
+    /// Renders the report as a JSON object
+    ///
+    /// Block positions are given as arrays of block indices `[x, y, z]`,
+    /// the top layer as an array of `[x, z, y]`.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\"world_name\":");
+        json::serialize_string(&self.world_name, &mut json);
+        let _ = write!(
+            json,
+            ",\"block_count\":{},\"total_size\":{},\"mod_usage\":",
+            self.block_count, self.total_size
+        );
+        json_counts(&self.mod_usage, &mut json);
+        json.push_str(",\"unknown_nodes\":");
+        json_counts(&self.unknown_nodes, &mut json);
+        json.push_str(",\"biggest_blocks\":[");
+        for (i, (pos, size)) in self.biggest_blocks.iter().enumerate() {
+            let index = pos.into_index_vec();
+            let _ = write!(
+                json,
+                "{}{{\"pos\":[{},{},{}],\"size\":{size}}}",
+                if i > 0 { "," } else { "" },
+                index.x,
+                index.y,
+                index.z
+            );
+        }
+        json.push_str("],\"undecodable\":[");
+        for (i, pos) in self.undecodable.iter().enumerate() {
+            let index = pos.into_index_vec();
+            let _ = write!(
+                json,
+                "{}[{},{},{}]",
+                if i > 0 { "," } else { "" },
+                index.x,
+                index.y,
+                index.z
+            );
+        }
+        json.push_str("],\"top_layer\":[");
+        let mut top_layer: Vec<_> = self.top_layer.iter().collect();
+        top_layer.sort_unstable();
+        for (i, ((x, z), y)) in top_layer.into_iter().enumerate() {
+            let _ = write!(json, "{}[{x},{z},{y}]", if i > 0 { "," } else { "" });
+        }
+        json.push_str("]}");
+        json
+    }
+
+    /// Renders the report as a self-contained HTML page
+    ///
+    /// The top-down view is an inline SVG with one square per block column,
+    /// shaded from dark (low) to bright (high).
+    pub fn to_html(&self) -> String {
+        let title = html_escape(&self.world_name);
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>World report: {title}</title>\n</head>\n<body>\n\
+             <h1>World report: {title}</h1>\n\
+             <p>{} mapblocks, {} bytes</p>\n",
+            self.block_count, self.total_size
+        );
+
+        html.push_str("<h2>Top-down view</h2>\n");
+        if let (Some(min_x), Some(max_x), Some(min_z), Some(max_z), Some(min_y), Some(max_y)) = (
+            self.top_layer.keys().map(|k| k.0).min(),
+            self.top_layer.keys().map(|k| k.0).max(),
+            self.top_layer.keys().map(|k| k.1).min(),
+            self.top_layer.keys().map(|k| k.1).max(),
+            self.top_layer.values().min(),
+            self.top_layer.values().max(),
+        ) {
+            let _ = writeln!(
+                html,
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">",
+                (i32::from(max_x) - i32::from(min_x) + 1) * 4,
+                (i32::from(max_z) - i32::from(min_z) + 1) * 4
+            );
+            let y_range = (i32::from(*max_y) - i32::from(*min_y)).max(1);
+            for (&(x, z), &y) in &self.top_layer {
+                let shade = 32 + (i32::from(y) - i32::from(*min_y)) * 223 / y_range;
+                // North (+z) is at the top
+                let _ = writeln!(
+                    html,
+                    "<rect x=\"{}\" y=\"{}\" width=\"4\" height=\"4\" fill=\"rgb({shade},{shade},{shade})\"/>",
+                    (i32::from(x) - i32::from(min_x)) * 4,
+                    (i32::from(max_z) - i32::from(z)) * 4
+                );
+            }
+            html.push_str("</svg>\n");
+        }
+
+        html_counts("Nodes per mod", &self.mod_usage, &mut html);
+        html_counts("Unknown nodes", &self.unknown_nodes, &mut html);
+
+        html.push_str("<h2>Biggest mapblocks</h2>\n<table>\n");
+        for (pos, size) in &self.biggest_blocks {
+            let index = pos.into_index_vec();
+            let _ = writeln!(
+                html,
+                "<tr><td>({},{},{})</td><td>{size}</td></tr>",
+                index.x, index.y, index.z
+            );
+        }
+        html.push_str("</table>\n");
+
+        if !self.undecodable.is_empty() {
+            html.push_str("<h2>Undecodable mapblocks</h2>\n<ul>\n");
+            for pos in &self.undecodable {
+                let index = pos.into_index_vec();
+                let _ = writeln!(html, "<li>({},{},{})</li>", index.x, index.y, index.z);
+            }
+            html.push_str("</ul>\n");
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
@@ -0,0 +1,221 @@
+//! Contains the rules for removing private data from a world
+//!
+//! Many mods store player names or player-written text in metadata, e.g. the
+//! owner of a locked chest or the pages of a book. Before publishing a world,
+//! such fields can be redacted with
+//! [`World::scrub_private_data`](`crate::World::scrub_private_data`).
+
+use std::collections::HashMap;
+
+use crate::inventory::{InventoryList, ItemStack};
+use crate::map_block::NodeMetadata;
+use crate::players::Player;
+use crate::positions::BlockPos;
+use crate::{MapBlock, MapDataError};
+
+/// What happens to a field matched by a [`ScrubRule`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScrubAction {
+    /// The field is removed entirely
+    Remove,
+    /// The value of the field is replaced by the given text
+    ///
+    /// Node metadata variables with an empty replacement are removed,
+    /// just like the engine does.
+    Redact(String),
+}
+
+/// Selects metadata fields by their key and determines what happens to them
+///
+/// ```
+/// use minetestworld::scrub::ScrubRule;
+///
+/// let rule = ScrubRule::remove("owner");
+/// assert!(rule.matches("owner"));
+/// let rule = ScrubRule::redact("*text", "[redacted]");
+/// assert!(rule.matches("infotext"));
+/// assert!(!rule.matches("text_color"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScrubRule {
+    /// The key of matching fields
+    ///
+    /// `*` matches any sequence of characters, including an empty one.
+    pub key_pattern: String,
+    /// What happens to matching fields
+    pub action: ScrubAction,
+}
+
+impl ScrubRule {
+    /// Creates a rule that removes all fields whose key matches `key_pattern`
+    pub fn remove(key_pattern: impl Into<String>) -> Self {
+        ScrubRule {
+            key_pattern: key_pattern.into(),
+            action: ScrubAction::Remove,
+        }
+    }
+
+    /// Creates a rule that replaces the value of all fields whose key matches `key_pattern`
+    pub fn redact(key_pattern: impl Into<String>, replacement: impl Into<String>) -> Self {
+        ScrubRule {
+            key_pattern: key_pattern.into(),
+            action: ScrubAction::Redact(replacement.into()),
+        }
+    }
+
+    /// Returns true if `key` matches the pattern of this rule
+    pub fn matches(&self, key: &str) -> bool {
+        let mut parts = self.key_pattern.split('*');
+        // There is always a first part, even for an empty pattern
+        let first = parts.next().unwrap_or_default();
+        let Some(mut rest) = key.strip_prefix(first) else {
+            return false;
+        };
+        let mut parts: Vec<_> = parts.collect();
+        let Some(last) = parts.pop() else {
+            // No wildcard at all
+            return rest.is_empty();
+        };
+        for part in parts {
+            match rest.find(part) {
+                Some(start) => rest = &rest[start + part.len()..],
+                None => return false,
+            }
+        }
+        rest.ends_with(last)
+    }
+}
+
+/// Statistics about a scrubbing run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScrubReport {
+    /// Number of mapblocks that were rewritten
+    pub mapblocks: usize,
+    /// Number of players that were rewritten
+    pub players: usize,
+    /// Total number of removed or redacted fields
+    pub fields: usize,
+    /// Mapblocks that could not be decoded and were left as they are
+    pub undecodable: Vec<BlockPos>,
+    /// Number of inventory items that could not be parsed and were left as they are
+    ///
+    /// Their metadata may still contain private data.
+    pub unparseable_items: usize,
+}
+
+/// Returns the first rule matching `key`
+fn matching_rule<'a>(rules: &'a [ScrubRule], key: &str) -> Option<&'a ScrubRule> {
+    rules.iter().find(|rule| rule.matches(key))
+}
+
+/// Applies `rules` to a string map, returning the number of affected fields
+fn scrub_string_map(map: &mut HashMap<String, String>, rules: &[ScrubRule]) -> usize {
+    let mut fields = 0;
+    map.retain(|key, value| match matching_rule(rules, key) {
+        Some(rule) => match &rule.action {
+            ScrubAction::Remove => {
+                fields += 1;
+                false
+            }
+            ScrubAction::Redact(replacement) => {
+                if value != replacement {
+                    fields += 1;
+                    value.clone_from(replacement);
+                }
+                true
+            }
+        },
+        None => true,
+    });
+    fields
+}
+
+/// Returns the number of items in `inventory` that cannot be parsed,
+/// e.g. because they are not valid UTF-8
+///
+/// Scrubbing leaves such items as they are.
+pub fn unparseable_items(inventory: &[InventoryList]) -> usize {
+    inventory
+        .iter()
+        .flat_map(InventoryList::stacks)
+        .filter(Result::is_err)
+        .count()
+}
+
+/// Applies `rules` to the metadata of all parseable items in `inventory`
+fn scrub_inventory(inventory: &mut [InventoryList], rules: &[ScrubRule]) -> usize {
+    let mut fields = 0;
+    for item in inventory.iter_mut().flat_map(|list| &mut list.items) {
+        let Ok(mut stack) = item.parse::<ItemStack>() else {
+            continue;
+        };
+        let scrubbed = scrub_string_map(&mut stack.metadata, rules);
+        if scrubbed > 0 {
+            fields += scrubbed;
+            *item = stack.to_string();
+        }
+    }
+    fields
+}
+
+/// Applies `rules` to the variables and inventory items of a node,
+/// returning the number of affected fields
+///
+/// Items that cannot be parsed are left as they are, see [`unparseable_items`].
+pub fn scrub_node_metadata<const LENGTH: u16>(
+    metadata: &mut NodeMetadata<LENGTH>,
+    rules: &[ScrubRule],
+) -> usize {
+    let mut fields = 0;
+    for i in (0..metadata.vars.len()).rev() {
+        let var = &metadata.vars[i];
+        let Some(rule) = matching_rule(rules, &String::from_utf8_lossy(&var.key)) else {
+            continue;
+        };
+        match &rule.action {
+            ScrubAction::Redact(replacement) if var.value == replacement.as_bytes() => continue,
+            ScrubAction::Redact(replacement) if !replacement.is_empty() => {
+                metadata.vars[i].value = replacement.as_bytes().to_vec();
+            }
+            _ => {
+                metadata.vars.remove(i);
+            }
+        }
+        fields += 1;
+    }
+    fields + scrub_inventory(&mut metadata.inventory, rules)
+}
+
+/// Applies `rules` to the node metadata of a serialized mapblock and adds the outcome to `report`
+///
+/// Returns the re-serialized mapblock, or `None` if nothing was changed.
+pub(crate) fn scrub_block(
+    data: &[u8],
+    rules: &[ScrubRule],
+    report: &mut ScrubReport,
+) -> Result<Option<Vec<u8>>, MapDataError> {
+    let mut block = MapBlock::from_data(data)?;
+    let mut fields = 0;
+    let mut unparseable = 0;
+    for metadata in &mut block.node_metadata {
+        unparseable += unparseable_items(&metadata.inventory);
+        fields += scrub_node_metadata(metadata, rules);
+    }
+    let scrubbed = if fields == 0 {
+        None
+    } else {
+        Some(block.to_binary_version(block.map_format_version)?)
+    };
+    report.mapblocks += usize::from(scrubbed.is_some());
+    report.fields += fields;
+    report.unparseable_items += unparseable;
+    Ok(scrubbed)
+}
+
+/// Applies `rules` to the metadata and inventory items of a player,
+/// returning the number of affected fields
+///
+/// Items that cannot be parsed are left as they are, see [`unparseable_items`].
+pub fn scrub_player(player: &mut Player, rules: &[ScrubRule]) -> usize {
+    scrub_string_map(&mut player.metadata, rules) + scrub_inventory(&mut player.inventory, rules)
+}
@@ -0,0 +1,289 @@
+//! Contains iterators over the node positions of geometric shapes
+//!
+//! They are used by [`MapEdit`](`crate::MapEdit`) to draw into the map,
+//! but can be combined with any other way of editing nodes.
+
+use glam::{I16Vec3, IVec3, Vec3};
+
+/// Iterates the nodes of a straight line from `a` to `b`, including both ends
+///
+/// This is a 3D Bresenham line: Every step advances along the axis with the largest
+/// distance, so consecutive nodes touch at least at an edge or corner,
+/// and no node is visited twice.
+///
+/// ```
+/// use glam::I16Vec3;
+/// use minetestworld::shapes::line;
+///
+/// let nodes: Vec<_> = line(I16Vec3::ZERO, I16Vec3::new(4, 2, 0)).collect();
+/// assert_eq!(nodes.len(), 5);
+/// assert_eq!(nodes[0], I16Vec3::ZERO);
+/// assert_eq!(nodes[2], I16Vec3::new(2, 1, 0));
+/// assert_eq!(nodes[4], I16Vec3::new(4, 2, 0));
+/// ```
+pub fn line(a: I16Vec3, b: I16Vec3) -> impl Iterator<Item = I16Vec3> {
+    let delta = b.as_ivec3() - a.as_ivec3();
+    let length = delta.abs().max_element();
+    let step = delta.signum();
+    let distance = delta.abs() * 2;
+    // Each axis accumulates its error relative to the axis with the largest distance
+    let mut error = IVec3::splat(-length);
+    let mut pos = a.as_ivec3();
+    (0..=length).map(move |i| {
+        let current = pos;
+        if i < length {
+            error += distance;
+            for axis in 0..3 {
+                if distance[axis] == length * 2 {
+                    pos[axis] += step[axis];
+                    error[axis] -= distance[axis];
+                } else if error[axis] >= 0 {
+                    pos[axis] += step[axis];
+                    error[axis] -= length * 2;
+                }
+            }
+        }
+        // The line stays between `a` and `b`
+        current.as_i16vec3()
+    })
+}
+
+/// One of the three axes of the world
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Axis {
+    /// The west-east axis
+    X,
+    /// The vertical axis
+    Y,
+    /// The south-north axis
+    Z,
+}
+
+impl Axis {
+    fn index(self) -> usize {
+        match self {
+            Axis::X => 0,
+            Axis::Y => 1,
+            Axis::Z => 2,
+        }
+    }
+}
+
+/// A solid shape in the world, see [`Brush`]
+///
+/// A radius of `r` covers the nodes whose centers are at most `r + 0.5` away
+/// from the center, so that a radius of 0 covers the center node alone.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    /// An ellipsoid around `center`, which is a sphere if all radii are equal
+    Ellipsoid {
+        /// The center node
+        center: I16Vec3,
+        /// The radius along each axis
+        radii: Vec3,
+    },
+    /// A cylinder that extends from `base` along `axis`
+    Cylinder {
+        /// The center node of the first layer
+        base: I16Vec3,
+        /// The axis of the cylinder, which it extends along in positive direction
+        axis: Axis,
+        /// The number of layers
+        length: u16,
+        /// The radius of every layer
+        radius: f32,
+    },
+    /// A box between two corners, including both
+    Cuboid {
+        /// The corner with the smallest coordinates
+        min: I16Vec3,
+        /// The corner with the largest coordinates
+        max: I16Vec3,
+    },
+}
+
+impl Shape {
+    /// Creates a sphere of `radius` around `center`
+    pub fn sphere(center: I16Vec3, radius: f32) -> Self {
+        Shape::Ellipsoid {
+            center,
+            radii: Vec3::splat(radius),
+        }
+    }
+
+    /// Creates a box between the corners `a` and `b`
+    pub fn cuboid(a: I16Vec3, b: I16Vec3) -> Self {
+        Shape::Cuboid {
+            min: a.min(b),
+            max: a.max(b),
+        }
+    }
+
+    /// Returns the corners of the smallest box that contains the shape
+    ///
+    /// Returns `None` if the shape covers no node at all.
+    pub fn bounds(&self) -> Option<(I16Vec3, I16Vec3)> {
+        match *self {
+            Shape::Ellipsoid { center, radii } => {
+                if radii.cmplt(Vec3::ZERO).any() {
+                    return None;
+                }
+                let extent = (radii + 0.5).floor().as_ivec3();
+                Some(clamp_box(
+                    center.as_ivec3() - extent,
+                    center.as_ivec3() + extent,
+                ))
+            }
+            Shape::Cylinder {
+                base,
+                axis,
+                length,
+                radius,
+            } => {
+                if length == 0 || radius < 0.0 {
+                    return None;
+                }
+                let mut extent = IVec3::splat((radius + 0.5).floor() as i32);
+                extent[axis.index()] = 0;
+                let mut top = base.as_ivec3() + extent;
+                top[axis.index()] += i32::from(length) - 1;
+                Some(clamp_box(base.as_ivec3() - extent, top))
+            }
+            Shape::Cuboid { min, max } => min.cmple(max).all().then_some((min, max)),
+        }
+    }
+
+    /// Returns true if the shape covers the node at `pos`
+    pub fn contains(&self, pos: I16Vec3) -> bool {
+        match *self {
+            Shape::Ellipsoid { center, radii } => {
+                if radii.cmplt(Vec3::ZERO).any() {
+                    return false;
+                }
+                let offset = (pos.as_ivec3() - center.as_ivec3()).as_vec3();
+                (offset / (radii + 0.5)).length_squared() <= 1.0
+            }
+            Shape::Cylinder {
+                base,
+                axis,
+                length,
+                radius,
+            } => {
+                let mut offset = pos.as_ivec3() - base.as_ivec3();
+                let along = offset[axis.index()];
+                offset[axis.index()] = 0;
+                (0..i32::from(length)).contains(&along)
+                    && radius >= 0.0
+                    && offset.as_vec3().length_squared() <= (radius + 0.5).powi(2)
+            }
+            Shape::Cuboid { min, max } => pos.cmpge(min).all() && pos.cmple(max).all(),
+        }
+    }
+
+    /// Returns the shape that is `thickness` nodes smaller on every side
+    fn shrunk(&self, thickness: u16) -> Self {
+        let t = f32::from(thickness);
+        match *self {
+            Shape::Ellipsoid { center, radii } => Shape::Ellipsoid {
+                center,
+                radii: radii - t,
+            },
+            Shape::Cylinder {
+                base,
+                axis,
+                length,
+                radius,
+            } => {
+                let mut base = base.as_ivec3();
+                base[axis.index()] += i32::from(thickness);
+                Shape::Cylinder {
+                    base: clamp_box(base, base).0,
+                    axis,
+                    length: length.saturating_sub(thickness.saturating_mul(2)),
+                    radius: radius - t,
+                }
+            }
+            Shape::Cuboid { min, max } => {
+                let thickness = I16Vec3::splat(i16::try_from(thickness).unwrap_or(i16::MAX));
+                Shape::Cuboid {
+                    min: min.saturating_add(thickness),
+                    max: max.saturating_sub(thickness),
+                }
+            }
+        }
+    }
+}
+
+/// Clamps the corners of a box to the coordinates of nodes
+fn clamp_box(min: IVec3, max: IVec3) -> (I16Vec3, I16Vec3) {
+    let clamp = |v: IVec3| {
+        v.clamp(IVec3::splat(i16::MIN.into()), IVec3::splat(i16::MAX.into()))
+            .as_i16vec3()
+    };
+    (clamp(min), clamp(max))
+}
+
+/// A [`Shape`] that is either solid or hollow, to edit the map with
+///
+/// ```
+/// use glam::I16Vec3;
+/// use minetestworld::shapes::{Brush, Shape};
+///
+/// let dome = Brush::hollow(Shape::sphere(I16Vec3::ZERO, 5.0), 1);
+/// assert!(dome.contains(I16Vec3::new(0, 5, 0)));
+/// assert!(!dome.contains(I16Vec3::new(0, 3, 0)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Brush {
+    /// The outline of the brush
+    pub shape: Shape,
+    /// The thickness of the walls if the brush is hollow
+    pub thickness: Option<u16>,
+}
+
+impl Brush {
+    /// Creates a brush that covers all nodes of `shape`
+    pub fn solid(shape: Shape) -> Self {
+        Brush {
+            shape,
+            thickness: None,
+        }
+    }
+
+    /// Creates a brush that only covers the outer `thickness` nodes of `shape`
+    ///
+    /// The walls are closed, e.g. a hollow cylinder has a bottom and a top.
+    pub fn hollow(shape: Shape, thickness: u16) -> Self {
+        Brush {
+            shape,
+            thickness: Some(thickness),
+        }
+    }
+
+    /// Returns the corners of the smallest box that contains the brush
+    pub fn bounds(&self) -> Option<(I16Vec3, I16Vec3)> {
+        self.shape.bounds()
+    }
+
+    /// Returns true if the brush covers the node at `pos`
+    pub fn contains(&self, pos: I16Vec3) -> bool {
+        self.shape.contains(pos)
+            && !self
+                .thickness
+                .is_some_and(|thickness| self.shape.shrunk(thickness).contains(pos))
+    }
+
+    /// Returns true if the brush covers all nodes of the box between `min` and `max`
+    pub(crate) fn covers(&self, min: I16Vec3, max: I16Vec3) -> bool {
+        // All shapes are convex, so they contain a box if they contain its corners
+        self.thickness.is_none()
+            && (0..8).all(|corner| {
+                let pick = |bit: i32, min: i16, max: i16| if corner & bit == 0 { min } else { max };
+                self.shape.contains(I16Vec3::new(
+                    pick(1, min.x, max.x),
+                    pick(2, min.y, max.y),
+                    pick(4, min.z, max.z),
+                ))
+            })
+    }
+}
@@ -0,0 +1,415 @@
+//! Conversion between [`Schematic`]s and Sponge schematics (`.schem`)
+//!
+//! Sponge schematics are the common exchange format of Minecraft building tools.
+//! Minecraft identifies blocks by block states like `minecraft:oak_log[axis=y]`,
+//! which have no counterpart in Minetest, so the conversion needs a mapping table
+//! provided by the user.
+//!
+//! A block state is looked up in the mapping first as a whole, then without its
+//! properties in brackets, so that `minecraft:stone` covers all its variants.
+//!
+//! Minecraft's z axis points south, while Minetest's points north.
+//! The z axis is therefore flipped, which keeps the build's orientation.
+//! Block entities, like the contents of chests, are not converted.
+//!
+//! Versions 2 and 3 of the format can be read, version 2 is written.
+//! A schematic that has been read can be placed with
+//! [`EditPlan::place_schematic`](`crate::edit_plan::EditPlan::place_schematic`),
+//! and [`Schematic::from_world`] provides one to write.
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use glam::U16Vec3;
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+
+use crate::edit_plan::Schematic;
+use crate::map_block::Node;
+
+/// An error during the conversion of a Sponge schematic
+#[derive(thiserror::Error, Debug)]
+pub enum SpongeError {
+    /// The data could not be read or written
+    #[error("IO error: {0}")]
+    IoError(#[from] io::Error),
+    /// The data is no valid NBT or lacks required fields
+    ///
+    /// This variant contains a more detailed error message.
+    #[error("Sponge schematic malformed: {0}")]
+    Malformed(String),
+    /// The schematic has a format version other than 2 or 3
+    #[error("Sponge schematic version {0} is not supported")]
+    UnsupportedVersion(i32),
+    /// The mapping has no entry for this block state or content name
+    #[error("No mapping for '{0}'")]
+    Unmapped(String),
+}
+
+fn malformed(message: &str) -> SpongeError {
+    SpongeError::Malformed(message.to_string())
+}
+
+/// A value of Minecraft's NBT format
+#[derive(Debug, Clone, PartialEq)]
+enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<u8>),
+    String(String),
+    List(u8, Vec<Tag>),
+    Compound(Vec<(String, Tag)>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+const TAG_END: u8 = 0;
+
+/// The Minecraft data version written into schematics, that of Minecraft 1.20.4
+const DATA_VERSION: i32 = 3700;
+
+impl Tag {
+    fn id(&self) -> u8 {
+        match self {
+            Tag::Byte(_) => 1,
+            Tag::Short(_) => 2,
+            Tag::Int(_) => 3,
+            Tag::Long(_) => 4,
+            Tag::Float(_) => 5,
+            Tag::Double(_) => 6,
+            Tag::ByteArray(_) => 7,
+            Tag::String(_) => 8,
+            Tag::List(..) => 9,
+            Tag::Compound(_) => 10,
+            Tag::IntArray(_) => 11,
+            Tag::LongArray(_) => 12,
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<&Tag> {
+        match self {
+            Tag::Compound(entries) => entries.iter().find(|(n, _)| n == name).map(|(_, t)| t),
+            _ => None,
+        }
+    }
+
+    /// Returns an integer tag of any width as i32
+    fn as_int(&self) -> Option<i32> {
+        match *self {
+            Tag::Byte(value) => Some(value.into()),
+            Tag::Short(value) => Some(value.into()),
+            Tag::Int(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+fn read_array<const N: usize>(r: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut buffer = [0; N];
+    r.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
+
+fn read_length(r: &mut impl Read) -> Result<usize, SpongeError> {
+    usize::try_from(i32::from_be_bytes(read_array(r)?)).map_err(|_| malformed("negative length"))
+}
+
+fn read_string(r: &mut impl Read) -> Result<String, SpongeError> {
+    let mut buffer = vec![0; u16::from_be_bytes(read_array(r)?).into()];
+    r.read_exact(&mut buffer)?;
+    // Java's modified UTF-8 only differs for NUL and supplementary characters
+    Ok(String::from_utf8_lossy(&buffer).into_owned())
+}
+
+fn read_payload(r: &mut impl Read, id: u8) -> Result<Tag, SpongeError> {
+    Ok(match id {
+        1 => Tag::Byte(i8::from_be_bytes(read_array(r)?)),
+        2 => Tag::Short(i16::from_be_bytes(read_array(r)?)),
+        3 => Tag::Int(i32::from_be_bytes(read_array(r)?)),
+        4 => Tag::Long(i64::from_be_bytes(read_array(r)?)),
+        5 => Tag::Float(f32::from_be_bytes(read_array(r)?)),
+        6 => Tag::Double(f64::from_be_bytes(read_array(r)?)),
+        7 => {
+            let length = read_length(r)?;
+            // Reading via take() avoids allocating a bogus length up front
+            let mut buffer = vec![];
+            r.take(length as u64).read_to_end(&mut buffer)?;
+            if buffer.len() != length {
+                return Err(malformed("byte array is truncated"));
+            }
+            Tag::ByteArray(buffer)
+        }
+        8 => Tag::String(read_string(r)?),
+        9 => {
+            let element_id = read_array::<1>(r)?[0];
+            let length = read_length(r)?;
+            let elements = (0..length)
+                .map(|_| read_payload(r, element_id))
+                .collect::<Result<_, _>>()?;
+            Tag::List(element_id, elements)
+        }
+        10 => {
+            let mut entries = vec![];
+            loop {
+                let id = read_array::<1>(r)?[0];
+                if id == TAG_END {
+                    break Tag::Compound(entries);
+                }
+                let name = read_string(r)?;
+                entries.push((name, read_payload(r, id)?));
+            }
+        }
+        11 => Tag::IntArray(
+            (0..read_length(r)?)
+                .map(|_| Ok(i32::from_be_bytes(read_array(r)?)))
+                .collect::<Result<_, SpongeError>>()?,
+        ),
+        12 => Tag::LongArray(
+            (0..read_length(r)?)
+                .map(|_| Ok(i64::from_be_bytes(read_array(r)?)))
+                .collect::<Result<_, SpongeError>>()?,
+        ),
+        _ => return Err(SpongeError::Malformed(format!("unknown tag type {id}"))),
+    })
+}
+
+fn write_string(s: &str, w: &mut impl Write) -> Result<(), SpongeError> {
+    let length = u16::try_from(s.len()).map_err(|_| malformed("string is too long"))?;
+    w.write_all(&length.to_be_bytes())?;
+    Ok(w.write_all(s.as_bytes())?)
+}
+
+fn write_payload(tag: &Tag, w: &mut impl Write) -> Result<(), SpongeError> {
+    let write_length = |length: usize, w: &mut dyn Write| -> Result<(), SpongeError> {
+        let length = i32::try_from(length).map_err(|_| malformed("array is too long"))?;
+        Ok(w.write_all(&length.to_be_bytes())?)
+    };
+    match tag {
+        Tag::Byte(value) => w.write_all(&value.to_be_bytes())?,
+        Tag::Short(value) => w.write_all(&value.to_be_bytes())?,
+        Tag::Int(value) => w.write_all(&value.to_be_bytes())?,
+        Tag::Long(value) => w.write_all(&value.to_be_bytes())?,
+        Tag::Float(value) => w.write_all(&value.to_be_bytes())?,
+        Tag::Double(value) => w.write_all(&value.to_be_bytes())?,
+        Tag::ByteArray(values) => {
+            write_length(values.len(), w)?;
+            w.write_all(values)?;
+        }
+        Tag::String(value) => write_string(value, w)?,
+        Tag::List(element_id, elements) => {
+            w.write_all(&[*element_id])?;
+            write_length(elements.len(), w)?;
+            for element in elements {
+                write_payload(element, w)?;
+            }
+        }
+        Tag::Compound(entries) => {
+            for (name, tag) in entries {
+                w.write_all(&[tag.id()])?;
+                write_string(name, w)?;
+                write_payload(tag, w)?;
+            }
+            w.write_all(&[TAG_END])?;
+        }
+        Tag::IntArray(values) => {
+            write_length(values.len(), w)?;
+            for value in values {
+                w.write_all(&value.to_be_bytes())?;
+            }
+        }
+        Tag::LongArray(values) => {
+            write_length(values.len(), w)?;
+            for value in values {
+                w.write_all(&value.to_be_bytes())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads the unsigned LEB128 integers Sponge uses for block data
+fn read_varints(data: &[u8]) -> Result<Vec<u32>, SpongeError> {
+    let mut values = vec![];
+    let mut value = 0u32;
+    let mut shift = 0;
+    for &byte in data {
+        if shift > 28 {
+            return Err(malformed("varint is too long"));
+        }
+        value |= u32::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            values.push(value);
+            value = 0;
+            shift = 0;
+        } else {
+            shift += 7;
+        }
+    }
+    if shift != 0 {
+        return Err(malformed("block data ends within a varint"));
+    }
+    Ok(values)
+}
+
+fn write_varint(mut value: u32, dest: &mut Vec<u8>) {
+    while value >= 0x80 {
+        dest.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    dest.push(value as u8);
+}
+
+/// Reads a Sponge schematic, converting block states to nodes via `mapping`
+///
+/// The data may be gzip-compressed, as `.schem` files are.
+/// Block states that map to `None` leave the world unchanged when placing the schematic,
+/// which is useful for `minecraft:air`.
+pub fn read(
+    mut data: impl Read,
+    mapping: &HashMap<String, Option<Node>>,
+) -> Result<Schematic, SpongeError> {
+    let mut raw = vec![];
+    data.read_to_end(&mut raw)?;
+    if raw.starts_with(&[0x1f, 0x8b]) {
+        let mut decompressed = vec![];
+        GzDecoder::new(raw.as_slice()).read_to_end(&mut decompressed)?;
+        raw = decompressed;
+    }
+    let mut r = raw.as_slice();
+    if read_array::<1>(&mut r)?[0] != 10 {
+        return Err(malformed("the root tag is no compound"));
+    }
+    read_string(&mut r)?;
+    let mut root = read_payload(&mut r, 10)?;
+    // Version 3 nests everything in a compound called `Schematic`
+    if let Some(inner @ Tag::Compound(_)) = root.get("Schematic") {
+        root = inner.clone();
+    }
+
+    let version = root
+        .get("Version")
+        .and_then(Tag::as_int)
+        .ok_or_else(|| malformed("no version"))?;
+    let (palette, block_data) = match version {
+        2 => (root.get("Palette"), root.get("BlockData")),
+        3 => {
+            let blocks = root.get("Blocks").ok_or_else(|| malformed("no blocks"))?;
+            (blocks.get("Palette"), blocks.get("Data"))
+        }
+        _ => return Err(SpongeError::UnsupportedVersion(version)),
+    };
+    let (Some(Tag::Compound(palette)), Some(Tag::ByteArray(block_data))) = (palette, block_data)
+    else {
+        return Err(malformed("no palette or block data"));
+    };
+
+    let dimension = |name| {
+        root.get(name)
+            .and_then(Tag::as_int)
+            // Sizes are stored as signed shorts, but meant as unsigned
+            .map(|size| size as u16)
+            .ok_or_else(|| SpongeError::Malformed(format!("no {name}")))
+    };
+    let size = U16Vec3::new(
+        dimension("Width")?,
+        dimension("Height")?,
+        dimension("Length")?,
+    );
+
+    let mut nodes_by_id = HashMap::new();
+    for (state, id) in palette {
+        let id = id
+            .as_int()
+            .ok_or_else(|| malformed("palette ID is no integer"))?;
+        let base = state
+            .split_once('[')
+            .map_or(state.as_str(), |(base, _)| base);
+        let node = mapping
+            .get(state)
+            .or_else(|| mapping.get(base))
+            .ok_or_else(|| SpongeError::Unmapped(state.clone()))?;
+        nodes_by_id.insert(id as u32, node);
+    }
+
+    let ids = read_varints(block_data)?;
+    let volume = size.x as usize * size.y as usize * size.z as usize;
+    if ids.len() != volume {
+        return Err(malformed("block data does not match the size"));
+    }
+    let mut schematic = Schematic::new(size);
+    let mut ids = ids.into_iter();
+    for y in 0..size.y {
+        for z in 0..size.z {
+            for x in 0..size.x {
+                // The length of ids has been checked
+                let id = ids.next().unwrap();
+                let node = nodes_by_id
+                    .get(&id)
+                    .ok_or_else(|| SpongeError::Malformed(format!("unknown palette ID {id}")))?;
+                let pos = U16Vec3::new(x, y, size.z - 1 - z);
+                schematic.set_node(pos, (*node).clone());
+            }
+        }
+    }
+    Ok(schematic)
+}
+
+/// Writes `schematic` as a gzip-compressed Sponge schematic of version 2
+///
+/// `mapping` maps content names to block states.
+/// Empty positions of the schematic are written as `minecraft:air`.
+pub fn write(
+    schematic: &Schematic,
+    mapping: &HashMap<Vec<u8>, String>,
+    dest: impl Write,
+) -> Result<(), SpongeError> {
+    let size = schematic.size();
+    let mut palette: Vec<(String, Tag)> = vec![];
+    let mut ids: HashMap<&str, u32> = HashMap::new();
+    let mut block_data = vec![];
+    for y in 0..size.y {
+        for z in 0..size.z {
+            for x in 0..size.x {
+                let state = match schematic.get_node(U16Vec3::new(x, y, size.z - 1 - z)) {
+                    Some(node) => {
+                        mapping
+                            .get(&node.param0)
+                            .map(String::as_str)
+                            .ok_or_else(|| {
+                                SpongeError::Unmapped(String::from_utf8_lossy(&node.param0).into())
+                            })?
+                    }
+                    None => "minecraft:air",
+                };
+                let next_id = ids.len() as u32;
+                let id = *ids.entry(state).or_insert_with(|| {
+                    palette.push((state.to_string(), Tag::Int(next_id as i32)));
+                    next_id
+                });
+                write_varint(id, &mut block_data);
+            }
+        }
+    }
+
+    let root = Tag::Compound(vec![
+        ("Version".into(), Tag::Int(2)),
+        ("DataVersion".into(), Tag::Int(DATA_VERSION)),
+        ("Width".into(), Tag::Short(size.x as i16)),
+        ("Height".into(), Tag::Short(size.y as i16)),
+        ("Length".into(), Tag::Short(size.z as i16)),
+        ("PaletteMax".into(), Tag::Int(palette.len() as i32)),
+        ("Palette".into(), Tag::Compound(palette)),
+        ("BlockData".into(), Tag::ByteArray(block_data)),
+        ("BlockEntities".into(), Tag::List(10, vec![])),
+    ]);
+    let mut encoder = GzEncoder::new(dest, Compression::default());
+    encoder.write_all(&[root.id()])?;
+    write_string("Schematic", &mut encoder)?;
+    write_payload(&root, &mut encoder)?;
+    encoder.finish()?;
+    Ok(())
+}
@@ -0,0 +1,165 @@
+//! Functions to mirror the map data of one world into another
+//!
+//! [`pull_changes`] copies every mapblock that has been saved since a given timestamp.
+//! Called repeatedly with the [`SyncReport::latest_timestamp`] of the previous run,
+//! it keeps a replica up to date with a live world.
+//! [`merge`] copies all mapblocks regardless of their age, e.g. to stitch together
+//! separately generated regions or to restore parts of a backup.
+
+use std::sync::Mutex;
+
+use crate::map_block::{MapBlockError, TIMESTAMP_UNDEFINED};
+use crate::map_data::{Progress, Rewrite};
+use crate::positions::BlockPos;
+use crate::{MapBlock, MapData, MapDataError};
+
+/// Decides what happens to a mapblock that has changed in both worlds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// The mapblock of the source replaces the destination's one
+    SourceWins,
+    /// The mapblock of the destination is kept
+    DestinationWins,
+    /// The mapblock with the more recent timestamp is kept
+    ///
+    /// On equal timestamps, the source wins.
+    NewerWins,
+}
+
+/// The outcome of [`pull_changes`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    /// Number of mapblocks written to the destination
+    pub copied: usize,
+    /// Number of changed mapblocks that were already identical in the destination
+    pub unchanged: usize,
+    /// Mapblocks that changed in both worlds, whether they were copied or not
+    pub conflicts: Vec<BlockPos>,
+    /// The most recent timestamp among the source's mapblocks
+    ///
+    /// Pass this as `since` to the next call to only receive newer changes.
+    pub latest_timestamp: u32,
+    /// Mapblocks whose header could not be decoded
+    ///
+    /// Those of the source are skipped. Those of the destination count as changed
+    /// before `since`, so they are replaced by the source's mapblock if that has changed.
+    pub undecodable: Vec<BlockPos>,
+}
+
+/// Returns the timestamp stored in a serialized mapblock
+fn block_timestamp(data: &[u8]) -> Result<u32, MapBlockError> {
+    Ok(MapBlock::header_from_data(data)?.timestamp)
+}
+
+fn changed_since(timestamp: u32, since: u32) -> bool {
+    timestamp == TIMESTAMP_UNDEFINED || timestamp >= since
+}
+
+/// Copies all mapblocks of `source` that were saved at or after `since` into `dest`
+///
+/// `since` is a mapblock timestamp, i.e. seconds of game time.
+/// If the destination mapblock has also been saved since then and differs,
+/// `policy` decides which one is kept.
+pub async fn pull_changes(
+    source: &MapData,
+    dest: &MapData,
+    since: u32,
+    policy: ConflictPolicy,
+) -> Result<SyncReport, MapDataError> {
+    pull_changes_with_progress(source, dest, since, policy, |_| ()).await
+}
+
+/// Like [`pull_changes`], but reports its [`Progress`] over the mapblocks of `source`
+///
+/// With `since` set to 0, this is [`merge`] with progress reporting,
+/// e.g. to show how far the conversion of a world into another backend has come.
+pub async fn pull_changes_with_progress(
+    source: &MapData,
+    dest: &MapData,
+    since: u32,
+    policy: ConflictPolicy,
+    progress: impl FnMut(Progress),
+) -> Result<SyncReport, MapDataError> {
+    // Shared with the futures of the mapblocks, which are awaited one after another
+    let report = Mutex::new(SyncReport {
+        latest_timestamp: since,
+        ..Default::default()
+    });
+    let lock = || report.lock().unwrap_or_else(|e| e.into_inner());
+    let positions = source.all_mapblock_positions().await;
+    dest.rewrite_blocks(source, positions, progress, move |pos, data| async move {
+        let Ok(timestamp) = block_timestamp(&data) else {
+            lock().undecodable.push(pos);
+            return Ok(Rewrite::Keep);
+        };
+        if timestamp != TIMESTAMP_UNDEFINED {
+            let mut report = lock();
+            report.latest_timestamp = report.latest_timestamp.max(timestamp);
+        }
+        if !changed_since(timestamp, since) {
+            return Ok(Rewrite::Keep);
+        }
+
+        let dest_data = dest.get_block_data(pos).await;
+        let mut report = lock();
+        let copy = match dest_data {
+            Ok(dest_data) if dest_data == data => {
+                report.unchanged += 1;
+                false
+            }
+            Ok(dest_data) => match block_timestamp(&dest_data) {
+                Ok(dest_timestamp) if changed_since(dest_timestamp, since) => {
+                    report.conflicts.push(pos);
+                    match policy {
+                        ConflictPolicy::SourceWins => true,
+                        ConflictPolicy::DestinationWins => false,
+                        ConflictPolicy::NewerWins => timestamp >= dest_timestamp,
+                    }
+                }
+                Ok(_) => true,
+                Err(_) => {
+                    report.undecodable.push(pos);
+                    true
+                }
+            },
+            Err(MapDataError::MapBlockNonexistent(_)) => true,
+            Err(e) => return Err(e),
+        };
+        if !copy {
+            return Ok(Rewrite::Keep);
+        }
+        report.copied += 1;
+        Ok(Rewrite::Replace(data))
+    })
+    .await?;
+    Ok(report.into_inner().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// Copies all mapblocks of `source` into `dest`
+///
+/// Mapblocks that exist in both worlds and differ are conflicts, `policy` decides which
+/// one is kept. Unlike [`pull_changes`], the age of the mapblocks does not matter otherwise.
+///
+/// ```no_run
+/// use minetestworld::sync::{self, ConflictPolicy};
+/// use minetestworld::World;
+/// use async_std::task;
+///
+/// task::block_on(async {
+///     let backup = World::open("Backup").get_map_data().await.unwrap();
+///     let world = World::open("MyWorld").get_map_data_backend(false).await.unwrap();
+///     // Restore everything that has been lost since the backup, keep the rest
+///     let report = sync::merge(&backup, &world, ConflictPolicy::DestinationWins)
+///         .await
+///         .unwrap();
+///     println!("Restored {} mapblocks", report.copied);
+/// });
+/// ```
+pub async fn merge(
+    source: &MapData,
+    dest: &MapData,
+    policy: ConflictPolicy,
+) -> Result<SyncReport, MapDataError> {
+    // Every mapblock counts as changed since the beginning of time
+    pull_changes(source, dest, 0, policy).await
+}
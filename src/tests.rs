@@ -1,17 +1,62 @@
+use crate::inventory::ItemStack;
+use crate::journal::{Journal, JournalEntry, Recovery};
+use crate::map_block::SizedMapBlock;
+use crate::map_block::StaticObject;
+use crate::map_block::OBJECT_TYPE_LUA_ENTITY;
+use crate::map_block::{BlockFace, LightBank};
+use crate::players::PlayerData;
+use crate::positions::BlockArea;
 use crate::positions::BlockKey;
 use crate::positions::BlockPos;
 use crate::positions::NodeIndex;
 use crate::positions::NodePos;
+use crate::positions::SizedNodeIndex;
+use crate::positions::SizedNodePos;
 use crate::positions::SplitPos;
+use crate::report::{self, ReportOptions};
 use crate::world::keyvalue_to_uri_connectionstr;
 use crate::MapBlock;
 use crate::MapData;
 use crate::MapDataError;
+use crate::MapEdit;
 use crate::World;
 use crate::NODE_BITS_1D;
 use futures::prelude::*;
 use glam::I16Vec3;
 use glam::U16Vec3;
+use std::collections::HashSet;
+
+#[test]
+fn generation_limit() {
+    // The engine's limit of 31007 nodes, divided by the block size
+    assert!(BlockPos::from_index_vec(I16Vec3::new(1937, 0, -1937)).is_within_generation_limit());
+    assert!(!BlockPos::from_index_vec(I16Vec3::new(1938, 0, 0)).is_within_generation_limit());
+    assert!(!BlockPos::from_index_vec(I16Vec3::new(0, -1938, 0)).is_within_generation_limit());
+
+    let beyond = I16Vec3::new(0, 2000, 0);
+    let strict = cfg!(feature = "strict-bounds");
+    assert_eq!(BlockPos::try_from(beyond).is_err(), strict);
+    assert_eq!(BlockPos::try_from_index_vec(beyond).is_err(), strict);
+    assert_eq!("(0,2000,0)".parse::<BlockPos>().is_err(), strict);
+    assert_eq!((beyond * 16).try_split().is_err(), strict);
+    let edge = BlockPos::from_index_vec(I16Vec3::new(0, 1937, 0));
+    assert_eq!(edge.checked_add(I16Vec3::Y).is_none(), strict);
+    assert!(BlockPos::try_from_index_vec(I16Vec3::new(0, 2048, 0)).is_err());
+    assert!(I16Vec3::new(0, 31007, 0).try_split().is_ok());
+}
+
+#[cfg(feature = "strict-bounds")]
+#[async_std::test]
+async fn refuse_writes_beyond_limit() {
+    let mapdata = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+        .await
+        .unwrap();
+    let pos = BlockPos::from_index_vec(I16Vec3::new(0, 0, 2040));
+    assert!(matches!(
+        mapdata.set_mapblock(pos, &MapBlock::unloaded()).await,
+        Err(MapDataError::BeyondGenerationLimit(_))
+    ));
+}
 
 #[test]
 fn simple_math() {
@@ -59,11 +104,89 @@ async fn mapblock_miss() {
     }
 }
 
+#[async_std::test]
+async fn positions_paging() {
+    let mapdata = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+        .await
+        .unwrap();
+    let mut cursor = None;
+    let mut keys = vec![];
+    loop {
+        let page = mapdata.mapblock_positions_page(cursor, 1000).await.unwrap();
+        match page.last() {
+            Some(&last) => cursor = Some(BlockKey::from(last)),
+            None => break,
+        }
+        keys.extend(page.into_iter().map(BlockKey::from));
+    }
+    assert_eq!(keys.len(), 5923);
+    assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
 #[test]
 fn can_parse_mapblock() {
     MapBlock::from_data(std::fs::File::open("TestWorld/testmapblock").unwrap()).unwrap();
 }
 
+#[test]
+fn serialize_version_28() {
+    use std::io::Read;
+
+    let block =
+        MapBlock::from_data(std::fs::File::open("TestWorld/testmapblock").unwrap()).unwrap();
+    assert!(block.to_binary_version(27).is_err());
+    let data = block.to_binary_version(28).unwrap();
+    assert_eq!(data[0], 28);
+    assert_eq!(data[1], block.flags);
+    assert_eq!(&data[4..6], &[2, 2]);
+
+    let mut rest = &data[6..];
+    let mut nodes = vec![];
+    flate2::bufread::ZlibDecoder::new(&mut rest)
+        .read_to_end(&mut nodes)
+        .unwrap();
+    assert_eq!(nodes.len(), 4096 * 4);
+    assert_eq!(u16::from_be_bytes([nodes[0], nodes[1]]), block.param0[0]);
+    assert_eq!(&nodes[8192..12288], &block.param1[..]);
+    let mut metadata = vec![];
+    flate2::bufread::ZlibDecoder::new(&mut rest)
+        .read_to_end(&mut metadata)
+        .unwrap();
+    assert_eq!(metadata[0] == 0, block.node_metadata.is_empty());
+
+    // Static objects are followed by the timestamp
+    assert_eq!(rest[0], 0);
+    let object_count = u16::from_be_bytes([rest[1], rest[2]]);
+    let mut rest = &rest[3..];
+    for _ in 0..object_count {
+        let data_len = u16::from_be_bytes([rest[13], rest[14]]) as usize;
+        rest = &rest[15 + data_len..];
+    }
+    assert_eq!(rest[..4], block.timestamp.to_be_bytes());
+
+    // Reading version 28 yields the same mapblock
+    let reread = MapBlock::from_data(data.as_slice()).unwrap();
+    assert_eq!(reread.map_format_version, 28);
+    assert!(reread.diff(&block).is_empty());
+    assert_eq!(reread.node_metadata, block.node_metadata);
+    assert_eq!(reread.static_objects, block.static_objects);
+    assert_eq!(reread.node_timers, block.node_timers);
+    let header = MapBlock::header_from_data(data.as_slice()).unwrap();
+    assert_eq!(header.timestamp, block.timestamp);
+    assert_eq!(header.name_id_mappings, block.name_id_mappings);
+    let (lenient, report) = MapBlock::from_data_lenient(data.as_slice()).unwrap();
+    assert!(report.is_intact());
+    assert_eq!(lenient.param0, block.param0);
+    MapBlock::roundtrip_check(&data).unwrap();
+
+    // Bulk rewrites keep the version
+    let content = block.content_from_id(block.param0[0]).to_vec();
+    let rewritten = crate::map_data::replace_in_block(&data, &content, b"test:replacement")
+        .unwrap()
+        .unwrap();
+    assert_eq!(rewritten[0], 28);
+}
+
 #[async_std::test]
 async fn can_parse_all_mapblocks() {
     let mapdata = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
@@ -83,6 +206,207 @@ async fn can_parse_all_mapblocks() {
     assert_eq!(failed, 0);
 }
 
+#[async_std::test]
+async fn stream_mapblocks() {
+    let mapdata = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+        .await
+        .unwrap();
+    let mut positions: Vec<_> = mapdata
+        .all_mapblock_positions()
+        .await
+        .try_collect()
+        .await
+        .unwrap();
+    let mut streamed: Vec<_> = mapdata
+        .stream_mapblocks(4)
+        .await
+        .map_ok(|(pos, _)| pos)
+        .try_collect()
+        .await
+        .unwrap();
+    positions.sort_unstable_by_key(|pos| BlockKey::from(*pos));
+    streamed.sort_unstable_by_key(|pos| BlockKey::from(*pos));
+    assert_eq!(streamed, positions);
+}
+
+#[async_std::test]
+async fn all_mapblocks() {
+    let mapdata = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+        .await
+        .unwrap();
+    let mut positions: Vec<_> = mapdata
+        .all_mapblock_positions()
+        .await
+        .try_collect()
+        .await
+        .unwrap();
+    let mut blocks: Vec<_> = mapdata.all_mapblocks().await.try_collect().await.unwrap();
+    positions.sort_unstable_by_key(|pos| BlockKey::from(*pos));
+    blocks.sort_unstable_by_key(|(pos, _)| BlockKey::from(*pos));
+    assert_eq!(
+        blocks.iter().map(|(pos, _)| *pos).collect::<Vec<_>>(),
+        positions
+    );
+    for (pos, block) in blocks.iter().step_by(50) {
+        let fetched = mapdata.get_mapblock(*pos).await.unwrap();
+        assert_eq!(block.param0, fetched.param0);
+        assert_eq!(block.name_id_mappings, fetched.name_id_mappings);
+    }
+}
+
+#[async_std::test]
+async fn check_integrity() {
+    use crate::map_block::BlockIntegrity;
+
+    let mapdata = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+        .await
+        .unwrap();
+    let results: Vec<_> = mapdata.check_integrity().await.try_collect().await.unwrap();
+    assert_eq!(
+        results.len(),
+        mapdata.all_mapblock_positions().await.count().await
+    );
+    assert!(results
+        .iter()
+        .all(|(_, integrity)| *integrity == BlockIntegrity::Ok));
+
+    let data = std::fs::read("TestWorld/testmapblock").unwrap();
+    assert!(matches!(
+        MapBlock::check_data(&data[..data.len() / 2]),
+        BlockIntegrity::DecompressFailed(_)
+    ));
+    assert!(matches!(
+        MapBlock::check_data(&[]),
+        BlockIntegrity::DecompressFailed(_)
+    ));
+    // Cut off the decompressed data within the node arrays
+    let decompressed = zstd::stream::decode_all(&data[1..]).unwrap();
+    let mut truncated = vec![29];
+    truncated.extend(zstd::stream::encode_all(&decompressed[..2000], 0).unwrap());
+    assert!(matches!(
+        MapBlock::check_data(&truncated),
+        BlockIntegrity::ParseFailed { offset: 2000, .. }
+    ));
+}
+
+#[async_std::test]
+async fn cancellation() {
+    use crate::map_data::CancellationToken;
+
+    let mapdata = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+        .await
+        .unwrap();
+    let token = CancellationToken::new();
+    let region = BlockArea::new(
+        BlockPos::from_index_vec(I16Vec3::splat(-2)),
+        BlockPos::from_index_vec(I16Vec3::splat(2)),
+    );
+    let found = token.guard(
+        mapdata
+            .find_nodes([b"default:stone"], I16Vec3::splat(-32), I16Vec3::splat(31))
+            .await,
+    );
+    token.clone().cancel();
+    let found: Vec<_> = found.collect().await;
+    assert!(matches!(found[..], [Err(MapDataError::Cancelled)]));
+    // The destination is read-only, so this would fail if anything was written
+    assert!(matches!(
+        mapdata
+            .copy_region_from_cancellable(&mapdata, region, None, &token)
+            .await,
+        Err(MapDataError::Cancelled)
+    ));
+}
+
+#[test]
+fn lenient_decoding() {
+    use crate::map_block::{BlockSection, DecodeMode};
+
+    let data = std::fs::read("TestWorld/testmapblock").unwrap();
+    let block = MapBlock::from_data(data.as_slice()).unwrap();
+    let decompressed = zstd::stream::decode_all(&data[1..]).unwrap();
+    let truncate = |len| {
+        let mut truncated = vec![29];
+        truncated.extend(zstd::stream::encode_all(&decompressed[..len], 0).unwrap());
+        truncated
+    };
+
+    // Cut off the last byte, which belongs to the node timers
+    let truncated = truncate(decompressed.len() - 1);
+    assert!(MapBlock::from_data(truncated.as_slice()).is_err());
+    let (salvaged, report) = MapBlock::from_data_lenient(truncated.as_slice()).unwrap();
+    assert_eq!(report.dropped, vec![BlockSection::NodeTimers]);
+    assert!(!report.is_intact());
+    assert_eq!(salvaged.param0, block.param0);
+    assert_eq!(salvaged.node_metadata, block.node_metadata);
+    assert_eq!(salvaged.static_objects, block.static_objects);
+    assert!(salvaged.node_timers.is_empty());
+    let lenient = MapBlock::from_data_mode(truncated.as_slice(), DecodeMode::Lenient).unwrap();
+    assert_eq!(lenient.node_metadata, salvaged.node_metadata);
+
+    // The node arrays cannot be salvaged
+    assert!(MapBlock::from_data_lenient(truncate(2000).as_slice()).is_err());
+}
+
+#[async_std::test]
+async fn roundtrip_all_mapblocks() {
+    let mapdata = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+        .await
+        .unwrap();
+    let positions: Vec<_> = mapdata
+        .all_mapblock_positions()
+        .await
+        .try_collect()
+        .await
+        .unwrap();
+    for pos in positions {
+        let data = mapdata.get_block_data(pos).await.unwrap();
+        if let Err(e) = MapBlock::roundtrip_check(&data) {
+            panic!("Mapblock {pos:?} did not survive the roundtrip: {e}");
+        }
+    }
+}
+
+#[async_std::test]
+async fn node_metadata_inventories() {
+    let mapdata = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+        .await
+        .unwrap();
+    let positions: Vec<_> = mapdata
+        .all_mapblock_positions()
+        .await
+        .try_collect()
+        .await
+        .unwrap();
+    let mut inventories = 0;
+    for pos in positions {
+        let block = mapdata.get_mapblock(pos).await.unwrap();
+        for metadata in &block.node_metadata {
+            assert!(std::ptr::eq(
+                block.node_metadata_at(metadata.position).unwrap(),
+                metadata
+            ));
+            if let Some(list) = metadata.inventory_list("main") {
+                assert!(!list.items.is_empty());
+                inventories += 1;
+            }
+        }
+    }
+    assert!(inventories > 0);
+}
+
+#[async_std::test]
+async fn mod_usage() {
+    let mapdata = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+        .await
+        .unwrap();
+    let usage = crate::analysis::mod_usage(&mapdata).await.unwrap();
+    let block_count = mapdata.all_mapblock_positions().await.count().await;
+    assert_eq!(usage.values().sum::<u64>(), block_count as u64 * 4096);
+    assert!(usage[""] > 0);
+    assert!(usage.keys().all(|name| !name.contains(':')));
+}
+
 #[async_std::test]
 async fn count_nodes() {
     let blockpos = BlockPos::from_index_vec(I16Vec3::new(-13, -8, 2));
@@ -105,6 +429,113 @@ async fn iter_node_positions() {
     }
 }
 
+#[async_std::test]
+async fn read_auth() {
+    let auth = World::open("TestWorld").get_auth().await.unwrap();
+    let users: Vec<_> = auth.all_users().try_collect().await.unwrap();
+    let singleplayer = users.iter().find(|u| u.name == "singleplayer").unwrap();
+    assert!(singleplayer.has_privilege("server"));
+    assert_eq!(singleplayer.password, "");
+    let sam = users.iter().find(|u| u.name == "sam").unwrap();
+    assert!(sam.has_privilege("interact"));
+    assert!(!sam.has_privilege("server"));
+    assert!(sam.password.starts_with("#1#"));
+}
+
+#[async_std::test]
+async fn read_players() {
+    let players = World::open("TestWorld").get_players().await.unwrap();
+    let player = players.get_player("singleplayer").await.unwrap();
+    assert_eq!(player.position, glam::Vec3::new(-203.5, -119.5, 40.4));
+    assert_eq!(player.hp, 20);
+    let main = player.inventory_list("main").unwrap();
+    assert_eq!(main.items.len(), 4);
+    assert_eq!(main.items[1], "default:torch 99");
+    assert_eq!(main.items[2], "");
+    assert_eq!(player.inventory_list("craft").unwrap().width, 3);
+    assert_eq!(player.metadata["stamina:level"], "20");
+    assert!(players.get_player("nobody").await.is_err());
+}
+
+#[test]
+fn json_strings() {
+    let parse = |json: &str| crate::json::parse_string(&mut json.chars().peekable());
+    assert_eq!(
+        parse(r#""says \"hi\"\n""#).as_deref(),
+        Some("says \"hi\"\n")
+    );
+    // Characters beyond the basic multilingual plane are escaped as surrogate pairs
+    assert_eq!(parse(r#""\ud83d\ude00 \u00e9""#).as_deref(), Some("😀 é"));
+    assert_eq!(parse(r#""\ud83d""#), None);
+    assert_eq!(parse(r#""\ude00""#), None);
+    assert_eq!(parse(r#""\ud83d\u0041""#), None);
+
+    let mut json = String::new();
+    crate::json::serialize_string("😀 \"\u{1}\"", &mut json);
+    assert_eq!(parse(&json).as_deref(), Some("😀 \"\u{1}\""));
+}
+
+#[async_std::test]
+async fn read_legacy_players() {
+    let players = PlayerData::Files("TestWorld/players".into());
+    let player = players.get_player("kim").await.unwrap();
+    assert_eq!(player.position, glam::Vec3::new(10.5, 7.5, -32.0));
+    assert_eq!(player.hp, 15);
+    assert_eq!(player.breath, 9);
+    let main = player.inventory_list("main").unwrap();
+    assert_eq!(
+        main.items,
+        ["default:pick_steel 1 12000", "", "default:dirt 42"]
+    );
+    let pick = main.stacks().next().unwrap().unwrap();
+    assert_eq!(
+        (pick.name.as_str(), pick.wear),
+        ("default:pick_steel", 12000)
+    );
+    assert_eq!(player.inventory_list("craft").unwrap().width, 3);
+    assert_eq!(player.metadata["note"], "says \"hi\"");
+    let all: Vec<_> = players.all_players().try_collect().await.unwrap();
+    assert_eq!(all, [player]);
+    assert!(players.get_player("nobody").await.is_err());
+}
+
+#[test]
+fn item_stacks() {
+    let stack: ItemStack = "default:torch 99".parse().unwrap();
+    assert_eq!(stack, ItemStack::new("default:torch", 99));
+    assert_eq!(stack.to_string(), "default:torch 99");
+
+    let stack: ItemStack = "default:dirt".parse().unwrap();
+    assert_eq!(stack.count, 1);
+    assert_eq!(stack.to_string(), "default:dirt");
+
+    let serialized = r#"default:book_written 1 0 "\u0001title\u0002My diary\u0003""#;
+    let stack: ItemStack = serialized.parse().unwrap();
+    assert_eq!(stack.metadata["title"], "My diary");
+    assert_eq!(stack.to_string(), serialized);
+
+    // The engine escapes each byte of a non-ASCII character on its own
+    let serialized =
+        r#"default:book_written 1 0 "\u0001text\u0002Gr\u00c3\u00bc\u00c3\u009fe\u0003""#;
+    let stack: ItemStack = serialized.parse().unwrap();
+    assert_eq!(stack.metadata["text"], "Grüße");
+    assert_eq!(stack.to_string(), serialized);
+    let mut book = ItemStack::new("default:book_written", 1);
+    book.metadata.insert("title".into(), "日記 ✓".into());
+    assert_eq!(book.to_string().parse::<ItemStack>().unwrap(), book);
+    assert!(book.to_string().is_ascii());
+
+    let legacy: ItemStack = "default:sign 1 0 hello".parse().unwrap();
+    assert_eq!(legacy.metadata[""], "hello");
+
+    assert!("".parse::<ItemStack>().unwrap().is_empty());
+    assert_eq!(ItemStack::new("default:dirt", 0).to_string(), "");
+    assert!("default:dirt many".parse::<ItemStack>().is_err());
+    assert!("default:dirt 1 0 \"unterminated"
+        .parse::<ItemStack>()
+        .is_err());
+}
+
 #[test]
 fn node_index() {
     assert_eq!(
@@ -117,6 +548,191 @@ fn node_index() {
     );
 }
 
+#[test]
+fn position_notation() {
+    use crate::positions::{display_pos, parse_pos, ParsePosError};
+
+    assert_eq!(parse_pos("(1,2,3)"), Ok(I16Vec3::new(1, 2, 3)));
+    assert_eq!(parse_pos(" -4, 5 ,-6 "), Ok(I16Vec3::new(-4, 5, -6)));
+    assert_eq!(
+        display_pos(I16Vec3::new(-4, 5, -6)).to_string(),
+        "(-4,5,-6)"
+    );
+    assert!(matches!(
+        parse_pos("(1,2)"),
+        Err(ParsePosError::Malformed(_))
+    ));
+    assert!(matches!(
+        parse_pos("(1,2,3,4)"),
+        Err(ParsePosError::Malformed(_))
+    ));
+    assert!(matches!(
+        parse_pos("(1.5,2,3)"),
+        Err(ParsePosError::Malformed(_))
+    ));
+    assert!(matches!(
+        parse_pos("(1,2,40000)"),
+        Err(ParsePosError::OutOfRange(_))
+    ));
+
+    let block = BlockPos::from_index_vec(I16Vec3::new(-12, -2, 4));
+    assert_eq!(block.to_string(), "(-12,-2,4)");
+    assert_eq!("(-12,-2,4)".parse::<BlockPos>(), Ok(block));
+    assert!("(0,0,4096)".parse::<BlockPos>().is_err());
+
+    let node = NodePos::try_from(U16Vec3::new(15, 0, 7)).unwrap();
+    assert_eq!(node.to_string(), "(15,0,7)");
+    assert_eq!("(15,0,7)".parse::<NodePos>(), Ok(node));
+    assert!("(16,0,0)".parse::<NodePos>().is_err());
+    assert!("(-1,0,0)".parse::<NodePos>().is_err());
+}
+
+#[test]
+fn position_arithmetic() {
+    use crate::positions::{neighbors26, neighbors6};
+
+    let block = BlockPos::from_index_vec(I16Vec3::new(-12, -2, 4));
+    assert_eq!(
+        (block + I16Vec3::X).into_index_vec(),
+        I16Vec3::new(-11, -2, 4)
+    );
+    assert_eq!(
+        (block - I16Vec3::Y).into_index_vec(),
+        I16Vec3::new(-12, -3, 4)
+    );
+    assert_eq!((block * 2).into_index_vec(), I16Vec3::new(-24, -4, 8));
+    assert_eq!((block + I16Vec3::Z) - block, I16Vec3::Z);
+    let mut moved = block;
+    moved += I16Vec3::ONE;
+    moved -= I16Vec3::ONE;
+    assert_eq!(moved, block);
+
+    let corner = BlockPos::from_index_vec(I16Vec3::splat(2047));
+    assert_eq!(corner.checked_add(I16Vec3::X), None);
+    assert_eq!(corner.checked_mul(2), None);
+    assert_eq!(block.neighbors6().count(), 6);
+    assert_eq!(block.neighbors26().count(), 26);
+    assert_eq!(corner.neighbors6().count(), 3);
+    assert_eq!(corner.neighbors26().count(), 7);
+    assert!(block
+        .neighbors26()
+        .all(|neighbor| (neighbor - block).abs().max_element() == 1));
+
+    let node = NodePos::try_from(U16Vec3::new(0, 15, 7)).unwrap();
+    assert_eq!(
+        node + I16Vec3::X,
+        NodePos::try_from(U16Vec3::new(1, 15, 7)).unwrap()
+    );
+    assert_eq!(node.checked_sub(I16Vec3::X), None);
+    assert_eq!(node.checked_add(I16Vec3::Y), None);
+    assert_eq!(node.neighbors6().count(), 4);
+    assert_eq!(node.neighbors26().count(), 11);
+
+    assert_eq!(neighbors6(I16Vec3::ZERO).count(), 6);
+    assert_eq!(neighbors26(I16Vec3::ZERO).count(), 26);
+    assert_eq!(neighbors6(I16Vec3::MAX).count(), 3);
+}
+
+#[test]
+fn block_iterators() {
+    let (a, b) = (I16Vec3::new(40, -1, -33), I16Vec3::new(-20, 15, 0));
+    let blocks: Vec<_> = BlockPos::iter_box(a, b).collect();
+    assert_eq!(blocks.len(), 5 * 2 * 4);
+    assert!(blocks
+        .windows(2)
+        .all(|pair| BlockKey::from(pair[0]) < BlockKey::from(pair[1])));
+    assert!(blocks.contains(&BlockPos::from_index_vec(I16Vec3::new(-2, -1, -3))));
+    assert!(blocks.contains(&BlockPos::from_index_vec(I16Vec3::new(2, 0, 0))));
+
+    let (center, radius) = (I16Vec3::new(-185, -15, 65), 20);
+    let blocks: HashSet<_> = BlockPos::iter_sphere(center, radius).collect();
+    let mut expected = HashSet::new();
+    for z in -20..=20 {
+        for y in -20..=20 {
+            for x in -20i16..=20 {
+                let offset = I16Vec3::new(x, y, z);
+                if offset.as_ivec3().length_squared() <= 400 {
+                    expected.insert(BlockPos::from_index_vec((center + offset) >> NODE_BITS_1D));
+                }
+            }
+        }
+    }
+    assert_eq!(blocks, expected);
+    assert_eq!(BlockPos::iter_sphere(I16Vec3::MAX, 0).count(), 1);
+}
+
+#[async_std::test]
+async fn morton_order() {
+    use crate::positions::MortonKey;
+
+    for index in [
+        I16Vec3::new(-12, -2, 4),
+        I16Vec3::splat(-2048),
+        I16Vec3::splat(2047),
+        I16Vec3::new(2047, -2048, 0),
+    ] {
+        let pos = BlockPos::from_index_vec(index);
+        assert_eq!(BlockPos::from(MortonKey::from(pos)), pos);
+        let key = BlockKey::from(pos);
+        assert_eq!(BlockKey::from(MortonKey::from(key)), key);
+    }
+    assert!(
+        u64::from(MortonKey::from(BlockPos::from_index_vec(I16Vec3::splat(
+            2047
+        )))) < 1 << 36
+    );
+
+    // The eight blocks of an aligned cube are adjacent on the curve
+    let area = BlockArea::new(
+        BlockPos::from_index_vec(I16Vec3::new(-4, 2, 6)),
+        BlockPos::from_index_vec(I16Vec3::new(-3, 3, 7)),
+    );
+    let keys: Vec<u64> = area
+        .iter_morton()
+        .map(|pos| MortonKey::from(pos).into())
+        .collect();
+    assert_eq!(keys.len(), 8);
+    assert!(keys.windows(2).all(|pair| pair[1] == pair[0] + 1));
+
+    let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+        .await
+        .unwrap();
+    let positions: Vec<BlockPos> = map
+        .stream_mapblocks_morton(4)
+        .await
+        .map_ok(|(pos, _)| pos)
+        .try_collect()
+        .await
+        .unwrap();
+    assert!(positions
+        .windows(2)
+        .all(|pair| MortonKey::from(pair[0]) < MortonKey::from(pair[1])));
+    let all: HashSet<BlockPos> = map
+        .all_mapblock_positions()
+        .await
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(positions.len(), all.len());
+}
+
+#[test]
+fn sized_blocks() {
+    type SmallNodePos = SizedNodePos<8>;
+    let pos = SmallNodePos::try_from(U16Vec3::new(7, 1, 2)).unwrap();
+    assert_eq!(u16::from(SizedNodeIndex::from(pos)), 7 + 8 + 2 * 64);
+    assert!(SmallNodePos::try_from(U16Vec3::new(8, 0, 0)).is_err());
+    assert!(SizedNodeIndex::<8>::try_from(512).is_err());
+
+    let mut block = SizedMapBlock::<8, 512>::unloaded();
+    let stone = block.get_or_create_content_id(b"default:stone");
+    block.set_content(pos, stone);
+    let decoded =
+        SizedMapBlock::<8, 512>::from_data(block.to_binary().unwrap().as_slice()).unwrap();
+    assert_eq!(decoded.get_node_at(pos).param0, b"default:stone");
+    assert!(MapBlock::from_data(block.to_binary().unwrap().as_slice()).is_err());
+}
+
 #[test]
 fn url_default_host() {
     assert_eq!(
@@ -137,3 +753,1624 @@ fn url_nondefault_values() {
         Ok("postgresql://u:p@localhorst:15432/mtdb".to_string())
     );
 }
+
+#[test]
+fn static_objects() {
+    let mut data = vec![1];
+    data.extend(10u16.to_be_bytes());
+    data.extend(b"mobs:sheep");
+    data.extend(5u32.to_be_bytes());
+    data.extend(b"{...}");
+    data.extend(8u16.to_be_bytes());
+    for component in [0i32, -20000, 5000, 90000] {
+        data.extend(component.to_be_bytes());
+    }
+    let object = StaticObject {
+        type_id: OBJECT_TYPE_LUA_ENTITY,
+        x: -2035000,
+        y: -1195000,
+        z: 404000,
+        data,
+    };
+    assert_eq!(object.position(), glam::Vec3::new(-203.5, -119.5, 40.4));
+    let entity = object.lua_entity().unwrap().unwrap();
+    assert_eq!(entity.name, "mobs:sheep");
+    assert_eq!(entity.staticdata, b"{...}");
+    assert_eq!(entity.hp, 8);
+    assert_eq!(entity.velocity, glam::Vec3::new(0.0, -2.0, 0.5));
+    assert_eq!(entity.rotation, glam::Vec3::new(0.0, 90.0, 0.0));
+
+    let mut block = MapBlock::unloaded();
+    block.static_objects.push(object);
+    let block = MapBlock::from_data(block.to_binary().unwrap().as_slice()).unwrap();
+    let entities: Vec<_> = block.lua_entities().collect::<Result<_, _>>().unwrap();
+    assert_eq!(
+        entities,
+        [(glam::Vec3::new(-203.5, -119.5, 40.4), entity.clone())]
+    );
+
+    let mut object = StaticObject::from_lua_entity(glam::Vec3::new(1.5, 2.0, -3.25), &entity);
+    assert_eq!(object.position(), glam::Vec3::new(1.5, 2.0, -3.25));
+    assert_eq!(object.lua_entity().unwrap(), Some(entity.clone()));
+    object.set_position(glam::Vec3::ZERO);
+    assert_eq!((object.x, object.y, object.z), (0, 0, 0));
+    assert_eq!(object.lua_entity().unwrap(), Some(entity));
+}
+
+#[async_std::test]
+async fn decode_lua_entities() {
+    let mapdata = World::open("TestWorld").get_map_data().await.unwrap();
+    let positions: Vec<_> = mapdata
+        .all_mapblock_positions()
+        .await
+        .try_collect()
+        .await
+        .unwrap();
+    for pos in positions {
+        let block = mapdata.get_mapblock(pos).await.unwrap();
+        for entity in block.lua_entities() {
+            entity.unwrap();
+        }
+    }
+}
+
+#[async_std::test]
+async fn index_records() {
+    let blockpos = BlockPos::from_index_vec(I16Vec3::new(-13, -8, 2));
+    let mapdata = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+        .await
+        .unwrap();
+    let records: Vec<_> = crate::export::index_records(
+        &mapdata,
+        crate::positions::BlockArea::new(blockpos, blockpos),
+    )
+    .await
+    .try_collect()
+    .await
+    .unwrap();
+    let solid = mapdata
+        .iter_mapblock_nodes(blockpos)
+        .await
+        .unwrap()
+        .filter(|(_, node)| node.param0 != b"air" && node.param0 != b"ignore")
+        .count();
+    assert_eq!(records.len(), solid);
+    for record in &records {
+        assert_eq!(record.pos.split().0, blockpos);
+        match record.content.split_once(':') {
+            Some((prefix, _)) => assert_eq!(record.mod_prefix, prefix),
+            None => assert!(record.mod_prefix.is_empty()),
+        }
+    }
+}
+
+#[async_std::test]
+async fn sqlite_predicate() {
+    let mapdata = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+        .await
+        .unwrap();
+    let MapData::Sqlite(pool) = &mapdata else {
+        unreachable!()
+    };
+    let all: Vec<BlockPos> = mapdata
+        .all_mapblock_positions()
+        .await
+        .try_collect()
+        .await
+        .unwrap();
+    // The second area has too many rows to list them in the predicate
+    for area in [
+        BlockArea::new(
+            BlockPos::from_index_vec(I16Vec3::new(-14, -9, 1)),
+            BlockPos::from_index_vec(I16Vec3::new(-12, -7, 3)),
+        ),
+        BlockArea::new(
+            BlockPos::from_index_vec(I16Vec3::new(-2048, -2048, 1)),
+            BlockPos::from_index_vec(I16Vec3::new(-12, 2047, 3)),
+        ),
+    ] {
+        let mut expected: Vec<_> = all
+            .iter()
+            .filter(|pos| area.contains(**pos))
+            .map(|&pos| BlockKey::from(pos))
+            .collect();
+        expected.sort_unstable();
+        assert!(!expected.is_empty());
+
+        let query = format!(
+            "SELECT pos FROM blocks WHERE {} ORDER BY pos",
+            area.sqlite_predicate("pos")
+        );
+        let keys: Vec<i64> = sqlx::query_scalar(&query).fetch_all(pool).await.unwrap();
+        let keys: Vec<_> = keys
+            .into_iter()
+            .map(|key| BlockKey::try_from(key).unwrap())
+            .collect();
+        assert_eq!(keys, expected);
+    }
+}
+
+#[async_std::test]
+async fn mapblock_positions_in() {
+    let mapdata = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+        .await
+        .unwrap();
+    let all: Vec<BlockPos> = mapdata
+        .all_mapblock_positions()
+        .await
+        .try_collect()
+        .await
+        .unwrap();
+    // The second area is too tall to list its key ranges
+    for area in [
+        BlockArea::new(
+            BlockPos::from_index_vec(I16Vec3::new(-14, -9, 1)),
+            BlockPos::from_index_vec(I16Vec3::new(-12, -7, 3)),
+        ),
+        BlockArea::new(
+            BlockPos::from_index_vec(I16Vec3::new(-13, -2048, 2)),
+            BlockPos::from_index_vec(I16Vec3::new(-11, 2047, 4)),
+        ),
+    ] {
+        let mut expected: Vec<_> = all
+            .iter()
+            .copied()
+            .filter(|pos| area.contains(*pos))
+            .map(BlockKey::from)
+            .collect();
+        expected.sort_unstable();
+        assert!(!expected.is_empty());
+        let mut keys: Vec<_> = mapdata
+            .mapblock_positions_in(area)
+            .await
+            .map_ok(BlockKey::from)
+            .try_collect()
+            .await
+            .unwrap();
+        keys.sort_unstable();
+        assert_eq!(keys, expected);
+    }
+}
+
+#[async_std::test]
+async fn palette() {
+    let mapdata = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+        .await
+        .unwrap();
+    let positions: Vec<_> = mapdata
+        .all_mapblock_positions()
+        .await
+        .try_collect()
+        .await
+        .unwrap();
+    for pos in positions {
+        let palette = mapdata.get_palette(pos).await.unwrap();
+        let block = mapdata.get_mapblock(pos).await.unwrap();
+        assert_eq!(palette, block.name_id_mappings);
+        for (id, name) in block.palette() {
+            assert_eq!(block.content_name(id), Some(name));
+            assert!(block.has_content(name));
+        }
+    }
+    let block = MapBlock::unloaded();
+    assert!(!block.has_content(b"air"));
+    assert_eq!(block.content_name(1), None);
+}
+
+async fn interrupted_commit(dir: &std::path::Path) -> Result<(), MapDataError> {
+    let map = &MapData::from_sqlite_file(dir.join("map.sqlite"), false).await?;
+    let journal = Journal::new(dir.join("map.journal"));
+    let existing = map.all_mapblock_positions().await.next().await.unwrap()?;
+    let missing = I16Vec3::new(0, 0, 0).split().0;
+    let old_data = map.get_block_data(existing).await?;
+    let new_data = MapBlock::unloaded().to_binary()?;
+
+    // A commit that is killed right after the journal has been written
+    let entries = [
+        JournalEntry {
+            pos: existing,
+            old_data: Some(old_data.clone()),
+            new_data: new_data.clone(),
+        },
+        JournalEntry {
+            pos: missing,
+            old_data: None,
+            new_data: new_data.clone(),
+        },
+    ];
+    journal.write(&entries).await?;
+    map.set_block_data(existing, &new_data).await?;
+    assert!(journal.is_pending().await);
+    assert_eq!(journal.recover(map, Recovery::RollBack).await?, 2);
+    assert!(!journal.is_pending().await);
+    assert_eq!(map.get_block_data(existing).await?, old_data);
+    assert!(matches!(
+        map.get_block_data(missing).await,
+        Err(MapDataError::MapBlockNonexistent(_))
+    ));
+
+    journal.write(&entries).await?;
+    assert_eq!(journal.recover(map, Recovery::Complete).await?, 2);
+    assert_eq!(map.get_block_data(existing).await?, new_data);
+    assert_eq!(map.get_block_data(missing).await?, new_data);
+    assert_eq!(journal.recover(map, Recovery::Complete).await?, 0);
+
+    // A regular commit leaves no journal behind
+    let mut vm = MapEdit::new(MapData::from_sqlite_file(dir.join("map.sqlite"), false).await?);
+    vm.set_journal(journal.clone());
+    vm.set_content(I16Vec3::new(0, 0, 0), b"default:stone")
+        .await?;
+    vm.commit().await?;
+    assert!(!journal.is_pending().await);
+    let vm = MapEdit::new(MapData::from_sqlite_file(dir.join("map.sqlite"), true).await?);
+    assert_eq!(
+        vm.get_node(I16Vec3::new(0, 0, 0)).await?.param0,
+        b"default:stone"
+    );
+    Ok(())
+}
+
+#[async_std::test]
+async fn commit_journal() {
+    let dir = std::path::Path::new("TestWorld journal");
+    std::fs::create_dir(dir).unwrap();
+    std::fs::copy("TestWorld/map.sqlite", dir.join("map.sqlite")).unwrap();
+    let result = interrupted_commit(dir).await;
+    std::fs::remove_dir_all(dir).unwrap();
+    result.unwrap();
+}
+
+#[test]
+fn compact_palette() {
+    let mut block =
+        MapBlock::from_data(std::fs::File::open("TestWorld/testmapblock").unwrap()).unwrap();
+    let nodes: Vec<_> = (0..4096u16)
+        .map(|index| {
+            block
+                .get_node_at(NodeIndex::try_from(index).unwrap().into())
+                .param0
+        })
+        .collect();
+    block.get_or_create_content_id(b"default:unused");
+    assert!(block.compact_palette() >= 1);
+    assert!(!block.has_content(b"default:unused"));
+    let mut ids: Vec<_> = block.palette().map(|(id, _)| id).collect();
+    ids.sort_unstable();
+    assert_eq!(ids, (0..ids.len() as u16).collect::<Vec<_>>());
+    for (index, node) in nodes.iter().enumerate() {
+        let node_pos = NodeIndex::try_from(index as u16).unwrap().into();
+        assert_eq!(&block.get_node_at(node_pos).param0, node);
+    }
+    // Compacting is idempotent
+    assert_eq!(block.compact_palette(), 0);
+
+    let stone = block.get_or_create_content_id(b"default:stone");
+    block.param0.fill(stone);
+    block.compact_palette();
+    assert_eq!(
+        block.palette().collect::<Vec<_>>(),
+        vec![(0, &b"default:stone"[..])]
+    );
+}
+
+#[test]
+fn replace_content() {
+    let mut block = MapBlock::unloaded();
+    let stone = block.get_or_create_content_id(b"default:stone");
+    block.param0[..100].fill(stone);
+
+    // The mapping is renamed in place
+    assert!(block.replace_content(b"default:stone", b"default:desert_stone"));
+    assert_eq!(block.get_content_id(b"default:desert_stone"), Some(stone));
+    assert!(!block.has_content(b"default:stone"));
+
+    // Both contents are present, so the nodes are changed
+    assert!(block.replace_content(b"ignore", b"default:desert_stone"));
+    assert!(block.param0.iter().all(|&id| id == stone));
+    assert_eq!(
+        block.palette().collect::<Vec<_>>(),
+        vec![(stone, &b"default:desert_stone"[..])]
+    );
+
+    assert!(!block.replace_content(b"default:stone", b"air"));
+    assert!(!block.replace_content(b"default:desert_stone", b"default:desert_stone"));
+}
+
+#[async_std::test]
+async fn world_report() {
+    let mapdata = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+        .await
+        .unwrap();
+    let options = ReportOptions {
+        biggest_blocks: 3,
+        known_contents: Some(HashSet::from(["default:stone".to_string()])),
+        ..Default::default()
+    };
+    let report = report::collect(&mapdata, "Hallo", &options).await.unwrap();
+    assert_eq!(report.block_count, 5923);
+    assert_eq!(
+        report.mod_usage.values().sum::<u64>(),
+        report.block_count as u64 * 4096
+    );
+    assert_eq!(report.biggest_blocks.len(), 3);
+    assert!(report.biggest_blocks.windows(2).all(|w| w[0].1 >= w[1].1));
+    assert!(report.total_size >= report.biggest_blocks.iter().map(|b| b.1).sum());
+    assert!(!report.unknown_nodes.contains_key("default:stone"));
+    assert!(!report.unknown_nodes.contains_key("air"));
+    assert!(report.unknown_nodes.contains_key("default:dirt"));
+
+    let json = report.to_json();
+    assert!(json.starts_with("{\"world_name\":\"Hallo\",\"block_count\":5923,"));
+    let html = report.to_html();
+    assert!(html.contains("<svg"));
+    assert!(html.contains("<td>default:dirt</td>"));
+}
+
+#[test]
+fn header_flags() {
+    let mut block = MapBlock::unloaded();
+    assert!(block.is_generated());
+    assert_eq!(block.saved_at(), None);
+
+    block.set_underground(true);
+    block.set_day_night_differs(true);
+    block.set_generated(false);
+    assert_eq!(block.flags, 0x0b);
+    assert!(block.is_underground() && block.day_night_differs() && !block.is_generated());
+    block.set_day_night_differs(false);
+    assert_eq!(block.flags, 0x09);
+
+    block.set_lighting_complete(LightBank::Day, BlockFace::PosX, true);
+    block.set_lighting_complete(LightBank::Night, BlockFace::NegX, true);
+    assert_eq!(block.lighting_complete, 0x0801);
+    block.set_lighting_complete(LightBank::Day, BlockFace::PosX, false);
+    assert!(!block.is_lighting_complete(LightBank::Day, BlockFace::PosX));
+
+    block.set_saved_at(Some(1234));
+    assert_eq!(block.timestamp, 1234);
+    let data = block.to_binary().unwrap();
+    block = MapBlock::from_data(data.as_slice()).unwrap();
+    assert_eq!(block.saved_at(), Some(1234));
+    assert!(block.is_underground() && !block.is_generated());
+    assert!(block.is_lighting_complete(LightBank::Night, BlockFace::NegX));
+}
+
+#[test]
+fn block_diff() {
+    let mut block =
+        MapBlock::from_data(std::fs::File::open("TestWorld/testmapblock").unwrap()).unwrap();
+    let data = block.to_binary().unwrap();
+    let original = MapBlock::from_data(data.as_slice()).unwrap();
+    assert!(original.diff(&block).is_empty());
+
+    // Renumbering the content IDs is not a change
+    block.get_or_create_content_id(b"default:unused");
+    block.compact_palette();
+    assert!(original.diff(&block).is_empty());
+
+    let pos = NodePos::try_from(U16Vec3::new(3, 4, 5)).unwrap();
+    let mese = block.get_or_create_content_id(b"default:mese");
+    block.set_content(pos, mese);
+    block.set_param2(pos, 3);
+    block.node_metadata.clear();
+    block.static_objects.push(StaticObject {
+        type_id: OBJECT_TYPE_LUA_ENTITY,
+        x: 0,
+        y: 0,
+        z: 0,
+        data: vec![],
+    });
+    let delta = original.diff(&block);
+    assert_eq!(delta.nodes.len(), 1);
+    assert_eq!(delta.nodes[0].position, pos);
+    assert_eq!(delta.nodes[0].after.param0, b"default:mese");
+    assert_eq!(delta.nodes[0].after.param2, 3);
+    assert_eq!(delta.added_contents, vec![b"default:mese".to_vec()]);
+    assert_eq!(
+        delta.metadata.len(),
+        original.node_metadata.len(),
+        "all metadata has been removed"
+    );
+    assert_eq!(delta.added_objects.len(), 1);
+    assert!(delta.removed_objects.is_empty());
+
+    // The diff in the opposite direction is mirrored
+    let reverse = block.diff(&original);
+    assert_eq!(reverse.removed_contents, delta.added_contents);
+    assert_eq!(reverse.nodes[0].before, delta.nodes[0].after);
+    assert_eq!(reverse.removed_objects, delta.added_objects);
+}
+
+#[test]
+fn node_defs() {
+    use crate::node_def::{NodeDef, NodeDefProvider, ParamType, ParamType2};
+
+    assert_eq!("light".parse(), Ok(ParamType::Light));
+    assert_eq!("4dir".parse(), Ok(ParamType2::FourDir));
+    assert_eq!("colorwallmounted".parse(), Ok(ParamType2::ColorWallMounted));
+    assert!("sideways".parse::<ParamType2>().is_err());
+
+    let defs = std::collections::HashMap::from([
+        (b"default:stone".to_vec(), NodeDef::default()),
+        (
+            b"default:torch".to_vec(),
+            NodeDef {
+                paramtype: ParamType::Light,
+                paramtype2: ParamType2::WallMounted,
+                ..Default::default()
+            },
+        ),
+    ]);
+    let stone = defs.node_def(b"default:stone").unwrap();
+    assert!(!stone.uses_param1() && !stone.uses_param2());
+    let torch = defs.node_def(b"default:torch").unwrap();
+    assert!(torch.uses_param1() && torch.uses_param2());
+}
+
+#[test]
+fn param2_rotation() {
+    use crate::node_def::ParamType2;
+
+    for paramtype2 in [
+        ParamType2::FaceDir,
+        ParamType2::ColorFaceDir,
+        ParamType2::WallMounted,
+        ParamType2::ColorWallMounted,
+        ParamType2::FourDir,
+        ParamType2::DegRotate,
+        ParamType2::ColorDegRotate,
+        ParamType2::Leveled,
+    ] {
+        for param2 in 0..=255 {
+            let turned = (0..4).fold(param2, |param2, _| paramtype2.rotate_y(param2, 1));
+            assert_eq!(turned, param2, "{paramtype2:?} {param2}");
+            assert_eq!(paramtype2.rotate_y(param2, 4), param2);
+            assert_eq!(
+                paramtype2.rotate_y(param2, 3),
+                paramtype2.rotate_y(paramtype2.rotate_y(param2, 2), 1)
+            );
+        }
+    }
+    // Lying on its back, facing downwards
+    assert_eq!(ParamType2::FaceDir.rotate_y(4, 1), 13);
+    // Upside down, the rotation counts the other way round
+    assert_eq!(ParamType2::FaceDir.rotate_y(20, 1), 23);
+    // The palette index is kept
+    assert_eq!(ParamType2::ColorFaceDir.rotate_y(0xe0 | 3, 1), 0xe0);
+    assert_eq!(ParamType2::WallMounted.rotate_y(1, 1), 7);
+    assert_eq!(ParamType2::WallMounted.rotate_y(4, 2), 5);
+    assert_eq!(ParamType2::ColorWallMounted.rotate_y(0x08 | 3, 1), 0x08 | 4);
+    assert_eq!(ParamType2::FourDir.rotate_y(3, 1), 0);
+    assert_eq!(ParamType2::DegRotate.rotate_y(200, 1), 20);
+    assert_eq!(ParamType2::Leveled.rotate_y(17, 1), 17);
+}
+
+#[test]
+fn param2_mirroring() {
+    use crate::node_def::{MirrorAxis, ParamType2};
+
+    for paramtype2 in [
+        ParamType2::FaceDir,
+        ParamType2::ColorFaceDir,
+        ParamType2::WallMounted,
+        ParamType2::ColorWallMounted,
+        ParamType2::FourDir,
+        ParamType2::DegRotate,
+        ParamType2::ColorDegRotate,
+        ParamType2::Leveled,
+    ] {
+        for param2 in 0..=255 {
+            for axis in [MirrorAxis::X, MirrorAxis::Z] {
+                let mirrored = paramtype2.mirror(param2, axis);
+                assert_eq!(
+                    paramtype2.mirror(mirrored, axis),
+                    param2,
+                    "{paramtype2:?} {param2}"
+                );
+            }
+            // Mirroring along both axes is a half turn
+            assert_eq!(
+                paramtype2.mirror(paramtype2.mirror(param2, MirrorAxis::X), MirrorAxis::Z),
+                paramtype2.rotate_y(param2, 2),
+                "{paramtype2:?} {param2}"
+            );
+        }
+    }
+    // Facing +z is not affected by mirroring along x
+    assert_eq!(ParamType2::FaceDir.mirror(0, MirrorAxis::X), 0);
+    assert_eq!(ParamType2::FaceDir.mirror(0, MirrorAxis::Z), 2);
+    // Top facing +z, front facing +x
+    assert_eq!(ParamType2::FaceDir.mirror(5, MirrorAxis::X), 7);
+    // The palette index is kept
+    assert_eq!(
+        ParamType2::ColorFaceDir.mirror(0xe0 | 1, MirrorAxis::X),
+        0xe0 | 3
+    );
+    assert_eq!(ParamType2::WallMounted.mirror(2, MirrorAxis::X), 3);
+    assert_eq!(ParamType2::WallMounted.mirror(6, MirrorAxis::X), 6);
+    assert_eq!(ParamType2::FourDir.mirror(1, MirrorAxis::Z), 1);
+    assert_eq!(ParamType2::DegRotate.mirror(20, MirrorAxis::X), 220);
+    assert_eq!(ParamType2::DegRotate.mirror(20, MirrorAxis::Z), 100);
+}
+
+#[test]
+fn spiral_outward() {
+    let center = BlockPos::from_index_vec(I16Vec3::new(3, -2, 5));
+    let positions: Vec<_> = crate::positions::spiral_outward(center, 3).collect();
+    assert_eq!(positions.len(), 7 * 7 * 7);
+    let unique: HashSet<_> = positions.iter().copied().collect();
+    assert_eq!(unique.len(), positions.len());
+    let distances: Vec<_> = positions
+        .iter()
+        .map(|pos| {
+            let offset = pos.into_index_vec() - center.into_index_vec();
+            offset.abs().max_element()
+        })
+        .collect();
+    assert!(distances.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert_eq!(distances.last(), Some(&3));
+
+    // Positions beyond the border of the world are skipped
+    let corner = BlockPos::from_index_vec(I16Vec3::splat(crate::WORLD_BLOCKS_MAX));
+    assert_eq!(crate::positions::spiral_outward(corner, 2).count(), 27);
+}
+
+async fn copy_raw_blocks(dir: &std::path::Path) -> Result<(), MapDataError> {
+    let source = MapData::from_sqlite_file("TestWorld/map.sqlite", true).await?;
+    let dest = MapData::from_sqlite_file(dir.join("map.sqlite"), false).await?;
+    let positions: Vec<_> = source
+        .all_mapblock_positions()
+        .await
+        .take(20)
+        .try_collect()
+        .await?;
+    for &pos in &positions {
+        assert!(dest.delete_mapblock(pos).await?);
+        dest.set_block_data(pos, &source.get_block_data(pos).await?)
+            .await?;
+        assert_eq!(
+            dest.get_block_data(pos).await?,
+            source.get_block_data(pos).await?
+        );
+    }
+    Ok(())
+}
+
+#[async_std::test]
+async fn raw_block_data() {
+    let dir = std::path::Path::new("TestWorld raw block data");
+    std::fs::create_dir(dir).unwrap();
+    std::fs::copy("TestWorld/map.sqlite", dir.join("map.sqlite")).unwrap();
+    let result = copy_raw_blocks(dir).await;
+    std::fs::remove_dir_all(dir).unwrap();
+    result.unwrap();
+}
+
+#[test]
+fn partial_decoding() {
+    use crate::map_block::{DecodeMode, MapBlockError};
+
+    let data = std::fs::read("TestWorld/testmapblock").unwrap();
+    let mut block = MapBlock::from_data(data.as_slice()).unwrap();
+    let header = MapBlock::header_from_data(data.as_slice()).unwrap();
+    assert_eq!(header.flags, block.flags);
+    assert_eq!(header.lighting_complete, block.lighting_complete);
+    assert_eq!(header.timestamp, block.timestamp);
+    assert_eq!(header.name_id_mappings, block.name_id_mappings);
+
+    let param0 = block.param0;
+    let param2 = block.param2;
+    block = MapBlock::from_data_mode(data.as_slice(), DecodeMode::NodesOnly).unwrap();
+    assert_eq!(block.param0, param0);
+    assert_eq!(block.param2, param2);
+    assert!(block.node_metadata.is_empty());
+    assert!(block.node_timers.is_empty());
+
+    assert!(matches!(
+        MapBlock::header_from_data(&[27u8, 0, 0][..]),
+        Err(MapBlockError::MapVersionError(27))
+    ));
+}
+
+#[async_std::test]
+async fn visit_nodes() {
+    let mapdata = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+        .await
+        .unwrap();
+    let pos = BlockPos::from_index_vec(I16Vec3::new(-13, -8, 2));
+    let expected: Vec<_> = mapdata.iter_mapblock_nodes(pos).await.unwrap().collect();
+    let mut visited = vec![];
+    mapdata
+        .visit_mapblock_nodes(pos, |world_pos, node| {
+            visited.push((world_pos, node.to_node()))
+        })
+        .await
+        .unwrap();
+    assert_eq!(visited, expected);
+}
+
+#[test]
+fn iter_content_ids() {
+    let block =
+        MapBlock::from_data(std::fs::File::open("TestWorld/testmapblock").unwrap()).unwrap();
+    let mut count = 0;
+    for ((position, content_id, param1, param2), node) in
+        block.iter_content_ids().zip(block.nodes())
+    {
+        assert_eq!(position, node.position);
+        assert_eq!(block.content_name(content_id), Some(node.content));
+        assert_eq!((param1, param2), (node.param1, node.param2));
+        count += 1;
+    }
+    assert_eq!(count, 4096);
+}
+
+#[test]
+fn scatter() {
+    let min = BlockPos::from_index_vec(I16Vec3::new(-1, 0, -1));
+    let max = BlockPos::from_index_vec(I16Vec3::new(0, 1, 0));
+    let region = BlockArea::new(min, max);
+    let positions: Vec<_> = crate::mapgen::scatter(region, 0.05, 42).collect();
+    // 8 blocks with 4096 nodes each
+    assert!((1300..1980).contains(&positions.len()));
+    assert_eq!(
+        positions,
+        crate::mapgen::scatter(region, 0.05, 42).collect::<Vec<_>>()
+    );
+    assert_ne!(
+        positions,
+        crate::mapgen::scatter(region, 0.05, 43).collect::<Vec<_>>()
+    );
+
+    // A part of the region yields the same positions as the whole region
+    let part: Vec<_> = crate::mapgen::scatter(BlockArea::new(min, min), 0.05, 42).collect();
+    assert!(!part.is_empty());
+    assert!(part.iter().all(|pos| positions.contains(pos)));
+
+    assert_eq!(crate::mapgen::scatter(region, 0.0, 42).count(), 0);
+    assert_eq!(crate::mapgen::scatter(region, 1.0, 42).count(), 8 * 4096);
+}
+
+async fn edit_aux_file(dir: &std::path::Path) -> std::io::Result<()> {
+    std::fs::write(
+        dir.join("mod.conf"),
+        "# A comment\nname = test\ndescription = \"\"\"\nfirst\nsecond\n\"\"\"\nEnvArgsEnd\n",
+    )?;
+    let world = World::open(dir);
+    let mut file = world.aux_file("mod.conf").await.unwrap();
+    assert_eq!(file.get("name"), Some("test"));
+    assert_eq!(file.get("description"), Some("first\nsecond"));
+    assert!(file.set("bad=key", "value").is_err());
+    file.set("name", "renamed")?;
+    file.set("depends", "default")?;
+    assert_eq!(file.remove("description").as_deref(), Some("first\nsecond"));
+    file.set("title", "multi\nline")?;
+    file.save().await?;
+    assert_eq!(
+        std::fs::read_to_string(dir.join("mod.conf"))?,
+        "# A comment\nname = renamed\nEnvArgsEnd\ndepends = default\ntitle = \"\"\"\nmulti\nline\n\"\"\"\n"
+    );
+    let reread = world.aux_file("mod.conf").await.unwrap();
+    assert_eq!(reread, file);
+
+    let missing = world.aux_file("missing.txt").await.unwrap();
+    assert_eq!(missing.entries().count(), 0);
+    Ok(())
+}
+
+#[async_std::test]
+async fn aux_file() {
+    let dir = std::path::Path::new("TestWorld aux file");
+    std::fs::create_dir(dir).unwrap();
+    let result = edit_aux_file(dir).await;
+    std::fs::remove_dir_all(dir).unwrap();
+    result.unwrap();
+}
+
+#[test]
+fn content_interner() {
+    let block =
+        MapBlock::from_data(std::fs::File::open("TestWorld/testmapblock").unwrap()).unwrap();
+    let interner = crate::interner::ContentInterner::new();
+    let nodes: Vec<_> = block
+        .nodes()
+        .map(|node| node.to_shared_node(&interner))
+        .collect();
+    assert_eq!(interner.len(), block.palette().count());
+    let first = &nodes[0];
+    let same = nodes[1..]
+        .iter()
+        .find(|node| node.param0 == first.param0)
+        .unwrap();
+    assert!(std::sync::Arc::ptr_eq(&first.param0, &same.param0));
+    for (shared, node) in nodes.iter().zip(block.nodes()) {
+        assert_eq!(shared.to_node(), node.to_node());
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_roundtrip() {
+    let block =
+        MapBlock::from_data(std::fs::File::open("TestWorld/testmapblock").unwrap()).unwrap();
+    let json = serde_json::to_string(&block).unwrap();
+    let decoded: MapBlock = serde_json::from_str(&json).unwrap();
+    assert!(block.diff(&decoded).is_empty());
+
+    let pos = BlockPos::from_index_vec(I16Vec3::new(-13, -8, 2));
+    assert_eq!(serde_json::to_string(&pos).unwrap(), "[-13,-8,2]");
+    assert_eq!(serde_json::from_str::<BlockPos>("[-13,-8,2]").unwrap(), pos);
+    assert!(serde_json::from_str::<BlockPos>("[5000,0,0]").is_err());
+    assert!(serde_json::from_str::<NodePos>("[15,0,3]").is_ok());
+    assert!(serde_json::from_str::<NodePos>("[16,0,3]").is_err());
+
+    let node = block.get_node_at(NodePos::try_from(U16Vec3::new(1, 2, 3)).unwrap());
+    let json = serde_json::to_string(&node).unwrap();
+    assert_eq!(serde_json::from_str::<crate::Node>(&json).unwrap(), node);
+}
+
+#[cfg(all(feature = "json", feature = "cbor"))]
+#[test]
+fn json_cbor_conversion() {
+    use crate::map_block::MapBlockError;
+
+    let mut block =
+        MapBlock::from_data(std::fs::File::open("TestWorld/testmapblock").unwrap()).unwrap();
+    // Metadata that is not valid UTF-8 survives as well
+    block.node_metadata.push(crate::map_block::NodeMetadata {
+        position: NodePos::try_from(U16Vec3::new(1, 2, 3)).unwrap(),
+        vars: vec![crate::map_block::NodeVar {
+            key: b"infotext".to_vec(),
+            value: vec![0xff, b'!'],
+            is_private: false,
+        }],
+        inventory: vec![],
+    });
+    let cbor = block.to_cbor().unwrap();
+    assert!(MapBlock::from_cbor(cbor.as_slice())
+        .unwrap()
+        .diff(&block)
+        .is_empty());
+    let json = block.to_json().unwrap();
+    assert!(cbor.len() < json.len());
+    let reread = MapBlock::from_json(&json).unwrap();
+    assert!(reread.diff(&block).is_empty());
+    assert_eq!(reread.node_metadata, block.node_metadata);
+    assert_eq!(
+        MapBlock::from_cbor(cbor.as_slice()).unwrap().node_metadata,
+        block.node_metadata
+    );
+
+    // Content names and metadata are strings, and each row of nodes is a line
+    assert!(json.contains("\"air\""));
+    assert!(json.lines().count() < 4096);
+
+    let mut invalid = block.clone();
+    invalid.map_format_version = 27;
+    assert!(matches!(
+        MapBlock::from_json(&invalid.to_json().unwrap()),
+        Err(MapBlockError::MapVersionError(27))
+    ));
+    invalid.map_format_version = 29;
+    invalid.params_width = 1;
+    assert!(matches!(
+        MapBlock::from_json(&invalid.to_json().unwrap()),
+        Err(MapBlockError::BlobMalformed(_))
+    ));
+    block.param0[0] = 4000;
+    assert!(matches!(
+        MapBlock::from_json(&block.to_json().unwrap()),
+        Err(MapBlockError::BlobMalformed(_))
+    ));
+    assert!(matches!(
+        MapBlock::from_json("{}"),
+        Err(MapBlockError::JsonError(_))
+    ));
+}
+
+#[test]
+fn sponge_schematic() {
+    use crate::edit_plan::Schematic;
+    use crate::sponge::{self, SpongeError};
+    use crate::Node;
+    use std::collections::HashMap;
+
+    let node = |content: &str| Node {
+        param0: content.as_bytes().to_vec(),
+        param1: 0,
+        param2: 0,
+    };
+    let mut schematic = Schematic::new(U16Vec3::new(3, 2, 200));
+    schematic.set_node(U16Vec3::new(0, 0, 0), Some(node("default:stone")));
+    schematic.set_node(U16Vec3::new(2, 1, 199), Some(node("default:wood")));
+    let to_state = HashMap::from([
+        (b"default:stone".to_vec(), "minecraft:stone".to_string()),
+        (b"default:wood".to_vec(), "minecraft:oak_planks".to_string()),
+    ]);
+    let mut schem = vec![];
+    sponge::write(&schematic, &to_state, &mut schem).unwrap();
+
+    let to_node = HashMap::from([
+        ("minecraft:stone".to_string(), Some(node("default:stone"))),
+        (
+            "minecraft:oak_planks".to_string(),
+            Some(node("default:wood")),
+        ),
+        ("minecraft:air".to_string(), None),
+    ]);
+    let read = sponge::read(schem.as_slice(), &to_node).unwrap();
+    assert_eq!(read.size(), schematic.size());
+    for (x, y, z) in [(0, 0, 0), (2, 1, 199), (1, 1, 100)] {
+        let pos = U16Vec3::new(x, y, z);
+        assert_eq!(read.get_node(pos), schematic.get_node(pos));
+    }
+
+    // Properties are ignored if only the block itself is mapped
+    let mut to_node = to_node;
+    to_node.remove("minecraft:air");
+    assert!(matches!(
+        sponge::read(schem.as_slice(), &to_node),
+        Err(SpongeError::Unmapped(state)) if state == "minecraft:air"
+    ));
+    let mut slab = Schematic::new(U16Vec3::ONE);
+    slab.set_node(U16Vec3::ZERO, Some(node("stairs:slab_wood")));
+    let to_state = HashMap::from([(
+        b"stairs:slab_wood".to_vec(),
+        "minecraft:oak_slab[type=bottom]".to_string(),
+    )]);
+    let mut schem = vec![];
+    sponge::write(&slab, &to_state, &mut schem).unwrap();
+    let to_node = HashMap::from([(
+        "minecraft:oak_slab".to_string(),
+        Some(node("stairs:slab_wood")),
+    )]);
+    let read = sponge::read(schem.as_slice(), &to_node).unwrap();
+    assert_eq!(read.get_node(U16Vec3::ZERO), slab.get_node(U16Vec3::ZERO));
+}
+
+#[cfg(feature = "render")]
+fn column_contains(data: &[u8], x: u16, z: u16, content: &[u8]) -> bool {
+    let block = MapBlock::from_data(data).unwrap();
+    (0..16).any(|y| {
+        block
+            .get_node_at(NodePos::try_from(U16Vec3::new(x, y, z)).unwrap())
+            .param0
+            == content
+    })
+}
+
+#[cfg(feature = "render")]
+#[async_std::test]
+async fn render_top_down() {
+    use crate::render::{self, ColorMap};
+
+    let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+        .await
+        .unwrap();
+    let pos = BlockPos::from_index_vec(I16Vec3::new(-13, -8, 2));
+    let min = pos.into_index_vec() << NODE_BITS_1D;
+    let max = min + I16Vec3::splat(15);
+    let content = map
+        .get_palette(pos)
+        .await
+        .unwrap()
+        .into_values()
+        .find(|content| content != b"air")
+        .unwrap();
+    let mut colors = ColorMap::new();
+    colors.insert(&content, [100, 100, 100, 255]);
+    let image = render::render_top_down(&map, min, max, &colors)
+        .await
+        .unwrap();
+    assert_eq!((image.width(), image.height()), (16, 16));
+
+    let data = map.get_block_data(pos).await.unwrap();
+    for x in 0..16 {
+        for z in 0..16 {
+            let expected = if column_contains(&data, x, z, &content) {
+                [100, 100, 100, 255]
+            } else {
+                [0; 4]
+            };
+            assert_eq!(image.pixel(x.into(), (15 - z).into()), expected);
+        }
+    }
+    assert!((0..16).any(|x| (0..16).any(|y| image.pixel(x, y)[3] == 255)));
+    assert!(image.to_png().unwrap().starts_with(b"\x89PNG"));
+}
+
+#[async_std::test]
+async fn heightmap() {
+    let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+        .await
+        .unwrap();
+    let (min, max) = (glam::I16Vec2::new(-210, 30), glam::I16Vec2::new(-190, 40));
+    let heightmap = map
+        .heightmap(max, min, |content| content != b"air")
+        .await
+        .unwrap();
+    assert_eq!((heightmap.min(), heightmap.max()), (min, max));
+
+    let mut expected = std::collections::HashMap::new();
+    let positions: Vec<_> = map
+        .all_mapblock_positions()
+        .await
+        .try_collect()
+        .await
+        .unwrap();
+    let columns = BlockArea::new(
+        BlockPos::from_index_vec(I16Vec3::new(-14, -2048, 1)),
+        BlockPos::from_index_vec(I16Vec3::new(-12, 2047, 3)),
+    );
+    for pos in positions.into_iter().filter(|pos| columns.contains(*pos)) {
+        map.visit_mapblock_nodes(pos, |world_pos, node| {
+            let column = glam::I16Vec2::new(world_pos.x, world_pos.z);
+            if node.content != b"air" && column.cmpge(min).all() && column.cmple(max).all() {
+                let height = expected.entry(column).or_insert(world_pos.y);
+                *height = (*height).max(world_pos.y);
+            }
+        })
+        .await
+        .unwrap();
+    }
+    assert!(!expected.is_empty());
+    for (column, height) in heightmap.iter() {
+        assert_eq!(height, expected.get(&column).copied());
+        assert_eq!(heightmap.get(column.x, column.y), height);
+    }
+    assert_eq!(heightmap.iter().count(), 21 * 11);
+    assert_eq!(heightmap.get(0, 0), None);
+}
+
+#[async_std::test]
+async fn surface() {
+    let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+        .await
+        .unwrap();
+    let (min, max) = (glam::I16Vec2::new(-200, 30), glam::I16Vec2::new(-190, 35));
+    let is_solid = |content: &[u8]| content != b"air";
+    let surface = map.surface(min, max, is_solid).await.unwrap();
+    let heightmap = map.heightmap(min, max, is_solid).await.unwrap();
+    let (x, z) = (-195, 33);
+    let column = map.surface_at(x, z, is_solid).await.unwrap();
+    assert_eq!(column.as_ref(), surface.get(x, z));
+    let vm = MapEdit::new(map);
+    let mut found = 0;
+    for (column, top) in surface.iter() {
+        assert_eq!(top.map(|(y, _)| *y), heightmap.get(column.x, column.y));
+        let Some((y, node)) = top else {
+            continue;
+        };
+        found += 1;
+        let pos = I16Vec3::new(column.x, *y, column.y);
+        assert_eq!(&vm.get_node(pos).await.unwrap(), node);
+    }
+    assert!(found > 0);
+}
+
+#[async_std::test]
+async fn generated_bounds() {
+    let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+        .await
+        .unwrap();
+    let bounds = map.generated_bounds().await.unwrap().unwrap();
+    let positions: Vec<_> = map
+        .all_mapblock_positions()
+        .await
+        .try_collect()
+        .await
+        .unwrap();
+    assert!(positions.iter().all(|pos| bounds.contains(*pos)));
+    let indices = || positions.iter().map(|pos| pos.into_index_vec());
+    assert_eq!(
+        bounds.min().into_index_vec(),
+        indices().fold(I16Vec3::MAX, I16Vec3::min)
+    );
+    assert_eq!(
+        bounds.max().into_index_vec(),
+        indices().fold(I16Vec3::MIN, I16Vec3::max)
+    );
+
+    let dir = std::path::Path::new("TestWorld empty bounds");
+    std::fs::create_dir(dir).unwrap();
+    let empty = MapData::from_sqlite_file(dir.join("map.sqlite"), false)
+        .await
+        .unwrap();
+    let result = empty.generated_bounds().await;
+    std::fs::remove_dir_all(dir).unwrap();
+    assert_eq!(result.unwrap(), None);
+}
+
+#[async_std::test]
+async fn content_histogram() {
+    let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+        .await
+        .unwrap();
+    let pos = BlockPos::from_index_vec(I16Vec3::new(-12, -2, 4));
+    let histogram = map
+        .content_histogram(Some(BlockArea::new(pos, pos)))
+        .await
+        .unwrap();
+    let mut expected = std::collections::HashMap::new();
+    map.visit_mapblock_nodes(pos, |_, node| {
+        *expected.entry(node.content.to_vec()).or_insert(0) += 1;
+    })
+    .await
+    .unwrap();
+    assert_eq!(histogram, expected);
+    assert!(histogram.contains_key(&b"default:dirt"[..]));
+
+    let block_count = map.all_mapblock_positions().await.count().await as u64;
+    let histogram = map.content_histogram(None).await.unwrap();
+    assert_eq!(histogram.values().sum::<u64>(), block_count * 4096);
+}
+
+#[async_std::test]
+async fn read_region() {
+    let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+        .await
+        .unwrap();
+    let region = BlockArea::new(
+        BlockPos::from_index_vec(I16Vec3::new(-13, -2, 1)),
+        BlockPos::from_index_vec(I16Vec3::new(-11, -1, 4)),
+    );
+    let mut fetched: Vec<_> = map
+        .get_region_data(region)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(pos, _)| BlockKey::from(pos))
+        .collect();
+    fetched.sort_unstable();
+    let mut expected: Vec<_> = map
+        .all_mapblock_positions()
+        .await
+        .try_filter(|pos| future::ready(region.contains(*pos)))
+        .map_ok(BlockKey::from)
+        .try_collect()
+        .await
+        .unwrap();
+    expected.sort_unstable();
+    assert!(!expected.is_empty());
+    assert_eq!(fetched, expected);
+
+    let (min, max) = (I16Vec3::new(-200, -30, 20), I16Vec3::new(-180, -10, 70));
+    let preloaded = MapEdit::new(map);
+    preloaded.read_region(max, min).await.unwrap();
+    assert!(preloaded.is_in_cache(min) && preloaded.is_in_cache(max));
+    let vm = MapEdit::new(
+        MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+            .await
+            .unwrap(),
+    );
+    for pos in [min, max, I16Vec3::new(-185, -15, 65)] {
+        assert_eq!(
+            preloaded.get_node(pos).await.unwrap(),
+            vm.get_node(pos).await.unwrap()
+        );
+    }
+}
+
+#[async_std::test]
+async fn modified_nodes() {
+    let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+        .await
+        .unwrap();
+    let vm = MapEdit::new(map);
+    let changed = [I16Vec3::new(-185, -15, 65), I16Vec3::new(-184, -15, 65)];
+    let unchanged = I16Vec3::new(-150, -15, 65);
+    for pos in changed {
+        vm.set_content(pos, b"default:mese").await.unwrap();
+    }
+    let node = vm.get_node(unchanged).await.unwrap();
+    vm.set_node(unchanged, node).await.unwrap();
+
+    let blocks = vm.modified_blocks().await;
+    assert_eq!(blocks.len(), 2);
+    assert!(blocks.contains(&unchanged.split().0));
+    let changes = vm.changes().await.unwrap();
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].0, changed[0].split().0);
+    let modified: Vec<_> = vm
+        .modified_nodes()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(pos, _)| pos)
+        .collect();
+    assert_eq!(modified, changed);
+}
+
+#[async_std::test]
+async fn find_nodes() {
+    let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+        .await
+        .unwrap();
+    let (min, max) = (I16Vec3::new(-190, -20, 60), I16Vec3::new(-180, -10, 70));
+    let contents = [&b"default:stone"[..], b"default:dirt"];
+    let mut found: Vec<_> = map
+        .find_nodes(contents, max, min)
+        .await
+        .try_collect()
+        .await
+        .unwrap();
+    found.sort_unstable_by_key(|(pos, _)| (pos.x, pos.y, pos.z));
+
+    let mut expected = Vec::new();
+    let blocks = BlockArea::new(
+        BlockPos::from_index_vec(min >> NODE_BITS_1D),
+        BlockPos::from_index_vec(max >> NODE_BITS_1D),
+    );
+    for pos in blocks.iter() {
+        let result = map
+            .visit_mapblock_nodes(pos, |world_pos, node| {
+                if contents.contains(&node.content)
+                    && world_pos.cmpge(min).all()
+                    && world_pos.cmple(max).all()
+                {
+                    expected.push((world_pos, node.to_node()));
+                }
+            })
+            .await;
+        match result {
+            Ok(()) | Err(MapDataError::MapBlockNonexistent(_)) => {}
+            Err(e) => panic!("{e}"),
+        }
+    }
+    expected.sort_unstable_by_key(|(pos, _)| (pos.x, pos.y, pos.z));
+    assert!(!expected.is_empty());
+    assert_eq!(found, expected);
+
+    let nothing = map
+        .find_nodes(["default:nonexistent"], min, max)
+        .await
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    assert!(nothing.is_empty());
+}
+
+#[cfg(feature = "render")]
+#[async_std::test]
+async fn render_slice() {
+    use crate::render::{self, ColorMap, SlicePlane};
+
+    let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+        .await
+        .unwrap();
+    let pos = BlockPos::from_index_vec(I16Vec3::new(-13, -8, 2));
+    let origin = pos.into_index_vec() << NODE_BITS_1D;
+    let mut colors = ColorMap::new();
+    let mut expected = vec![];
+    map.visit_mapblock_nodes(pos, |world_pos, node| {
+        if world_pos.z == origin.z + 5 {
+            if node.content != b"air" {
+                colors.insert(node.content, [1, 2, 3, 255]);
+            }
+            expected.push((world_pos, node.content != b"air"));
+        }
+    })
+    .await
+    .unwrap();
+    let image = render::render_slice(
+        &map,
+        SlicePlane::Z(origin.z + 5),
+        glam::I16Vec2::new(origin.x, origin.y),
+        glam::I16Vec2::new(origin.x + 15, origin.y + 15),
+        &colors,
+    )
+    .await
+    .unwrap();
+    assert_eq!((image.width(), image.height()), (16, 16));
+    assert_eq!(expected.len(), 256);
+    for (world_pos, visible) in expected {
+        let pixel = image.pixel(
+            (world_pos.x - origin.x) as u32,
+            (origin.y + 15 - world_pos.y) as u32,
+        );
+        assert_eq!(pixel, if visible { [1, 2, 3, 255] } else { [0; 4] });
+    }
+}
+
+#[cfg(feature = "render")]
+#[test]
+fn color_map_parse() {
+    use crate::render::{ColorMap, ColorMapError};
+
+    let mut colors = ColorMap::parse(
+        "# minetestmapper style\n\ndefault:stone 128 128 128\n  default:water_source\t39 66 106 128 224\ndefault:glass 1 2 3 4\n",
+    )
+    .unwrap();
+    assert_eq!(colors.get(b"default:stone"), Some([128, 128, 128, 255]));
+    assert_eq!(
+        colors.get(b"default:water_source"),
+        Some([39, 66, 106, 128])
+    );
+    assert_eq!(colors.get(b"default:dirt"), None);
+
+    let mut overrides = ColorMap::parse("default:stone 100 100 100").unwrap();
+    overrides.set_fallback(Some([255, 0, 255, 255]));
+    colors.merge(&overrides);
+    assert_eq!(colors.get(b"default:stone"), Some([100, 100, 100, 255]));
+    assert_eq!(colors.get(b"default:glass"), Some([1, 2, 3, 4]));
+    assert_eq!(colors.get(b"default:dirt"), Some([255, 0, 255, 255]));
+    assert_eq!(colors.get(b"air"), None);
+    // Merging a table without fallback keeps the current one
+    colors.merge(&ColorMap::new());
+    assert_eq!(colors.fallback(), Some([255, 0, 255, 255]));
+
+    assert!(matches!(
+        ColorMap::parse("default:stone 1 2 3\ndefault:dirt 1 2"),
+        Err(ColorMapError::Syntax(2, _))
+    ));
+    assert!(matches!(
+        ColorMap::parse("default:dirt 1 2 256"),
+        Err(ColorMapError::Syntax(1, _))
+    ));
+}
+
+#[test]
+fn calc_lighting() {
+    use crate::lighting::{calc_lighting, LIGHT_SUN};
+    use crate::node_def::{NodeDef, ParamType};
+    use crate::voxel_manip::VoxelData;
+
+    let defs = std::collections::HashMap::from([(
+        b"default:torch".to_vec(),
+        NodeDef {
+            paramtype: ParamType::Light,
+            light_source: 13,
+            ..Default::default()
+        },
+    )]);
+    let mut data = VoxelData::new(I16Vec3::ZERO, I16Vec3::new(4, 6, 4));
+    let air = data.get_or_create_content_id(b"air");
+    data.content.fill(air);
+    let roof = data.index(I16Vec3::new(2, 5, 2)).unwrap();
+    data.content[roof] = data.get_or_create_content_id(b"default:stone");
+    let torch = data.index(I16Vec3::new(1, 1, 1)).unwrap();
+    data.content[torch] = data.get_or_create_content_id(b"default:torch");
+    let light = |data: &VoxelData, x, y, z| {
+        let param1 = data.param1[data.index(I16Vec3::new(x, y, z)).unwrap()];
+        (param1 & 0x0f, param1 >> 4)
+    };
+    let (a, b) = (I16Vec3::ONE, I16Vec3::new(3, 5, 3));
+
+    // Without sunlight above the box, only the torch shines
+    calc_lighting(&mut data, a, b, &defs);
+    assert_eq!(light(&data, 1, 1, 1), (13, 13));
+    assert_eq!(light(&data, 2, 1, 1), (12, 12));
+    assert_eq!(light(&data, 3, 3, 3), (7, 7));
+    assert_eq!(light(&data, 2, 5, 2), (0, 0));
+
+    // Sunlight falls down, and spreads into the shadow of the stone
+    for x in 0..=4 {
+        for z in 0..=4 {
+            let above = data.index(I16Vec3::new(x, 6, z)).unwrap();
+            data.param1[above] = LIGHT_SUN;
+        }
+    }
+    calc_lighting(&mut data, a, b, &defs);
+    assert_eq!(light(&data, 1, 3, 1), (LIGHT_SUN, 11));
+    assert_eq!(light(&data, 2, 4, 2), (LIGHT_SUN - 1, 8));
+    assert_eq!(light(&data, 2, 1, 2), (LIGHT_SUN - 1, 11));
+    assert_eq!(light(&data, 1, 1, 1), (LIGHT_SUN - 1, 13));
+    assert_eq!(light(&data, 2, 5, 2), (0, 0));
+    // Nodes outside of the box are left alone
+    assert_eq!(light(&data, 0, 1, 1), (0, 0));
+}
+
+#[test]
+fn update_liquids() {
+    use crate::liquids::{update_liquids, Liquid};
+    use crate::voxel_manip::VoxelData;
+
+    let mut data = VoxelData::new(I16Vec3::ZERO, I16Vec3::new(6, 4, 6));
+    let stone = data.get_or_create_content_id(b"default:stone");
+    data.content.fill(stone);
+    let air = data.get_or_create_content_id(b"air");
+    for x in 1..=5 {
+        for z in 1..=5 {
+            for y in 2..=3 {
+                let index = data.index(I16Vec3::new(x, y, z)).unwrap();
+                data.content[index] = air;
+            }
+        }
+    }
+    let set = |data: &mut VoxelData, pos: I16Vec3, content: &[u8], param2: u8| {
+        let index = data.index(pos).unwrap();
+        data.content[index] = data.get_or_create_content_id(content);
+        data.param2[index] = param2;
+    };
+    // A hole in the floor, a source and some stale flowing water
+    set(&mut data, I16Vec3::new(3, 1, 3), b"air", 0);
+    set(&mut data, I16Vec3::new(1, 2, 3), b"default:water_source", 0);
+    set(
+        &mut data,
+        I16Vec3::new(5, 3, 5),
+        b"default:water_flowing",
+        7,
+    );
+
+    let water = Liquid::new(b"default:water_source", b"default:water_flowing");
+    update_liquids(&mut data, I16Vec3::ONE, I16Vec3::splat(5), &[water]);
+    let node = |x, y, z| data.get_node(I16Vec3::new(x, y, z)).unwrap();
+    let flowing = |param2| crate::Node {
+        param0: b"default:water_flowing".to_vec(),
+        param1: 0,
+        param2,
+    };
+    assert_eq!(node(2, 2, 3), flowing(7));
+    assert_eq!(node(3, 2, 3), flowing(6));
+    // The water falls into the hole
+    assert_eq!(node(3, 1, 3), flowing(0x0f));
+    // It does not flow upwards, and stale water dries up
+    assert_eq!(node(1, 3, 3).param0, b"air");
+    assert_eq!(node(5, 3, 5).param0, b"air");
+}
+
+#[test]
+fn param2_helpers() {
+    use crate::node_def::ParamType2;
+    use crate::param2::{self, FaceDir, FourDir, WallMounted};
+
+    for value in 0..24 {
+        let facedir = FaceDir::from_param2(value).unwrap();
+        assert_eq!(facedir.to_param2(), value);
+        assert_eq!(
+            FaceDir::from_dirs(facedir.top(), facedir.front()),
+            Some(facedir)
+        );
+        // The palette index is ignored
+        assert_eq!(FaceDir::from_param2(value | 0xe0), Some(facedir));
+    }
+    assert_eq!(FaceDir::from_param2(24), None);
+    assert_eq!(FaceDir::from_dirs(I16Vec3::Y, I16Vec3::Y), None);
+    assert_eq!(FourDir::from_param2(0xf5).front(), I16Vec3::X);
+
+    for value in 0..8 {
+        assert_eq!(WallMounted::from_param2(value).to_param2(), value);
+    }
+    assert_eq!(WallMounted::from_param2(7).dir(), I16Vec3::NEG_Y);
+    assert_eq!(WallMounted::from_dir(I16Vec3::Z), Some(WallMounted::PosZ));
+
+    assert_eq!(param2::degrotate(ParamType2::DegRotate, 60), Some(90.0));
+    assert_eq!(param2::degrotate(ParamType2::DegRotate, 240), None);
+    assert_eq!(param2::with_degrotate(ParamType2::DegRotate, 0, -90.0), 180);
+    assert_eq!(
+        param2::with_degrotate(ParamType2::ColorDegRotate, 0xe0, 45.0),
+        0xe3
+    );
+    assert_eq!(param2::palette_index(ParamType2::Color, 200), Some(200));
+    assert_eq!(
+        param2::with_palette_index(ParamType2::ColorFourDir, 0xff, 0),
+        0x03
+    );
+    assert_eq!(param2::leveled(0xff), 127);
+    assert_eq!(param2::with_leveled(0x80, 200), 0xff);
+}
+
+#[test]
+fn line() {
+    use crate::shapes::line;
+
+    for (a, b) in [
+        (I16Vec3::ZERO, I16Vec3::ZERO),
+        (I16Vec3::new(3, -7, 2), I16Vec3::new(-5, 4, 9)),
+        (I16Vec3::new(0, 0, 0), I16Vec3::new(6, 6, -6)),
+        (I16Vec3::new(-20, 1, 1), I16Vec3::new(20, 1, 1)),
+    ] {
+        let nodes: Vec<_> = line(a, b).collect();
+        assert_eq!(nodes.first(), Some(&a));
+        assert_eq!(nodes.last(), Some(&b));
+        let length = (b - a).abs().max_element();
+        assert_eq!(nodes.len(), length as usize + 1);
+        for step in nodes.windows(2) {
+            assert_eq!((step[1] - step[0]).abs().max_element(), 1);
+        }
+    }
+}
+
+#[async_std::test]
+async fn flood_fill() {
+    use crate::voxel_manip::FillLimits;
+
+    let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+        .await
+        .unwrap();
+    let vm = MapEdit::new(map);
+    let (a, b) = (I16Vec3::new(-190, -20, 60), I16Vec3::new(-180, -10, 70));
+    let start = I16Vec3::new(-185, -15, 65);
+    let content = vm.get_node(start).await.unwrap().param0;
+    let limits = FillLimits {
+        max_nodes: usize::MAX,
+        bounds: Some((a, b)),
+    };
+    let outcome = vm
+        .flood_fill(
+            start,
+            |node| node.param0 == content,
+            b"default:mese",
+            limits,
+        )
+        .await
+        .unwrap();
+    assert!(outcome.filled > 0);
+    assert!(!outcome.truncated);
+    assert_eq!(vm.get_node(start).await.unwrap().param0, b"default:mese");
+
+    // No node of the replaced content is left next to a filled one
+    let data = vm.get_data(a, b).await.unwrap();
+    let mese = data.content_id(b"default:mese").unwrap();
+    let mut filled = 0;
+    for index in 0..data.volume() {
+        let pos = data.position(index);
+        match data.content_id(&content) {
+            Some(id) if data.content[index] == id => {
+                for dir in [I16Vec3::X, I16Vec3::Y, I16Vec3::Z] {
+                    for neighbor in [pos + dir, pos - dir] {
+                        if let Some(neighbor) = data.index(neighbor) {
+                            assert_ne!(data.content[neighbor], mese);
+                        }
+                    }
+                }
+            }
+            _ => filled += usize::from(data.content[index] == mese),
+        }
+    }
+    assert_eq!(filled, outcome.filled);
+
+    let vm = MapEdit::new(
+        MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+            .await
+            .unwrap(),
+    );
+    let limits = FillLimits {
+        max_nodes: 1,
+        bounds: None,
+    };
+    let outcome = vm
+        .flood_fill(
+            start,
+            |node| node.param0 == content,
+            b"default:mese",
+            limits,
+        )
+        .await
+        .unwrap();
+    assert_eq!(outcome.filled, 1);
+    assert!(outcome.truncated);
+}
+
+#[async_std::test]
+async fn brushes() {
+    use crate::shapes::{Axis, Brush, Shape};
+
+    let sphere = Shape::sphere(I16Vec3::ZERO, 2.0);
+    assert_eq!(
+        sphere.bounds(),
+        Some((I16Vec3::splat(-2), I16Vec3::splat(2)))
+    );
+    assert!(sphere.contains(I16Vec3::new(2, 0, 0)));
+    assert!(!sphere.contains(I16Vec3::new(2, 2, 0)));
+    let box_shell = Brush::hollow(Shape::cuboid(I16Vec3::ZERO, I16Vec3::splat(4)), 1);
+    assert!(box_shell.contains(I16Vec3::new(0, 2, 2)));
+    assert!(!box_shell.contains(I16Vec3::splat(2)));
+
+    let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+        .await
+        .unwrap();
+    let vm = MapEdit::new(map);
+    let brushes = [
+        Brush::solid(Shape::sphere(I16Vec3::new(-185, -15, 65), 20.0)),
+        Brush::hollow(
+            Shape::Cylinder {
+                base: I16Vec3::new(-200, -15, 65),
+                axis: Axis::X,
+                length: 30,
+                radius: 4.0,
+            },
+            1,
+        ),
+        Brush::solid(Shape::Ellipsoid {
+            center: I16Vec3::new(-150, 0, 40),
+            radii: glam::Vec3::new(3.0, 10.0, 1.5),
+        }),
+    ];
+    for (brush, content) in brushes.iter().zip(["test:sphere", "test:tube", "test:egg"]) {
+        let content = content.as_bytes();
+        vm.fill_brush(brush, content).await.unwrap();
+        let (min, max) = brush.bounds().unwrap();
+        let data = vm.get_data(min, max).await.unwrap();
+        let id = data.content_id(content).unwrap();
+        for index in 0..data.volume() {
+            let pos = data.position(index);
+            assert_eq!(data.content[index] == id, brush.contains(pos), "{pos}");
+        }
+    }
+}
+
+#[async_std::test]
+async fn find_path() {
+    use crate::pathfinding::find_path;
+
+    let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true)
+        .await
+        .unwrap();
+    let vm = MapEdit::new(map);
+    vm.fill_region(
+        I16Vec3::new(-190, -20, 60),
+        I16Vec3::new(-180, -10, 70),
+        b"default:stone",
+    )
+    .await
+    .unwrap();
+    // A U-shaped corridor
+    for (a, b) in [
+        (I16Vec3::new(-189, -15, 62), I16Vec3::new(-181, -15, 62)),
+        (I16Vec3::new(-181, -15, 62), I16Vec3::new(-181, -15, 68)),
+        (I16Vec3::new(-181, -15, 68), I16Vec3::new(-189, -15, 68)),
+    ] {
+        vm.fill_region(a, b, b"air").await.unwrap();
+    }
+    let cost = |node: &crate::Node| (node.param0 == b"air").then_some(1);
+    let (start, goal) = (I16Vec3::new(-189, -15, 62), I16Vec3::new(-189, -15, 68));
+    let path = find_path(&vm, start, goal, cost, 1000)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(path.len(), 23);
+    assert_eq!(path.first(), Some(&start));
+    assert_eq!(path.last(), Some(&goal));
+    for step in path.windows(2) {
+        let distance = (step[1] - step[0]).abs();
+        assert_eq!(distance.x + distance.y + distance.z, 1);
+    }
+
+    let walled_in = I16Vec3::new(-185, -15, 65);
+    assert!(find_path(&vm, start, walled_in, cost, 1000)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn inventory_with_invalid_utf8() {
+    use crate::inventory::InventoryList;
+    use crate::map_block::NodeMetadata;
+    use crate::scrub::{scrub_block, ScrubReport, ScrubRule};
+    let mut block = MapBlock::unloaded();
+    block.node_metadata.push(NodeMetadata {
+        position: NodePos::try_from(U16Vec3::new(1, 2, 3)).unwrap(),
+        vars: vec![],
+        inventory: vec![InventoryList {
+            name: "main".to_string(),
+            width: 1,
+            items: vec!["default:book 1 0 \"\\u0001text\\u0002caf@\\u0003\"".to_string()],
+        }],
+    });
+    // Replace the marker with a byte that is not valid UTF-8 on its own
+    let compressed = block.to_binary().unwrap();
+    let mut data = zstd::decode_all(&compressed[1..]).unwrap();
+    let marker = data.windows(4).position(|bytes| bytes == b"caf@").unwrap();
+    data[marker + 3] = 0xe9;
+    let mut patched = vec![compressed[0]];
+    patched.extend(zstd::encode_all(data.as_slice(), 0).unwrap());
+
+    let block = MapBlock::from_data(patched.as_slice()).unwrap();
+    let list = block.node_metadata[0].inventory_list("main").unwrap();
+    assert_eq!(
+        list.items,
+        ["default:book 1 0 \"\\u0001text\\u0002caf\\u00e9\\u0003\""]
+    );
+    let reread = MapBlock::from_data(block.to_binary().unwrap().as_slice()).unwrap();
+    assert_eq!(reread.node_metadata, block.node_metadata);
+
+    // Scrubbing cannot parse the item, so it is reported instead of skipped silently
+    let mut report = ScrubReport::default();
+    let rules = [ScrubRule::remove("text")];
+    assert!(scrub_block(&patched, &rules, &mut report)
+        .unwrap()
+        .is_none());
+    assert_eq!(report.unparseable_items, 1);
+}
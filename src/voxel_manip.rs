@@ -1,24 +1,43 @@
 //! Contains a type to more high-level world reading and writing
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::{collections::hash_map::Entry, sync::Arc};
 
 use async_std::sync::Mutex;
-use glam::I16Vec3;
+use glam::{I16Vec3, U16Vec3};
 
-use crate::positions::NodePos;
+use crate::edit_plan::{fill_block, NodeBox, Schematic};
+use crate::inventory::InventoryList;
+use crate::journal::{Journal, JournalEntry};
+use crate::lighting;
+use crate::liquids::{self, Liquid};
+use crate::map_block::{
+    BlockDelta, BlockFace, LightBank, MapBlockError, NodeMetadata, NodeTimer, WriteMaintenance,
+    CONTENT_IGNORE, CONTENT_UNKNOWN, SERIALIZE_VERSION_LATEST, SERIALIZE_VERSION_OLDEST,
+};
+use crate::node_def::{MirrorAxis, NodeDef, NodeDefProvider};
+use crate::positions::{BlockArea, BlockKey, NodePos};
+use crate::shapes::{self, Brush};
 use crate::{
     positions::{BlockPos, SplitPos},
     MapBlock, MapData, MapDataError, Node,
 };
 type Result<T> = std::result::Result<T, MapDataError>;
 
+#[derive(Clone)]
 struct BlockEdit {
-    mapblock: MapBlock,
+    /// Shared with [snapshots](`MapEdit::snapshot`), and copied when it is modified
+    mapblock: Arc<MapBlock>,
     tainted: bool,
+    /// False if the mapblock did not exist in the map and is a placeholder
+    generated: bool,
 }
 
 impl BlockEdit {
+    fn mapblock_mut(&mut self) -> &mut MapBlock {
+        Arc::make_mut(&mut self.mapblock)
+    }
+
     /// Get the node at the given world position
     pub fn get_node(&self, node_pos: NodePos) -> Node {
         self.mapblock.get_node_at(node_pos)
@@ -29,10 +48,11 @@ impl BlockEdit {
     /// ⚠️ The change will be present locally only. To modify the map,
     /// the change has to be written back via [`VoxelManip::commit`].
     pub fn set_node(&mut self, node_pos: NodePos, node: Node) {
-        let content_id = self.mapblock.get_or_create_content_id(&node.param0);
-        self.mapblock.set_content(node_pos, content_id);
-        self.mapblock.set_param1(node_pos, node.param1);
-        self.mapblock.set_param2(node_pos, node.param2);
+        let mapblock = self.mapblock_mut();
+        let content_id = mapblock.get_or_create_content_id(&node.param0);
+        mapblock.set_content(node_pos, content_id);
+        mapblock.set_param1(node_pos, node.param1);
+        mapblock.set_param2(node_pos, node.param2);
         self.tainted = true;
     }
 
@@ -49,8 +69,9 @@ impl BlockEdit {
     /// ⚠️ Until the change is [commited](`VoxelManip::commit`),
     /// the node will only be changed in the cache.
     pub fn set_content(&mut self, node_pos: NodePos, content: &[u8]) {
-        let content_id = self.mapblock.get_or_create_content_id(content);
-        self.mapblock.set_content(node_pos, content_id);
+        let mapblock = self.mapblock_mut();
+        let content_id = mapblock.get_or_create_content_id(content);
+        mapblock.set_content(node_pos, content_id);
         self.tainted = true;
     }
 
@@ -59,7 +80,7 @@ impl BlockEdit {
     /// ⚠️ Until the change is [commited](`VoxelManip::commit`),
     /// the node will only be changed in the cache.
     pub fn set_param1(&mut self, node_pos: NodePos, param1: u8) {
-        self.mapblock.set_param1(node_pos, param1);
+        self.mapblock_mut().set_param1(node_pos, param1);
         self.tainted = true;
     }
 
@@ -68,11 +89,128 @@ impl BlockEdit {
     /// ⚠️ Until the change is [commited](`VoxelManip::commit`),
     /// the node will only be changed in the cache.
     pub fn set_param2(&mut self, node_pos: NodePos, param2: u8) {
-        self.mapblock.set_param2(node_pos, param2);
+        self.mapblock_mut().set_param2(node_pos, param2);
         self.tainted = true;
     }
 }
 
+/// The state of the cached mapblocks of a [`MapEdit`] at some point, see [`MapEdit::snapshot`]
+///
+/// Snapshots share the mapblocks with the cache until either side modifies them,
+/// so taking one is cheap and only modified mapblocks take up extra memory.
+#[derive(Clone)]
+pub struct Snapshot {
+    blocks: HashMap<BlockPos, BlockEdit>,
+}
+
+impl Snapshot {
+    /// Returns the number of mapblocks this snapshot holds
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Returns true if the cache was empty when this snapshot was taken
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+}
+
+/// The nodes of a box as flat arrays, see [`MapEdit::get_data`]
+///
+/// Like the engine's `VoxelArea`, the nodes are ordered with x changing fastest,
+/// then y, then z. The content of each node is an index into `palette`,
+/// which is shared by the whole box.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VoxelData {
+    /// The corner of the box with the smallest coordinates
+    pub min: I16Vec3,
+    /// The corner of the box with the largest coordinates
+    pub max: I16Vec3,
+    /// The content names, indexed by the values of `content`
+    pub palette: Vec<Vec<u8>>,
+    /// The content ID of every node, see [`Node::param0`]
+    pub content: Vec<u16>,
+    /// The param1 of every node
+    pub param1: Vec<u8>,
+    /// The param2 of every node
+    pub param2: Vec<u8>,
+}
+
+impl VoxelData {
+    /// Creates the arrays for the box between `a` and `b`, with all nodes set to [`CONTENT_IGNORE`]
+    pub fn new(a: I16Vec3, b: I16Vec3) -> Self {
+        let (min, max) = (a.min(b), a.max(b));
+        let volume = (max.as_ivec3() - min.as_ivec3() + 1)
+            .as_uvec3()
+            .to_array()
+            .iter()
+            .map(|&length| length as usize)
+            .product();
+        VoxelData {
+            min,
+            max,
+            palette: vec![CONTENT_IGNORE.to_vec()],
+            content: vec![0; volume],
+            param1: vec![0; volume],
+            param2: vec![0; volume],
+        }
+    }
+
+    /// Returns the number of nodes in the box
+    pub fn volume(&self) -> usize {
+        self.content.len()
+    }
+
+    /// Returns the array index of the node at `pos`, or `None` if it is outside of the box
+    pub fn index(&self, pos: I16Vec3) -> Option<usize> {
+        if pos.cmplt(self.min).any() || pos.cmpgt(self.max).any() {
+            return None;
+        }
+        let offset = (pos.as_ivec3() - self.min.as_ivec3()).as_uvec3();
+        let size = (self.max.as_ivec3() - self.min.as_ivec3() + 1).as_uvec3();
+        Some(
+            (offset.z as usize * size.y as usize + offset.y as usize) * size.x as usize
+                + offset.x as usize,
+        )
+    }
+
+    /// Returns the world position of the node at array index `index`
+    pub fn position(&self, index: usize) -> I16Vec3 {
+        let size = (self.max.as_ivec3() - self.min.as_ivec3() + 1).as_uvec3();
+        let x = index % size.x as usize;
+        let y = index / size.x as usize % size.y as usize;
+        let z = index / size.x as usize / size.y as usize;
+        // Each offset is smaller than the size of the box, which fits into an i16
+        self.min + I16Vec3::new(x as i16, y as i16, z as i16)
+    }
+
+    /// Returns the content ID of `content`, if it is part of the palette
+    pub fn content_id(&self, content: &[u8]) -> Option<u16> {
+        self.palette
+            .iter()
+            .position(|name| name == content)
+            .map(|id| id as u16)
+    }
+
+    /// Returns the content ID of `content`, adding it to the palette if necessary
+    pub fn get_or_create_content_id(&mut self, content: &[u8]) -> u16 {
+        self.content_id(content).unwrap_or_else(|| {
+            self.palette.push(content.to_vec());
+            (self.palette.len() - 1) as u16
+        })
+    }
+
+    /// Returns the node at `pos`, or `None` if it is outside of the box
+    pub fn get_node(&self, pos: I16Vec3) -> Option<Node> {
+        let index = self.index(pos)?;
+        Some(Node {
+            param0: self.palette[self.content[index] as usize].clone(),
+            param1: self.param1[index],
+            param2: self.param2[index],
+        })
+    }
+}
+
 /// In-memory world data cache that allows easy handling of single nodes.
 ///
 /// It is an abstraction on top of the MapBlocks the world data consists of.
@@ -81,10 +219,78 @@ impl BlockEdit {
 /// All changes to the world have to be committed via [`VoxelManip::commit`].
 /// Before this, they are only present in VoxelManip's local cache and lost after drop.
 ///
+/// Reading and editing only needs a shared reference, so one instance can be driven
+/// from several tasks at once, e.g. to process parts of a region in parallel.
+/// Each mapblock is locked only while a single operation accesses it.
+///
 /// ⚠️ You want to do a world backup before modifying the map data.
 pub struct MapEdit {
     map: MapData,
-    mapblock_cache: HashMap<BlockPos, Arc<async_std::sync::Mutex<BlockEdit>>>,
+    cache: std::sync::Mutex<BlockCache>,
+    serialize_version: u8,
+    journal: Option<Journal>,
+    maintenance: WriteMaintenance,
+    node_defs: Option<Arc<dyn NodeDefProvider + Send + Sync>>,
+    commit_options: CommitOptions,
+    cache_limit: Option<usize>,
+}
+
+/// The mapblocks a [`MapEdit`] holds, shared between all tasks using it
+///
+/// It is only locked for short, non-blocking bookkeeping and never across an `.await`.
+#[derive(Default)]
+struct BlockCache {
+    blocks: HashMap<BlockPos, Arc<Mutex<BlockEdit>>>,
+    /// The value of `clock` when each cached mapblock was last accessed
+    last_used: HashMap<BlockPos, u64>,
+    clock: u64,
+}
+
+impl BlockCache {
+    fn touch(&mut self, pos: BlockPos) {
+        self.clock += 1;
+        self.last_used.insert(pos, self.clock);
+    }
+
+    fn entries(&self) -> Vec<(BlockPos, Arc<Mutex<BlockEdit>>)> {
+        let mut entries: Vec<_> = self
+            .blocks
+            .iter()
+            .map(|(&pos, entry)| (pos, entry.clone()))
+            .collect();
+        entries.sort_unstable_by_key(|&(pos, _)| BlockKey::from(pos));
+        entries
+    }
+}
+
+/// How [`MapEdit::commit`] deals with mapblocks that cannot be written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CommitMode {
+    /// All modified mapblocks are written in one transaction
+    ///
+    /// If one of them cannot be serialized or written, none of them is written.
+    /// Redis applies the writes atomically, but cannot roll them back.
+    #[default]
+    Atomic,
+    /// Every mapblock that can be written is written, even if others fail
+    ///
+    /// This salvages as much as possible, e.g. for backends without transactions.
+    BestEffort,
+}
+
+/// How [`MapEdit::commit`] writes the modified mapblocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CommitOptions {
+    /// What happens if some mapblocks cannot be written
+    pub mode: CommitMode,
+    /// Whether to make the engine recompute the light around the modified mapblocks
+    ///
+    /// The [`lighting_complete`](`MapBlock::lighting_complete`) flags of every modified
+    /// mapblock are cleared, and so are the flags of its neighbors for the side that
+    /// faces it. Neighbors that exist in the map are written along.
+    /// Unlike [`WriteMaintenance::invalidate_lighting`], this also catches light
+    /// that leaks into the neighbors, like shadows cast by new nodes.
+    pub invalidate_lighting: bool,
 }
 
 impl MapEdit {
@@ -92,68 +298,209 @@ impl MapEdit {
     pub fn new(map: MapData) -> Self {
         MapEdit {
             map,
-            mapblock_cache: HashMap::new(),
+            cache: Default::default(),
+            serialize_version: SERIALIZE_VERSION_LATEST,
+            journal: None,
+            maintenance: WriteMaintenance::default(),
+            node_defs: None,
+            commit_options: CommitOptions::default(),
+            cache_limit: None,
         }
     }
 
-    /// Return a cache entry containing the given mapblock
-    async fn get_mapblock(&mut self, mapblock_pos: BlockPos) -> Result<Arc<Mutex<BlockEdit>>> {
-        // if let Some(occupied) = self.mapblock_cache.get(&mapblock_pos) {
-        //     return Ok(occupied.lock());
-        // }
-        // {
-        //     let mapblock = match self.map.get_mapblock(mapblock_pos).await {
-        //         Ok(mapblock) => Ok(mapblock),
-        //         Err(MapDataError::MapBlockNonexistent(_)) => Ok(MapBlock::unloaded()),
-        //         Err(e) => Err(e),
-        //     }?;
-
-        //     let v = Arc::new(Mutex::new(BlockEdit {
-        //         mapblock,
-        //         tainted: false,
-        //     }));
-
-        //     self.mapblock_cache.insert(mapblock_pos, v);
-
-        //     todo!()
-        // }
-        //  Ok(self.mapblock_cache.get(&mapblock_pos).unwrap().lock())
-        let c = match self.mapblock_cache.entry(mapblock_pos) {
-            Entry::Occupied(e) => {
-                //
-                let block = e.get();
-                block.clone()
+    /// Sets the map format version that modified mapblocks are committed in
+    ///
+    /// See [`MapBlock::to_binary_version`] for the supported versions.
+    /// Other versions are rejected with [`MapBlockError::MapVersionError`].
+    pub fn set_serialize_version(&mut self, serialize_version: u8) -> Result<()> {
+        if !(SERIALIZE_VERSION_OLDEST..=SERIALIZE_VERSION_LATEST).contains(&serialize_version) {
+            return Err(MapBlockError::MapVersionError(serialize_version).into());
+        }
+        self.serialize_version = serialize_version;
+        Ok(())
+    }
+
+    /// Sets how modified mapblocks are updated when they are committed
+    ///
+    /// By default, their header is left as is.
+    /// See [`MapBlock::apply_maintenance`].
+    pub fn set_write_maintenance(&mut self, maintenance: WriteMaintenance) {
+        self.maintenance = maintenance;
+    }
+
+    /// Enables warnings about edits that the engine ignores
+    ///
+    /// With node definitions, setting a `param1` on a node without
+    /// [`ParamType::Light`](`crate::node_def::ParamType::Light`) or a `param2` on a node with
+    /// [`ParamType2::None`](`crate::node_def::ParamType2::None`) is logged as a warning.
+    /// Contents without a definition are not checked.
+    pub fn set_node_defs(&mut self, node_defs: Arc<dyn NodeDefProvider + Send + Sync>) {
+        self.node_defs = Some(node_defs);
+    }
+
+    /// Warns if the node definition of `content` ignores the given params
+    fn check_params(&self, node_pos: I16Vec3, content: &[u8], param1: u8, param2: u8) {
+        let Some(def) = self
+            .node_defs
+            .as_ref()
+            .and_then(|node_defs| node_defs.node_def(content))
+        else {
+            return;
+        };
+        let content = String::from_utf8_lossy(content);
+        if param1 != 0 && !def.uses_param1() {
+            log::warn!("param1 of {content} at {node_pos} is set, but its paramtype is not light");
+        }
+        if param2 != 0 && !def.uses_param2() {
+            log::warn!("param2 of {content} at {node_pos} is set, but its paramtype2 is none");
+        }
+    }
+
+    /// Protects commits with an intent log
+    ///
+    /// With a journal, [`commit`](`MapEdit::commit`) records all modified mapblocks
+    /// before writing them. If the commit gets interrupted, it is completed when the map
+    /// is opened next time, provided that the journal is the one of the map, see
+    /// [`World::journal`](`crate::World::journal`) and [`Journal::for_map_file`].
+    /// [`Journal::recover`] can complete or roll back the commit of any other journal.
+    pub fn set_journal(&mut self, journal: Journal) {
+        self.journal = Some(journal);
+    }
+
+    /// Sets whether [`commit`](`MapEdit::commit`) writes all modified mapblocks or none
+    ///
+    /// The default is [`CommitMode::Atomic`].
+    pub fn set_commit_mode(&mut self, commit_mode: CommitMode) {
+        self.commit_options.mode = commit_mode;
+    }
+
+    /// Sets how [`commit`](`MapEdit::commit`) writes the modified mapblocks
+    ///
+    /// This includes the [commit mode](`Self::set_commit_mode`).
+    pub fn set_commit_options(&mut self, options: CommitOptions) {
+        self.commit_options = options;
+    }
+
+    /// Limits the number of cached mapblocks, which is unlimited by default
+    ///
+    /// When a mapblock is loaded beyond `limit`, the least recently used quarter of the
+    /// cache is dropped. Modified mapblocks among them are [committed](`MapEdit::commit`)
+    /// first, so with a limit, changes can reach the map before `commit` is called.
+    /// If they cannot be written, they stay cached and the error is returned.
+    pub fn set_cache_limit(&mut self, limit: Option<usize>) {
+        self.cache_limit = limit;
+    }
+
+    /// Locks the cache bookkeeping
+    ///
+    /// The guard must not be held across an `.await`.
+    fn cache(&self) -> std::sync::MutexGuard<'_, BlockCache> {
+        // The bookkeeping stays consistent even if a holder panicked
+        self.cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Drops the least recently used mapblocks from the cache, writing the modified ones
+    ///
+    /// Mapblocks that are currently borrowed by an operation are kept.
+    async fn shrink_cache(&self, limit: usize) -> Result<()> {
+        let victims: Vec<_> = {
+            let cache = self.cache();
+            let excess = cache.blocks.len().saturating_sub(limit - limit / 4);
+            let mut victims: Vec<_> = cache
+                .blocks
+                .iter()
+                .filter(|(_, entry)| Arc::strong_count(entry) == 1)
+                .map(|(pos, entry)| {
+                    let last_used = cache.last_used.get(pos).copied().unwrap_or_default();
+                    (last_used, *pos, entry.clone())
+                })
+                .collect();
+            victims.sort_unstable_by_key(|&(last_used, pos, _)| (last_used, BlockKey::from(pos)));
+            victims.truncate(excess);
+            victims
+                .into_iter()
+                .map(|(_, pos, entry)| (pos, entry))
+                .collect()
+        };
+
+        let mut dirty = vec![];
+        for (pos, entry) in &victims {
+            if entry.lock().await.tainted {
+                dirty.push(*pos);
             }
-            Entry::Vacant(e) => {
-                // If not in the database, create unloaded mapblock
-                let mapblock = match self.map.get_mapblock(mapblock_pos).await {
-                    Ok(mapblock) => Ok(mapblock),
-                    Err(MapDataError::MapBlockNonexistent(_)) => Ok(MapBlock::unloaded()),
-                    Err(e) => Err(e),
-                }?;
-                let block = e.insert(Arc::new(Mutex::new(BlockEdit {
-                    mapblock,
-                    tainted: false,
-                })));
+        }
+        let victims: Vec<_> = victims.into_iter().map(|(pos, _)| pos).collect();
+        if !dirty.is_empty() {
+            self.commit_blocks(&dirty).await?.into_result()?;
+        }
 
-                block.clone()
+        let mut cache = self.cache();
+        for pos in victims {
+            // Another task may have picked up or modified the mapblock in the meantime
+            let evictable = cache.blocks.get(&pos).is_some_and(|entry| {
+                Arc::strong_count(entry) == 1
+                    && entry.try_lock().is_some_and(|block| !block.tainted)
+            });
+            if evictable {
+                cache.blocks.remove(&pos);
+                cache.last_used.remove(&pos);
             }
-        };
+        }
+        Ok(())
+    }
 
-        Ok(c)
+    /// Return a cache entry containing the given mapblock
+    async fn get_mapblock(&self, mapblock_pos: BlockPos) -> Result<Arc<Mutex<BlockEdit>>> {
+        let entry = self.load_mapblock(mapblock_pos).await?;
+        let over_limit = self
+            .cache_limit
+            .filter(|&limit| self.cache().blocks.len() > limit);
+        if let Some(limit) = over_limit {
+            self.shrink_cache(limit).await?;
+        }
+        Ok(entry)
     }
 
-    // /// Get a reference to the mapblock at the given block position
-    // ///
-    // /// If there is no mapblock at this world position,
-    // /// a new [unloaded](`MapBlock::unloaded`) mapblock is returned.
-    // pub async fn get_mapblock(&mut self, mapblock_pos: BlockPos) -> Result<MutexGuard<BlockEdit>> {
-    //     let get_entry = self.get_entry(mapblock_pos).await?;
-    //     Ok(get_entry.await?.lock().await)
-    // }
+    /// Like [`get_mapblock`](`Self::get_mapblock`), but never shrinks the cache
+    ///
+    /// This is used while committing, which may happen while shrinking the cache.
+    async fn load_mapblock(&self, mapblock_pos: BlockPos) -> Result<Arc<Mutex<BlockEdit>>> {
+        {
+            let mut cache = self.cache();
+            if let Some(entry) = cache.blocks.get(&mapblock_pos).cloned() {
+                cache.touch(mapblock_pos);
+                return Ok(entry);
+            }
+        }
+
+        // The cache is not locked while loading, so other tasks can go on meanwhile.
+        // If not in the database, create unloaded mapblock
+        let (mapblock, generated) = match self.map.get_mapblock(mapblock_pos).await {
+            Ok(mapblock) => Ok((mapblock, true)),
+            Err(MapDataError::MapBlockNonexistent(_)) => Ok((MapBlock::unloaded(), false)),
+            Err(e) => Err(e),
+        }?;
+        let mut cache = self.cache();
+        // Another task may have loaded the same mapblock first, which wins
+        let entry = cache
+            .blocks
+            .entry(mapblock_pos)
+            .or_insert_with(|| {
+                Arc::new(Mutex::new(BlockEdit {
+                    mapblock: Arc::new(mapblock),
+                    tainted: false,
+                    generated,
+                }))
+            })
+            .clone();
+        cache.touch(mapblock_pos);
+        Ok(entry)
+    }
 
     /// Get the node at the given world position
-    pub async fn get_node(&mut self, node_pos: I16Vec3) -> Result<Node> {
+    pub async fn get_node(&self, node_pos: I16Vec3) -> Result<Node> {
         let (blockpos, nodepos) = node_pos.split();
         Ok(self
             .get_mapblock(blockpos)
@@ -163,27 +510,47 @@ impl MapEdit {
             .get_node(nodepos))
     }
 
-    /// Do something with the mapblock at `blockpos` and mark it as modified
-    async fn modify_mapblock(
-        &mut self,
-        blockpos: BlockPos,
-        op: impl FnOnce(&mut BlockEdit),
-    ) -> Result<()> {
-        let entry = &mut self.get_mapblock(blockpos).await?;
-        let mut block_edit = entry.lock().await;
-        op(&mut block_edit);
-        block_edit.tainted = true;
-        Ok(())
+    /// Like [`get_node`](`Self::get_node`), but returns `None` if the area is not generated yet
+    ///
+    /// [`get_node`](`Self::get_node`) returns [`CONTENT_IGNORE`] there, which could also be
+    /// stored in the map. This tells apart real nodes like air from space that the engine
+    /// has not generated yet. Nodes that have been set in such a mapblock are returned.
+    ///
+    /// ```
+    /// use minetestworld::{MapData, MapEdit};
+    /// use glam::I16Vec3;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true).await.unwrap();
+    ///     let vm = MapEdit::new(map);
+    ///     assert!(vm.get_node_option(I16Vec3::new(-185, -15, 65)).await.unwrap().is_some());
+    ///     assert!(vm.get_node_option(I16Vec3::new(0, 2000, 0)).await.unwrap().is_none());
+    /// });
+    /// ```
+    pub async fn get_node_option(&self, node_pos: I16Vec3) -> Result<Option<Node>> {
+        let (blockpos, nodepos) = node_pos.split();
+        let mutex = &self.get_mapblock(blockpos).await?;
+        let block_edit = mutex.lock().await;
+        let node = block_edit.get_node(nodepos);
+        Ok((block_edit.generated || node.param0 != CONTENT_IGNORE).then_some(node))
+    }
+
+    /// Like [`get_node_option`](`Self::get_node_option`), but returns `default`
+    /// if the area is not generated yet
+    pub async fn get_node_or(&self, node_pos: I16Vec3, default: Node) -> Result<Node> {
+        Ok(self.get_node_option(node_pos).await?.unwrap_or(default))
     }
 
     /// Set a voxel in VoxelManip's cache
     ///
     /// ⚠️ The change will be present locally only. To modify the map,
     /// the change has to be written back via [`VoxelManip::commit`].
-    pub async fn set_node(&mut self, node_pos: I16Vec3, node: Node) -> Result<()> {
+    pub async fn set_node(&self, node_pos: I16Vec3, node: Node) -> Result<()> {
         let (blockpos, nodepos) = node_pos.split();
         let mutex = &self.get_mapblock(blockpos).await?;
         let mut block_edit = mutex.lock().await;
+        self.check_params(node_pos, &node.param0, node.param1, node.param2);
         block_edit.set_node(nodepos, node);
         Ok(())
     }
@@ -200,7 +567,7 @@ impl MapEdit {
     ///
     /// ⚠️ Until the change is [commited](`VoxelManip::commit`),
     /// the node will only be changed in the cache.
-    pub async fn set_content(&mut self, node_pos: I16Vec3, content: &[u8]) -> Result<()> {
+    pub async fn set_content(&self, node_pos: I16Vec3, content: &[u8]) -> Result<()> {
         let (blockpos, nodepos) = node_pos.split();
         let mutex = &self.get_mapblock(blockpos).await?;
         let mut block_edit = mutex.lock().await;
@@ -212,10 +579,14 @@ impl MapEdit {
     ///
     /// ⚠️ Until the change is [commited](`VoxelManip::commit`),
     /// the node will only be changed in the cache.
-    pub async fn set_param1(&mut self, node_pos: I16Vec3, param1: u8) -> Result<()> {
+    pub async fn set_param1(&self, node_pos: I16Vec3, param1: u8) -> Result<()> {
         let (blockpos, nodepos) = node_pos.split();
         let mutex = &self.get_mapblock(blockpos).await?;
         let mut block_edit = mutex.lock().await;
+        if self.node_defs.is_some() {
+            let content = block_edit.get_node(nodepos).param0;
+            self.check_params(node_pos, &content, param1, 0);
+        }
         block_edit.set_param1(nodepos, param1);
         Ok(())
     }
@@ -224,42 +595,1179 @@ impl MapEdit {
     ///
     /// ⚠️ Until the change is [commited](`VoxelManip::commit`),
     /// the node will only be changed in the cache.
-    pub async fn set_param2(&mut self, node_pos: I16Vec3, param2: u8) -> Result<()> {
+    pub async fn set_param2(&self, node_pos: I16Vec3, param2: u8) -> Result<()> {
         let (blockpos, nodepos) = node_pos.split();
         let mutex = &self.get_mapblock(blockpos).await?;
         let mut block_edit = mutex.lock().await;
+        if self.node_defs.is_some() {
+            let content = block_edit.get_node(nodepos).param0;
+            self.check_params(node_pos, &content, 0, param2);
+        }
         block_edit.set_param2(nodepos, param2);
         Ok(())
     }
 
+    /// Runs `edit` on the cached mapblock at `blockpos`
+    ///
+    /// The mapblock is marked as modified if `edit` returns true.
+    pub(crate) async fn edit_mapblock(
+        &self,
+        blockpos: BlockPos,
+        edit: impl FnOnce(&mut MapBlock) -> bool,
+    ) -> Result<()> {
+        let mutex = &self.get_mapblock(blockpos).await?;
+        let mut block_edit = mutex.lock().await;
+        if edit(block_edit.mapblock_mut()) {
+            block_edit.tainted = true;
+        }
+        Ok(())
+    }
+
+    /// Returns a copy of the metadata of the node at this world position, if it has any
+    pub async fn get_metadata(&self, node_pos: I16Vec3) -> Result<Option<NodeMetadata>> {
+        let (blockpos, nodepos) = node_pos.split();
+        let mutex = &self.get_mapblock(blockpos).await?;
+        let block_edit = mutex.lock().await;
+        Ok(block_edit.mapblock.node_metadata_at(nodepos).cloned())
+    }
+
+    /// Sets the metadata variable `key` of the node at this world position
+    ///
+    /// This is used e.g. for the `infotext`, the `formspec` or the text of a sign.
+    /// An empty value removes the variable.
+    ///
+    /// ⚠️ Until the change is [commited](`VoxelManip::commit`),
+    /// the metadata will only be changed in the cache.
+    pub async fn set_metadata_var(
+        &self,
+        node_pos: I16Vec3,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<()> {
+        let (blockpos, nodepos) = node_pos.split();
+        self.edit_mapblock(blockpos, |block| {
+            block.node_metadata_mut(nodepos).set(key, value);
+            true
+        })
+        .await
+    }
+
+    /// Sets an inventory list of the node at this world position
+    ///
+    /// A list with the same name is replaced.
+    ///
+    /// ⚠️ Until the change is [commited](`VoxelManip::commit`),
+    /// the metadata will only be changed in the cache.
+    pub async fn set_inventory_list(&self, node_pos: I16Vec3, list: InventoryList) -> Result<()> {
+        let (blockpos, nodepos) = node_pos.split();
+        self.edit_mapblock(blockpos, |block| {
+            block.node_metadata_mut(nodepos).set_inventory_list(list);
+            true
+        })
+        .await
+    }
+
+    /// Removes all metadata of the node at this world position
+    ///
+    /// ⚠️ Until the change is [commited](`VoxelManip::commit`),
+    /// the metadata will only be changed in the cache.
+    pub async fn remove_metadata(&self, node_pos: I16Vec3) -> Result<()> {
+        let (blockpos, nodepos) = node_pos.split();
+        self.edit_mapblock(blockpos, |block| {
+            block.remove_node_metadata(nodepos).is_some()
+        })
+        .await
+    }
+
+    /// Returns a copy of the timer of the node at this world position, if it has one
+    pub async fn get_node_timer(&self, node_pos: I16Vec3) -> Result<Option<NodeTimer>> {
+        let (blockpos, nodepos) = node_pos.split();
+        let mutex = &self.get_mapblock(blockpos).await?;
+        let block_edit = mutex.lock().await;
+        Ok(block_edit.mapblock.node_timer_at(nodepos).cloned())
+    }
+
+    /// Starts a timer on the node at this world position, like `minetest.get_node_timer(pos):set()`
+    ///
+    /// `timeout` and `elapsed` are given in milliseconds.
+    /// A running timer of this node is replaced.
+    ///
+    /// ⚠️ Until the change is [commited](`VoxelManip::commit`),
+    /// the timer will only be changed in the cache.
+    pub async fn set_node_timer(
+        &self,
+        node_pos: I16Vec3,
+        timeout: i32,
+        elapsed: i32,
+    ) -> Result<()> {
+        let (blockpos, nodepos) = node_pos.split();
+        self.edit_mapblock(blockpos, |block| {
+            block.set_node_timer(NodeTimer {
+                position: nodepos,
+                timeout,
+                elapsed,
+            });
+            true
+        })
+        .await
+    }
+
+    /// Stops the timer of the node at this world position
+    ///
+    /// ⚠️ Until the change is [commited](`VoxelManip::commit`),
+    /// the timer will only be changed in the cache.
+    pub async fn remove_node_timer(&self, node_pos: I16Vec3) -> Result<()> {
+        let (blockpos, nodepos) = node_pos.split();
+        self.edit_mapblock(blockpos, |block| block.remove_node_timer(nodepos).is_some())
+            .await
+    }
+
+    /// Writes all nodes of `schematic` with its origin at `pos`
+    ///
+    /// Unlike placing a schematic with an [`EditPlan`](`crate::edit_plan::EditPlan`),
+    /// the metadata of every written node is replaced, i.e. removed if the schematic has none.
+    async fn write_schematic(&self, pos: I16Vec3, schematic: &Schematic) -> Result<()> {
+        if schematic.size().cmpeq(U16Vec3::ZERO).any() {
+            return Ok(());
+        }
+        let max = pos.saturating_add((schematic.size() - U16Vec3::ONE).as_i16vec3());
+        for node_pos in NodeBox::new(pos, max).iter() {
+            let offset = (node_pos - pos).as_u16vec3();
+            let Some(node) = schematic.get_node(offset) else {
+                continue;
+            };
+            self.set_node(node_pos, node.clone()).await?;
+            let metadata = schematic.get_metadata(offset);
+            let (blockpos, nodepos) = node_pos.split();
+            self.edit_mapblock(blockpos, |block| {
+                let removed = block.remove_node_metadata(nodepos).is_some();
+                let Some(metadata) = metadata else {
+                    return removed;
+                };
+                let block_metadata = block.node_metadata_mut(nodepos);
+                block_metadata.vars = metadata.vars.clone();
+                block_metadata.inventory = metadata.inventory.clone();
+                true
+            })
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Sets the content of all nodes in the box between `a` and `b` to `content`
+    ///
+    /// This has the same effect as calling [`set_content`](`Self::set_content`) on every node
+    /// of the box, i.e. param1, param2 and metadata are kept. Mapblocks that lie completely
+    /// within the box are filled at once, which makes filling large regions fast.
+    ///
+    /// ⚠️ Until the change is [commited](`VoxelManip::commit`),
+    /// the nodes will only be changed in the cache.
+    pub async fn fill_region(&self, a: I16Vec3, b: I16Vec3, content: &[u8]) -> Result<()> {
+        let area = NodeBox::new(a, b);
+        for blockpos in area.blocks() {
+            self.edit_mapblock(blockpos, |block| {
+                fill_block(block, blockpos, &area, content)
+            })
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Copies the nodes of the box between `a` and `b`, including their metadata, to `dest`
+    ///
+    /// `dest` is where the corner of the box with the smallest coordinates ends up,
+    /// like with WorldEdit's `//copy`. Source and destination may overlap,
+    /// as the whole box is read before anything is written.
+    ///
+    /// ⚠️ Until the change is [commited](`VoxelManip::commit`),
+    /// the nodes will only be changed in the cache.
+    pub async fn clone_region(&self, a: I16Vec3, b: I16Vec3, dest: I16Vec3) -> Result<()> {
+        let schematic = Schematic::from_world(self, a, b).await?;
+        self.write_schematic(dest, &schematic).await
+    }
+
+    /// Moves the nodes of the box between `a` and `b`, including their metadata, to `dest`
+    ///
+    /// Like [`clone_region`](`Self::clone_region`), but the source is replaced by air
+    /// wherever it is not overwritten by the destination. This is WorldEdit's `//move`.
+    ///
+    /// ⚠️ Until the change is [commited](`VoxelManip::commit`),
+    /// the nodes will only be changed in the cache.
+    pub async fn move_region(&self, a: I16Vec3, b: I16Vec3, dest: I16Vec3) -> Result<()> {
+        let schematic = Schematic::from_world(self, a, b).await?;
+        self.clear_region(a, b).await?;
+        self.write_schematic(dest, &schematic).await
+    }
+
+    /// Turns the box between `a` and `b` around the y axis by `quarter_turns` steps of 90°
+    ///
+    /// The box is turned clockwise as seen from above, i.e. from +z towards +x,
+    /// and keeps its corner with the smallest coordinates. If the box is not square,
+    /// its footprint changes; nodes of the original box that are not covered anymore
+    /// become air. Returns the corners of the turned box.
+    ///
+    /// With [node definitions](`Self::set_node_defs`), the `param2` of directional nodes
+    /// like stairs, chests and torches is turned along. Without, it is kept as is.
+    ///
+    /// ⚠️ Until the change is [commited](`VoxelManip::commit`),
+    /// the nodes will only be changed in the cache.
+    pub async fn rotate_region(
+        &self,
+        a: I16Vec3,
+        b: I16Vec3,
+        quarter_turns: u8,
+    ) -> Result<(I16Vec3, I16Vec3)> {
+        let schematic = Schematic::from_world(self, a, b).await?;
+        let rotated = schematic.rotated_y(quarter_turns, self.node_defs_or_empty().as_ref());
+        self.clear_region(a, b).await?;
+        let min = a.min(b);
+        self.write_schematic(min, &rotated).await?;
+        Ok((
+            min,
+            min.saturating_add((rotated.size() - U16Vec3::ONE).as_i16vec3()),
+        ))
+    }
+
+    /// Mirrors the box between `a` and `b` in place along `axis`
+    ///
+    /// With [node definitions](`Self::set_node_defs`), the `param2` of directional nodes
+    /// like stairs, chests and torches is mirrored along. Without, it is kept as is.
+    ///
+    /// ⚠️ Until the change is [commited](`VoxelManip::commit`),
+    /// the nodes will only be changed in the cache.
+    pub async fn mirror_region(&self, a: I16Vec3, b: I16Vec3, axis: MirrorAxis) -> Result<()> {
+        let schematic = Schematic::from_world(self, a, b).await?;
+        let mirrored = schematic.mirrored(axis, self.node_defs_or_empty().as_ref());
+        self.write_schematic(a.min(b), &mirrored).await
+    }
+
+    /// Sets all nodes on the straight line from `a` to `b` to `node`, including both ends
+    ///
+    /// See [`shapes::line`] for the nodes that make up the line.
+    ///
+    /// ```
+    /// use minetestworld::{MapData, MapEdit, Node};
+    /// use glam::I16Vec3;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true).await.unwrap();
+    ///     let vm = MapEdit::new(map);
+    ///     let beam = Node {
+    ///         param0: b"default:tree".to_vec(),
+    ///         param1: 0,
+    ///         param2: 0,
+    ///     };
+    ///     let (a, b) = (I16Vec3::new(-190, -20, 60), I16Vec3::new(-150, -10, 70));
+    ///     vm.set_line(a, b, &beam).await.unwrap();
+    ///     assert_eq!(vm.get_node(b).await.unwrap(), beam);
+    /// });
+    /// ```
+    ///
+    /// ⚠️ Until the change is [commited](`VoxelManip::commit`),
+    /// the nodes will only be changed in the cache.
+    pub async fn set_line(&self, a: I16Vec3, b: I16Vec3, node: &Node) -> Result<()> {
+        for pos in shapes::line(a, b) {
+            self.set_node(pos, node.clone()).await?;
+        }
+        Ok(())
+    }
+
+    /// Sets the content of all nodes covered by `brush` to `content`
+    ///
+    /// Like [`fill_region`](`Self::fill_region`), this keeps param1, param2 and metadata,
+    /// and works mapblock by mapblock: Mapblocks that lie completely within a solid
+    /// brush are filled at once.
+    ///
+    /// ```
+    /// use minetestworld::{MapData, MapEdit};
+    /// use minetestworld::shapes::{Brush, Shape};
+    /// use glam::I16Vec3;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true).await.unwrap();
+    ///     let vm = MapEdit::new(map);
+    ///     let center = I16Vec3::new(-185, -15, 65);
+    ///     let brush = Brush::hollow(Shape::sphere(center, 6.0), 1);
+    ///     vm.fill_brush(&brush, b"default:glass").await.unwrap();
+    ///     assert_eq!(vm.get_node(center + I16Vec3::new(0, 6, 0)).await.unwrap().param0, b"default:glass");
+    ///     assert_ne!(vm.get_node(center).await.unwrap().param0, b"default:glass");
+    /// });
+    /// ```
+    ///
+    /// ⚠️ Until the change is [commited](`VoxelManip::commit`),
+    /// the nodes will only be changed in the cache.
+    pub async fn fill_brush(&self, brush: &Brush, content: &[u8]) -> Result<()> {
+        self.paint_brush(brush, None, content).await
+    }
+
+    /// Replaces the content `from` by `to` in all nodes covered by `brush`
+    ///
+    /// See [`fill_brush`](`Self::fill_brush`).
+    ///
+    /// ⚠️ Until the change is [commited](`VoxelManip::commit`),
+    /// the nodes will only be changed in the cache.
+    pub async fn replace_brush(&self, brush: &Brush, from: &[u8], to: &[u8]) -> Result<()> {
+        self.paint_brush(brush, Some(from), to).await
+    }
+
+    /// Sets the content of the nodes covered by `brush`, or only of those with content `from`
+    async fn paint_brush(&self, brush: &Brush, from: Option<&[u8]>, content: &[u8]) -> Result<()> {
+        let Some((min, max)) = brush.bounds() else {
+            return Ok(());
+        };
+        let area = NodeBox::new(min, max);
+        for blockpos in area.blocks() {
+            let Some(part) = area.intersection(&NodeBox::of_block(blockpos)) else {
+                continue;
+            };
+            self.edit_mapblock(blockpos, |block| {
+                let from_id = match from {
+                    Some(from) => match block.get_content_id(from) {
+                        Some(id) => Some(id),
+                        None => return false,
+                    },
+                    None => None,
+                };
+                let block_area = NodeBox::of_block(blockpos);
+                if from.is_none() && brush.covers(block_area.min, block_area.max) {
+                    return fill_block(block, blockpos, &block_area, content);
+                }
+                let mut content_id = None;
+                let mut modified = false;
+                for pos in part.iter() {
+                    let nodepos = pos.split().1;
+                    let index = usize::from(nodepos);
+                    if from_id.is_some_and(|id| block.param0[index] != id) || !brush.contains(pos) {
+                        continue;
+                    }
+                    let id =
+                        *content_id.get_or_insert_with(|| block.get_or_create_content_id(content));
+                    block.set_content(nodepos, id);
+                    modified = true;
+                }
+                modified
+            })
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the connected nodes around `start` that match `replace` by `content`
+    ///
+    /// Starting at `start`, the fill spreads to the six neighbors of every replaced node,
+    /// as long as they match `replace`, lie within the [bounds](`FillLimits::bounds`),
+    /// and belong to generated mapblocks. Like [`set_content`](`Self::set_content`),
+    /// this keeps param1, param2 and metadata. The fill stops after
+    /// [`FillLimits::max_nodes`] nodes, so that it cannot run away into a huge cave or ocean.
+    ///
+    /// ```
+    /// use minetestworld::{MapData, MapEdit};
+    /// use minetestworld::voxel_manip::FillLimits;
+    /// use glam::I16Vec3;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true).await.unwrap();
+    ///     let vm = MapEdit::new(map);
+    ///     let start = I16Vec3::new(-185, -15, 65);
+    ///     let content = vm.get_node(start).await.unwrap().param0;
+    ///     let limits = FillLimits {
+    ///         max_nodes: 100,
+    ///         bounds: Some((I16Vec3::new(-190, -20, 60), I16Vec3::new(-180, -10, 70))),
+    ///     };
+    ///     let outcome = vm
+    ///         .flood_fill(start, |node| node.param0 == content, b"default:mese", limits)
+    ///         .await
+    ///         .unwrap();
+    ///     assert!(outcome.filled > 0);
+    /// });
+    /// ```
+    ///
+    /// ⚠️ Until the change is [commited](`VoxelManip::commit`),
+    /// the nodes will only be changed in the cache.
+    pub async fn flood_fill(
+        &self,
+        start: I16Vec3,
+        mut replace: impl FnMut(&Node) -> bool,
+        content: &[u8],
+        limits: FillLimits,
+    ) -> Result<FillOutcome> {
+        let bounds = limits.bounds.map(|(a, b)| NodeBox::new(a, b));
+        let in_bounds = |pos: I16Vec3| match bounds {
+            Some(bounds) => pos.cmpge(bounds.min).all() && pos.cmple(bounds.max).all(),
+            None => true,
+        };
+        let mut outcome = FillOutcome::default();
+        let mut visited = HashSet::from([start]);
+        let mut queue = VecDeque::from([start]);
+        while let Some(pos) = queue.pop_front() {
+            if outcome.filled >= limits.max_nodes {
+                outcome.truncated = true;
+                break;
+            }
+            if !in_bounds(pos) {
+                continue;
+            }
+            match self.get_node_option(pos).await? {
+                Some(node) if replace(&node) => {}
+                _ => continue,
+            }
+            self.set_content(pos, content).await?;
+            outcome.filled += 1;
+            for dir in [
+                I16Vec3::X,
+                I16Vec3::NEG_X,
+                I16Vec3::Y,
+                I16Vec3::NEG_Y,
+                I16Vec3::Z,
+                I16Vec3::NEG_Z,
+            ] {
+                let neighbor = pos.saturating_add(dir);
+                if visited.insert(neighbor) {
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        Ok(outcome)
+    }
+
+    /// Returns the node definitions, or an empty set of definitions if there are none
+    fn node_defs_or_empty(&self) -> Arc<dyn NodeDefProvider + Send + Sync> {
+        self.node_defs
+            .clone()
+            .unwrap_or_else(|| Arc::new(HashMap::<Vec<u8>, NodeDef>::new()))
+    }
+
+    /// Replaces all nodes of the box between `a` and `b` by air, removing their metadata
+    async fn clear_region(&self, a: I16Vec3, b: I16Vec3) -> Result<()> {
+        for pos in NodeBox::new(a, b).iter() {
+            self.set_node(
+                pos,
+                Node {
+                    param0: b"air".to_vec(),
+                    param1: 0,
+                    param2: 0,
+                },
+            )
+            .await?;
+            self.remove_metadata(pos).await?;
+        }
+        Ok(())
+    }
+
     /// Returns true if this world position is cached
     pub fn is_in_cache(&self, node_pos: I16Vec3) -> bool {
         let (blockpos, _) = node_pos.split();
-        self.mapblock_cache.contains_key(&blockpos)
+        self.cache().blocks.contains_key(&blockpos)
     }
 
     /// Ensures that this world position is in the cache
-    pub async fn visit(&mut self, node_pos: I16Vec3) -> Result<()> {
+    pub async fn visit(&self, node_pos: I16Vec3) -> Result<()> {
         let (blockpos, _) = node_pos.split();
         self.get_mapblock(blockpos).await?;
         Ok(())
     }
 
+    /// Loads all mapblocks of `area` into the cache
+    ///
+    /// A mapblock that fails to load does not keep the others from being loaded.
+    /// Returns the mapblocks that failed, along with their error.
+    pub async fn preload(&self, area: BlockArea) -> Vec<(BlockPos, MapDataError)> {
+        let mut failed = vec![];
+        for pos in area.iter() {
+            if let Err(e) = self.get_mapblock(pos).await {
+                failed.push((pos, e));
+            }
+        }
+        failed
+    }
+
+    /// Loads all mapblocks touching the box between the nodes `a` and `b` into the cache
+    ///
+    /// Like `VoxelManip:read_from_map` in Lua, this fetches them all at once with
+    /// [`MapData::get_region_data`], instead of one by one while editing.
+    /// Mapblocks that are cached already are kept, including their changes.
+    /// Mapblocks that do not exist yet are cached as [unloaded](`MapBlock::unloaded`).
+    /// With a [cache limit](`Self::set_cache_limit`) below the size of the region,
+    /// some of them are dropped again right away.
+    ///
+    /// ```
+    /// use minetestworld::{MapData, MapEdit};
+    /// use glam::I16Vec3;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true).await.unwrap();
+    ///     let vm = MapEdit::new(map);
+    ///     vm.read_region(I16Vec3::new(-190, -20, 60), I16Vec3::new(-180, -10, 70))
+    ///         .await
+    ///         .unwrap();
+    ///     assert!(vm.is_in_cache(I16Vec3::new(-185, -15, 65)));
+    /// });
+    /// ```
+    pub async fn read_region(&self, a: I16Vec3, b: I16Vec3) -> Result<()> {
+        let region = BlockArea::new(a.split().0, b.split().0);
+        let mut fetched: HashMap<_, _> = self
+            .map
+            .get_region_data(region)
+            .await?
+            .into_iter()
+            .collect();
+        let mut loaded = vec![];
+        for pos in region.iter() {
+            let (mapblock, generated) = match fetched.remove(&pos) {
+                Some(data) => (
+                    MapBlock::from_data(data.as_slice())
+                        .map_err(|e| MapDataError::AtBlock(pos, Box::new(e.into())))?,
+                    true,
+                ),
+                None => (MapBlock::unloaded(), false),
+            };
+            loaded.push((pos, mapblock, generated));
+        }
+        let over_limit = {
+            let mut cache = self.cache();
+            for (pos, mapblock, generated) in loaded {
+                if let Entry::Vacant(entry) = cache.blocks.entry(pos) {
+                    entry.insert(Arc::new(Mutex::new(BlockEdit {
+                        mapblock: Arc::new(mapblock),
+                        tainted: false,
+                        generated,
+                    })));
+                }
+                cache.touch(pos);
+            }
+            self.cache_limit.filter(|&limit| cache.blocks.len() > limit)
+        };
+        if let Some(limit) = over_limit {
+            self.shrink_cache(limit).await?;
+        }
+        Ok(())
+    }
+
+    /// Reads the nodes of the box between `a` and `b` into flat arrays
+    ///
+    /// This is the counterpart of `VoxelManip:get_data()` in Lua. Algorithms that touch
+    /// every node, like cellular automata or erosion, run much faster on the arrays
+    /// than with one call per node. Write them back with [`set_data`](`Self::set_data`).
+    ///
+    /// ```
+    /// use minetestworld::{MapData, MapEdit};
+    /// use glam::I16Vec3;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true).await.unwrap();
+    ///     let vm = MapEdit::new(map);
+    ///     let (a, b) = (I16Vec3::new(-190, -20, 60), I16Vec3::new(-180, -10, 70));
+    ///     let mut data = vm.get_data(a, b).await.unwrap();
+    ///     let stone = data.get_or_create_content_id(b"default:stone");
+    ///     let mese = data.get_or_create_content_id(b"default:mese");
+    ///     for content in &mut data.content {
+    ///         if *content == stone {
+    ///             *content = mese;
+    ///         }
+    ///     }
+    ///     vm.set_data(&data).await.unwrap();
+    ///     assert!(!vm.modified_blocks().await.is_empty());
+    /// });
+    /// ```
+    pub async fn get_data(&self, a: I16Vec3, b: I16Vec3) -> Result<VoxelData> {
+        let area = NodeBox::new(a, b);
+        let mut data = VoxelData::new(a, b);
+        for blockpos in area.blocks() {
+            let Some(part) = area.intersection(&NodeBox::of_block(blockpos)) else {
+                continue;
+            };
+            let mutex = self.get_mapblock(blockpos).await?;
+            let block_edit = mutex.lock().await;
+            let mapblock = &block_edit.mapblock;
+            // Translate the palette once per mapblock instead of once per node
+            let ids: HashMap<u16, u16> = mapblock
+                .palette()
+                .map(|(id, name)| (id, data.get_or_create_content_id(name)))
+                .collect();
+            for pos in part.iter() {
+                let Some(index) = data.index(pos) else {
+                    continue;
+                };
+                let node_index = usize::from(pos.split().1);
+                let block_id = mapblock.param0[node_index];
+                data.content[index] = match ids.get(&block_id) {
+                    Some(&id) => id,
+                    None => data.get_or_create_content_id(CONTENT_UNKNOWN),
+                };
+                data.param1[index] = mapblock.param1[node_index];
+                data.param2[index] = mapblock.param2[node_index];
+            }
+        }
+        Ok(data)
+    }
+
+    /// Writes flat node arrays as returned by [`get_data`](`Self::get_data`) back to the cache
+    ///
+    /// This is the counterpart of `VoxelManip:set_data()` in Lua. All nodes of the box
+    /// are written, including their param1 and param2. Node metadata is kept.
+    /// Only mapblocks in which a node actually changed are marked as modified.
+    ///
+    /// # Panics
+    ///
+    /// If the arrays do not match the size of the box, or a content ID is not in the palette.
+    ///
+    /// ⚠️ Until the change is [commited](`VoxelManip::commit`),
+    /// the nodes will only be changed in the cache.
+    pub async fn set_data(&self, data: &VoxelData) -> Result<()> {
+        let area = NodeBox::new(data.min, data.max);
+        let volume = data.volume();
+        assert!(
+            data.param1.len() == volume && data.param2.len() == volume,
+            "the arrays of the voxel data differ in length"
+        );
+        assert_eq!(
+            data.index(data.max).map(|index| index + 1),
+            Some(volume),
+            "the arrays of the voxel data do not match the size of its box"
+        );
+        assert!(
+            data.content
+                .iter()
+                .all(|&id| (id as usize) < data.palette.len()),
+            "a content ID of the voxel data is missing from its palette"
+        );
+        for blockpos in area.blocks() {
+            let Some(part) = area.intersection(&NodeBox::of_block(blockpos)) else {
+                continue;
+            };
+            self.edit_mapblock(blockpos, |block| {
+                let mut ids = HashMap::new();
+                let mut modified = false;
+                for pos in part.iter() {
+                    let Some(index) = data.index(pos) else {
+                        continue;
+                    };
+                    let node_index = usize::from(pos.split().1);
+                    let content = data.content[index];
+                    let id = *ids.entry(content).or_insert_with(|| {
+                        block.get_or_create_content_id(&data.palette[content as usize])
+                    });
+                    let node = (id, data.param1[index], data.param2[index]);
+                    let old = (
+                        block.param0[node_index],
+                        block.param1[node_index],
+                        block.param2[node_index],
+                    );
+                    if node != old {
+                        block.param0[node_index] = node.0;
+                        block.param1[node_index] = node.1;
+                        block.param2[node_index] = node.2;
+                        modified = true;
+                    }
+                }
+                modified
+            })
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Recomputes the light of the nodes in the box between `a` and `b`
+    ///
+    /// This is the counterpart of `VoxelManip:calc_lighting()` in Lua. Which nodes emit
+    /// light or let it pass is taken from the [node definitions](`Self::set_node_defs`),
+    /// see [`lighting::calc_lighting`] for the details. The light of the nodes
+    /// around the box shines into it. Mapblocks that lie completely within the box
+    /// are marked as [lit completely](`MapBlock::is_lighting_complete`),
+    /// so that the engine does not light them again.
+    ///
+    /// ⚠️ Until the change is [commited](`VoxelManip::commit`),
+    /// the nodes will only be changed in the cache.
+    pub async fn calc_lighting(&self, a: I16Vec3, b: I16Vec3) -> Result<()> {
+        let area = NodeBox::new(a, b);
+        let mut data = self
+            .get_data(
+                area.min.saturating_sub(I16Vec3::ONE),
+                area.max.saturating_add(I16Vec3::ONE),
+            )
+            .await?;
+        lighting::calc_lighting(
+            &mut data,
+            area.min,
+            area.max,
+            self.node_defs_or_empty().as_ref(),
+        );
+        self.set_data(&data).await?;
+
+        for blockpos in area.blocks() {
+            let block_area = NodeBox::of_block(blockpos);
+            if block_area.min.cmplt(area.min).any() || block_area.max.cmpgt(area.max).any() {
+                continue;
+            }
+            self.edit_mapblock(blockpos, |block| {
+                let before = block.lighting_complete;
+                for bank in [LightBank::Day, LightBank::Night] {
+                    for face in [
+                        BlockFace::PosX,
+                        BlockFace::PosY,
+                        BlockFace::PosZ,
+                        BlockFace::NegZ,
+                        BlockFace::NegY,
+                        BlockFace::NegX,
+                    ] {
+                        block.set_lighting_complete(bank, face, true);
+                    }
+                }
+                block.lighting_complete != before
+            })
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Lets `liquids` flow into the air within the box between `a` and `b`
+    ///
+    /// This plays the role of `VoxelManip:update_liquids()` in Lua, but settles the
+    /// liquids right away instead of leaving that to the engine, which only moves liquids
+    /// that changed while the map was loaded. See [`liquids::update_liquids`] for details.
+    ///
+    /// ```
+    /// use minetestworld::{MapData, MapEdit};
+    /// use minetestworld::liquids::Liquid;
+    /// use glam::I16Vec3;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true).await.unwrap();
+    ///     let vm = MapEdit::new(map);
+    ///     let (a, b) = (I16Vec3::new(-190, -20, 60), I16Vec3::new(-180, -10, 70));
+    ///     let water = Liquid::new(b"default:water_source", b"default:water_flowing");
+    ///     vm.update_liquids(a, b, &[water]).await.unwrap();
+    /// });
+    /// ```
+    ///
+    /// ⚠️ Until the change is [commited](`VoxelManip::commit`),
+    /// the nodes will only be changed in the cache.
+    pub async fn update_liquids(&self, a: I16Vec3, b: I16Vec3, liquids: &[Liquid]) -> Result<()> {
+        let area = NodeBox::new(a, b);
+        let mut data = self
+            .get_data(
+                area.min.saturating_sub(I16Vec3::ONE),
+                area.max.saturating_add(I16Vec3::ONE),
+            )
+            .await?;
+        liquids::update_liquids(&mut data, area.min, area.max, liquids);
+        self.set_data(&data).await
+    }
+
+    /// Returns the positions of all mapblocks that have been modified since the last commit
+    ///
+    /// The positions are sorted by their [`BlockKey`]. A mapblock counts as modified
+    /// as soon as it has been written to, even if its nodes ended up unchanged.
+    pub async fn modified_blocks(&self) -> Vec<BlockPos> {
+        let entries = self.cache().entries();
+        let mut modified = vec![];
+        for (pos, entry) in entries {
+            if entry.lock().await.tainted {
+                modified.push(pos);
+            }
+        }
+        modified
+    }
+
+    /// Reverts the mapblock at `pos` to its state in the map, dropping its uncommitted changes
+    ///
+    /// The mapblock is removed from the cache and loaded again when it is accessed next.
+    /// Returns true if it had been modified.
+    pub async fn discard_block(&self, pos: BlockPos) -> bool {
+        let removed = {
+            let mut cache = self.cache();
+            cache.last_used.remove(&pos);
+            cache.blocks.remove(&pos)
+        };
+        match removed {
+            Some(entry) => entry.lock().await.tainted,
+            None => false,
+        }
+    }
+
+    /// Drops all changes that have not been committed yet
+    ///
+    /// Unmodified mapblocks stay cached. Returns the number of reverted mapblocks.
+    ///
+    /// ```
+    /// use minetestworld::{MapData, MapEdit};
+    /// use glam::I16Vec3;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true).await.unwrap();
+    ///     let vm = MapEdit::new(map);
+    ///     let pos = I16Vec3::new(-185, -15, 65);
+    ///     let before = vm.get_node(pos).await.unwrap();
+    ///     vm.set_content(pos, b"default:mese").await.unwrap();
+    ///     assert_eq!(vm.discard().await, 1);
+    ///     assert_eq!(vm.get_node(pos).await.unwrap(), before);
+    /// });
+    /// ```
+    pub async fn discard(&self) -> usize {
+        let modified = self.modified_blocks().await;
+        for &pos in &modified {
+            self.discard_block(pos).await;
+        }
+        modified.len()
+    }
+
+    /// Captures the state of all cached mapblocks, including their uncommitted changes
+    ///
+    /// Keeping the snapshots of previous steps allows to undo them with
+    /// [`revert_to`](`Self::revert_to`) before committing.
+    ///
+    /// ```
+    /// use minetestworld::{MapData, MapEdit};
+    /// use glam::I16Vec3;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true).await.unwrap();
+    ///     let vm = MapEdit::new(map);
+    ///     let pos = I16Vec3::new(-185, -15, 65);
+    ///     let mut undo = vec![];
+    ///     for content in [b"default:mese", b"default:gold"] {
+    ///         undo.push(vm.snapshot().await);
+    ///         vm.set_content(pos, content).await.unwrap();
+    ///     }
+    ///
+    ///     let redo = vm.snapshot().await;
+    ///     vm.revert_to(&undo.pop().unwrap()).await;
+    ///     assert_eq!(vm.get_node(pos).await.unwrap().param0, b"default:mese");
+    ///     vm.revert_to(&redo).await;
+    ///     assert_eq!(vm.get_node(pos).await.unwrap().param0, b"default:gold");
+    /// });
+    /// ```
+    pub async fn snapshot(&self) -> Snapshot {
+        let entries = self.cache().entries();
+        let mut blocks = HashMap::with_capacity(entries.len());
+        for (pos, entry) in entries {
+            blocks.insert(pos, entry.lock().await.clone());
+        }
+        Snapshot { blocks }
+    }
+
+    /// Restores the cached mapblocks to the state of `snapshot`
+    ///
+    /// Changes to mapblocks that have been loaded after the snapshot are
+    /// [discarded](`Self::discard_block`). Snapshots only cover the cache:
+    /// Changes that have been committed since the snapshot stay in the map,
+    /// until the restored state is committed.
+    pub async fn revert_to(&self, snapshot: &Snapshot) {
+        for pos in self.modified_blocks().await {
+            if !snapshot.blocks.contains_key(&pos) {
+                self.discard_block(pos).await;
+            }
+        }
+        let mut cache = self.cache();
+        for (&pos, state) in &snapshot.blocks {
+            cache
+                .blocks
+                .insert(pos, Arc::new(Mutex::new(state.clone())));
+            cache.touch(pos);
+        }
+    }
+
+    /// Lists the differences of every modified mapblock to its version in the map
+    ///
+    /// This is what [`commit`](`Self::commit`) would change, e.g. to preview or log it.
+    /// Mapblocks that have been written to without changing anything are left out.
+    /// See [`MapBlock::diff`].
+    pub async fn changes(&self) -> Result<Vec<(BlockPos, BlockDelta)>> {
+        let mut changes = vec![];
+        for pos in self.modified_blocks().await {
+            let stored = match self.map.get_mapblock(pos).await {
+                Ok(mapblock) => mapblock,
+                Err(MapDataError::MapBlockNonexistent(_)) => MapBlock::unloaded(),
+                Err(e) => return Err(e),
+            };
+            // The mapblock may have been discarded by another task meanwhile
+            let Some(entry) = self.cache().blocks.get(&pos).cloned() else {
+                continue;
+            };
+            let delta = stored.diff(&entry.lock().await.mapblock);
+            if !delta.is_empty() {
+                changes.push((pos, delta));
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Returns the world position and the new version of every node changed since the last commit
+    ///
+    /// ```
+    /// use minetestworld::{MapData, MapEdit};
+    /// use glam::I16Vec3;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let map = MapData::from_sqlite_file("TestWorld/map.sqlite", true).await.unwrap();
+    ///     let vm = MapEdit::new(map);
+    ///     let pos = I16Vec3::new(-185, -15, 65);
+    ///     vm.set_content(pos, b"default:mese").await.unwrap();
+    ///     let modified = vm.modified_nodes().await.unwrap();
+    ///     assert_eq!(modified.len(), 1);
+    ///     assert_eq!(modified[0].0, pos);
+    ///     assert_eq!(modified[0].1.param0, b"default:mese");
+    /// });
+    /// ```
+    pub async fn modified_nodes(&self) -> Result<Vec<(I16Vec3, Node)>> {
+        Ok(self
+            .changes()
+            .await?
+            .into_iter()
+            .flat_map(|(pos, delta)| {
+                delta
+                    .nodes
+                    .into_iter()
+                    .map(move |change| (pos.join(change.position), change.after))
+            })
+            .collect())
+    }
+
     /// Apply all changes made to the map
     ///
     /// Without this, all changes made with [`VoxelManip::set_node`], [`VoxelManip::set_content`],
     /// [`VoxelManip::set_param1`], and [`VoxelManip::set_param2`] are lost when this
     /// instance is dropped.
-    pub async fn commit(&mut self) -> Result<()> {
-        // Write modified mapblocks back into the map data
-        for (&pos, cache_entry) in self.mapblock_cache.iter_mut() {
+    ///
+    /// Content names that are no longer used are dropped from the modified mapblocks,
+    /// see [`MapBlock::compact_palette`].
+    ///
+    /// By default, all mapblocks are written in one transaction, so that a failure leaves
+    /// the map unchanged. If a mapblock cannot be serialized, nothing is written
+    /// and its error is returned as [`MapDataError::AtBlock`].
+    /// With [`CommitMode::BestEffort`], the other mapblocks are still written instead.
+    /// See [`try_commit`](`Self::try_commit`) to get the status of every mapblock.
+    pub async fn commit(&self) -> Result<()> {
+        self.try_commit().await?.into_result()
+    }
+
+    /// Like [`commit`](`Self::commit`), but reports the status of each modified mapblock
+    ///
+    /// Failed mapblocks stay modified, so that committing can be retried.
+    /// With a [journal](`Self::set_journal`), the journal is only removed if all
+    /// mapblocks have been written. Otherwise, [`Journal::recover`] can complete or
+    /// roll back the commit later.
+    ///
+    /// The outer error reports a failure that affects the whole commit,
+    /// like being unable to write the journal or a failed [atomic](`CommitMode::Atomic`)
+    /// transaction. In both cases, all mapblocks stay modified.
+    pub async fn try_commit(&self) -> Result<CommitOutcome> {
+        let positions: Vec<_> = self.cache().blocks.keys().copied().collect();
+        self.commit_blocks(&positions).await
+    }
+
+    /// Clears the lighting flags of the modified mapblocks among `positions`
+    /// and the facing sides of their neighbors
+    ///
+    /// Returns the neighbors that have been modified by this.
+    async fn invalidate_lighting(&self, positions: &[BlockPos]) -> Result<Vec<BlockPos>> {
+        let mut modified = vec![];
+        for &pos in positions {
+            let entry = self.cache().blocks.get(&pos).cloned();
+            let Some(entry) = entry else {
+                continue;
+            };
+            let mut block_edit = entry.lock().await;
+            if !block_edit.tainted {
+                continue;
+            }
+            if block_edit.mapblock.lighting_complete != 0 {
+                block_edit.mapblock_mut().lighting_complete = 0;
+            }
+            modified.push(pos);
+        }
+
+        let mut neighbors = vec![];
+        for pos in modified {
+            for (dir, face) in [
+                (I16Vec3::X, BlockFace::NegX),
+                (I16Vec3::NEG_X, BlockFace::PosX),
+                (I16Vec3::Y, BlockFace::NegY),
+                (I16Vec3::NEG_Y, BlockFace::PosY),
+                (I16Vec3::Z, BlockFace::NegZ),
+                (I16Vec3::NEG_Z, BlockFace::PosZ),
+            ] {
+                let Some(neighbor) = pos.checked_add(dir) else {
+                    continue;
+                };
+                let entry = self.load_mapblock(neighbor).await?;
+                let mut block_edit = entry.lock().await;
+                let banks = [LightBank::Day, LightBank::Night];
+                let complete = banks
+                    .iter()
+                    .any(|&bank| block_edit.mapblock.is_lighting_complete(bank, face));
+                // Mapblocks that do not exist yet are not created for this
+                if !block_edit.generated || !complete {
+                    continue;
+                }
+                for bank in banks {
+                    block_edit
+                        .mapblock_mut()
+                        .set_lighting_complete(bank, face, false);
+                }
+                if !block_edit.tainted {
+                    block_edit.tainted = true;
+                    neighbors.push(neighbor);
+                }
+            }
+        }
+        Ok(neighbors)
+    }
+
+    /// Writes the modified mapblocks among `positions`, see [`try_commit`](`Self::try_commit`)
+    async fn commit_blocks(&self, positions: &[BlockPos]) -> Result<CommitOutcome> {
+        let mut positions = positions.to_vec();
+        if self.commit_options.invalidate_lighting {
+            let neighbors = self.invalidate_lighting(&positions).await?;
+            positions.extend(neighbors);
+        }
+        // Locking in a fixed order keeps concurrent commits from deadlocking
+        let cached: Vec<_> = {
+            let cache = self.cache();
+            let mut cached: Vec<_> = positions
+                .iter()
+                .filter_map(|pos| Some((*pos, cache.blocks.get(pos)?.clone())))
+                .collect();
+            cached.sort_unstable_by_key(|&(pos, _)| BlockKey::from(pos));
+            cached.dedup_by_key(|&mut (pos, _)| pos);
+            cached
+        };
+        let mut outcome = CommitOutcome::default();
+        let mut entries = vec![];
+        // The written mapblocks stay locked, so that no concurrent change gets lost
+        let mut locked = vec![];
+        for (pos, cache_entry) in &cached {
+            let pos = *pos;
             let mut cache_entry = cache_entry.lock().await;
-            if cache_entry.tainted {
-                self.map.set_mapblock(pos, &cache_entry.mapblock).await?;
-                cache_entry.tainted = false;
+            if !cache_entry.tainted {
+                continue;
             }
+            let mapblock = cache_entry.mapblock_mut();
+            mapblock.compact_palette();
+            mapblock.apply_maintenance(&self.maintenance);
+            let new_data = match cache_entry
+                .mapblock
+                .to_binary_version(self.serialize_version)
+            {
+                Ok(data) => data,
+                Err(e) => {
+                    outcome.failed.push((pos, e.into()));
+                    continue;
+                }
+            };
+            let old_data = if self.journal.is_some() {
+                match self.map.get_block_data(pos).await {
+                    Ok(data) => Some(data),
+                    Err(MapDataError::MapBlockNonexistent(_)) => None,
+                    Err(e) => {
+                        outcome.failed.push((pos, e));
+                        continue;
+                    }
+                }
+            } else {
+                None
+            };
+            entries.push(JournalEntry {
+                pos,
+                old_data,
+                new_data,
+            });
+            locked.push(cache_entry);
+        }
+        let atomic = self.commit_options.mode == CommitMode::Atomic;
+        if entries.is_empty() || (atomic && !outcome.failed.is_empty()) {
+            return Ok(outcome);
         }
 
-        Ok(())
+        if let Some(journal) = &self.journal {
+            journal.write(&entries).await?;
+        }
+        let batch = entries
+            .iter()
+            .map(|entry| (entry.pos, entry.new_data.as_slice()));
+        match self.map.set_mapblocks_data(batch).await {
+            Ok(()) => {
+                for (entry, cache_entry) in entries.iter().zip(&mut locked) {
+                    cache_entry.tainted = false;
+                    outcome.written.push(entry.pos);
+                }
+            }
+            Err(e) if atomic => {
+                // The transaction has been rolled back, so there is nothing to recover
+                if let Some(journal) = &self.journal {
+                    journal.clear().await?;
+                }
+                return Err(e);
+            }
+            // Find out which mapblocks fail, and write the others anyway
+            Err(_) => {
+                for (entry, cache_entry) in entries.iter().zip(&mut locked) {
+                    match self.map.set_block_data(entry.pos, &entry.new_data).await {
+                        Ok(()) => {
+                            cache_entry.tainted = false;
+                            outcome.written.push(entry.pos);
+                        }
+                        Err(e) => outcome.failed.push((entry.pos, e)),
+                    }
+                }
+            }
+        }
+        if let Some(journal) = &self.journal {
+            if outcome.failed.is_empty() {
+                journal.clear().await?;
+            }
+        }
+        Ok(outcome)
     }
 }
+
+/// The status of every modified mapblock after [`MapEdit::try_commit`]
+#[derive(Debug, Default)]
+pub struct CommitOutcome {
+    /// The mapblocks that have been written
+    pub written: Vec<BlockPos>,
+    /// The mapblocks that could not be written, along with the reason
+    pub failed: Vec<(BlockPos, MapDataError)>,
+}
+
+impl CommitOutcome {
+    /// Returns true if every modified mapblock has been written
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    /// Returns the error of the first failed mapblock, if any
+    pub fn into_result(self) -> Result<()> {
+        match self.failed.into_iter().next() {
+            Some((pos, e)) => Err(MapDataError::AtBlock(pos, Box::new(e))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// How far [`MapEdit::flood_fill`] may spread
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FillLimits {
+    /// The largest number of nodes to replace
+    pub max_nodes: usize,
+    /// The corners of the box that the fill stays in, if any
+    pub bounds: Option<(I16Vec3, I16Vec3)>,
+}
+
+/// The result of [`MapEdit::flood_fill`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FillOutcome {
+    /// The number of replaced nodes
+    pub filled: usize,
+    /// True if the fill stopped at [`FillLimits::max_nodes`] before checking all candidates
+    pub truncated: bool,
+}
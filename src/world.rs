@@ -1,5 +1,16 @@
 //! Contains the [`World`] along with [`WorldError`]
 
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+use crate::auth::{AuthData, AuthError};
+use crate::aux_file::AuxFile;
+use crate::journal::Journal;
+use crate::map_block::CONTENT_IGNORE;
+use crate::map_data::Rewrite;
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+use crate::mod_storage::{ModStorage, ModStorageError};
+use crate::players::{PlayerData, PlayerError};
+use crate::report::{self, ReportOptions};
+use crate::scrub::{scrub_block, scrub_player, unparseable_items, ScrubReport, ScrubRule};
 use crate::MapData;
 use crate::MapDataError;
 use crate::MapEdit;
@@ -7,6 +18,9 @@ use async_std::fs;
 use async_std::fs::File;
 use async_std::io::BufReader;
 use async_std::prelude::*;
+use futures::future;
+use futures::TryStreamExt;
+use glam::I16Vec3;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
@@ -72,20 +86,60 @@ impl World {
         Ok(result)
     }
 
-    async fn get_backend_name(&self) -> Result<String, WorldError> {
+    /// Opens a `key = value` file in the world directory, like `env_meta.txt`
+    ///
+    /// `name` is relative to the world directory. A missing file is treated as empty.
+    pub async fn aux_file(&self, name: impl AsRef<Path>) -> Result<AuxFile, WorldError> {
+        let World(path) = self;
+        Ok(AuxFile::open(path.join(name)).await?)
+    }
+
+    /// Reads the value of `key` from a `key = value` file in the world directory
+    async fn read_setting(&self, file_name: &str, key: &str) -> Result<String, WorldError> {
+        match self.aux_file(file_name).await?.get(key) {
+            Some(value) => Ok(value.to_string()),
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("{file_name} lacks '{key}'"),
+            )
+            .into()),
+        }
+    }
+
+    /// Reads the current game time of the world in seconds
+    ///
+    /// This is the time the engine stores in mapblocks when saving them,
+    /// see [`WriteMaintenance`](`crate::map_block::WriteMaintenance`).
+    /// The engine records it in `env_meta.txt` when shutting down.
+    pub async fn get_game_time(&self) -> Result<u32, WorldError> {
+        Ok(self
+            .read_setting("env_meta.txt", "game_time")
+            .await?
+            .parse()?)
+    }
+
+    /// Reads the seed of the map generator
+    ///
+    /// The engine records it in `map_meta.txt` when the world is created.
+    pub async fn get_seed(&self) -> Result<u64, WorldError> {
+        Ok(self.read_setting("map_meta.txt", "seed").await?.parse()?)
+    }
+
+    /// Reads the backend configured under `key` in world.mt
+    async fn get_backend_name(&self, key: &str) -> Result<String, WorldError> {
         match self.get_world_metadata().await {
             Err(e) => {
                 if e.kind() == std::io::ErrorKind::NotFound {
-                    log::warn!("No world.mt found, falling back to sqlite3 backend");
+                    log::warn!("No world.mt found, falling back to sqlite3 {key}");
                     Ok(String::from("sqlite3"))
                 } else {
                     Err(WorldError::IOError(e))
                 }
             }
-            Ok(metadata) => match metadata.get("backend") {
+            Ok(metadata) => match metadata.get(key) {
                 Some(backend) => Ok(backend.clone()),
                 None => {
-                    log::warn!("No backend mentioned in world.mt, falling back to sqlite3");
+                    log::warn!("No {key} mentioned in world.mt, falling back to sqlite3");
                     Ok(String::from("sqlite3"))
                 }
             },
@@ -103,12 +157,12 @@ impl World {
     /// });
     /// ```
     pub async fn get_map_data_backend(&self, read_only: bool) -> Result<MapData, WorldError> {
-        let backend = self.get_backend_name().await?;
-        match backend.as_str() {
+        let backend = self.get_backend_name("backend").await?;
+        let map = match backend.as_str() {
             #[cfg(feature = "sqlite")]
             "sqlite3" => {
                 let World(path) = self;
-                Ok(MapData::from_sqlite_file(path.join("map.sqlite"), read_only).await?)
+                MapData::from_sqlite_file(path.join("map.sqlite"), read_only).await?
             }
             #[cfg(feature = "postgres")]
             "postgresql" => {
@@ -120,7 +174,7 @@ impl World {
                 })?;
                 let uri = &keyvalue_to_uri_connectionstr(connstr)
                     .map_err(WorldError::BogusBackendConfig)?;
-                Ok(MapData::from_pg_connection_params(uri).await?)
+                MapData::from_pg_connection_params(uri).await?
             }
             #[cfg(feature = "redis")]
             "redis" => {
@@ -137,19 +191,28 @@ impl World {
                         "The backend 'redis' requires a 'redis_hash' in world.mt",
                     ))
                 })?;
-                Ok(MapData::from_redis_connection_params(host, port, hash).await?)
+                MapData::from_redis_connection_params(host, port, hash).await?
             }
             #[cfg(feature = "experimental-leveldb")]
             "leveldb" => {
                 let World(path) = self;
                 let path = path.clone();
-                Ok(
-                    task::spawn_blocking(move || MapData::from_leveldb(path.join("map.db")))
-                        .await?,
-                )
+                task::spawn_blocking(move || MapData::from_leveldb(path.join("map.db"))).await?
             }
-            _ => Err(WorldError::UnknownBackend(backend)),
-        }
+            _ => return Err(WorldError::UnknownBackend(backend)),
+        };
+        // Already done by `from_sqlite_file`, but not by the other backends
+        self.journal().check_on_open(&map, read_only).await?;
+        Ok(map)
+    }
+
+    /// Returns the journal that protects commits to the map, see [`Journal::for_map_file`]
+    ///
+    /// Pass it to [`MapEdit::set_journal`] to make commits recoverable.
+    /// An interrupted commit is completed when the map is opened writable next time.
+    pub fn journal(&self) -> Journal {
+        let World(path) = self;
+        Journal::for_map_file(path.join("map.sqlite"))
     }
 
     /// Returns a handle to the map database
@@ -184,10 +247,285 @@ impl World {
         self.get_map_data_backend(true).await
     }
 
+    /// Returns a handle to the authentication database
+    ///
+    /// The backend is selected by `auth_backend` in world.mt.
+    #[cfg(any(feature = "sqlite", feature = "postgres"))]
+    pub async fn get_auth_backend(&self, read_only: bool) -> Result<AuthData, WorldError> {
+        let backend = self.get_backend_name("auth_backend").await?;
+        match backend.as_str() {
+            #[cfg(feature = "sqlite")]
+            "sqlite3" => {
+                let World(path) = self;
+                Ok(AuthData::from_sqlite_file(path.join("auth.sqlite"), read_only).await?)
+            }
+            #[cfg(feature = "postgres")]
+            "postgresql" => {
+                let meta = self.get_world_metadata().await?;
+                let connstr = meta.get("pgsql_auth_connection").ok_or_else(|| {
+                    WorldError::BogusBackendConfig(String::from(
+                        "The auth backend 'postgres' requires a 'pgsql_auth_connection' in world.mt",
+                    ))
+                })?;
+                let uri = &keyvalue_to_uri_connectionstr(connstr)
+                    .map_err(WorldError::BogusBackendConfig)?;
+                Ok(AuthData::from_pg_connection_params(uri).await?)
+            }
+            _ => Err(WorldError::UnknownBackend(backend)),
+        }
+    }
+
+    /// Returns a read-only handle to the authentication database
+    #[cfg(any(feature = "sqlite", feature = "postgres"))]
+    pub async fn get_auth(&self) -> Result<AuthData, WorldError> {
+        self.get_auth_backend(true).await
+    }
+
+    /// Returns a handle to the authentication database that allows modifications
+    #[cfg(any(feature = "sqlite", feature = "postgres"))]
+    pub async fn get_mutable_auth(&self) -> Result<AuthData, WorldError> {
+        self.get_auth_backend(false).await
+    }
+
+    /// Returns a handle to the mod storage database
+    ///
+    /// The backend is selected by `mod_storage_backend` in world.mt.
+    #[cfg(any(feature = "sqlite", feature = "postgres"))]
+    pub async fn get_mod_storage_backend(&self, read_only: bool) -> Result<ModStorage, WorldError> {
+        let backend = self.get_backend_name("mod_storage_backend").await?;
+        match backend.as_str() {
+            #[cfg(feature = "sqlite")]
+            "sqlite3" => {
+                let World(path) = self;
+                Ok(
+                    ModStorage::from_sqlite_file(path.join("mod_storage.sqlite"), read_only)
+                        .await?,
+                )
+            }
+            #[cfg(feature = "postgres")]
+            "postgresql" => {
+                let meta = self.get_world_metadata().await?;
+                let connstr = meta.get("pgsql_mod_storage_connection").ok_or_else(|| {
+                    WorldError::BogusBackendConfig(String::from(
+                        "The mod storage backend 'postgres' requires a 'pgsql_mod_storage_connection' in world.mt",
+                    ))
+                })?;
+                let uri = &keyvalue_to_uri_connectionstr(connstr)
+                    .map_err(WorldError::BogusBackendConfig)?;
+                Ok(ModStorage::from_pg_connection_params(uri).await?)
+            }
+            _ => Err(WorldError::UnknownBackend(backend)),
+        }
+    }
+
+    /// Returns a read-only handle to the mod storage database
+    #[cfg(any(feature = "sqlite", feature = "postgres"))]
+    pub async fn mod_storage(&self) -> Result<ModStorage, WorldError> {
+        self.get_mod_storage_backend(true).await
+    }
+
+    /// Returns a handle to the mod storage database that allows modifications
+    #[cfg(any(feature = "sqlite", feature = "postgres"))]
+    pub async fn mutable_mod_storage(&self) -> Result<ModStorage, WorldError> {
+        self.get_mod_storage_backend(false).await
+    }
+
+    /// Returns a handle to the player database
+    ///
+    /// The backend is selected by `player_backend` in world.mt.
+    pub async fn get_players_backend(&self, read_only: bool) -> Result<PlayerData, WorldError> {
+        let backend = self.get_backend_name("player_backend").await?;
+        let World(path) = self;
+        match backend.as_str() {
+            #[cfg(feature = "sqlite")]
+            "sqlite3" => {
+                Ok(PlayerData::from_sqlite_file(path.join("players.sqlite"), read_only).await?)
+            }
+            "files" => Ok(PlayerData::Files(path.join("players"))),
+            _ => Err(WorldError::UnknownBackend(backend)),
+        }
+    }
+
+    /// Returns a read-only handle to the player database
+    pub async fn get_players(&self) -> Result<PlayerData, WorldError> {
+        self.get_players_backend(true).await
+    }
+
+    /// Returns a handle to the player database that allows modifications
+    pub async fn get_mutable_players(&self) -> Result<PlayerData, WorldError> {
+        self.get_players_backend(false).await
+    }
+
     /// Returns a VoxelManip with the ability to read and write nodes
     pub async fn get_voxel_manip(&self, writable: bool) -> Result<MapEdit, WorldError> {
         Ok(MapEdit::new(self.get_map_data_backend(!writable).await?))
     }
+
+    /// Suggests a safe spawn point within the box between `a` and `b`
+    ///
+    /// A safe spot has two air nodes above the highest node of its column within the box,
+    /// which has to be solid ground. Columns topped by water or lava, i.e. contents
+    /// whose item name contains `water` or `lava`, or reaching into mapblocks that have
+    /// not been generated, are skipped. Of all safe spots, the one closest to `anchor`
+    /// or, if it is `None`, to the world origin is returned.
+    ///
+    /// The returned position is the lower of the two air nodes,
+    /// as expected by the `static_spawnpoint` setting.
+    ///
+    /// ```
+    /// use minetestworld::World;
+    /// use glam::I16Vec3;
+    /// use async_std::task;
+    ///
+    /// task::block_on(async {
+    ///     let world = World::open("TestWorld");
+    ///     let (a, b) = (I16Vec3::new(-200, -20, 40), I16Vec3::new(-170, 10, 70));
+    ///     if let Some(pos) = world.suggest_spawn(a, b, None).await.unwrap() {
+    ///         println!("static_spawnpoint = {}, {}, {}", pos.x, pos.y, pos.z);
+    ///     }
+    /// });
+    /// ```
+    pub async fn suggest_spawn(
+        &self,
+        a: I16Vec3,
+        b: I16Vec3,
+        anchor: Option<I16Vec3>,
+    ) -> Result<Option<I16Vec3>, WorldError> {
+        let (min, max) = (a.min(b), a.max(b));
+        // Nodes at the top of the box need air above them as well
+        let top = max.saturating_add(I16Vec3::new(0, 2, 0));
+        let data = MapEdit::new(self.get_map_data().await?)
+            .get_data(min, top)
+            .await?;
+        let is_hazard = |content: &[u8]| {
+            let name = content.rsplit(|&c| c == b':').next().unwrap_or(content);
+            name.windows(5).any(|w| w == b"water") || name.windows(4).any(|w| w == b"lava")
+        };
+        let content = |pos: I16Vec3| {
+            data.index(pos)
+                .map(|i| &data.palette[data.content[i] as usize][..])
+        };
+
+        let anchor = anchor.unwrap_or(I16Vec3::ZERO).as_ivec3();
+        let mut best: Option<(i32, I16Vec3)> = None;
+        for z in min.z..=max.z {
+            for x in min.x..=max.x {
+                let Some(ground) = (min.y..=top.y)
+                    .rev()
+                    .map(|y| I16Vec3::new(x, y, z))
+                    .find(|&pos| content(pos) != Some(&b"air"[..]))
+                else {
+                    continue;
+                };
+                let spawn = ground + I16Vec3::Y;
+                let safe = ground.y <= max.y
+                    && ground.y + 2 <= top.y
+                    && content(ground).is_some_and(|c| c != CONTENT_IGNORE && !is_hazard(c));
+                if !safe {
+                    continue;
+                }
+                let distance = (spawn.as_ivec3() - anchor).length_squared();
+                if best.is_none_or(|(closest, _)| distance < closest) {
+                    best = Some((distance, spawn));
+                }
+            }
+        }
+        Ok(best.map(|(_, pos)| pos))
+    }
+
+    /// Removes or redacts private information from the map and the player database
+    ///
+    /// Every node metadata variable, player metadata field and item metadata field
+    /// (e.g. the text of a book) is checked against `rules`; the first matching
+    /// rule is applied. Only modified mapblocks and players are written back.
+    /// Mapblocks that cannot be decoded and inventory items that cannot be parsed
+    /// are left as they are and reported.
+    ///
+    /// The server must not be running while the world is scrubbed.
+    ///
+    /// ```no_run
+    /// use minetestworld::scrub::ScrubRule;
+    /// use minetestworld::World;
+    /// use async_std::task;
+    ///
+    /// let rules = [
+    ///     ScrubRule::remove("owner"),
+    ///     ScrubRule::redact("infotext", "[redacted]"),
+    ///     ScrubRule::redact("text", ""),
+    /// ];
+    /// task::block_on(async {
+    ///     let report = World::open("MyWorld").scrub_private_data(&rules).await.unwrap();
+    ///     println!("Scrubbed {} fields", report.fields);
+    /// });
+    /// ```
+    pub async fn scrub_private_data(&self, rules: &[ScrubRule]) -> Result<ScrubReport, WorldError> {
+        let mut report = ScrubReport::default();
+
+        let map = self.get_map_data_backend(false).await?;
+        let positions = map.all_mapblock_positions().await;
+        map.rewrite_blocks(
+            &map,
+            positions,
+            |_| (),
+            |pos, data| {
+                future::ready(match scrub_block(&data, rules, &mut report) {
+                    Ok(Some(data)) => Ok(Rewrite::Replace(data)),
+                    Ok(None) => Ok(Rewrite::Keep),
+                    Err(MapDataError::MapBlockError(_)) => {
+                        report.undecodable.push(pos);
+                        Ok(Rewrite::Keep)
+                    }
+                    Err(e) => Err(e),
+                })
+            },
+        )
+        .await?;
+
+        let players = self.get_mutable_players().await?;
+        let all_players: Vec<_> = players.all_players().try_collect().await?;
+        for mut player in all_players {
+            report.unparseable_items += unparseable_items(&player.inventory);
+            let fields = scrub_player(&mut player, rules);
+            if fields > 0 {
+                players.save_player(&player).await?;
+                report.players += 1;
+                report.fields += fields;
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Summarizes the map data in a report
+    ///
+    /// The report contains the map size, the nodes per mod, unknown nodes,
+    /// the biggest mapblocks and a top-down view, rendered as HTML or JSON
+    /// according to `options`. See [`report`](`crate::report`) for details.
+    ///
+    /// ```
+    /// use minetestworld::report::{ReportFormat, ReportOptions};
+    /// use minetestworld::World;
+    /// use async_std::task;
+    ///
+    /// let options = ReportOptions {
+    ///     format: ReportFormat::Json,
+    ///     ..Default::default()
+    /// };
+    /// let json = task::block_on(async {
+    ///     World::open("TestWorld").generate_report(&options).await
+    /// }).unwrap();
+    /// assert!(json.starts_with("{\"world_name\":\"Hallo\""));
+    /// ```
+    pub async fn generate_report(&self, options: &ReportOptions) -> Result<String, WorldError> {
+        let world_name = self
+            .get_world_metadata()
+            .await?
+            .remove("world_name")
+            .unwrap_or_default();
+        let map = self.get_map_data().await?;
+        let report = report::collect(&map, &world_name, options).await?;
+        Ok(report.render(options.format))
+    }
 }
 
 /// Represents a failure to interact with the world
@@ -199,6 +537,17 @@ pub enum WorldError {
     #[error("Map data error: {0}")]
     /// The map data backend returned an error
     MapDataError(#[from] MapDataError),
+    #[cfg(any(feature = "sqlite", feature = "postgres"))]
+    #[error("Auth data error: {0}")]
+    /// The auth backend returned an error
+    AuthError(#[from] AuthError),
+    #[cfg(any(feature = "sqlite", feature = "postgres"))]
+    #[error("Mod storage error: {0}")]
+    /// The mod storage backend returned an error
+    ModStorageError(#[from] ModStorageError),
+    #[error("Player data error: {0}")]
+    /// The player backend returned an error
+    PlayerError(#[from] PlayerError),
     #[error("Unknown backend '{0}'")]
     /// The map data backend is not known or implemented
     UnknownBackend(String),
@@ -0,0 +1,503 @@
+//! Reading and writing schematics in the format of the WorldEdit mod
+//!
+//! WorldEdit saves regions as `.we` files, which contain a version header followed by
+//! a serialized Lua table with one entry per node:
+//!
+//! ```text
+//! 5:return {{x=0,y=0,z=0,name="default:stone",param1=0,param2=0}, ...}
+//! ```
+//!
+//! The JSON variant of this format, a JSON array of the same entries, is read as well.
+//! The result is a [`Schematic`] that can be placed with
+//! [`EditPlan::place_schematic`](`crate::edit_plan::EditPlan::place_schematic`).
+//!
+//! ```
+//! use minetestworld::worldedit;
+//! use glam::U16Vec3;
+//!
+//! let schematic = worldedit::parse(r#"5:return {{x=1,y=0,z=0,name="default:stone"}}"#).unwrap();
+//! assert_eq!(schematic.size(), U16Vec3::new(2, 1, 1));
+//! assert_eq!(schematic.get_node(U16Vec3::X).unwrap().param0, b"default:stone");
+//! assert!(schematic.get_node(U16Vec3::ZERO).is_none());
+//! ```
+
+use glam::{I16Vec3, IVec3, U16Vec3};
+use std::fmt::Write;
+
+use crate::edit_plan::{Schematic, SchematicMetadata};
+use crate::inventory::InventoryList;
+use crate::json;
+use crate::map_block::{Node, NodeVar};
+
+/// The serialization version written by [`serialize`]
+pub const WORLDEDIT_VERSION: u32 = 5;
+
+/// An error while reading a WorldEdit schematic
+#[derive(thiserror::Error, Debug)]
+pub enum WorldEditError {
+    /// The file is not valid Lua or JSON
+    ///
+    /// The byte offset of the error and a description are contained.
+    #[error("Syntax error at byte {0}: {1}")]
+    Syntax(usize, String),
+    /// The data is well-formed, but does not describe a schematic
+    #[error("Invalid schematic: {0}")]
+    Invalid(String),
+    /// The file uses one of the legacy formats before version 4
+    #[error("WorldEdit format version {0} is not supported")]
+    UnsupportedVersion(u32),
+}
+
+/// A value of a serialized Lua table or JSON document
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    String(Vec<u8>),
+    Table {
+        array: Vec<Value>,
+        fields: Vec<(Vec<u8>, Value)>,
+    },
+}
+
+impl Value {
+    fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Table { fields, .. } => fields
+                .iter()
+                .find(|(k, _)| k == key.as_bytes())
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+
+struct Parser<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> WorldEditError {
+        WorldEditError::Syntax(self.offset, message.to_string())
+    }
+
+    fn skip_whitespace(&mut self) {
+        loop {
+            while self
+                .data
+                .get(self.offset)
+                .is_some_and(|c| c.is_ascii_whitespace())
+            {
+                self.offset += 1;
+            }
+            // Lua comments
+            if self.data[self.offset..].starts_with(b"--") {
+                while self.data.get(self.offset).is_some_and(|&c| c != b'\n') {
+                    self.offset += 1;
+                }
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.data.get(self.offset).copied()
+    }
+
+    fn eat(&mut self, token: &[u8]) -> bool {
+        self.skip_whitespace();
+        let found = self.data[self.offset..].starts_with(token);
+        if found {
+            self.offset += token.len();
+        }
+        found
+    }
+
+    fn identifier(&mut self) -> Option<&[u8]> {
+        self.skip_whitespace();
+        let start = self.offset;
+        while self
+            .data
+            .get(self.offset)
+            .is_some_and(|&c| c == b'_' || c.is_ascii_alphanumeric())
+        {
+            self.offset += 1;
+        }
+        (self.offset > start && !self.data[start].is_ascii_digit())
+            .then(|| &self.data[start..self.offset])
+            .or_else(|| {
+                self.offset = start;
+                None
+            })
+    }
+
+    fn value(&mut self) -> Result<Value, WorldEditError> {
+        match self.peek() {
+            Some(b'{') | Some(b'[') => self.table(),
+            Some(b'"') | Some(b'\'') => Ok(Value::String(self.string()?)),
+            Some(c) if c == b'-' || c == b'.' || c.is_ascii_digit() => self.number(),
+            _ => match self.identifier() {
+                Some(b"nil") | Some(b"null") => Ok(Value::Nil),
+                Some(b"true") => Ok(Value::Bool(true)),
+                Some(b"false") => Ok(Value::Bool(false)),
+                _ => Err(self.error("expected a value")),
+            },
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, WorldEditError> {
+        let start = self.offset;
+        while self
+            .data
+            .get(self.offset)
+            .is_some_and(|&c| c.is_ascii_alphanumeric() || b"+-.".contains(&c))
+        {
+            self.offset += 1;
+        }
+        std::str::from_utf8(&self.data[start..self.offset])
+            .ok()
+            .and_then(|number| number.parse().ok())
+            .map(Value::Number)
+            .ok_or_else(|| self.error("malformed number"))
+    }
+
+    /// Reads a string literal, with the escape sequences of both Lua and JSON
+    fn string(&mut self) -> Result<Vec<u8>, WorldEditError> {
+        let quote = self.data[self.offset];
+        self.offset += 1;
+        let mut string = vec![];
+        loop {
+            let Some(&c) = self.data.get(self.offset) else {
+                return Err(self.error("unterminated string"));
+            };
+            self.offset += 1;
+            if c == quote {
+                return Ok(string);
+            }
+            if c != b'\\' {
+                string.push(c);
+                continue;
+            }
+            let Some(&escaped) = self.data.get(self.offset) else {
+                return Err(self.error("unterminated string"));
+            };
+            self.offset += 1;
+            match escaped {
+                b'n' | b'\n' => string.push(b'\n'),
+                b'r' => string.push(b'\r'),
+                b't' => string.push(b'\t'),
+                b'a' => string.push(0x07),
+                b'b' => string.push(0x08),
+                b'f' => string.push(0x0c),
+                b'v' => string.push(0x0b),
+                b'0'..=b'9' => {
+                    let start = self.offset - 1;
+                    while self.offset < start + 3
+                        && self.data.get(self.offset).is_some_and(u8::is_ascii_digit)
+                    {
+                        self.offset += 1;
+                    }
+                    // The digits are ASCII
+                    let code: u32 = std::str::from_utf8(&self.data[start..self.offset])
+                        .unwrap()
+                        .parse()
+                        .unwrap();
+                    string.push(
+                        u8::try_from(code).map_err(|_| self.error("escape is out of range"))?,
+                    );
+                }
+                b'u' => {
+                    let c = json::unescape_unicode(|| {
+                        let c = self.data.get(self.offset)?;
+                        self.offset += 1;
+                        Some(char::from(*c))
+                    })
+                    .ok_or_else(|| self.error("malformed unicode escape"))?;
+                    string.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                other => string.push(other),
+            }
+        }
+    }
+
+    /// Reads a Lua table or a JSON array or object
+    fn table(&mut self) -> Result<Value, WorldEditError> {
+        let close = if self.eat(b"{") {
+            b'}'
+        } else {
+            self.eat(b"[");
+            b']'
+        };
+        let mut array = vec![];
+        let mut fields = vec![];
+        loop {
+            if self.eat(&[close]) {
+                return Ok(Value::Table { array, fields });
+            }
+            let start = self.offset;
+            let key = if close == b'}' && self.eat(b"[") {
+                // Lua: ["key"] = value or [1] = value
+                let key = match self.value()? {
+                    Value::String(key) => key,
+                    Value::Number(n) => n.to_string().into_bytes(),
+                    _ => return Err(self.error("unsupported table key")),
+                };
+                if !self.eat(b"]") {
+                    return Err(self.error("expected ']'"));
+                }
+                Some(key)
+            } else if let Some(identifier) = self.identifier().map(<[u8]>::to_vec) {
+                if self.peek() == Some(b'=') {
+                    Some(identifier)
+                } else {
+                    // A keyword like `true` as a positional value
+                    self.offset = start;
+                    None
+                }
+            } else if self.peek() == Some(b'"') {
+                // JSON: "key": value
+                let key = self.string()?;
+                if self.peek() == Some(b':') {
+                    Some(key)
+                } else {
+                    self.offset = start;
+                    None
+                }
+            } else {
+                None
+            };
+            match key {
+                Some(key) => {
+                    if !self.eat(b"=") && !self.eat(b":") {
+                        return Err(self.error("expected '=' or ':'"));
+                    }
+                    fields.push((key, self.value()?));
+                }
+                None => array.push(self.value()?),
+            }
+            if !self.eat(b",") && !self.eat(b";") && self.peek() != Some(close) {
+                return Err(self.error("expected ',' or the end of the table"));
+            }
+        }
+    }
+}
+
+fn invalid(message: &str) -> WorldEditError {
+    WorldEditError::Invalid(message.to_string())
+}
+
+fn integer(entry: &Value, key: &str) -> Result<Option<i32>, WorldEditError> {
+    match entry.get(key) {
+        None | Some(Value::Nil) => Ok(None),
+        Some(&Value::Number(n)) if n.fract() == 0.0 && n.abs() <= f64::from(u16::MAX) => {
+            Ok(Some(n as i32))
+        }
+        Some(_) => Err(WorldEditError::Invalid(format!(
+            "'{key}' is not an integer"
+        ))),
+    }
+}
+
+fn metadata(meta: &Value) -> Result<SchematicMetadata, WorldEditError> {
+    let mut metadata = SchematicMetadata::default();
+    if let Some(Value::Table { fields, .. }) = meta.get("fields") {
+        for (key, value) in fields {
+            let value = match value {
+                Value::String(value) => value.clone(),
+                Value::Number(n) => n.to_string().into_bytes(),
+                _ => return Err(invalid("metadata values have to be strings")),
+            };
+            metadata.vars.push(NodeVar {
+                key: key.clone(),
+                value,
+                is_private: false,
+            });
+        }
+    }
+    if let Some(Value::Table { fields, .. }) = meta.get("inventory") {
+        for (name, list) in fields {
+            let Value::Table { array, .. } = list else {
+                return Err(invalid("inventory lists have to be tables"));
+            };
+            let items = array
+                .iter()
+                .map(|item| match item {
+                    Value::String(item) => Ok(String::from_utf8_lossy(item).into_owned()),
+                    _ => Err(invalid("inventory items have to be strings")),
+                })
+                .collect::<Result<_, _>>()?;
+            metadata.inventory.push(InventoryList {
+                name: String::from_utf8_lossy(name).into_owned(),
+                width: 0,
+                items,
+            });
+        }
+    }
+    Ok(metadata)
+}
+
+/// Parses a WorldEdit schematic, in the Lua or the JSON variant
+///
+/// The schematic's origin is the origin of the saved region. Only if some nodes have
+/// negative coordinates, it is moved to their smallest coordinates.
+/// Positions without an entry are left untouched when placing the schematic,
+/// which is how WorldEdit treats air.
+pub fn parse(data: &str) -> Result<Schematic, WorldEditError> {
+    let mut data = data.trim_start();
+    if let Some((version, rest)) = data.split_once(':') {
+        if let Ok(version) = version.parse::<u32>() {
+            if version < 4 {
+                return Err(WorldEditError::UnsupportedVersion(version));
+            }
+            data = rest;
+        }
+    }
+    let mut parser = Parser {
+        data: data.as_bytes(),
+        offset: 0,
+    };
+    if parser.identifier().is_some_and(|word| word != b"return") {
+        return Err(parser.error("expected 'return' or a table"));
+    }
+    if !matches!(parser.peek(), Some(b'{') | Some(b'[')) {
+        return Err(parser.error("expected a table"));
+    }
+    let Value::Table { array: entries, .. } = parser.value()? else {
+        unreachable!()
+    };
+    if parser.peek().is_some() {
+        return Err(parser.error("unexpected data after the table"));
+    }
+
+    let mut nodes = vec![];
+    for entry in &entries {
+        let pos = IVec3::new(
+            integer(entry, "x")?.ok_or_else(|| invalid("node without x"))?,
+            integer(entry, "y")?.ok_or_else(|| invalid("node without y"))?,
+            integer(entry, "z")?.ok_or_else(|| invalid("node without z"))?,
+        );
+        let Some(Value::String(name)) = entry.get("name") else {
+            return Err(invalid("node without name"));
+        };
+        let param = |key| {
+            integer(entry, key)?
+                .unwrap_or(0)
+                .try_into()
+                .map_err(|_| WorldEditError::Invalid(format!("'{key}' is out of range")))
+        };
+        let node = Node {
+            param0: name.clone(),
+            param1: param("param1")?,
+            param2: param("param2")?,
+        };
+        let meta = match entry.get("meta") {
+            Some(meta @ Value::Table { .. }) => Some(metadata(meta)?),
+            _ => None,
+        };
+        nodes.push((pos, node, meta));
+    }
+
+    if nodes.is_empty() {
+        return Ok(Schematic::new(U16Vec3::ZERO));
+    }
+    let min = nodes
+        .iter()
+        .map(|(pos, _, _)| *pos)
+        .fold(IVec3::ZERO, IVec3::min);
+    let max = nodes.iter().map(|(pos, _, _)| *pos).fold(min, IVec3::max);
+    let size = max - min + 1;
+    if size.max_element() > i32::from(i16::MAX) {
+        return Err(invalid("the schematic is too large"));
+    }
+    let mut schematic = Schematic::new(size.as_u16vec3());
+    for (pos, node, meta) in nodes {
+        let offset = (pos - min).as_u16vec3();
+        schematic.set_node(offset, Some(node));
+        schematic.set_metadata(offset, meta);
+    }
+    Ok(schematic)
+}
+
+/// Appends `data` to `dest` as a Lua string literal
+fn write_lua_string(data: &[u8], dest: &mut String) {
+    dest.push('"');
+    for &c in data {
+        match c {
+            b'"' => dest.push_str("\\\""),
+            b'\\' => dest.push_str("\\\\"),
+            b'\n' => dest.push_str("\\n"),
+            b'\r' => dest.push_str("\\r"),
+            // Escaping everything else that is not printable ASCII keeps the file valid UTF-8
+            0x20..=0x7e => dest.push(char::from(c)),
+            _ => {
+                let _ = write!(dest, "\\{c:03}");
+            }
+        }
+    }
+    dest.push('"');
+}
+
+/// Serializes `schematic` in the current WorldEdit format
+///
+/// Like WorldEdit does, air and empty positions are skipped.
+pub fn serialize(schematic: &Schematic) -> String {
+    let mut dest = format!("{WORLDEDIT_VERSION}:return {{");
+    let size = schematic.size().as_i16vec3();
+    let mut first = true;
+    for z in 0..size.z {
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let offset = I16Vec3::new(x, y, z).as_u16vec3();
+                let Some(node) = schematic.get_node(offset) else {
+                    continue;
+                };
+                if node.param0 == b"air" {
+                    continue;
+                }
+                if !first {
+                    dest.push(',');
+                }
+                first = false;
+                let _ = write!(dest, "{{x={x},y={y},z={z},name=");
+                write_lua_string(&node.param0, &mut dest);
+                let _ = write!(dest, ",param1={},param2={}", node.param1, node.param2);
+                if let Some(metadata) = schematic.get_metadata(offset) {
+                    dest.push_str(",meta={fields={");
+                    for (i, var) in metadata.vars.iter().enumerate() {
+                        if i > 0 {
+                            dest.push(',');
+                        }
+                        dest.push('[');
+                        write_lua_string(&var.key, &mut dest);
+                        dest.push_str("]=");
+                        write_lua_string(&var.value, &mut dest);
+                    }
+                    dest.push_str("},inventory={");
+                    for (i, list) in metadata.inventory.iter().enumerate() {
+                        if i > 0 {
+                            dest.push(',');
+                        }
+                        dest.push('[');
+                        write_lua_string(list.name.as_bytes(), &mut dest);
+                        dest.push_str("]={");
+                        for (j, item) in list.items.iter().enumerate() {
+                            if j > 0 {
+                                dest.push(',');
+                            }
+                            write_lua_string(item.as_bytes(), &mut dest);
+                        }
+                        dest.push('}');
+                    }
+                    dest.push_str("}}");
+                }
+                dest.push('}');
+            }
+        }
+    }
+    dest.push('}');
+    dest
+}
@@ -0,0 +1,203 @@
+use std::error::Error;
+mod common;
+
+use glam::{I16Vec3, U16Vec3};
+use minetestworld::analysis;
+use minetestworld::positions::{BlockArea, BlockPos, NodePos};
+use minetestworld::{MapBlock, MapData};
+
+const ANALYSIS_DIR: &str = "TestWorld analysis";
+const AIR_VOLUME_DIR: &str = "TestWorld air volume";
+const CO_OCCURRENCE_DIR: &str = "TestWorld co-occurrence";
+const COMPONENTS_DIR: &str = "TestWorld components";
+
+async fn generation_holes() -> Result<(), Box<dyn Error>> {
+    let map = MapData::from_sqlite_file(format!("{ANALYSIS_DIR}/map.sqlite"), false).await?;
+    let block = MapBlock::unloaded();
+    let center = I16Vec3::new(4, -2, 7);
+    let corner = center + I16Vec3::ONE;
+    let region = BlockArea::new(
+        BlockPos::from_index_vec(center - I16Vec3::ONE),
+        BlockPos::from_index_vec(corner),
+    );
+    for pos in region.iter() {
+        let index = pos.into_index_vec();
+        if index != center && index != corner {
+            map.set_mapblock(pos, &block).await?;
+        }
+    }
+
+    // The missing corner is not enclosed, so only the center is a hole
+    let holes = analysis::find_generation_holes(&map, region).await?;
+    assert_eq!(holes, [BlockPos::from_index_vec(center)]);
+
+    // Holes outside of the region are not reported
+    let elsewhere = BlockArea::new(
+        BlockPos::from_index_vec(corner),
+        BlockPos::from_index_vec(corner),
+    );
+    assert!(analysis::find_generation_holes(&map, elsewhere)
+        .await?
+        .is_empty());
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_generation_holes() -> Result<(), Box<dyn Error>> {
+    common::tear_up_empty_at(ANALYSIS_DIR).await?;
+    // No early return here, so that tear down happens in every case
+    let result = generation_holes().await;
+    let cleanup_result = common::tear_down_at(ANALYSIS_DIR).await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
+
+async fn air_volume() -> Result<(), Box<dyn Error>> {
+    let map = MapData::from_sqlite_file(format!("{AIR_VOLUME_DIR}/map.sqlite"), false).await?;
+    let mut block = MapBlock::unloaded();
+    let stone = block.get_or_create_content_id(b"default:stone");
+    let air = block.get_or_create_content_id(b"air");
+    for z in 0..16 {
+        for y in 0..16 {
+            for x in 0..16 {
+                let cavity = (4..6).contains(&x) && (4..6).contains(&y) && (4..6).contains(&z);
+                let shaft = x == 10 && z == 10 && y >= 6;
+                let tunnel = (11..14).contains(&x) && y == 6 && z == 10;
+                let content = if y >= 12 || cavity || shaft || tunnel {
+                    air
+                } else {
+                    stone
+                };
+                block.set_content(NodePos::try_from(U16Vec3::new(x, y, z)).unwrap(), content);
+            }
+        }
+    }
+    let pos = BlockPos::from_index_vec(I16Vec3::new(0, 0, 0));
+    map.set_mapblock(pos, &block).await?;
+
+    // The tunnel is connected to the sky by the shaft
+    let volume = analysis::air_volume_below_surface(&map, BlockArea::new(pos, pos)).await?;
+    assert_eq!(volume.cavities, [8]);
+    assert_eq!(volume.enclosed, 8);
+    assert_eq!(volume.solid, 255 * 11 - 8 - 3 + 5);
+    assert!(volume.cave_density() > 0.0);
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_air_volume() -> Result<(), Box<dyn Error>> {
+    common::tear_up_empty_at(AIR_VOLUME_DIR).await?;
+    // No early return here, so that tear down happens in every case
+    let result = air_volume().await;
+    let cleanup_result = common::tear_down_at(AIR_VOLUME_DIR).await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
+
+async fn co_occurrence() -> Result<(), Box<dyn Error>> {
+    let map = MapData::from_sqlite_file(format!("{CO_OCCURRENCE_DIR}/map.sqlite"), false).await?;
+    let node = |x, y, z| NodePos::try_from(U16Vec3::new(x, y, z)).unwrap();
+    let mut block = MapBlock::unloaded();
+    let ore = block.get_or_create_content_id(b"default:stone_with_iron");
+    block.set_content(node(0, 0, 0), ore);
+    block.set_content(node(15, 0, 0), ore);
+    map.set_mapblock(BlockPos::from_index_vec(I16Vec3::ZERO), &block)
+        .await?;
+    block = MapBlock::unloaded();
+    let lava = block.get_or_create_content_id(b"default:lava_source");
+    block.set_content(node(0, 1, 0), lava);
+    map.set_mapblock(BlockPos::from_index_vec(I16Vec3::X), &block)
+        .await?;
+
+    // Only the ore next to the block border is close to the lava
+    let contents = ["default:stone_with_iron", "default:lava_source"];
+    let stats = analysis::co_occurrence(&map, &contents, 2).await?;
+    assert_eq!(stats.totals["default:stone_with_iron"], 2);
+    assert_eq!(stats.totals["default:lava_source"], 1);
+    assert_eq!(stats.pairs.len(), 2);
+    assert_eq!(
+        stats.share("default:stone_with_iron", "default:lava_source"),
+        0.5
+    );
+    assert_eq!(
+        stats.share("default:lava_source", "default:stone_with_iron"),
+        1.0
+    );
+
+    let stats = analysis::co_occurrence(&map, &contents, 1).await?;
+    assert!(stats.pairs.is_empty());
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_co_occurrence() -> Result<(), Box<dyn Error>> {
+    common::tear_up_empty_at(CO_OCCURRENCE_DIR).await?;
+    // No early return here, so that tear down happens in every case
+    let result = co_occurrence().await;
+    let cleanup_result = common::tear_down_at(CO_OCCURRENCE_DIR).await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
+
+async fn components() -> Result<(), Box<dyn Error>> {
+    let map = MapData::from_sqlite_file(format!("{COMPONENTS_DIR}/map.sqlite"), false).await?;
+    let mut block = MapBlock::unloaded();
+    let stone = block.get_or_create_content_id(b"default:stone");
+    let air = block.get_or_create_content_id(b"air");
+    for z in 0..16 {
+        for y in 0..16 {
+            for x in 0..16 {
+                let room = (2..4).contains(&x) && (2..4).contains(&y) && (2..4).contains(&z);
+                let pocket = x == 10 && y == 10 && z == 10;
+                let opening = x == 15 && y == 5 && z == 5;
+                let content = if room || pocket || opening {
+                    air
+                } else {
+                    stone
+                };
+                block.set_content(NodePos::try_from(U16Vec3::new(x, y, z)).unwrap(), content);
+            }
+        }
+    }
+    let pos = BlockPos::from_index_vec(I16Vec3::new(1, 0, 0));
+    map.set_mapblock(pos, &block).await?;
+
+    let region = BlockArea::new(pos, pos);
+    let found =
+        analysis::connected_components(&map, region, |_, node| node.param0 == b"air").await?;
+    assert_eq!(found.components.len(), 3);
+    let [room, opening, pocket] = &found.components[..] else {
+        unreachable!()
+    };
+    assert_eq!(room.size, 8);
+    assert_eq!(room.min, I16Vec3::new(18, 2, 2));
+    assert_eq!(room.max, I16Vec3::new(19, 3, 3));
+    assert!(!room.touches_border);
+    assert_eq!(opening.size, 1);
+    assert!(opening.touches_border);
+    assert_eq!(pocket.min, I16Vec3::new(26, 10, 10));
+    assert_eq!(found.component_at(I16Vec3::new(19, 2, 3)), Some(room));
+    assert_eq!(found.component_at(I16Vec3::new(20, 2, 3)), None);
+
+    // The position is passed to the predicate
+    let found = analysis::connected_components(&map, region, |pos, node| {
+        pos.y > 5 && node.param0 == b"air"
+    })
+    .await?;
+    assert_eq!(found.components.len(), 1);
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_components() -> Result<(), Box<dyn Error>> {
+    common::tear_up_empty_at(COMPONENTS_DIR).await?;
+    // No early return here, so that tear down happens in every case
+    let result = components().await;
+    let cleanup_result = common::tear_down_at(COMPONENTS_DIR).await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
@@ -0,0 +1,44 @@
+use std::error::Error;
+mod common;
+
+use glam::I16Vec3;
+use minetestworld::positions::BlockPos;
+use minetestworld::MapData;
+
+const BATCH_DIR: &str = "TestWorld batch write";
+
+async fn batch_write() -> Result<(), Box<dyn Error>> {
+    let map = MapData::from_sqlite_file(format!("{BATCH_DIR}/map.sqlite"), false).await?;
+    let source = BlockPos::from_index_vec(I16Vec3::new(-12, -2, 4));
+    let data = map.get_block_data(source).await?;
+    let targets: Vec<_> = (-20..-15)
+        .map(|x| BlockPos::from_index_vec(I16Vec3::new(x, -2, 4)))
+        .collect();
+
+    map.set_mapblocks_data(targets.iter().map(|&pos| (pos, data.as_slice())))
+        .await?;
+    for &pos in &targets {
+        assert_eq!(map.get_block_data(pos).await?, data);
+    }
+
+    // Overwrite them with a modified mapblock
+    let mut block = map.get_mapblock(source).await?;
+    block.timestamp = 1234;
+    map.set_mapblocks(targets.iter().map(|&pos| (pos, &block)))
+        .await?;
+    for &pos in &targets {
+        assert_eq!(map.get_mapblock(pos).await?.timestamp, 1234);
+    }
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_batch_write() -> Result<(), Box<dyn Error>> {
+    common::tear_up_at(BATCH_DIR).await?;
+    // No early return here, so that tear down happens in every case
+    let result = batch_write().await;
+    let cleanup_result = common::tear_down_at(BATCH_DIR).await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use std::error::Error;
+mod common;
+
+use glam::I16Vec3;
+use minetestworld::{MapData, MapEdit};
+
+const CACHE_DIR: &str = "TestWorld cache limit";
+
+async fn cache_limit() -> Result<(), Box<dyn Error>> {
+    let map_path = format!("{CACHE_DIR}/map.sqlite");
+    // One node in each of 40 mapblocks
+    let nodes: Vec<_> = (0..40).map(|i| I16Vec3::new(i * 16, 40, 70)).collect();
+
+    let mut vm = MapEdit::new(MapData::from_sqlite_file(&map_path, false).await?);
+    vm.set_cache_limit(Some(8));
+    for &node in &nodes {
+        vm.set_content(node, b"default:mese").await?;
+    }
+    // The first mapblocks have been evicted, and written on the way out
+    assert!(!vm.is_in_cache(nodes[0]));
+    assert!(vm.is_in_cache(nodes[39]));
+    vm.commit().await?;
+    std::mem::drop(vm);
+
+    let vm = MapEdit::new(MapData::from_sqlite_file(&map_path, true).await?);
+    for &node in &nodes {
+        assert_eq!(vm.get_node(node).await?.param0, b"default:mese");
+    }
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_cache_limit() -> Result<(), Box<dyn Error>> {
+    common::tear_up_at(CACHE_DIR).await?;
+    // No early return here, so that tear down happens in every case
+    let result = cache_limit().await;
+    let cleanup_result = common::tear_down_at(CACHE_DIR).await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use std::error::Error;
+mod common;
+use minetestworld::auth::AuthError;
+use minetestworld::World;
+
+async fn change_auth() -> Result<(), Box<dyn Error>> {
+    let world = World::open("TestWorld copy");
+    let auth = world.get_mutable_auth().await?;
+
+    auth.grant_privilege("sam", "fly").await?;
+    auth.revoke_privilege("sam", "shout").await?;
+    auth.set_password("sam", "").await?;
+    let sam = auth.get_user("sam").await?;
+    assert!(sam.has_privilege("fly"));
+    assert!(sam.has_privilege("interact"));
+    assert!(!sam.has_privilege("shout"));
+    assert_eq!(sam.password, "");
+
+    auth.delete_user("sam").await?;
+    assert!(matches!(
+        auth.get_user("sam").await,
+        Err(AuthError::UserNonexistent(_))
+    ));
+    assert!(matches!(
+        auth.grant_privilege("sam", "fly").await,
+        Err(AuthError::UserNonexistent(_))
+    ));
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_change_auth() -> Result<(), Box<dyn Error>> {
+    common::tear_up().await?;
+    // No early return here, so that tear down happens in every case
+    let result = change_auth().await;
+    let cleanup_result = common::tear_down().await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
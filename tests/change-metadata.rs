@@ -0,0 +1,62 @@
+use std::error::Error;
+mod common;
+use glam::I16Vec3;
+use minetestworld::inventory::InventoryList;
+use minetestworld::World;
+
+async fn change_metadata() -> Result<(), Box<dyn Error>> {
+    let world = World::open("TestWorld copy");
+    let sign = I16Vec3::new(3, 10, -4);
+    let chest = I16Vec3::new(4, 10, -4);
+
+    let vm = world.get_voxel_manip(true).await?;
+    vm.set_content(sign, b"default:sign_wall_wood").await?;
+    vm.set_metadata_var(sign, b"text", b"Welcome").await?;
+    vm.set_metadata_var(sign, b"infotext", b"\"Welcome\"")
+        .await?;
+    vm.set_content(chest, b"default:chest").await?;
+    vm.set_metadata_var(chest, b"formspec", b"size[8,9]")
+        .await?;
+    vm.set_inventory_list(
+        chest,
+        InventoryList {
+            name: String::from("main"),
+            width: 0,
+            items: vec![String::from("default:apple 5"), String::new()],
+        },
+    )
+    .await?;
+    vm.commit().await?;
+    std::mem::drop(vm);
+
+    let vm = world.get_voxel_manip(true).await?;
+    let metadata = vm.get_metadata(sign).await?.unwrap();
+    assert_eq!(metadata.get(b"text"), Some(&b"Welcome"[..]));
+    let metadata = vm.get_metadata(chest).await?.unwrap();
+    assert_eq!(metadata.get(b"formspec"), Some(&b"size[8,9]"[..]));
+    let main = metadata.inventory_list("main").unwrap();
+    assert_eq!(main.items, ["default:apple 5", ""]);
+
+    // Removing the last variable leaves empty metadata, which is not saved
+    vm.remove_metadata(chest).await?;
+    vm.set_metadata_var(sign, b"text", b"").await?;
+    vm.set_metadata_var(sign, b"infotext", b"").await?;
+    vm.commit().await?;
+    std::mem::drop(vm);
+
+    let vm = world.get_voxel_manip(true).await?;
+    assert!(vm.get_metadata(chest).await?.is_none());
+    assert!(vm.get_metadata(sign).await?.is_none());
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_change_metadata() -> Result<(), Box<dyn Error>> {
+    common::tear_up().await?;
+    // No early return here, so that tear down happens in every case
+    let result = change_metadata().await;
+    let cleanup_result = common::tear_down().await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use std::error::Error;
+mod common;
+use futures::TryStreamExt;
+use minetestworld::World;
+
+async fn change_mod_storage() -> Result<(), Box<dyn Error>> {
+    let world = World::open("TestWorld copy");
+    let storage = world.mutable_mod_storage().await?;
+
+    storage.set("areas", b"count", b"3").await?;
+    storage.set("areas", b"3", b"return {}").await?;
+    assert!(storage.delete("xban2", b"db_version").await?);
+    assert!(!storage.delete("xban2", b"db_version").await?);
+
+    assert_eq!(
+        storage.get("areas", b"count").await?.as_deref(),
+        Some(&b"3"[..])
+    );
+    let keys: Vec<_> = storage
+        .entries("areas")
+        .map_ok(|(key, _)| key)
+        .try_collect()
+        .await?;
+    assert_eq!(keys, [&b"3"[..], b"areas_version", b"count"]);
+    let mods: Vec<_> = storage.mod_names().try_collect().await?;
+    assert_eq!(mods, ["areas", "unified_inventory"]);
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_change_mod_storage() -> Result<(), Box<dyn Error>> {
+    common::tear_up().await?;
+    // No early return here, so that tear down happens in every case
+    let result = change_mod_storage().await;
+    let cleanup_result = common::tear_down().await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
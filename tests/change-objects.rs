@@ -0,0 +1,69 @@
+use std::error::Error;
+mod common;
+use futures::TryStreamExt;
+use glam::Vec3;
+use minetestworld::map_block::{LuaEntity, StaticObject};
+use minetestworld::{MapBlock, World};
+
+fn entity(name: &str) -> LuaEntity {
+    LuaEntity {
+        name: name.to_string(),
+        staticdata: vec![],
+        hp: 10,
+        velocity: Vec3::ZERO,
+        rotation: Vec3::ZERO,
+    }
+}
+
+/// Decodes the Lua entities of a serialized mapblock
+fn lua_entities(data: &[u8]) -> Result<Vec<(Vec3, LuaEntity)>, Box<dyn Error>> {
+    let block = MapBlock::from_data(data)?;
+    Ok(block.lua_entities().collect::<Result<_, _>>()?)
+}
+
+async fn change_objects() -> Result<(), Box<dyn Error>> {
+    let map = World::open("TestWorld copy")
+        .get_map_data_backend(false)
+        .await?;
+    let positions: Vec<_> = map.all_mapblock_positions().await.try_collect().await?;
+    let pos = positions[0];
+    let center = (pos.into_index_vec() * 16).as_vec3() + Vec3::splat(8.0);
+
+    let mut block = MapBlock::from_data(map.get_block_data(pos).await?.as_slice())?;
+    block.static_objects.clear();
+    for name in ["mobs:sheep", "__builtin:item", "mobs:sheep"] {
+        block
+            .static_objects
+            .push(StaticObject::from_lua_entity(center, &entity(name)));
+    }
+    map.set_block_data(pos, &block.to_binary()?).await?;
+
+    let names: Vec<_> = lua_entities(&map.get_block_data(pos).await?)?
+        .into_iter()
+        .map(|(_, entity)| entity.name)
+        .collect();
+    assert_eq!(names, ["mobs:sheep", "__builtin:item", "mobs:sheep"]);
+
+    let removed = map
+        .clear_objects(
+            |object| matches!(object.lua_entity(), Ok(Some(entity)) if entity.name == "mobs:sheep"),
+        )
+        .await?;
+    assert_eq!(removed, 2);
+    assert_eq!(
+        lua_entities(&map.get_block_data(pos).await?)?,
+        [(center, entity("__builtin:item"))]
+    );
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_change_objects() -> Result<(), Box<dyn Error>> {
+    common::tear_up().await?;
+    // No early return here, so that tear down happens in every case
+    let result = change_objects().await;
+    let cleanup_result = common::tear_down().await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use std::error::Error;
+mod common;
+use futures::TryStreamExt;
+use glam::Vec3;
+use minetestworld::players::PlayerData;
+use minetestworld::World;
+
+async fn change_players() -> Result<(), Box<dyn Error>> {
+    let world = World::open("TestWorld copy");
+    let players = world.get_mutable_players().await?;
+
+    let mut player = players.get_player("sam").await?;
+    player.position = Vec3::new(12.5, 30.0, -7.0);
+    player.hp = 20;
+    player.inventory[0].items[1] = String::from("default:mese 3");
+    player
+        .metadata
+        .insert(String::from("rescued"), String::from("yes"));
+    players.save_player(&player).await?;
+
+    let reread = players.get_player("sam").await?;
+    assert_eq!(reread, player);
+
+    // The other player stays untouched
+    let singleplayer = players.get_player("singleplayer").await?;
+    assert_eq!(singleplayer.hp, 20);
+    assert_eq!(singleplayer.inventory.len(), 4);
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_change_players() -> Result<(), Box<dyn Error>> {
+    common::tear_up().await?;
+    // No early return here, so that tear down happens in every case
+    let result = change_players().await;
+    let cleanup_result = common::tear_down().await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
+
+async fn convert_players(dir: &str) -> Result<(), Box<dyn Error>> {
+    let players = World::open("TestWorld").get_players().await?;
+    let files = PlayerData::Files(dir.into());
+    let mut sam = players.get_player("sam").await?;
+    sam.metadata
+        .insert(String::from("motto"), String::from("\"quoted\"\n"));
+    files.save_player(&sam).await?;
+    files
+        .save_player(&players.get_player("singleplayer").await?)
+        .await?;
+
+    assert_eq!(files.get_player("sam").await?, sam);
+    let mut names: Vec<_> = files
+        .all_players()
+        .map_ok(|player| player.name)
+        .try_collect()
+        .await?;
+    names.sort();
+    assert_eq!(names, ["sam", "singleplayer"]);
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_legacy_player_files() -> Result<(), Box<dyn Error>> {
+    let dir = "TestWorld legacy players";
+    common::tear_up_empty_at(dir).await?;
+    let result = convert_players(dir).await;
+    let cleanup_result = common::tear_down_at(dir).await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
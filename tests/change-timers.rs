@@ -0,0 +1,87 @@
+use std::error::Error;
+mod common;
+use glam::I16Vec3;
+use minetestworld::{MapData, MapEdit, World};
+
+const RESCHEDULE_DIR: &str = "TestWorld reschedule timers";
+
+async fn change_timers() -> Result<(), Box<dyn Error>> {
+    let world = World::open("TestWorld copy");
+    let furnace = I16Vec3::new(5, 10, -4);
+
+    let vm = world.get_voxel_manip(true).await?;
+    assert!(vm.get_node_timer(furnace).await?.is_none());
+    vm.set_content(furnace, b"default:furnace_active").await?;
+    vm.set_node_timer(furnace, 1000, 250).await?;
+    vm.commit().await?;
+    std::mem::drop(vm);
+
+    let vm = world.get_voxel_manip(true).await?;
+    let timer = vm.get_node_timer(furnace).await?.unwrap();
+    assert_eq!((timer.timeout, timer.elapsed), (1000, 250));
+    assert_eq!(timer.remaining(), 750);
+
+    // Setting a timer again replaces it
+    vm.set_node_timer(furnace, 2000, 0).await?;
+    assert_eq!(vm.get_node_timer(furnace).await?.unwrap().timeout, 2000);
+    vm.remove_node_timer(furnace).await?;
+    vm.commit().await?;
+    std::mem::drop(vm);
+
+    let vm = world.get_voxel_manip(true).await?;
+    assert!(vm.get_node_timer(furnace).await?.is_none());
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_change_timers() -> Result<(), Box<dyn Error>> {
+    common::tear_up().await?;
+    // No early return here, so that tear down happens in every case
+    let result = change_timers().await;
+    let cleanup_result = common::tear_down().await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
+
+async fn reschedule_timers() -> Result<(), Box<dyn Error>> {
+    let map_path = format!("{RESCHEDULE_DIR}/map.sqlite");
+    let furnaces = [I16Vec3::new(5, 10, -4), I16Vec3::new(-40, 3, 17)];
+    let chest = I16Vec3::new(6, 10, -4);
+
+    let vm = MapEdit::new(MapData::from_sqlite_file(&map_path, false).await?);
+    for furnace in furnaces {
+        vm.set_content(furnace, b"default:furnace_active").await?;
+        vm.set_node_timer(furnace, 1000, 250).await?;
+    }
+    vm.set_content(chest, b"default:chest").await?;
+    vm.set_node_timer(chest, 1000, 0).await?;
+    vm.commit().await?;
+    std::mem::drop(vm);
+
+    let map = MapData::from_sqlite_file(&map_path, false).await?;
+    let rescheduled = map
+        .reschedule_timers(|content| content == b"default:furnace_active", 3000)
+        .await?;
+    assert_eq!(rescheduled, 2);
+    std::mem::drop(map);
+
+    let vm = MapEdit::new(MapData::from_sqlite_file(&map_path, true).await?);
+    for furnace in furnaces {
+        let timer = vm.get_node_timer(furnace).await?.unwrap();
+        assert_eq!((timer.timeout, timer.elapsed), (3000, 250));
+    }
+    assert_eq!(vm.get_node_timer(chest).await?.unwrap().timeout, 1000);
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_reschedule_timers() -> Result<(), Box<dyn Error>> {
+    common::tear_up_at(RESCHEDULE_DIR).await?;
+    // No early return here, so that tear down happens in every case
+    let result = reschedule_timers().await;
+    let cleanup_result = common::tear_down_at(RESCHEDULE_DIR).await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
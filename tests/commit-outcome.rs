@@ -0,0 +1,79 @@
+use std::error::Error;
+mod common;
+use glam::I16Vec3;
+use minetestworld::positions::{BlockArea, SplitPos};
+use minetestworld::voxel_manip::CommitMode;
+use minetestworld::{MapDataError, World};
+
+async fn commit_outcome() -> Result<(), Box<dyn Error>> {
+    let world = World::open("TestWorld copy");
+    let nodes = [I16Vec3::new(1, 2, 3), I16Vec3::new(100, 2, 3)];
+    let blocks = nodes.map(|node| node.split().0);
+
+    let mut vm = world.get_voxel_manip(true).await?;
+    assert!(vm
+        .preload(BlockArea::new(blocks[0], blocks[1]))
+        .await
+        .is_empty());
+    for node in nodes {
+        vm.set_content(node, b"default:mese").await?;
+    }
+
+    // This map format version cannot be written, so it is rejected before committing
+    assert!(vm.set_serialize_version(27).is_err());
+    vm.set_serialize_version(28)?;
+    let outcome = vm.try_commit().await?;
+    assert!(outcome.is_complete());
+    assert_eq!(outcome.written.len(), 2);
+    assert!(vm.try_commit().await?.written.is_empty());
+    std::mem::drop(vm);
+
+    // Version 28 is read back like version 29
+    let vm = world.get_voxel_manip(false).await?;
+    for node in nodes {
+        assert_eq!(vm.get_node(node).await?.param0, b"default:mese");
+    }
+    Ok(())
+}
+
+async fn atomic_commit() -> Result<(), Box<dyn Error>> {
+    let world = World::open("TestWorld copy");
+    let nodes = [I16Vec3::new(1, 2, 3), I16Vec3::new(100, 2, 3)];
+
+    // A read-only map fails every write
+    let mut vm = world.get_voxel_manip(false).await?;
+    for node in nodes {
+        vm.set_content(node, b"default:goldblock").await?;
+    }
+    assert!(matches!(
+        vm.try_commit().await,
+        Err(MapDataError::SqlError(_))
+    ));
+
+    // All blocks are still modified, and each of them fails on its own
+    vm.set_commit_mode(CommitMode::BestEffort);
+    let outcome = vm.try_commit().await?;
+    assert!(outcome.written.is_empty());
+    assert_eq!(outcome.failed.len(), 2);
+    std::mem::drop(vm);
+
+    let vm = world.get_voxel_manip(false).await?;
+    for node in nodes {
+        assert_ne!(vm.get_node(node).await?.param0, b"default:goldblock");
+    }
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_commit_outcome() -> Result<(), Box<dyn Error>> {
+    common::tear_up().await?;
+    // No early return here, so that tear down happens in every case
+    let result = match commit_outcome().await {
+        Ok(()) => atomic_commit().await,
+        Err(e) => Err(e),
+    };
+    let cleanup_result = common::tear_down().await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
@@ -1,12 +1,38 @@
+// Every test file uses only some of these
+#![allow(dead_code)]
+
 use async_std::fs;
 
 pub async fn tear_up() -> std::io::Result<()> {
     fs::create_dir("TestWorld copy").await?;
     fs::copy("TestWorld/world.mt", "TestWorld copy/world.mt").await?;
     fs::copy("TestWorld/map.sqlite", "TestWorld copy/map.sqlite").await?;
+    fs::copy("TestWorld/auth.sqlite", "TestWorld copy/auth.sqlite").await?;
+    fs::copy("TestWorld/players.sqlite", "TestWorld copy/players.sqlite").await?;
+    fs::copy(
+        "TestWorld/mod_storage.sqlite",
+        "TestWorld copy/mod_storage.sqlite",
+    )
+    .await?;
     Ok(())
 }
 
 pub async fn tear_down() -> std::io::Result<()> {
     fs::remove_dir_all("TestWorld copy").await
 }
+
+/// Creates `dir` with a copy of the test world's map, for tests that run in parallel
+pub async fn tear_up_at(dir: &str) -> std::io::Result<()> {
+    fs::create_dir(dir).await?;
+    fs::copy("TestWorld/map.sqlite", format!("{dir}/map.sqlite")).await?;
+    Ok(())
+}
+
+/// Creates the empty directory `dir`, for tests that build their maps from scratch
+pub async fn tear_up_empty_at(dir: &str) -> std::io::Result<()> {
+    fs::create_dir(dir).await
+}
+
+pub async fn tear_down_at(dir: &str) -> std::io::Result<()> {
+    fs::remove_dir_all(dir).await
+}
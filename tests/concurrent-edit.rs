@@ -0,0 +1,56 @@
+use std::error::Error;
+use std::sync::Arc;
+mod common;
+
+use async_std::task;
+use glam::I16Vec3;
+use minetestworld::{MapData, MapEdit};
+
+const CONCURRENT_DIR: &str = "TestWorld concurrent edit";
+
+/// The nodes one task works on: a row across several mapblocks
+fn row(task: i16) -> impl Iterator<Item = I16Vec3> {
+    (-40..40).map(move |x| I16Vec3::new(x, 20 + task, 30))
+}
+
+async fn concurrent_edit() -> Result<(), Box<dyn Error>> {
+    let map_path = format!("{CONCURRENT_DIR}/map.sqlite");
+    let vm = Arc::new(MapEdit::new(
+        MapData::from_sqlite_file(&map_path, false).await?,
+    ));
+    // The rows share their mapblocks, so the tasks contend for them
+    let tasks: Vec<_> = (0..4)
+        .map(|i| {
+            let vm = vm.clone();
+            task::spawn(async move {
+                for pos in row(i) {
+                    vm.set_content(pos, b"default:mese").await?;
+                    assert_eq!(vm.get_node(pos).await?.param0, b"default:mese");
+                }
+                Ok::<_, minetestworld::MapDataError>(())
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await?;
+    }
+    vm.commit().await?;
+    std::mem::drop(vm);
+
+    let vm = MapEdit::new(MapData::from_sqlite_file(&map_path, true).await?);
+    for pos in (0..4).flat_map(row) {
+        assert_eq!(vm.get_node(pos).await?.param0, b"default:mese");
+    }
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_concurrent_edit() -> Result<(), Box<dyn Error>> {
+    common::tear_up_at(CONCURRENT_DIR).await?;
+    // No early return here, so that tear down happens in every case
+    let result = concurrent_edit().await;
+    let cleanup_result = common::tear_down_at(CONCURRENT_DIR).await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use std::error::Error;
+mod common;
+
+use futures::TryStreamExt;
+use glam::I16Vec3;
+use minetestworld::positions::{BlockArea, BlockPos};
+use minetestworld::{MapBlock, MapData, World};
+
+const COPY_DIR: &str = "TestWorld region copy";
+
+async fn copy_region() -> Result<(), Box<dyn Error>> {
+    let source = World::open("TestWorld").get_map_data().await?;
+    let dest = MapData::from_sqlite_file(format!("{COPY_DIR}/map.sqlite"), false).await?;
+    let region = BlockArea::new(
+        BlockPos::from_index_vec(I16Vec3::new(-14, -9, 1)),
+        BlockPos::from_index_vec(I16Vec3::new(-12, -7, 3)),
+    );
+    let mut stamp = |_pos: BlockPos, block: &mut MapBlock| block.timestamp = 42;
+    let count = dest
+        .copy_region_from(&source, region, Some(&mut stamp))
+        .await?;
+    assert!(count > 0);
+
+    let positions: Vec<_> = dest.all_mapblock_positions().await.try_collect().await?;
+    assert_eq!(positions.len(), count);
+    for pos in positions {
+        assert!(region.contains(pos));
+        assert_eq!(dest.get_mapblock(pos).await?.timestamp, 42);
+    }
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_copy_region() -> Result<(), Box<dyn Error>> {
+    common::tear_up_empty_at(COPY_DIR).await?;
+    // No early return here, so that tear down happens in every case
+    let result = copy_region().await;
+    let cleanup_result = common::tear_down_at(COPY_DIR).await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use std::error::Error;
+mod common;
+
+use async_std::fs;
+use futures::TryStreamExt;
+use glam::I16Vec3;
+use minetestworld::map_data::OptimizeStep;
+use minetestworld::positions::{BlockArea, BlockPos};
+use minetestworld::MapData;
+
+const DELETE_DIR: &str = "TestWorld delete blocks";
+const Y_RANGE_DIR: &str = "TestWorld delete y range";
+const OPTIMIZE_DIR: &str = "TestWorld optimize";
+
+async fn delete_blocks() -> Result<(), Box<dyn Error>> {
+    let map = MapData::from_sqlite_file(format!("{DELETE_DIR}/map.sqlite"), false).await?;
+    let region = BlockArea::new(
+        BlockPos::from_index_vec(I16Vec3::new(-5, -3, 2)),
+        BlockPos::from_index_vec(I16Vec3::new(5, 3, 6)),
+    );
+    let before: Vec<BlockPos> = map.all_mapblock_positions().await.try_collect().await?;
+    let inside = before.iter().filter(|pos| region.contains(**pos)).count();
+    assert!(inside > 0);
+
+    assert_eq!(map.delete_blocks(region).await?, inside);
+    let after: Vec<BlockPos> = map.all_mapblock_positions().await.try_collect().await?;
+    assert_eq!(after.len(), before.len() - inside);
+    assert!(after.iter().all(|pos| !region.contains(*pos)));
+
+    // Nothing is left to delete
+    assert_eq!(map.delete_blocks(region).await?, 0);
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_delete_blocks() -> Result<(), Box<dyn Error>> {
+    common::tear_up_at(DELETE_DIR).await?;
+    // No early return here, so that tear down happens in every case
+    let result = delete_blocks().await;
+    let cleanup_result = common::tear_down_at(DELETE_DIR).await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
+
+async fn delete_y_range() -> Result<(), Box<dyn Error>> {
+    let map = MapData::from_sqlite_file(format!("{Y_RANGE_DIR}/map.sqlite"), false).await?;
+    let in_range = |pos: &BlockPos| (10..=13).contains(&pos.into_index_vec().y);
+    let before: Vec<BlockPos> = map.all_mapblock_positions().await.try_collect().await?;
+    let inside = before.iter().filter(|pos| in_range(pos)).count();
+    assert!(inside > 0);
+
+    assert_eq!(map.delete_y_range(13, 10).await?, inside);
+    let after: Vec<BlockPos> = map.all_mapblock_positions().await.try_collect().await?;
+    assert_eq!(after.len(), before.len() - inside);
+    assert!(!after.iter().any(in_range));
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_delete_y_range() -> Result<(), Box<dyn Error>> {
+    common::tear_up_at(Y_RANGE_DIR).await?;
+    // No early return here, so that tear down happens in every case
+    let result = delete_y_range().await;
+    let cleanup_result = common::tear_down_at(Y_RANGE_DIR).await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
+
+async fn optimize() -> Result<(), Box<dyn Error>> {
+    let map_path = format!("{OPTIMIZE_DIR}/map.sqlite");
+    let map = MapData::from_sqlite_file(&map_path, false).await?;
+    assert!(map.delete_y_range(-13, 0).await? > 0);
+    let size_before = fs::metadata(&map_path).await?.len();
+
+    let mut steps = Vec::new();
+    map.optimize(|step| steps.push(step)).await?;
+    assert_eq!(steps, [OptimizeStep::Vacuum, OptimizeStep::Analyze]);
+    assert!(fs::metadata(&map_path).await?.len() < size_before);
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_optimize() -> Result<(), Box<dyn Error>> {
+    common::tear_up_at(OPTIMIZE_DIR).await?;
+    // No early return here, so that tear down happens in every case
+    let result = optimize().await;
+    let cleanup_result = common::tear_down_at(OPTIMIZE_DIR).await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
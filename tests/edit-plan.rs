@@ -0,0 +1,66 @@
+use std::error::Error;
+mod common;
+
+use glam::{I16Vec3, U16Vec3};
+use minetestworld::edit_plan::{EditPlan, Schematic};
+use minetestworld::{MapData, MapEdit, Node};
+
+const PLAN_DIR: &str = "TestWorld edit plan";
+
+async fn content_at(vm: &MapEdit, x: i16, y: i16, z: i16) -> Result<Vec<u8>, Box<dyn Error>> {
+    Ok(vm.get_node(I16Vec3::new(x, y, z)).await?.param0)
+}
+
+async fn edit_plan() -> Result<(), Box<dyn Error>> {
+    let map_path = format!("{PLAN_DIR}/map.sqlite");
+    let vm = MapEdit::new(MapData::from_sqlite_file(&map_path, false).await?);
+
+    let mut schematic = Schematic::new(U16Vec3::new(2, 1, 2));
+    let lamp = Node {
+        param0: b"default:meselamp".to_vec(),
+        param1: 0,
+        param2: 3,
+    };
+    schematic.set_node(U16Vec3::new(0, 0, 0), Some(lamp.clone()));
+    schematic.set_node(U16Vec3::new(1, 0, 1), Some(lamp));
+
+    let visited = EditPlan::new()
+        .fill(
+            I16Vec3::new(-4, 0, -4),
+            I16Vec3::new(19, 0, 3),
+            b"default:stone",
+        )
+        .replace(
+            I16Vec3::new(10, 0, 0),
+            I16Vec3::new(30, 0, 0),
+            b"default:stone",
+            b"default:dirt",
+        )
+        .place_schematic(I16Vec3::new(15, 0, -1), schematic)
+        .execute(&vm)
+        .await?;
+    assert_eq!(visited, 6);
+
+    let vm = MapEdit::new(MapData::from_sqlite_file(&map_path, false).await?);
+    assert_eq!(content_at(&vm, -4, 0, -4).await?, b"default:stone");
+    assert_eq!(content_at(&vm, 9, 0, 0).await?, b"default:stone");
+    assert_eq!(content_at(&vm, 10, 0, 0).await?, b"default:dirt");
+    assert_eq!(content_at(&vm, 19, 0, 0).await?, b"default:dirt");
+    assert_eq!(content_at(&vm, 20, 0, 0).await?, b"ignore");
+    assert_eq!(content_at(&vm, 15, 0, -1).await?, b"default:meselamp");
+    assert_eq!(vm.get_node(I16Vec3::new(16, 0, 0)).await?.param2, 3);
+    // Positions left empty in the schematic are kept
+    assert_eq!(content_at(&vm, 16, 0, -1).await?, b"default:stone");
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_edit_plan() -> Result<(), Box<dyn Error>> {
+    common::tear_up_empty_at(PLAN_DIR).await?;
+    // No early return here, so that tear down happens in every case
+    let result = edit_plan().await;
+    let cleanup_result = common::tear_down_at(PLAN_DIR).await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
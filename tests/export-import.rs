@@ -0,0 +1,125 @@
+use std::error::Error;
+mod common;
+
+use async_std::fs;
+use futures::TryStreamExt;
+use glam::I16Vec3;
+use minetestworld::export;
+use minetestworld::positions::{BlockArea, BlockPos};
+use minetestworld::{MapData, World};
+
+const EXPORT_DIR: &str = "TestWorld export";
+
+async fn export_import() -> Result<(), Box<dyn Error>> {
+    let source = World::open("TestWorld").get_map_data().await?;
+    let region = BlockArea::new(
+        BlockPos::from_index_vec(I16Vec3::new(-14, -9, 1)),
+        BlockPos::from_index_vec(I16Vec3::new(-12, -7, 3)),
+    );
+    let count = export::blocks_to_dir(&source, region, format!("{EXPORT_DIR}/blocks")).await?;
+    assert!(count > 0);
+
+    let dest = MapData::from_sqlite_file(format!("{EXPORT_DIR}/map.sqlite"), false).await?;
+    assert_eq!(
+        export::blocks_from_dir(&dest, format!("{EXPORT_DIR}/blocks")).await?,
+        region
+    );
+    let positions: Vec<_> = dest.all_mapblock_positions().await.try_collect().await?;
+    assert_eq!(positions.len(), count);
+    for pos in positions {
+        assert!(region.contains(pos));
+        assert_eq!(
+            source.get_block_data(pos).await?,
+            dest.get_block_data(pos).await?
+        );
+    }
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_export_import() -> Result<(), Box<dyn Error>> {
+    common::tear_up_empty_at(EXPORT_DIR).await?;
+    // No early return here, so that tear down happens in every case
+    let result = export_import().await;
+    let cleanup_result = common::tear_down_at(EXPORT_DIR).await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
+
+#[cfg(feature = "signatures")]
+const SIGNED_DIR: &str = "TestWorld signed export";
+
+#[cfg(feature = "signatures")]
+async fn signed_export_import() -> Result<(), Box<dyn Error>> {
+    use ed25519_dalek::SigningKey;
+
+    let source = World::open("TestWorld").get_map_data().await?;
+    let region = BlockArea::new(
+        BlockPos::from_index_vec(I16Vec3::new(-14, -9, 1)),
+        BlockPos::from_index_vec(I16Vec3::new(-12, -7, 3)),
+    );
+    let blocks_dir = format!("{SIGNED_DIR}/blocks");
+    export::blocks_to_dir(&source, region, &blocks_dir).await?;
+    let key = SigningKey::from_bytes(&[7; 32]);
+    export::sign_dir(&blocks_dir, &key).await?;
+
+    let dest = MapData::from_sqlite_file(format!("{SIGNED_DIR}/map.sqlite"), false).await?;
+    let other_key = SigningKey::from_bytes(&[8; 32]);
+    assert!(
+        export::verified_blocks_from_dir(&dest, &blocks_dir, &other_key.verifying_key())
+            .await
+            .is_err()
+    );
+    assert_eq!(
+        export::verified_blocks_from_dir(&dest, &blocks_dir, &key.verifying_key()).await?,
+        region
+    );
+
+    // Tampering with a block invalidates its checksum
+    let pos = dest
+        .all_mapblock_positions()
+        .await
+        .try_next()
+        .await?
+        .unwrap();
+    let index = pos.into_index_vec();
+    let block_file = format!("{blocks_dir}/{}.{}.{}.bin", index.x, index.y, index.z);
+    let mut data = fs::read(&block_file).await?;
+    data.push(0);
+    fs::write(&block_file, data).await?;
+    assert!(
+        export::verified_blocks_from_dir(&dest, &blocks_dir, &key.verifying_key())
+            .await
+            .is_err()
+    );
+    assert!(export::blocks_from_dir(&dest, &blocks_dir).await.is_err());
+
+    // A failed import writes none of the blocks
+    let fresh = MapData::from_sqlite_file(format!("{SIGNED_DIR}/fresh.sqlite"), false).await?;
+    assert!(export::blocks_from_dir(&fresh, &blocks_dir).await.is_err());
+    assert!(fresh
+        .all_mapblock_positions()
+        .await
+        .try_next()
+        .await?
+        .is_none());
+
+    // Signing again replaces the signature
+    export::sign_dir(&blocks_dir, &key).await?;
+    export::sign_dir(&blocks_dir, &key).await?;
+    let manifest = fs::read_to_string(format!("{blocks_dir}/manifest.txt")).await?;
+    assert_eq!(manifest.matches("signature = ").count(), 1);
+    Ok(())
+}
+
+#[cfg(feature = "signatures")]
+#[async_std::test]
+async fn test_signed_export_import() -> Result<(), Box<dyn Error>> {
+    common::tear_up_empty_at(SIGNED_DIR).await?;
+    let result = signed_export_import().await;
+    let cleanup_result = common::tear_down_at(SIGNED_DIR).await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
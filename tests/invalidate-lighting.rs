@@ -0,0 +1,102 @@
+use std::error::Error;
+mod common;
+
+use glam::I16Vec3;
+use minetestworld::map_block::{BlockFace, LightBank};
+use minetestworld::positions::{BlockPos, SplitPos};
+use minetestworld::voxel_manip::CommitOptions;
+use minetestworld::{MapBlock, MapData, MapDataError, MapEdit};
+
+const LIGHTING_DIR: &str = "TestWorld invalidate lighting";
+const WORLD_EDGE_DIR: &str = "TestWorld invalidate lighting edge";
+
+async fn invalidate_lighting() -> Result<(), Box<dyn Error>> {
+    let map_path = format!("{LIGHTING_DIR}/map.sqlite");
+    let node = I16Vec3::new(-185, -25, 70);
+    let block = node.split().0;
+
+    let mut vm = MapEdit::new(MapData::from_sqlite_file(&map_path, false).await?);
+    vm.set_commit_options(CommitOptions {
+        invalidate_lighting: true,
+        ..Default::default()
+    });
+    vm.set_content(node, b"default:mese").await?;
+    let outcome = vm.try_commit().await?;
+    assert!(outcome.is_complete());
+    assert!(outcome.written.contains(&block));
+    std::mem::drop(vm);
+
+    let map = MapData::from_sqlite_file(&map_path, true).await?;
+    assert_eq!(map.get_mapblock(block).await?.lighting_complete, 0);
+    for (dir, face) in [
+        (I16Vec3::X, BlockFace::NegX),
+        (I16Vec3::NEG_X, BlockFace::PosX),
+        (I16Vec3::Y, BlockFace::NegY),
+        (I16Vec3::NEG_Y, BlockFace::PosY),
+        (I16Vec3::Z, BlockFace::NegZ),
+        (I16Vec3::NEG_Z, BlockFace::PosZ),
+    ] {
+        let neighbor = BlockPos::from_index_vec(block.into_index_vec() + dir);
+        match map.get_mapblock(neighbor).await {
+            Ok(mapblock) => {
+                assert!(!mapblock.is_lighting_complete(LightBank::Day, face));
+                assert!(!mapblock.is_lighting_complete(LightBank::Night, face));
+            }
+            // Missing neighbors are not created
+            Err(MapDataError::MapBlockNonexistent(_)) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_invalidate_lighting() -> Result<(), Box<dyn Error>> {
+    common::tear_up_at(LIGHTING_DIR).await?;
+    // No early return here, so that tear down happens in every case
+    let result = invalidate_lighting().await;
+    let cleanup_result = common::tear_down_at(LIGHTING_DIR).await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
+
+#[cfg(not(feature = "strict-bounds"))]
+async fn invalidate_lighting_at_world_edge() -> Result<(), Box<dyn Error>> {
+    let map_path = format!("{WORLD_EDGE_DIR}/map.sqlite");
+    let node = I16Vec3::new(i16::MAX, 0, 0);
+    let edge = node.split().0;
+    let opposite = BlockPos::from_index_vec(I16Vec3::new(-2048, 0, 0));
+    let map = MapData::from_sqlite_file(&map_path, false).await?;
+    let mut block = MapBlock::unloaded();
+    block.lighting_complete = 0xffff;
+    map.set_mapblock(edge, &block).await?;
+    map.set_mapblock(opposite, &block).await?;
+
+    let mut vm = MapEdit::new(map);
+    vm.set_commit_options(CommitOptions {
+        invalidate_lighting: true,
+        ..Default::default()
+    });
+    vm.set_content(node, b"default:mese").await?;
+    assert!(vm.try_commit().await?.is_complete());
+    std::mem::drop(vm);
+
+    // The block on the other side of the world is no neighbor
+    let map = MapData::from_sqlite_file(&map_path, true).await?;
+    assert_eq!(map.get_mapblock(edge).await?.lighting_complete, 0);
+    assert_eq!(map.get_mapblock(opposite).await?.lighting_complete, 0xffff);
+    Ok(())
+}
+
+#[cfg(not(feature = "strict-bounds"))]
+#[async_std::test]
+async fn test_invalidate_lighting_at_world_edge() -> Result<(), Box<dyn Error>> {
+    common::tear_up_empty_at(WORLD_EDGE_DIR).await?;
+    // No early return here, so that tear down happens in every case
+    let result = invalidate_lighting_at_world_edge().await;
+    let cleanup_result = common::tear_down_at(WORLD_EDGE_DIR).await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use std::error::Error;
+mod common;
+use glam::I16Vec3;
+use minetestworld::voxel_manip::CommitMode;
+use minetestworld::world::WorldError;
+use minetestworld::{MapDataError, World};
+
+async fn reopen_with_journal() -> Result<(), Box<dyn Error>> {
+    let world = World::open("TestWorld copy");
+    let journal = world.journal();
+    let node = I16Vec3::new(1, 2, 3);
+
+    // A read-only map fails every write after the journal has been written,
+    // which leaves the journal behind like a killed process would
+    let mut vm = world.get_voxel_manip(false).await?;
+    vm.set_journal(journal.clone());
+    vm.set_commit_mode(CommitMode::BestEffort);
+    vm.set_content(node, b"default:mese").await?;
+    assert!(!vm.try_commit().await?.is_complete());
+    std::mem::drop(vm);
+    assert!(journal.is_pending().await);
+
+    // The interrupted commit cannot be repaired through a read-only handle
+    assert!(matches!(
+        world.get_map_data().await,
+        Err(WorldError::MapDataError(MapDataError::PendingJournal(_)))
+    ));
+
+    // Opening the map writable completes the commit
+    world.get_map_data_backend(false).await?;
+    assert!(!journal.is_pending().await);
+    let vm = world.get_voxel_manip(false).await?;
+    assert_eq!(vm.get_node(node).await?.param0, b"default:mese");
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_reopen_with_journal() -> Result<(), Box<dyn Error>> {
+    common::tear_up().await?;
+    // No early return here, so that tear down happens in every case
+    let result = reopen_with_journal().await;
+    let cleanup_result = common::tear_down().await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Arc;
+
+use glam::I16Vec3;
+use minetestworld::node_def::{MirrorAxis, NodeDef, ParamType2};
+use minetestworld::{MapData, MapEdit, Node};
+
+/// Opens the test world read-only, so that edits stay in the cache
+async fn open() -> Result<MapEdit, Box<dyn Error>> {
+    Ok(MapEdit::new(
+        MapData::from_sqlite_file("TestWorld/map.sqlite", true).await?,
+    ))
+}
+
+/// Sets up a 3x1x1 row of distinct nodes at `pos`, the middle one with metadata
+async fn build_row(vm: &MapEdit, pos: I16Vec3) -> Result<(), Box<dyn Error>> {
+    for (i, content) in [&b"default:stone"[..], b"default:chest", b"default:glass"]
+        .into_iter()
+        .enumerate()
+    {
+        vm.set_content(pos + I16Vec3::X * i as i16, content).await?;
+    }
+    vm.set_metadata_var(pos + I16Vec3::X, b"infotext", b"Chest")
+        .await?;
+    Ok(())
+}
+
+async fn content(vm: &MapEdit, pos: I16Vec3) -> Result<Vec<u8>, Box<dyn Error>> {
+    Ok(vm.get_node(pos).await?.param0)
+}
+
+#[async_std::test]
+async fn test_clone_region() -> Result<(), Box<dyn Error>> {
+    let vm = open().await?;
+    let start = I16Vec3::new(-200, 10, -200);
+    build_row(&vm, start).await?;
+    vm.set_metadata_var(start + I16Vec3::new(14, 0, 0), b"stale", b"yes")
+        .await?;
+
+    // The destination crosses a mapblock border
+    let dest = start + I16Vec3::new(13, 0, 0);
+    vm.clone_region(start, start + I16Vec3::new(2, 0, 0), dest)
+        .await?;
+    assert_eq!(content(&vm, start).await?, b"default:stone");
+    assert_eq!(content(&vm, dest).await?, b"default:stone");
+    assert_eq!(content(&vm, dest + I16Vec3::X).await?, b"default:chest");
+    assert_eq!(content(&vm, dest + I16Vec3::X * 2).await?, b"default:glass");
+    let metadata = vm.get_metadata(dest + I16Vec3::X).await?.unwrap();
+    assert_eq!(metadata.get(b"infotext"), Some(&b"Chest"[..]));
+    assert!(metadata.get(b"stale").is_none());
+    assert!(vm.get_metadata(start + I16Vec3::X).await?.is_some());
+
+    // Overlapping clone, shifted by one node
+    vm.clone_region(start, start + I16Vec3::new(2, 0, 0), start + I16Vec3::X)
+        .await?;
+    assert_eq!(content(&vm, start).await?, b"default:stone");
+    assert_eq!(content(&vm, start + I16Vec3::X).await?, b"default:stone");
+    assert_eq!(
+        content(&vm, start + I16Vec3::X * 2).await?,
+        b"default:chest"
+    );
+    assert_eq!(
+        content(&vm, start + I16Vec3::X * 3).await?,
+        b"default:glass"
+    );
+    assert!(vm.get_metadata(start + I16Vec3::X).await?.is_none());
+    assert!(vm.get_metadata(start + I16Vec3::X * 2).await?.is_some());
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_move_region() -> Result<(), Box<dyn Error>> {
+    let vm = open().await?;
+    let start = I16Vec3::new(-200, 10, -200);
+    build_row(&vm, start).await?;
+
+    // Overlapping move, shifted by one node
+    vm.move_region(start, start + I16Vec3::new(2, 0, 0), start + I16Vec3::X)
+        .await?;
+    assert_eq!(content(&vm, start).await?, b"air");
+    assert_eq!(content(&vm, start + I16Vec3::X).await?, b"default:stone");
+    assert_eq!(
+        content(&vm, start + I16Vec3::X * 2).await?,
+        b"default:chest"
+    );
+    assert_eq!(
+        content(&vm, start + I16Vec3::X * 3).await?,
+        b"default:glass"
+    );
+    assert!(vm.get_metadata(start + I16Vec3::X).await?.is_none());
+    let metadata = vm.get_metadata(start + I16Vec3::X * 2).await?.unwrap();
+    assert_eq!(metadata.get(b"infotext"), Some(&b"Chest"[..]));
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_rotate_region() -> Result<(), Box<dyn Error>> {
+    let mut vm = open().await?;
+    let def = |paramtype2| NodeDef {
+        paramtype2,
+        ..Default::default()
+    };
+    vm.set_node_defs(Arc::new(HashMap::from([
+        (b"stairs:stair_wood".to_vec(), def(ParamType2::FaceDir)),
+        (b"default:torch_wall".to_vec(), def(ParamType2::WallMounted)),
+    ])));
+    let start = I16Vec3::new(-200, 10, -200);
+    build_row(&vm, start).await?;
+    let node = |content: &[u8], param2| Node {
+        param0: content.to_vec(),
+        param1: 0,
+        param2,
+    };
+    // A stair facing +z in front of the stone, a torch on the glass' wall in +x direction
+    vm.set_node(start + I16Vec3::Z, node(b"stairs:stair_wood", 0))
+        .await?;
+    vm.set_node(
+        start + I16Vec3::new(2, 0, 1),
+        node(b"default:torch_wall", 2),
+    )
+    .await?;
+
+    let (min, max) = vm
+        .rotate_region(start, start + I16Vec3::new(2, 0, 1), 1)
+        .await?;
+    assert_eq!((min, max), (start, start + I16Vec3::new(1, 0, 2)));
+    // (x, z) ends up at (z, 2 - x)
+    assert_eq!(
+        content(&vm, start + I16Vec3::new(0, 0, 2)).await?,
+        b"default:stone"
+    );
+    assert_eq!(
+        content(&vm, start + I16Vec3::new(0, 0, 0)).await?,
+        b"default:glass"
+    );
+    assert_eq!(content(&vm, start + I16Vec3::new(2, 0, 0)).await?, b"air");
+    let chest = start + I16Vec3::new(0, 0, 1);
+    assert_eq!(content(&vm, chest).await?, b"default:chest");
+    assert!(vm.get_metadata(chest).await?.is_some());
+    assert!(vm.get_metadata(start + I16Vec3::X).await?.is_none());
+    let stair = vm.get_node(start + I16Vec3::new(1, 0, 2)).await?;
+    assert_eq!(stair, node(b"stairs:stair_wood", 1));
+    let torch = vm.get_node(start + I16Vec3::new(1, 0, 0)).await?;
+    assert_eq!(torch, node(b"default:torch_wall", 5));
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_mirror_region() -> Result<(), Box<dyn Error>> {
+    let mut vm = open().await?;
+    vm.set_node_defs(Arc::new(HashMap::from([(
+        b"default:torch_wall".to_vec(),
+        NodeDef {
+            paramtype2: ParamType2::WallMounted,
+            ..Default::default()
+        },
+    )])));
+    let start = I16Vec3::new(-200, 10, -220);
+    build_row(&vm, start).await?;
+    let torch = |param2| Node {
+        param0: b"default:torch_wall".to_vec(),
+        param1: 0,
+        param2,
+    };
+    // A torch on the stone's wall in -x direction
+    vm.set_node(start + I16Vec3::Z, torch(3)).await?;
+
+    vm.mirror_region(start, start + I16Vec3::new(2, 0, 1), MirrorAxis::X)
+        .await?;
+    assert_eq!(content(&vm, start).await?, b"default:glass");
+    assert_eq!(
+        content(&vm, start + I16Vec3::X * 2).await?,
+        b"default:stone"
+    );
+    let metadata = vm.get_metadata(start + I16Vec3::X).await?.unwrap();
+    assert_eq!(metadata.get(b"infotext"), Some(&b"Chest"[..]));
+    assert_eq!(vm.get_node(start + I16Vec3::new(2, 0, 1)).await?, torch(2));
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_fill_region() -> Result<(), Box<dyn Error>> {
+    let vm = open().await?;
+    // Covers the mapblock at (-15, 0, -15) completely and its neighbours partially
+    let (a, b) = (I16Vec3::new(-240, 0, -240), I16Vec3::new(-205, 17, -205));
+    let torch = a + I16Vec3::new(3, 2, 1);
+    vm.set_node(
+        torch,
+        Node {
+            param0: b"default:torch_wall".to_vec(),
+            param1: 0,
+            param2: 4,
+        },
+    )
+    .await?;
+
+    vm.fill_region(b, a, b"default:sandstone").await?;
+    for pos in [
+        a,
+        b,
+        torch,
+        I16Vec3::new(-225, 15, -225),
+        I16Vec3::new(-224, 16, -206),
+    ] {
+        assert_eq!(content(&vm, pos).await?, b"default:sandstone");
+    }
+    assert_eq!(vm.get_node(torch).await?.param2, 4);
+    for pos in [a - I16Vec3::X, b + I16Vec3::Y] {
+        assert_ne!(content(&vm, pos).await?, b"default:sandstone");
+    }
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use std::error::Error;
+mod common;
+
+use glam::I16Vec3;
+use minetestworld::positions::{BlockArea, BlockPos};
+use minetestworld::{MapData, MapEdit};
+
+const REPLACE_DIR: &str = "TestWorld replace content";
+
+async fn replace_content() -> Result<(), Box<dyn Error>> {
+    let map_path = format!("{REPLACE_DIR}/map.sqlite");
+    let inside = I16Vec3::new(3, 4, 5);
+    let outside = I16Vec3::new(40, 4, 5);
+
+    let vm = MapEdit::new(MapData::from_sqlite_file(&map_path, false).await?);
+    for pos in [inside, outside] {
+        vm.set_content(pos, b"moreores:mineral_tin").await?;
+    }
+    vm.commit().await?;
+    std::mem::drop(vm);
+
+    let map = MapData::from_sqlite_file(&map_path, false).await?;
+    let region = BlockArea::new(
+        BlockPos::from_index_vec(I16Vec3::splat(-1)),
+        BlockPos::from_index_vec(I16Vec3::splat(1)),
+    );
+    let rewritten = map
+        .replace_content(b"moreores:mineral_tin", b"default:stone_with_tin", region)
+        .await?;
+    assert_eq!(rewritten, 1);
+    // Nothing is left to replace
+    let mut reports = vec![];
+    let rewritten = map
+        .replace_content_with_progress(
+            b"moreores:mineral_tin",
+            b"default:stone_with_tin",
+            region,
+            |progress| reports.push(progress),
+        )
+        .await?;
+    assert_eq!(rewritten, 0);
+    // Only the mapblock that was created above lies within the region
+    let counts: Vec<_> = reports.iter().map(|p| (p.done, p.total)).collect();
+    assert_eq!(counts, vec![(0, 1), (1, 1)]);
+    assert_eq!(reports[0].eta(), None);
+    assert_eq!(reports[1].fraction(), 1.0);
+    std::mem::drop(map);
+
+    let vm = MapEdit::new(MapData::from_sqlite_file(&map_path, true).await?);
+    assert_eq!(vm.get_node(inside).await?.param0, b"default:stone_with_tin");
+    assert_eq!(vm.get_node(outside).await?.param0, b"moreores:mineral_tin");
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_replace_content() -> Result<(), Box<dyn Error>> {
+    common::tear_up_at(REPLACE_DIR).await?;
+    // No early return here, so that tear down happens in every case
+    let result = replace_content().await;
+    let cleanup_result = common::tear_down_at(REPLACE_DIR).await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use std::error::Error;
+mod common;
+
+use glam::I16Vec3;
+use minetestworld::positions::BlockPos;
+use minetestworld::report::{self, ReportOptions};
+use minetestworld::{MapBlock, MapData};
+
+const REPORT_DIR: &str = "TestWorld report";
+
+async fn undecodable_blocks() -> Result<(), Box<dyn Error>> {
+    let map = MapData::from_sqlite_file(format!("{REPORT_DIR}/map.sqlite"), false).await?;
+    let mut block = MapBlock::unloaded();
+    let stone = block.get_or_create_content_id(b"default:stone");
+    block.param0[0] = stone;
+    let intact = BlockPos::from_index_vec(I16Vec3::new(0, 0, 0));
+    let broken = BlockPos::from_index_vec(I16Vec3::new(1, 0, 0));
+    map.set_mapblock(intact, &block).await?;
+    map.set_block_data(broken, &[29, 0]).await?;
+
+    // Undecodable mapblocks are reported instead of aborting the report
+    let options = ReportOptions {
+        biggest_blocks: 1,
+        ..Default::default()
+    };
+    let report = report::collect(&map, "Broken", &options).await?;
+    assert_eq!(report.block_count, 2);
+    assert_eq!(report.undecodable, [broken]);
+    assert_eq!(report.mod_usage["default"], 1);
+    assert_eq!(report.mod_usage.values().sum::<u64>(), 4096);
+    assert_eq!(report.biggest_blocks.len(), 1);
+    assert_eq!(report.biggest_blocks[0].0, intact);
+    assert!(report.to_json().contains("\"undecodable\":[[1,0,0]]"));
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_undecodable_blocks() -> Result<(), Box<dyn Error>> {
+    common::tear_up_empty_at(REPORT_DIR).await?;
+    // No early return here, so that tear down happens in every case
+    let result = undecodable_blocks().await;
+    let cleanup_result = common::tear_down_at(REPORT_DIR).await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use std::error::Error;
+mod common;
+use glam::I16Vec3;
+use minetestworld::inventory::{InventoryList, ItemStack};
+use minetestworld::positions::BlockPos;
+use minetestworld::scrub::ScrubRule;
+use minetestworld::{MapData, World};
+
+async fn scrub() -> Result<(), Box<dyn Error>> {
+    let world = World::open("TestWorld copy");
+    let chest = I16Vec3::new(4, 10, -4);
+
+    let mut book = ItemStack::new("default:book_written", 1);
+    book.metadata.insert("owner".into(), "singleplayer".into());
+    book.metadata.insert("text".into(), "Dear diary".into());
+    let vm = world.get_voxel_manip(true).await?;
+    vm.set_content(chest, b"default:chest_locked").await?;
+    vm.set_metadata_var(chest, b"owner", b"singleplayer")
+        .await?;
+    vm.set_metadata_var(chest, b"infotext", b"Locked Chest (owned by singleplayer)")
+        .await?;
+    vm.set_inventory_list(
+        chest,
+        InventoryList {
+            name: String::from("main"),
+            width: 0,
+            items: vec![book.to_string(), String::from("default:apple 5")],
+        },
+    )
+    .await?;
+    vm.commit().await?;
+    std::mem::drop(vm);
+
+    // Undecodable mapblocks are reported instead of aborting the scrub
+    let broken = BlockPos::from_index_vec(I16Vec3::new(100, 100, 100));
+    let map = MapData::from_sqlite_file("TestWorld copy/map.sqlite", false).await?;
+    map.set_block_data(broken, &[29, 0]).await?;
+    std::mem::drop(map);
+
+    let players = world.get_mutable_players().await?;
+    let mut player = players.get_player("singleplayer").await?;
+    player.metadata.insert("mail:inbox".into(), "secret".into());
+    players.save_player(&player).await?;
+    std::mem::drop(players);
+
+    let rules = [
+        ScrubRule::remove("owner"),
+        ScrubRule::redact("infotext", "Locked Chest"),
+        ScrubRule::redact("text", "[redacted]"),
+        ScrubRule::remove("mail:*"),
+    ];
+    let report = world.scrub_private_data(&rules).await?;
+    // The fixture contains more nodes with an infotext
+    assert!(report.mapblocks >= 1);
+    assert_eq!(report.players, 1);
+    assert!(report.fields >= 5);
+    assert_eq!(report.undecodable, [broken]);
+
+    let vm = world.get_voxel_manip(false).await?;
+    let metadata = vm.get_metadata(chest).await?.unwrap();
+    assert_eq!(metadata.get(b"owner"), None);
+    assert_eq!(metadata.get(b"infotext"), Some(&b"Locked Chest"[..]));
+    let main = metadata.inventory_list("main").unwrap();
+    let book: ItemStack = main.items[0].parse()?;
+    assert_eq!(book.metadata.get("owner"), None);
+    assert_eq!(book.metadata["text"], "[redacted]");
+    assert_eq!(main.items[1], "default:apple 5");
+
+    let player = world
+        .get_players()
+        .await?
+        .get_player("singleplayer")
+        .await?;
+    assert!(!player.metadata.contains_key("mail:inbox"));
+    assert_eq!(player.metadata["stamina:level"], "20");
+
+    // Scrubbing is idempotent
+    let report = world.scrub_private_data(&rules).await?;
+    assert_eq!(report.fields, 0);
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_scrub() -> Result<(), Box<dyn Error>> {
+    common::tear_up().await?;
+    // No early return here, so that tear down happens in every case
+    let result = scrub().await;
+    let cleanup_result = common::tear_down().await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
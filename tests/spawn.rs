@@ -0,0 +1,61 @@
+use std::error::Error;
+mod common;
+
+use glam::{I16Vec3, U16Vec3};
+use minetestworld::positions::{BlockPos, NodePos};
+use minetestworld::{MapBlock, World};
+
+const SPAWN_DIR: &str = "TestWorld spawn";
+
+async fn suggest_spawn() -> Result<(), Box<dyn Error>> {
+    let world = World::create_sqlite(SPAWN_DIR).await?;
+    let mut block = MapBlock::unloaded();
+    let air = block.get_or_create_content_id(b"air");
+    let stone = block.get_or_create_content_id(b"default:stone");
+    let water = block.get_or_create_content_id(b"default:water_source");
+    for z in 0..16 {
+        for y in 0..16 {
+            for x in 0..16 {
+                // A stone floor with a pool on one half
+                let content = match y {
+                    0..4 => stone,
+                    4 if x < 8 => water,
+                    _ => air,
+                };
+                block.set_content(NodePos::try_from(U16Vec3::new(x, y, z)).unwrap(), content);
+            }
+        }
+    }
+    let map = world.get_mutable_map_data().await?;
+    map.set_mapblock(BlockPos::from_index_vec(I16Vec3::ZERO), &block)
+        .await?;
+
+    let (a, b) = (I16Vec3::ZERO, I16Vec3::new(15, 10, 15));
+    let spawn = world.suggest_spawn(a, b, None).await?;
+    assert_eq!(spawn, Some(I16Vec3::new(8, 4, 0)));
+    let spawn = world
+        .suggest_spawn(a, b, Some(I16Vec3::new(2, 4, 12)))
+        .await?;
+    assert_eq!(spawn, Some(I16Vec3::new(8, 4, 12)));
+
+    // Only the pool lies within this box
+    let pool = (I16Vec3::ZERO, I16Vec3::new(7, 10, 15));
+    assert_eq!(world.suggest_spawn(pool.0, pool.1, None).await?, None);
+    // Nothing has been generated here
+    let elsewhere = (I16Vec3::splat(100), I16Vec3::splat(110));
+    assert_eq!(
+        world.suggest_spawn(elsewhere.0, elsewhere.1, None).await?,
+        None
+    );
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_suggest_spawn() -> Result<(), Box<dyn Error>> {
+    // No early return here, so that tear down happens in every case
+    let result = suggest_spawn().await;
+    let cleanup_result = common::tear_down_at(SPAWN_DIR).await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
@@ -0,0 +1,140 @@
+use std::error::Error;
+mod common;
+
+use futures::TryStreamExt;
+use glam::I16Vec3;
+use minetestworld::positions::{BlockArea, BlockPos};
+use minetestworld::sync::{merge, pull_changes, ConflictPolicy};
+use minetestworld::{MapData, World};
+
+const SYNC_DIR: &str = "TestWorld sync";
+const MERGE_DIR: &str = "TestWorld merge";
+
+async fn stamp(
+    map: &MapData,
+    pos: BlockPos,
+    timestamp: u32,
+    param2: u8,
+) -> Result<(), Box<dyn Error>> {
+    let mut block = map.get_mapblock(pos).await?;
+    block.timestamp = timestamp;
+    block.param2[0] = param2;
+    map.set_mapblock(pos, &block).await?;
+    Ok(())
+}
+
+async fn sync() -> Result<(), Box<dyn Error>> {
+    let world = World::open("TestWorld").get_map_data().await?;
+    let source = MapData::from_sqlite_file(format!("{SYNC_DIR}/source.sqlite"), false).await?;
+    let dest = MapData::from_sqlite_file(format!("{SYNC_DIR}/dest.sqlite"), false).await?;
+    let region = BlockArea::new(
+        BlockPos::from_index_vec(I16Vec3::new(-14, -9, 1)),
+        BlockPos::from_index_vec(I16Vec3::new(-12, -7, 3)),
+    );
+    let count = source.copy_region_from(&world, region, None).await?;
+    // The fixture's blocks carry no timestamp, which counts as always changed
+    let positions: Vec<_> = source.all_mapblock_positions().await.try_collect().await?;
+    for &pos in &positions {
+        let mut block = source.get_mapblock(pos).await?;
+        block.timestamp = 100;
+        source.set_mapblock(pos, &block).await?;
+    }
+
+    let report = pull_changes(&source, &dest, 0, ConflictPolicy::SourceWins).await?;
+    assert_eq!(report.copied, count);
+    assert!(report.conflicts.is_empty());
+    assert_eq!(report.latest_timestamp, 100);
+    let since = report.latest_timestamp + 1;
+    let report = pull_changes(&source, &dest, since, ConflictPolicy::SourceWins).await?;
+    assert_eq!(report.copied, 0);
+
+    // One block changes in the source only, another one in both worlds
+    let (changed, conflicting) = (positions[0], positions[1]);
+    stamp(&source, changed, since + 5, 1).await?;
+    stamp(&source, conflicting, since + 5, 1).await?;
+    stamp(&dest, conflicting, since + 10, 2).await?;
+
+    let report = pull_changes(&source, &dest, since, ConflictPolicy::NewerWins).await?;
+    assert_eq!(report.copied, 1);
+    assert_eq!(report.conflicts, [conflicting]);
+    assert_eq!(report.latest_timestamp, since + 5);
+    assert_eq!(dest.get_mapblock(conflicting).await?.param2[0], 2);
+
+    let report = pull_changes(&source, &dest, since, ConflictPolicy::SourceWins).await?;
+    assert_eq!((report.copied, report.unchanged), (1, 1));
+    assert_eq!(dest.get_mapblock(conflicting).await?.param2[0], 1);
+
+    // Undecodable mapblocks are reported instead of aborting the sync
+    let (broken_source, broken_dest) = (positions[2], positions[3]);
+    source.set_block_data(broken_source, &[29, 0]).await?;
+    stamp(&source, broken_dest, since + 20, 3).await?;
+    dest.set_block_data(broken_dest, &[29, 0]).await?;
+    let report = pull_changes(&source, &dest, since, ConflictPolicy::DestinationWins).await?;
+    assert_eq!(report.copied, 1);
+    assert_eq!(report.undecodable.len(), 2);
+    assert!(report.undecodable.contains(&broken_source));
+    assert!(report.undecodable.contains(&broken_dest));
+    assert_eq!(dest.get_mapblock(broken_dest).await?.param2[0], 3);
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_sync() -> Result<(), Box<dyn Error>> {
+    common::tear_up_empty_at(SYNC_DIR).await?;
+    // No early return here, so that tear down happens in every case
+    let result = sync().await;
+    let cleanup_result = common::tear_down_at(SYNC_DIR).await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
+
+async fn merge_worlds() -> Result<(), Box<dyn Error>> {
+    let world = World::open("TestWorld").get_map_data().await?;
+    let source = MapData::from_sqlite_file(format!("{MERGE_DIR}/source.sqlite"), false).await?;
+    let dest = MapData::from_sqlite_file(format!("{MERGE_DIR}/dest.sqlite"), false).await?;
+    // Two overlapping regions
+    let low = BlockArea::new(
+        BlockPos::from_index_vec(I16Vec3::new(-14, -9, 1)),
+        BlockPos::from_index_vec(I16Vec3::new(-12, -8, 3)),
+    );
+    let high = BlockArea::new(
+        BlockPos::from_index_vec(I16Vec3::new(-14, -8, 1)),
+        BlockPos::from_index_vec(I16Vec3::new(-12, -7, 3)),
+    );
+    source.copy_region_from(&world, low, None).await?;
+    dest.copy_region_from(&world, high, None).await?;
+    let positions: Vec<_> = source.all_mapblock_positions().await.try_collect().await?;
+    let shared = *positions
+        .iter()
+        .find(|pos| high.contains(**pos))
+        .expect("the regions overlap");
+    stamp(&source, shared, 10, 1).await?;
+    stamp(&dest, shared, 20, 2).await?;
+
+    let report = merge(&source, &dest, ConflictPolicy::DestinationWins).await?;
+    assert_eq!(report.conflicts, [shared]);
+    assert_eq!(dest.get_mapblock(shared).await?.param2[0], 2);
+    for &pos in &positions {
+        assert!(dest.get_block_data(pos).await.is_ok());
+    }
+
+    let report = merge(&source, &dest, ConflictPolicy::NewerWins).await?;
+    assert_eq!(report.copied, 0);
+    assert_eq!(dest.get_mapblock(shared).await?.param2[0], 2);
+    let report = merge(&source, &dest, ConflictPolicy::SourceWins).await?;
+    assert_eq!(report.copied, 1);
+    assert_eq!(dest.get_mapblock(shared).await?.param2[0], 1);
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_merge() -> Result<(), Box<dyn Error>> {
+    common::tear_up_empty_at(MERGE_DIR).await?;
+    // No early return here, so that tear down happens in every case
+    let result = merge_worlds().await;
+    let cleanup_result = common::tear_down_at(MERGE_DIR).await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
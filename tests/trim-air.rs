@@ -0,0 +1,57 @@
+use std::error::Error;
+mod common;
+
+use futures::TryStreamExt;
+use glam::I16Vec3;
+use minetestworld::positions::BlockPos;
+use minetestworld::{MapData, MapDataError, MapEdit};
+
+const TRIM_DIR: &str = "TestWorld trim air";
+
+async fn trim_air_blocks() -> Result<(), Box<dyn Error>> {
+    let map_path = format!("{TRIM_DIR}/map.sqlite");
+    // The mapblock at (-12, 2, 3) consists of air only
+    let marker = I16Vec3::new(-12, 2, 3) * 16 + I16Vec3::splat(8);
+    let vm = MapEdit::new(MapData::from_sqlite_file(&map_path, false).await?);
+    vm.set_metadata_var(marker, b"infotext", b"Keep me").await?;
+    vm.commit().await?;
+    std::mem::drop(vm);
+
+    let map = MapData::from_sqlite_file(&map_path, false).await?;
+    let before = map
+        .all_mapblock_positions()
+        .await
+        .try_collect::<Vec<_>>()
+        .await?;
+    let removed = map.trim_air_blocks().await?;
+    assert!(removed > 0);
+    let after = map
+        .all_mapblock_positions()
+        .await
+        .try_collect::<Vec<_>>()
+        .await?;
+    assert_eq!(after.len(), before.len() - removed);
+    let kept = BlockPos::from_index_vec(I16Vec3::new(-12, 2, 3));
+    assert!(after.contains(&kept));
+    match map
+        .get_block_data(BlockPos::from_index_vec(I16Vec3::new(-12, 2, 4)))
+        .await
+    {
+        Err(MapDataError::MapBlockNonexistent(_)) => {}
+        other => panic!("expected the air block to be removed, got {other:?}"),
+    }
+    // Nothing is left to trim
+    assert_eq!(map.trim_air_blocks().await?, 0);
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_trim_air_blocks() -> Result<(), Box<dyn Error>> {
+    common::tear_up_at(TRIM_DIR).await?;
+    // No early return here, so that tear down happens in every case
+    let result = trim_air_blocks().await;
+    let cleanup_result = common::tear_down_at(TRIM_DIR).await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
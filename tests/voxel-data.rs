@@ -0,0 +1,69 @@
+use std::error::Error;
+mod common;
+
+use glam::I16Vec3;
+use minetestworld::voxel_manip::VoxelData;
+use minetestworld::{MapData, MapEdit};
+
+const DATA_DIR: &str = "TestWorld voxel data";
+
+#[test]
+fn index_order() {
+    let data = VoxelData::new(I16Vec3::new(5, -3, 2), I16Vec3::new(-2, 4, 9));
+    assert_eq!(data.volume(), 8 * 8 * 8);
+    assert_eq!(data.index(I16Vec3::new(-2, -3, 2)), Some(0));
+    assert_eq!(data.index(I16Vec3::new(-1, -3, 2)), Some(1));
+    assert_eq!(data.index(I16Vec3::new(-2, -2, 2)), Some(8));
+    assert_eq!(data.index(I16Vec3::new(-2, -3, 3)), Some(64));
+    assert_eq!(data.index(I16Vec3::new(6, 0, 5)), None);
+    for index in 0..data.volume() {
+        assert_eq!(data.index(data.position(index)), Some(index));
+    }
+}
+
+async fn voxel_data() -> Result<(), Box<dyn Error>> {
+    let map_path = format!("{DATA_DIR}/map.sqlite");
+    let (a, b) = (I16Vec3::new(-190, -20, 60), I16Vec3::new(-180, -10, 70));
+
+    let vm = MapEdit::new(MapData::from_sqlite_file(&map_path, false).await?);
+    let mut data = vm.get_data(a, b).await?;
+    for index in (0..data.volume()).step_by(97) {
+        let pos = data.position(index);
+        assert_eq!(data.get_node(pos), Some(vm.get_node(pos).await?));
+    }
+
+    // Writing the data back unchanged modifies nothing
+    vm.set_data(&data).await?;
+    assert!(vm.modified_blocks().await.is_empty());
+
+    let stone = data.get_or_create_content_id(b"default:stone");
+    let mese = data.get_or_create_content_id(b"default:mese");
+    let mut replaced = vec![];
+    for index in 0..data.volume() {
+        if data.content[index] == stone {
+            data.content[index] = mese;
+            replaced.push(data.position(index));
+        }
+    }
+    assert!(!replaced.is_empty());
+    vm.set_data(&data).await?;
+    vm.commit().await?;
+    std::mem::drop(vm);
+
+    let vm = MapEdit::new(MapData::from_sqlite_file(&map_path, true).await?);
+    for pos in replaced {
+        assert_eq!(vm.get_node(pos).await?.param0, b"default:mese");
+    }
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_voxel_data() -> Result<(), Box<dyn Error>> {
+    common::tear_up_at(DATA_DIR).await?;
+    // No early return here, so that tear down happens in every case
+    let result = voxel_data().await;
+    let cleanup_result = common::tear_down_at(DATA_DIR).await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use std::error::Error;
+mod common;
+
+use glam::{I16Vec3, U16Vec3};
+use minetestworld::edit_plan::{EditPlan, Schematic};
+use minetestworld::worldedit::{self, WorldEditError};
+use minetestworld::{MapData, MapEdit};
+
+const WORLDEDIT_DIR: &str = "TestWorld worldedit";
+
+const SIGN: &str = r#"5:return {{["x"] = 0, ["y"] = 1, ["z"] = 0, ["name"] = "default:sign_wall_wood",
+    ["param1"] = 0, ["param2"] = 4, ["meta"] = {["fields"] = {["text"] = "Say \"hi\"\
+twice", ["infotext"] = "\072i"}, ["inventory"] = {}}},
+    {x=1,y=1,z=0,name="default:chest",param2=2,meta={fields={},inventory={main={"default:dirt 5",""}}}}}"#;
+
+async fn place_worldedit() -> Result<(), Box<dyn Error>> {
+    let map_path = format!("{WORLDEDIT_DIR}/map.sqlite");
+    let vm = MapEdit::new(MapData::from_sqlite_file(&map_path, false).await?);
+
+    let schematic = worldedit::parse(SIGN)?;
+    // The bottom layer has been skipped as air, but still belongs to the schematic
+    assert_eq!(schematic.size(), U16Vec3::new(2, 2, 1));
+    EditPlan::new()
+        .fill(I16Vec3::new(10, 0, 10), I16Vec3::new(11, 1, 10), b"air")
+        .place_schematic(I16Vec3::new(10, 0, 10), schematic)
+        .execute(&vm)
+        .await?;
+
+    let vm = MapEdit::new(MapData::from_sqlite_file(&map_path, false).await?);
+    let sign = vm.get_node(I16Vec3::new(10, 1, 10)).await?;
+    assert_eq!(sign.param0, b"default:sign_wall_wood");
+    assert_eq!(sign.param2, 4);
+    let metadata = vm.get_metadata(I16Vec3::new(10, 1, 10)).await?.unwrap();
+    assert_eq!(metadata.get(b"text"), Some(&b"Say \"hi\"\ntwice"[..]));
+    assert_eq!(metadata.get(b"infotext"), Some(&b"Hi"[..]));
+    let chest = vm.get_metadata(I16Vec3::new(11, 1, 10)).await?.unwrap();
+    assert_eq!(
+        chest.inventory_list("main").unwrap().items,
+        vec!["default:dirt 5", ""]
+    );
+
+    // Saving and loading again keeps everything but the air
+    let saved =
+        Schematic::from_world(&vm, I16Vec3::new(10, 0, 10), I16Vec3::new(11, 1, 10)).await?;
+    let serialized = worldedit::serialize(&saved);
+    assert!(!serialized.contains("\"air\""));
+    let loaded = worldedit::parse(&serialized)?;
+    assert_eq!(loaded.size(), U16Vec3::new(2, 2, 1));
+    assert!(loaded.get_node(U16Vec3::new(0, 0, 0)).is_none());
+    assert_eq!(
+        loaded.get_node(U16Vec3::new(0, 1, 0)),
+        saved.get_node(U16Vec3::new(0, 1, 0))
+    );
+    assert_eq!(
+        loaded.get_metadata(U16Vec3::new(1, 1, 0)),
+        saved.get_metadata(U16Vec3::new(1, 1, 0))
+    );
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_worldedit() -> Result<(), Box<dyn Error>> {
+    common::tear_up_at(WORLDEDIT_DIR).await?;
+    // No early return here, so that tear down happens in every case
+    let result = place_worldedit().await;
+    let cleanup_result = common::tear_down_at(WORLDEDIT_DIR).await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}
+
+#[test]
+fn test_worldedit_json() {
+    let schematic = worldedit::parse(
+        r#"[{"x": 0, "y": 0, "z": 2, "name": "default:glass", "param1": 0, "param2": 0},
+            {"x": 0, "y": 0, "z": 0, "name": "default:wood", "meta": null}]"#,
+    )
+    .unwrap();
+    assert_eq!(schematic.size(), U16Vec3::new(1, 1, 3));
+    assert_eq!(
+        schematic.get_node(U16Vec3::new(0, 0, 2)).unwrap().param0,
+        b"default:glass"
+    );
+
+    // Characters beyond the basic multilingual plane are escaped as surrogate pairs
+    let schematic =
+        worldedit::parse(r#"[{"x": 0, "y": 0, "z": 0, "name": "mod:\u00e9\ud83d\ude00"}]"#)
+            .unwrap();
+    assert_eq!(
+        schematic.get_node(U16Vec3::ZERO).unwrap().param0,
+        "mod:é😀".as_bytes()
+    );
+    assert!(matches!(
+        worldedit::parse(r#"[{"x": 0, "y": 0, "z": 0, "name": "mod:\ud83d"}]"#),
+        Err(WorldEditError::Syntax(..))
+    ));
+
+    assert!(matches!(
+        worldedit::parse("3:0 0 0 default:stone 0 0"),
+        Err(WorldEditError::UnsupportedVersion(3))
+    ));
+    assert!(matches!(
+        worldedit::parse("5:return {{x=0,y=0,name=\"air\"}}"),
+        Err(WorldEditError::Invalid(_))
+    ));
+    assert!(matches!(
+        worldedit::parse("5:return {{x=0,"),
+        Err(WorldEditError::Syntax(..))
+    ));
+}
@@ -0,0 +1,68 @@
+use std::error::Error;
+mod common;
+use async_std::fs;
+use futures::StreamExt;
+use glam::I16Vec3;
+use minetestworld::map_block::WriteMaintenance;
+use minetestworld::positions::SplitPos;
+use minetestworld::{MapBlock, MapData, World};
+
+/// Returns the timestamp and the lighting flags of a serialized mapblock
+fn header(data: &[u8]) -> Result<(Option<u32>, u16), Box<dyn Error>> {
+    let block = MapBlock::from_data(data)?;
+    Ok((block.saved_at(), block.lighting_complete))
+}
+
+fn rewrite(data: &[u8], maintenance: &WriteMaintenance) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut block = MapBlock::from_data(data)?;
+    block.apply_maintenance(maintenance);
+    Ok(block.to_binary()?)
+}
+
+async fn write_maintenance() -> Result<(), Box<dyn Error>> {
+    let world = World::open("TestWorld copy");
+    assert!(world.get_game_time().await.is_err());
+    fs::write(
+        "TestWorld copy/env_meta.txt",
+        "game_time = 4711\ntime_of_day = 6000\nEnvArgsEnd\n",
+    )
+    .await?;
+    let game_time = world.get_game_time().await?;
+    assert_eq!(game_time, 4711);
+
+    let node_pos = I16Vec3::new(-200, 10, 40);
+    let mut vm = world.get_voxel_manip(true).await?;
+    vm.set_write_maintenance(WriteMaintenance {
+        timestamp: Some(game_time),
+        invalidate_lighting: true,
+    });
+    vm.set_content(node_pos, b"default:glass").await?;
+    vm.commit().await?;
+    std::mem::drop(vm);
+
+    let map = world.get_map_data().await?;
+    let data = map.get_block_data(node_pos.split().0).await?;
+    assert_eq!(header(&data)?, (Some(4711), 0));
+
+    // Without maintenance, the header of other blocks is left as is
+    let pos = map.all_mapblock_positions().await.next().await.unwrap()?;
+    let untouched = header(&map.get_block_data(pos).await?)?;
+    std::mem::drop(map);
+    let map = MapData::from_sqlite_file("TestWorld copy/map.sqlite", false).await?;
+    let data = map.get_block_data(pos).await?;
+    map.set_block_data(pos, &rewrite(&data, &WriteMaintenance::default())?)
+        .await?;
+    assert_eq!(header(&map.get_block_data(pos).await?)?, untouched);
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_write_maintenance() -> Result<(), Box<dyn Error>> {
+    common::tear_up().await?;
+    // No early return here, so that tear down happens in every case
+    let result = write_maintenance().await;
+    let cleanup_result = common::tear_down().await;
+    result?;
+    cleanup_result?;
+    Ok(())
+}